@@ -0,0 +1,111 @@
+//! The [component model](https://github.com/WebAssembly/component-model/blob/main/design/mvp/Binary.md)
+//! binary format.
+
+pub mod parser;
+pub mod section;
+pub mod types;
+
+use crate::decode::Decoder;
+use crate::features::Features;
+use crate::limits::Limits;
+use crate::module::Module;
+
+use self::parser::ComponentParser;
+use self::section::ComponentSection;
+use self::types::{ComponentExport, ComponentImport};
+
+#[derive(Debug, Default)]
+pub struct Component<'a> {
+    pub version: u16,
+    pub layer: u16,
+    pub sections: Vec<ComponentSection<'a>>,
+}
+
+impl<'a> Component<'a> {
+    pub fn from_bytes(bytes: &'a [u8]) -> ComponentParser<'a> {
+        let decoder = Decoder::new(bytes);
+
+        ComponentParser {
+            decoder,
+            depth: 0,
+            features: Features::default(),
+            limits: Limits::default(),
+        }
+    }
+
+    /// Detaches the component, and every nested module, from the input buffer.
+    pub fn into_owned(self) -> Component<'static> {
+        Component {
+            version: self.version,
+            layer: self.layer,
+            sections: self
+                .sections
+                .into_iter()
+                .map(ComponentSection::into_owned)
+                .collect(),
+        }
+    }
+
+    /// Nested core modules, in definition order.
+    pub fn modules(&self) -> impl Iterator<Item = &Module<'a>> {
+        self.sections.iter().filter_map(|section| match section {
+            ComponentSection::CoreModule(module) => Some(&**module),
+            _ => None,
+        })
+    }
+
+    /// Nested components, in definition order.
+    pub fn components(&self) -> impl Iterator<Item = &Component<'a>> {
+        self.sections.iter().filter_map(|section| match section {
+            ComponentSection::Component(component) => Some(component),
+            _ => None,
+        })
+    }
+
+    pub fn imports(&self) -> SectionItems<'_, 'a, ComponentImport> {
+        SectionItems::new(&self.sections, |section| match section {
+            ComponentSection::Import(imports) => imports,
+            _ => &[],
+        })
+    }
+
+    pub fn exports(&self) -> SectionItems<'_, 'a, ComponentExport> {
+        SectionItems::new(&self.sections, |section| match section {
+            ComponentSection::Export(exports) => exports,
+            _ => &[],
+        })
+    }
+}
+
+/// The items of every section of one kind, see [Component::imports] and [Component::exports].
+pub struct SectionItems<'s, 'a, T> {
+    sections: std::slice::Iter<'s, ComponentSection<'a>>,
+    items: std::slice::Iter<'s, T>,
+    select: fn(&'s ComponentSection<'a>) -> &'s [T],
+}
+
+impl<'s, 'a, T> SectionItems<'s, 'a, T> {
+    fn new(
+        sections: &'s [ComponentSection<'a>],
+        select: fn(&'s ComponentSection<'a>) -> &'s [T],
+    ) -> Self {
+        SectionItems {
+            sections: sections.iter(),
+            items: [].iter(),
+            select,
+        }
+    }
+}
+
+impl<'s, T> Iterator for SectionItems<'s, '_, T> {
+    type Item = &'s T;
+
+    fn next(&mut self) -> Option<&'s T> {
+        loop {
+            if let Some(item) = self.items.next() {
+                return Some(item);
+            }
+            self.items = (self.select)(self.sections.next()?).iter();
+        }
+    }
+}
@@ -0,0 +1,719 @@
+use smol_str::SmolStr;
+
+use crate::component::section::{
+    Canon, CanonOption, ComponentInlineExport, ComponentInstance, ComponentInstantiationArg,
+    ComponentSection, ComponentSectionId, ComponentStart, ComponentValue, CoreInlineExport,
+    CoreInstance, CoreInstantiationArg,
+};
+use crate::component::types::{
+    ComponentAlias, ComponentDefinedType, ComponentExport, ComponentFuncResult, ComponentFuncType,
+    ComponentImport, ComponentSort, ComponentType, ComponentTypeDecl, ComponentTypeRef,
+    ComponentValType, CoreSort, CoreType, InstanceTypeDecl, ModuleTypeDecl, PrimitiveValType,
+    TypeBound, VariantCase,
+};
+use crate::component::Component;
+use crate::decode::Decoder;
+use crate::error::{Error, Location};
+use crate::features::Features;
+use crate::limits::{self, Limits};
+use crate::module::Module;
+use crate::parser::{ModuleParser, COMPONENT_VERSION, MODULE_VERSION};
+use crate::section::CustomSection;
+
+/// Components, and component or instance types, nested deeper than this fail to parse
+/// with [Error::LimitExceeded], as each level is parsed recursively.
+pub const MAX_NESTING_DEPTH: u32 = 100;
+
+pub struct ComponentParser<'a> {
+    pub decoder: Decoder<'a>,
+    /// How many components enclose this one.
+    pub(crate) depth: u32,
+    pub(crate) features: Features,
+    pub(crate) limits: Limits,
+}
+
+impl<'a> ComponentParser<'a> {
+    /// Reject proposals disabled in `features` in the nested core modules.
+    pub fn features(mut self, features: Features) -> Self {
+        self.decoder = self.decoder.with_features(features);
+        self.features = features;
+        self
+    }
+
+    /// Replace the default [Limits] the nested core modules are parsed with.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    fn parse_header(&mut self) -> Result<(u16, u16), Error> {
+        let magic = self.decoder.read_n::<4>()?;
+        let version = self.decoder.read_u32()?;
+        if &magic != b"\0asm" {
+            Err(Error::InvalidWasm)
+        } else if version == MODULE_VERSION {
+            Err(Error::UnexpectedModule)
+        } else if version != COMPONENT_VERSION {
+            Err(Error::InvalidVersion(version))
+        } else {
+            Ok((version as u16, (version >> 16) as u16))
+        }
+    }
+
+    pub fn read_section_header(&mut self) -> Result<(ComponentSectionId, u32), Error> {
+        let id = self.decoder.read_u8()?;
+        let size = self.decoder.read_var_u32()?;
+        Ok((ComponentSectionId::try_from(id)?, size))
+    }
+
+    pub fn parse(mut self) -> Result<Component<'a>, Error> {
+        limits::check("component nesting depth", self.depth, MAX_NESTING_DEPTH)
+            .map_err(|err| err.at(self.decoder.offset(), Location::Module))?;
+        let (version, layer) = self
+            .parse_header()
+            .map_err(|err| err.at(self.decoder.offset(), Location::Module))?;
+        let mut component = Component {
+            version,
+            layer,
+            sections: Vec::new(),
+        };
+
+        while !self.decoder.remaining_slice().is_empty() {
+            let (id, size) = self
+                .read_section_header()
+                .map_err(|err| err.at(self.decoder.offset(), Location::Module))?;
+            let location = Location::ComponentSection(id);
+            if size as usize > self.decoder.remaining_slice().len() {
+                return Err(Error::SectionOutOfBounds(
+                    id as u8,
+                    size,
+                    self.decoder.remaining_slice().len() as u32,
+                )
+                .at(self.decoder.offset(), location));
+            }
+            let mut decoder = self.decoder.slice_with(size as u64);
+            let section = self
+                .parse_section(id, &mut decoder)
+                .map_err(|err| err.at(decoder.offset(), location))?;
+
+            if !decoder.remaining_slice().is_empty() {
+                return Err(Error::SectionOutOfBounds(
+                    id as u8,
+                    size,
+                    size - decoder.remaining_slice().len() as u32,
+                )
+                .at(decoder.offset(), location));
+            }
+            component.sections.push(section);
+        }
+
+        Ok(component)
+    }
+
+    /// Nested modules and components are decoded from the section's offset,
+    /// so that their errors point into the outermost binary.
+    fn parse_section(
+        &self,
+        id: ComponentSectionId,
+        decoder: &mut Decoder<'a>,
+    ) -> Result<ComponentSection<'a>, Error> {
+        let data = *decoder.reader.get_ref();
+        let offset = decoder.offset();
+        Ok(match id {
+            ComponentSectionId::Custom => {
+                let name = decoder.read_name()?;
+                let data = decoder.read_slice(decoder.remaining_slice().len())?;
+                ComponentSection::Custom(CustomSection::new(name, data))
+            }
+            ComponentSectionId::CoreModule => {
+                decoder.reader.set_position(data.len() as u64);
+                let mut parser = Module::from_bytes_with(data, self.features).limits(self.limits);
+                parser.decoder =
+                    Decoder::new_with_offset(data, offset).with_features(self.features);
+                ComponentSection::CoreModule(Box::new(parser.parse()?))
+            }
+            ComponentSectionId::CoreInstance => {
+                ComponentSection::CoreInstance(decoder.read_vec(Self::parse_core_instance)?)
+            }
+            ComponentSectionId::CoreType => {
+                ComponentSection::CoreType(decoder.read_vec(Self::parse_core_type)?)
+            }
+            ComponentSectionId::Component => {
+                decoder.reader.set_position(data.len() as u64);
+                let parser = ComponentParser {
+                    decoder: Decoder::new_with_offset(data, offset).with_features(self.features),
+                    depth: self.depth + 1,
+                    features: self.features,
+                    limits: self.limits,
+                };
+                ComponentSection::Component(parser.parse()?)
+            }
+            ComponentSectionId::Instance => {
+                ComponentSection::Instance(decoder.read_vec(Self::parse_instance)?)
+            }
+            ComponentSectionId::Alias => {
+                ComponentSection::Alias(decoder.read_vec(Self::parse_alias)?)
+            }
+            ComponentSectionId::Type => {
+                ComponentSection::Type(decoder.read_vec(|decoder| Self::parse_type(decoder, 0))?)
+            }
+            ComponentSectionId::Canon => {
+                ComponentSection::Canon(decoder.read_vec(Self::parse_canon)?)
+            }
+            ComponentSectionId::Start => ComponentSection::Start(ComponentStart {
+                func: decoder.read_var_u32()?,
+                args: decoder.read_vec(Decoder::read_var_u32)?,
+                results: decoder.read_var_u32()?,
+            }),
+            ComponentSectionId::Import => {
+                ComponentSection::Import(decoder.read_vec(Self::parse_import)?)
+            }
+            ComponentSectionId::Export => {
+                ComponentSection::Export(decoder.read_vec(Self::parse_export)?)
+            }
+            ComponentSectionId::Value => ComponentSection::Value(decoder.read_vec(|decoder| {
+                let ty = Self::parse_valtype(decoder)?;
+                let len = decoder.read_var_u32()? as usize;
+                let data = decoder.read_bytes(len)?;
+                Ok(ComponentValue { ty, data })
+            })?),
+        })
+    }
+
+    fn parse_core_instance(decoder: &mut Decoder) -> Result<CoreInstance, Error> {
+        let tag = decoder.read_u8()?;
+        Ok(match tag {
+            0x00 => CoreInstance::Instantiate {
+                module: decoder.read_var_u32()?,
+                args: decoder.read_vec(|decoder| {
+                    let name = decoder.read_str()?;
+                    let kind = decoder.read_u8()?;
+                    if kind != CoreSort::Instance as u8 {
+                        return Err(Error::InvalidLeadingByte(kind, "instantiation arg kind"));
+                    }
+                    let instance = decoder.read_var_u32()?;
+                    Ok(CoreInstantiationArg { name, instance })
+                })?,
+            },
+            0x01 => CoreInstance::FromExports(decoder.read_vec(|decoder| {
+                Ok(CoreInlineExport {
+                    name: decoder.read_str()?,
+                    sort: CoreSort::try_from(decoder.read_u8()?)?,
+                    index: decoder.read_var_u32()?,
+                })
+            })?),
+            _ => return Err(Error::InvalidLeadingByte(tag, "core instance")),
+        })
+    }
+
+    fn parse_core_type(decoder: &mut Decoder) -> Result<CoreType, Error> {
+        // 0x50 is `sub` in a core type section, but a module type here.
+        if decoder.peek()? != 0x50 {
+            return decoder.read_subtype().map(CoreType::Sub);
+        }
+        decoder.read_u8()?;
+        decoder
+            .read_vec(|decoder| {
+                let tag = decoder.read_u8()?;
+                Ok(match tag {
+                    0x00 => {
+                        ModuleTypeDecl::Import(ModuleParser::parse_import(decoder)?.into_owned())
+                    }
+                    0x01 => ModuleTypeDecl::Type(decoder.read_subtype()?),
+                    0x02 => {
+                        let sort = CoreSort::try_from(decoder.read_u8()?)?;
+                        let target = decoder.read_u8()?;
+                        if target != 0x01 {
+                            return Err(Error::InvalidLeadingByte(target, "outer alias target"));
+                        }
+                        ModuleTypeDecl::OuterAlias {
+                            sort,
+                            count: decoder.read_var_u32()?,
+                            index: decoder.read_var_u32()?,
+                        }
+                    }
+                    0x03 => ModuleTypeDecl::Export {
+                        name: decoder.read_str()?,
+                        ty: ModuleParser::parse_import_kind(decoder)?,
+                    },
+                    _ => return Err(Error::InvalidLeadingByte(tag, "module type declaration")),
+                })
+            })
+            .map(CoreType::Module)
+    }
+
+    fn parse_instance(decoder: &mut Decoder) -> Result<ComponentInstance, Error> {
+        let tag = decoder.read_u8()?;
+        Ok(match tag {
+            0x00 => ComponentInstance::Instantiate {
+                component: decoder.read_var_u32()?,
+                args: decoder.read_vec(|decoder| {
+                    Ok(ComponentInstantiationArg {
+                        name: decoder.read_str()?,
+                        sort: Self::parse_sort(decoder)?,
+                        index: decoder.read_var_u32()?,
+                    })
+                })?,
+            },
+            0x01 => ComponentInstance::FromExports(decoder.read_vec(|decoder| {
+                Ok(ComponentInlineExport {
+                    name: Self::parse_extern_name(decoder)?,
+                    sort: Self::parse_sort(decoder)?,
+                    index: decoder.read_var_u32()?,
+                })
+            })?),
+            _ => return Err(Error::InvalidLeadingByte(tag, "instance")),
+        })
+    }
+
+    fn parse_sort(decoder: &mut Decoder) -> Result<ComponentSort, Error> {
+        let sort = decoder.read_u8()?;
+        Ok(match sort {
+            0x00 => ComponentSort::Core(CoreSort::try_from(decoder.read_u8()?)?),
+            0x01 => ComponentSort::Func,
+            0x02 => ComponentSort::Value,
+            0x03 => ComponentSort::Type,
+            0x04 => ComponentSort::Component,
+            0x05 => ComponentSort::Instance,
+            _ => return Err(Error::InvalidLeadingByte(sort, "sort")),
+        })
+    }
+
+    /// `importname'` and `exportname'`. The discriminator `0x01` historically marked
+    /// interface names, which are now inferred from the string, so both are accepted.
+    fn parse_extern_name(decoder: &mut Decoder) -> Result<SmolStr, Error> {
+        let tag = decoder.read_u8()?;
+        match tag {
+            0x00 | 0x01 => decoder.read_str(),
+            _ => Err(Error::InvalidLeadingByte(tag, "extern name")),
+        }
+    }
+
+    fn parse_alias(decoder: &mut Decoder) -> Result<ComponentAlias, Error> {
+        let sort = Self::parse_sort(decoder)?;
+        let target = decoder.read_u8()?;
+        Ok(match (target, sort) {
+            (0x00, sort) => ComponentAlias::InstanceExport {
+                sort,
+                instance: decoder.read_var_u32()?,
+                name: decoder.read_str()?,
+            },
+            (0x01, ComponentSort::Core(sort)) => ComponentAlias::CoreInstanceExport {
+                sort,
+                instance: decoder.read_var_u32()?,
+                name: decoder.read_str()?,
+            },
+            (0x02, sort) => ComponentAlias::Outer {
+                sort,
+                count: decoder.read_var_u32()?,
+                index: decoder.read_var_u32()?,
+            },
+            _ => return Err(Error::InvalidLeadingByte(target, "alias target")),
+        })
+    }
+
+    fn parse_valtype(decoder: &mut Decoder) -> Result<ComponentValType, Error> {
+        if let Ok(ty) = PrimitiveValType::try_from(decoder.peek()?) {
+            decoder.read_u8()?;
+            return Ok(ComponentValType::Primitive(ty));
+        }
+        let idx = decoder.read_var_s33()?;
+        u32::try_from(idx)
+            .map(ComponentValType::Type)
+            .map_err(|_| Error::Other("negative component type index"))
+    }
+
+    fn parse_optional<'b, T, F>(decoder: &mut Decoder<'b>, ele: F) -> Result<Option<T>, Error>
+    where
+        F: Fn(&mut Decoder<'b>) -> Result<T, Error>,
+    {
+        let tag = decoder.read_u8()?;
+        match tag {
+            0x00 => Ok(None),
+            0x01 => ele(decoder).map(Some),
+            _ => Err(Error::InvalidLeadingByte(tag, "optional")),
+        }
+    }
+
+    fn parse_labeled_valtypes(
+        decoder: &mut Decoder,
+    ) -> Result<Vec<(SmolStr, ComponentValType)>, Error> {
+        decoder.read_vec(|decoder| Ok((decoder.read_str()?, Self::parse_valtype(decoder)?)))
+    }
+
+    /// `depth` counts the component and instance types enclosing this one.
+    fn parse_type(decoder: &mut Decoder, depth: u32) -> Result<ComponentType, Error> {
+        let tag = decoder.read_u8()?;
+        if matches!(tag, 0x41 | 0x42) {
+            limits::check("component type nesting depth", depth + 1, MAX_NESTING_DEPTH)?;
+        }
+        Ok(match tag {
+            0x3f => ComponentType::Resource {
+                rep: decoder.read_valtype()?,
+                dtor: Self::parse_optional(decoder, Decoder::read_var_u32)?,
+            },
+            0x40 => {
+                let params = Self::parse_labeled_valtypes(decoder)?;
+                let tag = decoder.read_u8()?;
+                let results = match tag {
+                    0x00 => ComponentFuncResult::Unnamed(Self::parse_valtype(decoder)?),
+                    0x01 => ComponentFuncResult::Named(Self::parse_labeled_valtypes(decoder)?),
+                    _ => return Err(Error::InvalidLeadingByte(tag, "component function results")),
+                };
+                ComponentType::Func(ComponentFuncType { params, results })
+            }
+            0x41 => ComponentType::Component(decoder.read_vec(|decoder| {
+                if decoder.peek()? == 0x03 {
+                    decoder.read_u8()?;
+                    return Self::parse_import(decoder).map(ComponentTypeDecl::Import);
+                }
+                Ok(match Self::parse_instance_type_decl(decoder, depth + 1)? {
+                    InstanceTypeDecl::CoreType(ty) => ComponentTypeDecl::CoreType(ty),
+                    InstanceTypeDecl::Type(ty) => ComponentTypeDecl::Type(ty),
+                    InstanceTypeDecl::Alias(alias) => ComponentTypeDecl::Alias(alias),
+                    InstanceTypeDecl::Export { name, ty } => ComponentTypeDecl::Export { name, ty },
+                })
+            })?),
+            0x42 => ComponentType::Instance(
+                decoder.read_vec(|decoder| Self::parse_instance_type_decl(decoder, depth + 1))?,
+            ),
+            _ => ComponentType::Defined(Self::parse_defined_type(decoder, tag)?),
+        })
+    }
+
+    fn parse_instance_type_decl(
+        decoder: &mut Decoder,
+        depth: u32,
+    ) -> Result<InstanceTypeDecl, Error> {
+        let tag = decoder.read_u8()?;
+        Ok(match tag {
+            0x00 => InstanceTypeDecl::CoreType(Self::parse_core_type(decoder)?),
+            0x01 => InstanceTypeDecl::Type(Self::parse_type(decoder, depth)?),
+            0x02 => InstanceTypeDecl::Alias(Self::parse_alias(decoder)?),
+            0x04 => InstanceTypeDecl::Export {
+                name: Self::parse_extern_name(decoder)?,
+                ty: Self::parse_type_ref(decoder)?,
+            },
+            _ => {
+                return Err(Error::InvalidLeadingByte(
+                    tag,
+                    "component or instance type declaration",
+                ))
+            }
+        })
+    }
+
+    fn parse_defined_type(decoder: &mut Decoder, tag: u8) -> Result<ComponentDefinedType, Error> {
+        if let Ok(ty) = PrimitiveValType::try_from(tag) {
+            return Ok(ComponentDefinedType::Primitive(ty));
+        }
+        Ok(match tag {
+            0x72 => ComponentDefinedType::Record(Self::parse_labeled_valtypes(decoder)?),
+            0x71 => ComponentDefinedType::Variant(decoder.read_vec(|decoder| {
+                Ok(VariantCase {
+                    name: decoder.read_str()?,
+                    ty: Self::parse_optional(decoder, Self::parse_valtype)?,
+                    refines: Self::parse_optional(decoder, Decoder::read_var_u32)?,
+                })
+            })?),
+            0x70 => ComponentDefinedType::List(Self::parse_valtype(decoder)?),
+            0x6f => ComponentDefinedType::Tuple(decoder.read_vec(Self::parse_valtype)?),
+            0x6e => ComponentDefinedType::Flags(decoder.read_vec(Decoder::read_str)?),
+            0x6d => ComponentDefinedType::Enum(decoder.read_vec(Decoder::read_str)?),
+            0x6b => ComponentDefinedType::Option(Self::parse_valtype(decoder)?),
+            0x6a => ComponentDefinedType::Result {
+                ok: Self::parse_optional(decoder, Self::parse_valtype)?,
+                err: Self::parse_optional(decoder, Self::parse_valtype)?,
+            },
+            0x69 => ComponentDefinedType::Own(decoder.read_var_u32()?),
+            0x68 => ComponentDefinedType::Borrow(decoder.read_var_u32()?),
+            _ => return Err(Error::InvalidLeadingByte(tag, "component defined type")),
+        })
+    }
+
+    fn parse_type_ref(decoder: &mut Decoder) -> Result<ComponentTypeRef, Error> {
+        Ok(match Self::parse_sort(decoder)? {
+            ComponentSort::Core(CoreSort::Module) => {
+                ComponentTypeRef::Module(decoder.read_var_u32()?)
+            }
+            ComponentSort::Func => ComponentTypeRef::Func(decoder.read_var_u32()?),
+            ComponentSort::Value => ComponentTypeRef::Value(Self::parse_valtype(decoder)?),
+            ComponentSort::Type => {
+                let tag = decoder.read_u8()?;
+                ComponentTypeRef::Type(match tag {
+                    0x00 => TypeBound::Eq(decoder.read_var_u32()?),
+                    0x01 => TypeBound::SubResource,
+                    _ => return Err(Error::InvalidLeadingByte(tag, "type bound")),
+                })
+            }
+            ComponentSort::Instance => ComponentTypeRef::Instance(decoder.read_var_u32()?),
+            ComponentSort::Component => ComponentTypeRef::Component(decoder.read_var_u32()?),
+            ComponentSort::Core(_) => return Err(Error::Other("invalid core sort in externdesc")),
+        })
+    }
+
+    fn parse_canon(decoder: &mut Decoder) -> Result<Canon, Error> {
+        let tag = decoder.read_u8()?;
+        Ok(match tag {
+            0x00 | 0x01 => {
+                let sub = decoder.read_u8()?;
+                if sub != 0x00 {
+                    return Err(Error::InvalidLeadingByte(sub, "canonical function"));
+                }
+                let func = decoder.read_var_u32()?;
+                let options = decoder.read_vec(Self::parse_canon_option)?;
+                if tag == 0x00 {
+                    Canon::Lift {
+                        core_func: func,
+                        options,
+                        ty: decoder.read_var_u32()?,
+                    }
+                } else {
+                    Canon::Lower { func, options }
+                }
+            }
+            0x02 => Canon::ResourceNew(decoder.read_var_u32()?),
+            0x03 => Canon::ResourceDrop(decoder.read_var_u32()?),
+            0x04 => Canon::ResourceRep(decoder.read_var_u32()?),
+            _ => return Err(Error::InvalidLeadingByte(tag, "canonical function")),
+        })
+    }
+
+    fn parse_canon_option(decoder: &mut Decoder) -> Result<CanonOption, Error> {
+        let tag = decoder.read_u8()?;
+        Ok(match tag {
+            0x00 => CanonOption::Utf8,
+            0x01 => CanonOption::Utf16,
+            0x02 => CanonOption::CompactUtf16,
+            0x03 => CanonOption::Memory(decoder.read_var_u32()?),
+            0x04 => CanonOption::Realloc(decoder.read_var_u32()?),
+            0x05 => CanonOption::PostReturn(decoder.read_var_u32()?),
+            _ => return Err(Error::InvalidLeadingByte(tag, "canonical option")),
+        })
+    }
+
+    fn parse_import(decoder: &mut Decoder) -> Result<ComponentImport, Error> {
+        Ok(ComponentImport {
+            name: Self::parse_extern_name(decoder)?,
+            ty: Self::parse_type_ref(decoder)?,
+        })
+    }
+
+    fn parse_export(decoder: &mut Decoder) -> Result<ComponentExport, Error> {
+        Ok(ComponentExport {
+            name: Self::parse_extern_name(decoder)?,
+            sort: Self::parse_sort(decoder)?,
+            index: decoder.read_var_u32()?,
+            ty: Self::parse_optional(decoder, Self::parse_type_ref)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::component::parser::MAX_NESTING_DEPTH;
+    use crate::component::section::{Canon, ComponentSection, ComponentSectionId, CoreInstance};
+    use crate::component::types::{
+        ComponentDefinedType, ComponentSort, ComponentType, ComponentTypeRef, PrimitiveValType,
+    };
+    use crate::component::Component;
+    use crate::encode::Encoder;
+    use crate::error::{Error, Location};
+    use crate::features::Features;
+    use crate::limits::Limits;
+
+    #[test]
+    fn test_parse_component() {
+        let data = wat::parse_str(
+            r#"
+            (component
+                (import "host" (instance $host
+                    (export "log" (func (param "msg" string)))))
+                (core module $m
+                    (memory (export "memory") 1)
+                    (func (export "add") (param i32 i32) (result i32)
+                        local.get 0
+                        local.get 1
+                        i32.add))
+                (core instance $i (instantiate $m))
+                (type $color (enum "red" "green"))
+                (type $add (func (param "a" u32) (param "b" u32) (result u32)))
+                (func $add (type $add) (canon lift (core func $i "add")))
+                (export "add" (func $add))
+                (component $inner))
+            "#,
+        )
+        .unwrap();
+        let component = Component::from_bytes(&data).parse().unwrap();
+
+        assert_eq!(component.version, 0x0d);
+        assert_eq!(component.layer, 0x01);
+
+        let imports = component.imports().collect::<Vec<_>>();
+        assert_eq!(imports.len(), 1);
+        assert_eq!(imports[0].name, "host");
+        assert!(matches!(imports[0].ty, ComponentTypeRef::Instance(_)));
+
+        let modules = component.modules().collect::<Vec<_>>();
+        assert_eq!(modules.len(), 1);
+        assert_eq!(modules[0].export_section.0.len(), 2);
+        assert_eq!(modules[0].code_section.0.len(), 1);
+        assert_eq!(component.components().count(), 1);
+
+        assert!(component.sections.iter().any(|section| matches!(
+            section,
+            ComponentSection::CoreInstance(instances)
+                if matches!(instances[0], CoreInstance::Instantiate { module: 0, .. })
+        )));
+        assert!(component.sections.iter().any(|section| matches!(
+            section,
+            ComponentSection::Type(types) if types.iter().any(|ty| matches!(
+                ty,
+                ComponentType::Defined(ComponentDefinedType::Enum(cases)) if cases.len() == 2
+            ))
+        )));
+        assert!(component.sections.iter().any(|section| matches!(
+            section,
+            ComponentSection::Type(types) if types.iter().any(|ty| matches!(
+                ty,
+                ComponentType::Func(func) if func.params.len() == 2
+                    && func.params[0].1 == crate::component::types::ComponentValType::Primitive(PrimitiveValType::U32)
+            ))
+        )));
+        assert!(component.sections.iter().any(|section| matches!(
+            section,
+            ComponentSection::Canon(canons) if matches!(canons[0], Canon::Lift { .. })
+        )));
+
+        let exports = component.exports().collect::<Vec<_>>();
+        assert_eq!(exports.len(), 1);
+        assert_eq!(exports[0].name, "add");
+        assert_eq!(exports[0].sort, ComponentSort::Func);
+    }
+
+    #[test]
+    fn test_parsers_reject_the_other_format() {
+        let data = wat::parse_str("(component)").unwrap();
+        let err = crate::module::Module::from_bytes(&data)
+            .parse()
+            .unwrap_err();
+        assert!(matches!(err.kind(), Error::UnexpectedComponent));
+
+        let data = wat::parse_str("(module)").unwrap();
+        let err = Component::from_bytes(&data).parse().unwrap_err();
+        assert!(matches!(err.kind(), Error::UnexpectedModule));
+    }
+
+    #[test]
+    fn test_nesting_depth() {
+        const HEADER: &[u8] = b"\0asm\x0d\0\x01\0";
+        let section = |id: u8, content: &[u8]| {
+            let mut encoder = Encoder::new();
+            encoder.write_u8(id);
+            encoder.write_var_u32(content.len() as u32);
+            let mut bytes = encoder.finish();
+            bytes.extend_from_slice(content);
+            bytes
+        };
+
+        // Components nested in component sections.
+        let nested = |depth: u32| {
+            let mut data = HEADER.to_vec();
+            for _ in 0..depth {
+                data = [HEADER, &section(0x04, &data)].concat();
+            }
+            data
+        };
+        Component::from_bytes(&nested(MAX_NESTING_DEPTH))
+            .parse()
+            .unwrap();
+        let err = Component::from_bytes(&nested(MAX_NESTING_DEPTH + 1))
+            .parse()
+            .unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            Error::LimitExceeded("component nesting depth", _)
+        ));
+
+        // Instance types declaring instance types, `0x01` declares a type in an instance type.
+        let nested_types = |depth: u32| {
+            let mut ty = vec![0x42, 0x00];
+            for _ in 1..depth {
+                ty = [&[0x42, 0x01, 0x01][..], &ty].concat();
+            }
+            [HEADER, &section(0x07, &[&[0x01][..], &ty].concat())].concat()
+        };
+        Component::from_bytes(&nested_types(MAX_NESTING_DEPTH))
+            .parse()
+            .unwrap();
+        let err = Component::from_bytes(&nested_types(MAX_NESTING_DEPTH + 1))
+            .parse()
+            .unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            Error::LimitExceeded("component type nesting depth", _)
+        ));
+    }
+
+    #[test]
+    fn test_error_locations() {
+        // A core module with an unknown section id, nested in a component.
+        let module = b"\0asm\x01\0\0\0\x20\0";
+        let module_err = crate::module::Module::from_bytes(module)
+            .parse()
+            .unwrap_err();
+        let mut data = b"\0asm\x0d\0\x01\0".to_vec();
+        data.extend([0x01, module.len() as u8]);
+        data.extend(module);
+        let err = Component::from_bytes(&data).parse().unwrap_err();
+        assert!(matches!(err.kind(), Error::InvalidSectionId(0x20)));
+        assert_eq!(err.offset(), Some(module_err.offset().unwrap() + 10));
+        assert_eq!(err.location(), module_err.location());
+
+        // An unknown component type in a type section.
+        let mut data = b"\0asm\x0d\0\x01\0".to_vec();
+        data.extend([0x07, 0x02, 0x01, 0x01]);
+        let err = Component::from_bytes(&data).parse().unwrap_err();
+        assert!(matches!(err.kind(), Error::InvalidLeadingByte(0x01, _)));
+        assert_eq!(err.offset(), Some(data.len()));
+        assert_eq!(
+            err.location(),
+            Some(Location::ComponentSection(ComponentSectionId::Type))
+        );
+    }
+    #[test]
+    fn test_nested_module_options() {
+        let data = wat::parse_str(
+            r#"
+            (component
+                (component
+                    (core module
+                        (func (block (block)))
+                        (func (drop (i8x16.splat (i32.const 0)))))))
+            "#,
+        )
+        .unwrap();
+        Component::from_bytes(&data).parse().unwrap();
+
+        let err = Component::from_bytes(&data)
+            .features(Features::mvp())
+            .parse()
+            .unwrap_err();
+        assert!(matches!(err.kind(), Error::FeatureDisabled("simd")));
+        assert_eq!(err.location(), Some(Location::Function(1)));
+
+        let limits = Limits {
+            nesting_depth: 1,
+            ..Limits::default()
+        };
+        let err = Component::from_bytes(&data)
+            .limits(limits)
+            .parse()
+            .unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            Error::LimitExceeded("nesting depth", 1)
+        ));
+        assert_eq!(err.location(), Some(Location::Function(0)));
+    }
+}
@@ -0,0 +1,179 @@
+use smol_str::SmolStr;
+
+use crate::component::types::{
+    ComponentAlias, ComponentExport, ComponentImport, ComponentSort, ComponentType,
+    ComponentValType, CoreSort, CoreType,
+};
+use crate::component::Component;
+use crate::error::Error;
+use crate::module::Module;
+use crate::section::CustomSection;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComponentSectionId {
+    Custom = 0x00,
+    CoreModule = 0x01,
+    CoreInstance = 0x02,
+    CoreType = 0x03,
+    Component = 0x04,
+    Instance = 0x05,
+    Alias = 0x06,
+    Type = 0x07,
+    Canon = 0x08,
+    Start = 0x09,
+    Import = 0x0a,
+    Export = 0x0b,
+    Value = 0x0c,
+}
+
+impl TryFrom<u8> for ComponentSectionId {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x00 => Ok(ComponentSectionId::Custom),
+            0x01 => Ok(ComponentSectionId::CoreModule),
+            0x02 => Ok(ComponentSectionId::CoreInstance),
+            0x03 => Ok(ComponentSectionId::CoreType),
+            0x04 => Ok(ComponentSectionId::Component),
+            0x05 => Ok(ComponentSectionId::Instance),
+            0x06 => Ok(ComponentSectionId::Alias),
+            0x07 => Ok(ComponentSectionId::Type),
+            0x08 => Ok(ComponentSectionId::Canon),
+            0x09 => Ok(ComponentSectionId::Start),
+            0x0a => Ok(ComponentSectionId::Import),
+            0x0b => Ok(ComponentSectionId::Export),
+            0x0c => Ok(ComponentSectionId::Value),
+            _ => Err(Error::InvalidSectionId(value)),
+        }
+    }
+}
+
+/// Sections of a component, kept in binary order,
+/// since they may repeat and each one extends the index spaces defined before it.
+#[derive(Debug)]
+pub enum ComponentSection<'a> {
+    Custom(CustomSection<'a>),
+    CoreModule(Box<Module<'a>>),
+    CoreInstance(Vec<CoreInstance>),
+    CoreType(Vec<CoreType>),
+    Component(Component<'a>),
+    Instance(Vec<ComponentInstance>),
+    Alias(Vec<ComponentAlias>),
+    Type(Vec<ComponentType>),
+    Canon(Vec<Canon>),
+    Start(ComponentStart),
+    Import(Vec<ComponentImport>),
+    Export(Vec<ComponentExport>),
+    Value(Vec<ComponentValue>),
+}
+
+impl ComponentSection<'_> {
+    pub fn into_owned(self) -> ComponentSection<'static> {
+        match self {
+            ComponentSection::Custom(section) => ComponentSection::Custom(section.into_owned()),
+            ComponentSection::CoreModule(module) => {
+                ComponentSection::CoreModule(Box::new(module.into_owned()))
+            }
+            ComponentSection::CoreInstance(instances) => ComponentSection::CoreInstance(instances),
+            ComponentSection::CoreType(types) => ComponentSection::CoreType(types),
+            ComponentSection::Component(component) => {
+                ComponentSection::Component(component.into_owned())
+            }
+            ComponentSection::Instance(instances) => ComponentSection::Instance(instances),
+            ComponentSection::Alias(aliases) => ComponentSection::Alias(aliases),
+            ComponentSection::Type(types) => ComponentSection::Type(types),
+            ComponentSection::Canon(canons) => ComponentSection::Canon(canons),
+            ComponentSection::Start(start) => ComponentSection::Start(start),
+            ComponentSection::Import(imports) => ComponentSection::Import(imports),
+            ComponentSection::Export(exports) => ComponentSection::Export(exports),
+            ComponentSection::Value(values) => ComponentSection::Value(values),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CoreInstance {
+    Instantiate {
+        module: u32,
+        args: Vec<CoreInstantiationArg>,
+    },
+    FromExports(Vec<CoreInlineExport>),
+}
+
+/// `name 0x12 instanceidx`, only core instances can be passed as arguments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoreInstantiationArg {
+    pub name: SmolStr,
+    pub instance: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoreInlineExport {
+    pub name: SmolStr,
+    pub sort: CoreSort,
+    pub index: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ComponentInstance {
+    Instantiate {
+        component: u32,
+        args: Vec<ComponentInstantiationArg>,
+    },
+    FromExports(Vec<ComponentInlineExport>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComponentInstantiationArg {
+    pub name: SmolStr,
+    pub sort: ComponentSort,
+    pub index: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComponentInlineExport {
+    pub name: SmolStr,
+    pub sort: ComponentSort,
+    pub index: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Canon {
+    Lift {
+        core_func: u32,
+        options: Vec<CanonOption>,
+        ty: u32,
+    },
+    Lower {
+        func: u32,
+        options: Vec<CanonOption>,
+    },
+    ResourceNew(u32),
+    ResourceDrop(u32),
+    ResourceRep(u32),
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum CanonOption {
+    Utf8,
+    Utf16,
+    CompactUtf16,
+    Memory(u32),
+    Realloc(u32),
+    PostReturn(u32),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComponentStart {
+    pub func: u32,
+    pub args: Vec<u32>,
+    pub results: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComponentValue {
+    pub ty: ComponentValType,
+    /// The encoded value, see `val(t)` in the component binary format.
+    pub data: Vec<u8>,
+}
@@ -0,0 +1,208 @@
+use smol_str::SmolStr;
+
+use crate::error::Error;
+use crate::section::{Import, ImportKind};
+use crate::types::{ty_enum, SubType, ValType};
+
+ty_enum!(PrimitiveValType {
+    Bool = 0x7f,
+    S8 = 0x7e,
+    U8 = 0x7d,
+    S16 = 0x7c,
+    U16 = 0x7b,
+    S32 = 0x7a,
+    U32 = 0x79,
+    S64 = 0x78,
+    U64 = 0x77,
+    F32 = 0x76,
+    F64 = 0x75,
+    Char = 0x74,
+    String = 0x73,
+});
+
+/// Sorts of the core index spaces, as referenced from a component.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum CoreSort {
+    Func = 0x00,
+    Table = 0x01,
+    Memory = 0x02,
+    Global = 0x03,
+    Tag = 0x04,
+    Type = 0x10,
+    Module = 0x11,
+    Instance = 0x12,
+}
+
+impl TryFrom<u8> for CoreSort {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0x00 => CoreSort::Func,
+            0x01 => CoreSort::Table,
+            0x02 => CoreSort::Memory,
+            0x03 => CoreSort::Global,
+            0x04 => CoreSort::Tag,
+            0x10 => CoreSort::Type,
+            0x11 => CoreSort::Module,
+            0x12 => CoreSort::Instance,
+            _ => return Err(Error::InvalidLeadingByte(value, "core sort")),
+        })
+    }
+}
+
+/// Sorts of the component index spaces, `0x00` prefixes a core sort.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum ComponentSort {
+    Core(CoreSort),
+    Func,
+    Value,
+    Type,
+    Component,
+    Instance,
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum ComponentValType {
+    Primitive(PrimitiveValType),
+    Type(u32),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CoreType {
+    Sub(SubType),
+    Module(Vec<ModuleTypeDecl>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ModuleTypeDecl {
+    Import(Import<'static>),
+    Type(SubType),
+    OuterAlias {
+        sort: CoreSort,
+        count: u32,
+        index: u32,
+    },
+    Export {
+        name: SmolStr,
+        ty: ImportKind,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ComponentType {
+    Defined(ComponentDefinedType),
+    Func(ComponentFuncType),
+    Component(Vec<ComponentTypeDecl>),
+    Instance(Vec<InstanceTypeDecl>),
+    Resource {
+        /// Core representation of the resource, currently always `i32`.
+        rep: ValType,
+        dtor: Option<u32>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ComponentDefinedType {
+    Primitive(PrimitiveValType),
+    Record(Vec<(SmolStr, ComponentValType)>),
+    Variant(Vec<VariantCase>),
+    List(ComponentValType),
+    Tuple(Vec<ComponentValType>),
+    Flags(Vec<SmolStr>),
+    Enum(Vec<SmolStr>),
+    Option(ComponentValType),
+    Result {
+        ok: Option<ComponentValType>,
+        err: Option<ComponentValType>,
+    },
+    Own(u32),
+    Borrow(u32),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VariantCase {
+    pub name: SmolStr,
+    pub ty: Option<ComponentValType>,
+    pub refines: Option<u32>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComponentFuncType {
+    pub params: Vec<(SmolStr, ComponentValType)>,
+    pub results: ComponentFuncResult,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ComponentFuncResult {
+    Unnamed(ComponentValType),
+    Named(Vec<(SmolStr, ComponentValType)>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ComponentTypeDecl {
+    CoreType(CoreType),
+    Type(ComponentType),
+    Alias(ComponentAlias),
+    Import(ComponentImport),
+    Export { name: SmolStr, ty: ComponentTypeRef },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InstanceTypeDecl {
+    CoreType(CoreType),
+    Type(ComponentType),
+    Alias(ComponentAlias),
+    Export { name: SmolStr, ty: ComponentTypeRef },
+}
+
+/// `externdesc`, the type of an import or export.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum ComponentTypeRef {
+    /// Index of a core module type.
+    Module(u32),
+    Func(u32),
+    Value(ComponentValType),
+    Type(TypeBound),
+    Instance(u32),
+    Component(u32),
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum TypeBound {
+    Eq(u32),
+    SubResource,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ComponentAlias {
+    InstanceExport {
+        sort: ComponentSort,
+        instance: u32,
+        name: SmolStr,
+    },
+    CoreInstanceExport {
+        sort: CoreSort,
+        instance: u32,
+        name: SmolStr,
+    },
+    Outer {
+        sort: ComponentSort,
+        count: u32,
+        index: u32,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComponentImport {
+    pub name: SmolStr,
+    pub ty: ComponentTypeRef,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComponentExport {
+    pub name: SmolStr,
+    pub sort: ComponentSort,
+    pub index: u32,
+    pub ty: Option<ComponentTypeRef>,
+}
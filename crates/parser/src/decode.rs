@@ -3,38 +3,65 @@ use std::io::{Cursor, Read};
 use smol_str::SmolStr;
 
 use crate::error::Error;
-use crate::instruction::{ConstExpr, Expr, Instruction};
+use crate::features::Features;
+use crate::instruction::{ConstExpr, Expr, Instruction, InstructionReader};
 use crate::types::{
-    BlockType, GlobalType, HeapType, Limit, MemoryType, RefType, ResultType, TableType, ValType,
+    ArrayType, BlockType, CompositeType, FieldType, FuncType, GlobalType, HeapType, IndexType,
+    Limit, MemoryType, RefType, ResultType, StorageType, StructType, SubType, TableType,
+    TagAttribute, TagType, ValType,
 };
 
 #[derive(Clone)]
 pub struct Decoder<'a> {
     pub reader: Cursor<&'a [u8]>,
+    /// Offset of `reader`'s first byte in the whole binary.
+    base: usize,
+    /// Proposals whose encodings are accepted, see [Decoder::with_features].
+    pub(crate) features: Features,
 }
 
 impl<'a> Decoder<'a> {
     pub fn new(bytes: &'a [u8]) -> Self {
+        Self::new_with_offset(bytes, 0)
+    }
+
+    /// A decoder over a part of a binary starting at `offset`, so that errors point into the binary.
+    pub fn new_with_offset(bytes: &'a [u8], offset: usize) -> Self {
         Decoder {
             reader: Cursor::new(bytes),
+            base: offset,
+            features: Features::all(),
         }
     }
 
+    /// Reject encodings that only proposals disabled in `features` define,
+    /// the rest of a module is checked once parsed, see [Features].
+    pub(crate) fn with_features(mut self, features: Features) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// Offset of the next byte in the whole binary.
+    pub fn offset(&self) -> usize {
+        self.base + self.reader.position() as usize
+    }
+
     pub fn is_empty(&mut self) -> bool {
         self.reader.get_ref().len() as u64 - self.reader.position() <= 1
     }
 
     /// copy from [Cursor::remaining_slice], because it is unstable
-    pub fn remaining_slice(&self) -> &[u8] {
-        let len = self
-            .reader
-            .position()
-            .min(self.reader.get_ref().len() as u64);
-        &self.reader.get_ref()[(len as usize)..]
+    pub fn remaining_slice(&self) -> &'a [u8] {
+        let bytes: &'a [u8] = self.reader.get_ref();
+        let len = self.reader.position().min(bytes.len() as u64);
+        &bytes[(len as usize)..]
     }
 
-    pub fn peek(&self) -> u8 {
-        self.reader.get_ref()[self.reader.position() as usize]
+    pub fn peek(&self) -> Result<u8, Error> {
+        self.remaining_slice()
+            .first()
+            .copied()
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into())
     }
 
     pub fn read_n<const N: usize>(&mut self) -> Result<[u8; N], Error> {
@@ -46,20 +73,30 @@ impl<'a> Decoder<'a> {
     }
 
     pub fn read_bytes(&mut self, len: usize) -> Result<Vec<u8>, Error> {
-        let mut buf = vec![0u8; len];
-        self.reader
-            .read_exact(&mut buf)
-            .map(|_| buf)
-            .map_err(Into::into)
+        self.read_slice(len).map(<[u8]>::to_vec)
     }
 
-    pub fn read_str(&mut self) -> Result<SmolStr, Error> {
+    /// Read `len` bytes without copying, the slice borrows from the input.
+    pub fn read_slice(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        let bytes: &'a [u8] = self.reader.get_ref();
+        let start = self.reader.position() as usize;
+        let slice = start
+            .checked_add(len)
+            .and_then(|end| bytes.get(start..end))
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::UnexpectedEof))?;
+        self.reader.set_position((start + len) as u64);
+        Ok(slice)
+    }
+
+    /// Read a name without copying, the str borrows from the input.
+    pub fn read_name(&mut self) -> Result<&'a str, Error> {
         let len = self.read_var_u32()? as usize;
-        let end = self.reader.position() as usize + len;
-        let buf = &self.remaining_slice()[..len];
-        let s = SmolStr::new(std::str::from_utf8(&buf)?);
-        self.reader.set_position(end as u64);
-        Ok(s)
+        let bytes = self.read_slice(len)?;
+        std::str::from_utf8(bytes).map_err(Into::into)
+    }
+
+    pub fn read_str(&mut self) -> Result<SmolStr, Error> {
+        self.read_name().map(SmolStr::new)
     }
 
     pub fn read_u8(&mut self) -> Result<u8, Error> {
@@ -77,11 +114,22 @@ impl<'a> Decoder<'a> {
     }
 
     pub fn read_var_u32(&mut self) -> Result<u32, Error> {
+        // Most indices and opcodes fit in a single byte.
+        if let Some(&byte) = self.remaining_slice().first() {
+            if byte & 0x80 == 0 {
+                self.reader.set_position(self.reader.position() + 1);
+                return Ok(byte as u32);
+            }
+        }
         leb128::read::unsigned(&mut self.reader)
             .map(|i| i as u32)
             .map_err(Into::into)
     }
 
+    pub fn read_var_u64(&mut self) -> Result<u64, Error> {
+        leb128::read::unsigned(&mut self.reader).map_err(Into::into)
+    }
+
     pub fn read_var_i64(&mut self) -> Result<i64, Error> {
         leb128::read::signed(&mut self.reader).map_err(Into::into)
     }
@@ -125,25 +173,35 @@ impl<'a> Decoder<'a> {
         Ok((result << ashift) >> ashift)
     }
 
+    /// How many of `len` items of type `T` to allocate upfront,
+    /// so that a length prefix never reserves more memory than the remaining input.
+    pub fn capacity<T>(&self, len: u32) -> usize {
+        let remaining = self.remaining_slice().len() / std::mem::size_of::<T>().max(1);
+        remaining.min(len as usize)
+    }
+
     pub fn read_svec<F, T>(&mut self, ele: F) -> Result<crate::SVec<T>, Error>
     where
         F: Fn(&mut Self) -> Result<T, Error>,
     {
         let len = self.read_var_u32()?;
-        (0..len)
-            .map(|_| ele(self))
-            .collect::<Result<crate::SVec<_>, _>>()
+        let mut items = crate::SVec::with_capacity(self.capacity::<T>(len));
+        for _ in 0..len {
+            items.push(ele(self)?);
+        }
+        Ok(items)
     }
 
-    
     pub fn read_vec<F, T>(&mut self, ele: F) -> Result<Vec<T>, Error>
     where
         F: Fn(&mut Self) -> Result<T, Error>,
     {
         let len = self.read_var_u32()?;
-        (0..len)
-            .map(|_| ele(self))
-            .collect::<Result<Vec<_>, _>>()
+        let mut items = Vec::with_capacity(self.capacity::<T>(len));
+        for _ in 0..len {
+            items.push(ele(self)?);
+        }
+        Ok(items)
     }
 
     pub fn slice_with(&mut self, len: u64) -> Self {
@@ -152,45 +210,131 @@ impl<'a> Decoder<'a> {
         self.reader.set_position(end);
         Decoder {
             reader: Cursor::new(&self.reader.get_ref()[pos as usize..end as usize]),
+            base: self.base + pos as usize,
+            features: self.features,
         }
     }
 
     // wasm type
 
     pub fn read_valtype(&mut self) -> Result<ValType, Error> {
-        ValType::try_from(self.read_u8()?)
+        match self.read_u8()? {
+            0x63 => self
+                .read_heaptype()
+                .map(RefType::nullable)
+                .map(ValType::Ref),
+            0x64 => self
+                .read_heaptype()
+                .map(RefType::non_null)
+                .map(ValType::Ref),
+            byte => ValType::try_from(byte),
+        }
     }
 
     pub fn read_reftype(&mut self) -> Result<RefType, Error> {
-        RefType::try_from(self.read_u8()?)
+        match self.read_u8()? {
+            0x63 => self.read_heaptype().map(RefType::nullable),
+            0x64 => self.read_heaptype().map(RefType::non_null),
+            byte => RefType::try_from(byte),
+        }
+    }
+
+    /// The element type of segments listing function indices, `0x00` is the only elemkind.
+    pub fn read_elemkind(&mut self) -> Result<RefType, Error> {
+        match self.read_u8()? {
+            0x00 => Ok(RefType::non_null(HeapType::Func)),
+            byte => Err(Error::InvalidElmType(byte)),
+        }
+    }
+
+    pub fn read_storagetype(&mut self) -> Result<StorageType, Error> {
+        match self.peek()? {
+            0x78 | 0x77 => StorageType::try_from(self.read_u8()?),
+            _ => self.read_valtype().map(StorageType::Val),
+        }
     }
 
     pub fn read_resulttype(&mut self) -> Result<ResultType, Error> {
-        let len = self.read_var_u32()?;
+        self.read_svec(Self::read_valtype).map(ResultType)
+    }
 
-        Ok((0..len)
-            .map(|_| self.read_valtype())
-            .collect::<Result<crate::SVec<_>, _>>()
-            .map(ResultType)?)
+    pub fn read_subtype(&mut self) -> Result<SubType, Error> {
+        let tag = self.read_u8()?;
+        match tag {
+            0x50 | 0x4f => {
+                let supertypes = self.read_svec(Decoder::read_var_u32)?;
+                let composite_tag = self.read_u8()?;
+                Ok(SubType {
+                    is_final: tag == 0x4f,
+                    supertypes,
+                    composite: self.read_composite_type(composite_tag)?,
+                })
+            }
+            _ => self.read_composite_type(tag).map(SubType::from),
+        }
     }
 
-    pub fn read_limit(&mut self) -> Result<Limit, Error> {
-        let limited = self.read_u8()? == 0x01;
-        let min = self.read_var_u32()?;
-        Ok(Limit {
-            min,
-            max: limited.then(|| self.read_var_u32()).transpose()?,
+    pub fn read_composite_type(&mut self, tag: u8) -> Result<CompositeType, Error> {
+        Ok(match tag {
+            0x60 => {
+                let params = self.read_resulttype()?;
+                let results = self.read_resulttype()?;
+                CompositeType::Func(FuncType { params, results })
+            }
+            0x5f => CompositeType::Struct(StructType {
+                fields: self.read_svec(Decoder::read_fieldtype)?,
+            }),
+            0x5e => CompositeType::Array(ArrayType(self.read_fieldtype()?)),
+            _ => return Err(Error::InvalidTypeTag(tag)),
         })
     }
 
+    pub fn read_fieldtype(&mut self) -> Result<FieldType, Error> {
+        let ty = self.read_storagetype()?;
+        let mutable = self.read_u8()? == 0x01;
+        Ok(FieldType { ty, mutable })
+    }
+
+    /// The limits flags also carry the memory kind:
+    /// bit 0 a maximum follows, bit 1 shared, bit 2 64-bit addresses.
     pub fn read_memtype(&mut self) -> Result<MemoryType, Error> {
-        self.read_limit().map(MemoryType)
+        let flags = self.read_u8()?;
+        if flags & !0x07 != 0 {
+            return Err(Error::InvalidFlags(flags as u32, "memory limits"));
+        }
+        let index_type = index_type(flags);
+        Ok(MemoryType {
+            limit: self.read_limit(flags, index_type)?,
+            shared: flags & 0x02 != 0,
+            index_type,
+        })
     }
 
+    /// Tables share the limits flags of memories, except they cannot be shared.
     pub fn read_tabletype(&mut self) -> Result<TableType, Error> {
         let element = self.read_reftype()?;
-        let limit = self.read_limit()?;
-        Ok(TableType { element, limit })
+        let flags = self.read_u8()?;
+        if flags & !0x05 != 0 {
+            return Err(Error::InvalidFlags(flags as u32, "table limits"));
+        }
+        let index_type = index_type(flags);
+        Ok(TableType {
+            element,
+            limit: self.read_limit(flags, index_type)?,
+            index_type,
+        })
+    }
+
+    fn read_limit(&mut self, flags: u8, index_type: IndexType) -> Result<Limit, Error> {
+        let read = |decoder: &mut Self| match index_type {
+            IndexType::I32 => decoder.read_var_u32().map(u64::from),
+            IndexType::I64 => decoder.read_var_u64(),
+        };
+        let min = read(self)?;
+        Ok(Limit {
+            min,
+            max: (flags & 0x01 != 0).then(|| read(self)).transpose()?,
+        })
     }
 
     pub fn read_globaltype(&mut self) -> Result<GlobalType, Error> {
@@ -202,6 +346,15 @@ impl<'a> Decoder<'a> {
         })
     }
 
+    pub fn read_tagtype(&mut self) -> Result<TagType, Error> {
+        let attribute = TagAttribute::try_from(self.read_u8()?)?;
+        let type_idx = self.read_var_u32()?;
+        Ok(TagType {
+            attribute,
+            type_idx,
+        })
+    }
+
     pub fn read_const_expr(&mut self) -> Result<ConstExpr, Error> {
         let mut instrs = Vec::new();
         loop {
@@ -211,6 +364,7 @@ impl<'a> Decoder<'a> {
             if !instr.is_const() && !is_end {
                 return Err(Error::InvalidConstExprOpcode(instr));
             }
+            self.features.const_instruction(&instr)?;
 
             instrs.push(instr);
 
@@ -222,34 +376,14 @@ impl<'a> Decoder<'a> {
     }
 
     pub fn read_expr(&mut self) -> Result<Expr, Error> {
-        let mut block_depth = 0;
-        let mut instrs = Vec::new();
-        loop {
-            let instr = self.read_instruction()?;
-            let is_end = instr == Instruction::End;
-            let start_block = matches!(
-                &instr,
-                Instruction::Block(_)
-                    | Instruction::Loop(_)
-                    | Instruction::If(_)
-                    | Instruction::Try(_)
-                    | Instruction::TryTable(_)
-            );
-
-            instrs.push(instr);
-
-            if start_block {
-                block_depth += 1;
-            }
-
-            if is_end {
-                if block_depth == 0 {
-                    break;
-                }
-                block_depth -= 1;
-            }
-        }
-        Ok(Expr(instrs))
+        let mut reader = InstructionReader::new_with_offset(self.remaining_slice(), self.offset());
+        let instrs = reader.by_ref().collect::<Result<Vec<_>, _>>()?;
+        self.reader
+            .set_position(self.reader.position() + reader.position() as u64);
+        Ok(Expr {
+            instrs,
+            offsets: None,
+        })
     }
 
     pub fn read_instruction(&mut self) -> Result<Instruction, Error> {
@@ -257,11 +391,13 @@ impl<'a> Decoder<'a> {
     }
 
     pub fn read_block_type(&mut self) -> Result<BlockType, Error> {
-        let peek = self.peek();
+        let peek = self.peek()?;
 
         Ok(if peek == 0x40 {
             self.read_u8()?;
             BlockType::Empty
+        } else if matches!(peek, 0x63 | 0x64) {
+            BlockType::Type(self.read_valtype()?)
         } else if let Ok(valtype) = ValType::try_from(peek) {
             self.read_u8()?;
             BlockType::Type(valtype)
@@ -275,3 +411,11 @@ impl<'a> Decoder<'a> {
         HeapType::try_from(self.read_var_s33()?)
     }
 }
+
+fn index_type(limits_flags: u8) -> IndexType {
+    if limits_flags & 0x04 != 0 {
+        IndexType::I64
+    } else {
+        IndexType::I32
+    }
+}
@@ -0,0 +1,351 @@
+//! DWARF debug info carried in the `.debug_*` custom sections.
+//!
+//! https://yurydelendik.github.io/webassembly-dwarf/
+//!
+//! Addresses in wasm DWARF are relative to the start of the code section contents,
+//! [DebugInfo] takes and returns offsets in the binary instead, the same offsets as
+//! [Code::offset] and [InstructionReader::offset](crate::instruction::InstructionReader::offset).
+
+use std::borrow::Cow;
+
+use addr2line::gimli::{Dwarf, EndianSlice, LittleEndian};
+use addr2line::Context;
+
+use crate::error::Error;
+use crate::module::Module;
+use crate::section::Code;
+
+type Reader<'a> = EndianSlice<'a, LittleEndian>;
+
+/// Source lookups over the DWARF sections of a module.
+///
+/// Units, line programs and functions are parsed lazily on the first lookup that needs them.
+pub struct DebugInfo<'a> {
+    context: Context<Reader<'a>>,
+    code_section_offset: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SourceLocation {
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    /// `Some(0)` is the start of the line.
+    pub column: Option<u32>,
+}
+
+impl From<addr2line::Location<'_>> for SourceLocation {
+    fn from(location: addr2line::Location<'_>) -> Self {
+        SourceLocation {
+            file: location.file.map(str::to_owned),
+            line: location.line,
+            column: location.column,
+        }
+    }
+}
+
+/// A function active at an offset, see [DebugInfo::find_frames].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// The name as recorded in DWARF, possibly mangled.
+    pub function: Option<String>,
+    /// The executing line in the innermost frame, the call site of the inner frame otherwise.
+    pub location: Option<SourceLocation>,
+}
+
+/// Instructions in `start..end` map to `location`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SourceRange {
+    pub start: usize,
+    pub end: usize,
+    pub location: SourceLocation,
+}
+
+impl<'a> DebugInfo<'a> {
+    /// Load the DWARF sections of `module`, missing sections are treated as empty.
+    pub fn new(module: &'a Module<'_>) -> Result<Self, Error> {
+        let dwarf = Dwarf::load(|id| {
+            let data = module
+                .custom_section(id.name())
+                .map_or(&[][..], |section| &section.data);
+            Ok::<_, Error>(EndianSlice::new(data, LittleEndian))
+        })?;
+        Ok(DebugInfo {
+            context: Context::from_dwarf(dwarf)?,
+            code_section_offset: module.code_section_offset,
+        })
+    }
+
+    /// The source location of the instruction at `offset`.
+    pub fn find_location(&self, offset: usize) -> Result<Option<SourceLocation>, Error> {
+        let Some(address) = self.address(offset) else {
+            return Ok(None);
+        };
+        Ok(self
+            .context
+            .find_location(address)?
+            .map(SourceLocation::from))
+    }
+
+    /// The functions executing the instruction at `offset`, innermost first.
+    ///
+    /// There is more than one frame only if the instruction belongs to an inlined function,
+    /// the last frame is the function containing the code.
+    pub fn find_frames(&self, offset: usize) -> Result<Vec<Frame>, Error> {
+        let mut frames = Vec::new();
+        let Some(address) = self.address(offset) else {
+            return Ok(frames);
+        };
+        let mut iter = self.context.find_frames(address).skip_all_loads()?;
+        while let Some(frame) = iter.next()? {
+            frames.push(Frame {
+                function: frame
+                    .function
+                    .as_ref()
+                    .map(|name| name.raw_name().map(Cow::into_owned))
+                    .transpose()?,
+                location: frame.location.map(SourceLocation::from),
+            });
+        }
+        Ok(frames)
+    }
+
+    /// The source locations of the body of `code`, in increasing offset order.
+    pub fn function_ranges(&self, code: &Code) -> Result<Vec<SourceRange>, Error> {
+        // The entry starts with the locals, `size` does not count its own LEB128 bytes.
+        let end = code.offset + code.body.len();
+        let start = end - code.size as usize;
+        let (Some(low), Some(high)) = (self.address(start), self.address(end)) else {
+            return Ok(Vec::new());
+        };
+
+        // The range iterator drops errors, surface them first.
+        self.context.parse_lines()?;
+        Ok(self
+            .context
+            .find_location_range(low, high)?
+            .map(|(address, len, location)| SourceRange {
+                start: self.offset(address).max(start),
+                end: self.offset(address + len).min(end),
+                location: location.into(),
+            })
+            .collect())
+    }
+
+    fn address(&self, offset: usize) -> Option<u64> {
+        offset
+            .checked_sub(self.code_section_offset)
+            .map(|address| address as u64)
+    }
+
+    fn offset(&self, address: u64) -> usize {
+        self.code_section_offset + address as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gimli::write::{
+        Address, AttributeValue, DwarfUnit, EndianVec, LineProgram, LineString, Sections,
+    };
+    use gimli::{Encoding, Format, LineEncoding, LittleEndian};
+
+    use super::{Frame, SourceLocation, SourceRange};
+    use crate::module::Module;
+    use crate::section::CustomSection;
+
+    fn location(line: u32, column: u32) -> Option<SourceLocation> {
+        Some(SourceLocation {
+            file: Some("main.c".into()),
+            line: Some(line),
+            column: Some(column),
+        })
+    }
+
+    #[test]
+    fn test_debug_info_lookups() {
+        let data = wat::parse_str(
+            r#"
+            (module
+                (func (export "f") (param i32) (result i32)
+                    local.get 0
+                    i32.const 1
+                    i32.add
+                    local.get 0
+                    local.get 0
+                    i32.mul
+                    i32.add))
+            "#,
+        )
+        .unwrap();
+        let mut module = Module::from_bytes(&data).parse().unwrap();
+        let code = &module.code_section.0[0];
+        let offsets = code.expr_with_offsets().unwrap().offsets.unwrap();
+        let end = code.offset + code.body.len();
+        let start = end - code.size as usize;
+        let address = |offset: usize| (offset - module.code_section_offset) as u64;
+
+        // `f` at main.c:1 computes `x + 1 + square(x)`, the body of `square` at main.c:11 is inlined.
+        let encoding = Encoding {
+            format: Format::Dwarf32,
+            version: 5,
+            address_size: 4,
+        };
+        let mut dwarf = DwarfUnit::new(encoding);
+        let mut program = LineProgram::new(
+            encoding,
+            LineEncoding::default(),
+            LineString::String(b"/src".to_vec()),
+            LineString::String(b"main.c".to_vec()),
+            None,
+        );
+        let file = program.add_file(
+            LineString::String(b"main.c".to_vec()),
+            program.default_directory(),
+            None,
+        );
+        program.begin_sequence(Some(Address::Constant(address(start))));
+        for (offset, line, column) in [
+            (start, 1, 0),
+            (offsets[0], 2, 12),
+            (offsets[3], 11, 12),
+            (offsets[6], 2, 16),
+        ] {
+            let row = program.row();
+            row.address_offset = address(offset) - address(start);
+            row.file = file;
+            row.line = line;
+            row.column = column;
+            program.generate_row();
+        }
+        program.end_sequence(address(end) - address(start));
+        dwarf.unit.line_program = program;
+
+        let root = dwarf.unit.root();
+        let entry = dwarf.unit.get_mut(root);
+        entry.set(
+            gimli::DW_AT_name,
+            AttributeValue::String(b"main.c".to_vec()),
+        );
+        entry.set(
+            gimli::DW_AT_low_pc,
+            AttributeValue::Address(Address::Constant(address(start))),
+        );
+        entry.set(
+            gimli::DW_AT_high_pc,
+            AttributeValue::Udata(address(end) - address(start)),
+        );
+
+        let square = dwarf.unit.add(root, gimli::DW_TAG_subprogram);
+        let entry = dwarf.unit.get_mut(square);
+        entry.set(
+            gimli::DW_AT_name,
+            AttributeValue::String(b"square".to_vec()),
+        );
+        entry.set(
+            gimli::DW_AT_inline,
+            AttributeValue::Inline(gimli::DW_INL_inlined),
+        );
+
+        let f = dwarf.unit.add(root, gimli::DW_TAG_subprogram);
+        let entry = dwarf.unit.get_mut(f);
+        entry.set(gimli::DW_AT_name, AttributeValue::String(b"f".to_vec()));
+        entry.set(
+            gimli::DW_AT_low_pc,
+            AttributeValue::Address(Address::Constant(address(start))),
+        );
+        entry.set(
+            gimli::DW_AT_high_pc,
+            AttributeValue::Udata(address(end) - address(start)),
+        );
+
+        let inlined = dwarf.unit.add(f, gimli::DW_TAG_inlined_subroutine);
+        let entry = dwarf.unit.get_mut(inlined);
+        entry.set(
+            gimli::DW_AT_abstract_origin,
+            AttributeValue::UnitRef(square),
+        );
+        entry.set(
+            gimli::DW_AT_low_pc,
+            AttributeValue::Address(Address::Constant(address(offsets[3]))),
+        );
+        entry.set(
+            gimli::DW_AT_high_pc,
+            AttributeValue::Udata(address(offsets[6]) - address(offsets[3])),
+        );
+        entry.set(
+            gimli::DW_AT_call_file,
+            AttributeValue::FileIndex(Some(file)),
+        );
+        entry.set(gimli::DW_AT_call_line, AttributeValue::Udata(2));
+        entry.set(gimli::DW_AT_call_column, AttributeValue::Udata(16));
+
+        let mut sections = Sections::new(EndianVec::new(LittleEndian));
+        dwarf.write(&mut sections).unwrap();
+        sections
+            .for_each(|id, data| {
+                module
+                    .custom_sections
+                    .push(CustomSection::new(id.name(), data.slice().to_vec()));
+                gimli::Result::Ok(())
+            })
+            .unwrap();
+
+        let debug_info = module.debug_info().unwrap();
+        assert_eq!(
+            debug_info.find_location(offsets[1]).unwrap(),
+            location(2, 12)
+        );
+        assert_eq!(
+            debug_info.find_location(offsets[5]).unwrap(),
+            location(11, 12)
+        );
+        assert_eq!(debug_info.find_location(0).unwrap(), None);
+        assert_eq!(
+            debug_info.find_frames(offsets[4]).unwrap(),
+            [
+                Frame {
+                    function: Some("square".into()),
+                    location: location(11, 12),
+                },
+                Frame {
+                    function: Some("f".into()),
+                    location: location(2, 16),
+                },
+            ]
+        );
+        assert_eq!(
+            debug_info.find_frames(offsets[2]).unwrap(),
+            [Frame {
+                function: Some("f".into()),
+                location: location(2, 12),
+            }]
+        );
+
+        let code = &module.code_section.0[0];
+        assert_eq!(
+            debug_info.function_ranges(code).unwrap(),
+            [
+                SourceRange {
+                    start,
+                    end: offsets[0],
+                    location: location(1, 0).unwrap(),
+                },
+                SourceRange {
+                    start: offsets[0],
+                    end: offsets[3],
+                    location: location(2, 12).unwrap(),
+                },
+                SourceRange {
+                    start: offsets[3],
+                    end: offsets[6],
+                    location: location(11, 12).unwrap(),
+                },
+                SourceRange {
+                    start: offsets[6],
+                    end,
+                    location: location(2, 16).unwrap(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,535 @@
+use crate::instruction::{ConstExpr, Instruction};
+use crate::linking::Dylink;
+use crate::module::Module;
+use crate::section::{
+    CustomSection, DataKind, Element, ElementKind, ExportKind, ImportKind, SectionId, TypeSectionTy,
+};
+use crate::types::{
+    BlockType, CompositeType, FieldType, GlobalType, HeapType, IndexType, Limit, MemoryType,
+    RefType, ResultType, StorageType, SubType, TableType, TagType, ValType,
+};
+
+/// Write-side counterpart of [Decoder](crate::decode::Decoder).
+#[derive(Clone, Debug, Default)]
+pub struct Encoder {
+    pub bytes: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Encoder::default()
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    pub fn write_u8(&mut self, byte: u8) {
+        self.bytes.push(byte);
+    }
+
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_var_u32(&mut self, value: u32) {
+        self.write_var_u64(value as u64);
+    }
+
+    pub fn write_var_u64(&mut self, mut value: u64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                self.write_u8(byte);
+                break;
+            }
+            self.write_u8(byte | 0x80);
+        }
+    }
+
+    pub fn write_var_i32(&mut self, value: i32) {
+        self.write_var_i64(value as i64);
+    }
+
+    pub fn write_var_i64(&mut self, mut value: i64) {
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            let done = (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0);
+            if done {
+                self.write_u8(byte);
+                break;
+            }
+            self.write_u8(byte | 0x80);
+        }
+    }
+
+    pub fn write_str(&mut self, s: &str) {
+        self.write_var_u32(s.len() as u32);
+        self.write_bytes(s.as_bytes());
+    }
+
+    pub fn write_vec<I, F>(&mut self, items: I, ele: F)
+    where
+        I: IntoIterator,
+        I::IntoIter: ExactSizeIterator,
+        F: Fn(&mut Self, I::Item),
+    {
+        let items = items.into_iter();
+        self.write_var_u32(items.len() as u32);
+        items.for_each(|item| ele(self, item));
+    }
+
+    /// Write `len` followed by the bytes produced by `content`,
+    /// the framing shared by sections and subsections.
+    pub fn write_sized<F>(&mut self, content: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        let mut inner = Encoder::new();
+        content(&mut inner);
+        self.write_var_u32(inner.bytes.len() as u32);
+        self.write_bytes(&inner.bytes);
+    }
+
+    // wasm type
+
+    /// Nullable abstract references use their single byte abbreviation.
+    pub fn write_valtype(&mut self, ty: ValType) {
+        match ty {
+            ValType::I32 => self.write_u8(0x7f),
+            ValType::I64 => self.write_u8(0x7e),
+            ValType::F32 => self.write_u8(0x7d),
+            ValType::F64 => self.write_u8(0x7c),
+            ValType::V128 => self.write_u8(0x7b),
+            ValType::Ref(ty) => self.write_reftype(ty),
+        }
+    }
+
+    pub fn write_reftype(&mut self, ty: RefType) {
+        match (ty.nullable, ty.heap_type) {
+            (true, HeapType::Concrete(_)) => self.write_u8(0x63),
+            (true, heap_type) => return self.write_heaptype(heap_type),
+            (false, _) => self.write_u8(0x64),
+        }
+        self.write_heaptype(ty.heap_type);
+    }
+
+    /// Abstract heap types are single byte negative s33s, type indices non-negative ones.
+    pub fn write_heaptype(&mut self, ty: HeapType) {
+        let byte = match ty {
+            HeapType::NoExn => 0x74,
+            HeapType::NoFunc => 0x73,
+            HeapType::NoExtern => 0x72,
+            HeapType::None => 0x71,
+            HeapType::Func => 0x70,
+            HeapType::Extern => 0x6f,
+            HeapType::Any => 0x6e,
+            HeapType::Eq => 0x6d,
+            HeapType::I31 => 0x6c,
+            HeapType::Struct => 0x6b,
+            HeapType::Array => 0x6a,
+            HeapType::Exn => 0x69,
+            HeapType::Concrete(idx) => return self.write_var_i64(idx as i64),
+        };
+        self.write_u8(byte);
+    }
+
+    pub fn write_storagetype(&mut self, ty: StorageType) {
+        match ty {
+            StorageType::I8 => self.write_u8(0x78),
+            StorageType::I16 => self.write_u8(0x77),
+            StorageType::Val(ty) => self.write_valtype(ty),
+        }
+    }
+
+    pub fn write_resulttype(&mut self, ty: &ResultType) {
+        self.write_vec(&ty.0, |encoder, ty| encoder.write_valtype(*ty));
+    }
+
+    /// Final sub types without supertypes are written as the bare composite type.
+    pub fn write_subtype(&mut self, ty: &SubType) {
+        if !ty.is_final || !ty.supertypes.is_empty() {
+            self.write_u8(if ty.is_final { 0x4f } else { 0x50 });
+            self.write_vec(&ty.supertypes, |encoder, idx| encoder.write_var_u32(*idx));
+        }
+        self.write_composite_type(&ty.composite);
+    }
+
+    pub fn write_composite_type(&mut self, ty: &CompositeType) {
+        match ty {
+            CompositeType::Func(ty) => {
+                self.write_u8(0x60);
+                self.write_resulttype(&ty.params);
+                self.write_resulttype(&ty.results);
+            }
+            CompositeType::Struct(ty) => {
+                self.write_u8(0x5f);
+                self.write_vec(&ty.fields, |encoder, field| encoder.write_fieldtype(*field));
+            }
+            CompositeType::Array(ty) => {
+                self.write_u8(0x5e);
+                self.write_fieldtype(ty.0);
+            }
+        }
+    }
+
+    pub fn write_fieldtype(&mut self, ty: FieldType) {
+        self.write_storagetype(ty.ty);
+        self.write_u8(ty.mutable as u8);
+    }
+
+    pub fn write_memtype(&mut self, ty: MemoryType) {
+        let flags = limits_flags(ty.limit, ty.index_type) | (ty.shared as u8) << 1;
+        self.write_u8(flags);
+        self.write_limit(ty.limit);
+    }
+
+    pub fn write_tabletype(&mut self, ty: TableType) {
+        self.write_reftype(ty.element);
+        self.write_u8(limits_flags(ty.limit, ty.index_type));
+        self.write_limit(ty.limit);
+    }
+
+    fn write_limit(&mut self, limit: Limit) {
+        self.write_var_u64(limit.min);
+        if let Some(max) = limit.max {
+            self.write_var_u64(max);
+        }
+    }
+
+    pub fn write_globaltype(&mut self, ty: GlobalType) {
+        self.write_valtype(ty.ty);
+        self.write_u8(ty.mutable as u8);
+    }
+
+    pub fn write_tagtype(&mut self, ty: TagType) {
+        self.write_u8(ty.attribute as u8);
+        self.write_var_u32(ty.type_idx);
+    }
+
+    /// The instructions of a constant expression, its final `end` included.
+    pub fn write_const_expr(&mut self, expr: &ConstExpr) {
+        expr.0
+            .iter()
+            .for_each(|instr| self.write_instruction(instr));
+    }
+
+    pub fn write_instruction(&mut self, instr: &Instruction) {
+        instr.encode(self);
+    }
+
+    pub fn write_block_type(&mut self, ty: BlockType) {
+        match ty {
+            BlockType::Empty => self.write_u8(0x40),
+            BlockType::Type(ty) => self.write_valtype(ty),
+            BlockType::FuncType(idx) => self.write_var_i64(idx as i64),
+        }
+    }
+
+    // sections
+
+    /// Encode a module, sections are written in the spec order and empty ones are left out.
+    ///
+    /// The module does not record where custom sections appeared, they follow the other sections
+    /// in their original order, except `dylink.0` which must be the first section.
+    pub fn write_module(&mut self, module: &Module) {
+        self.write_bytes(b"\0asm");
+        self.write_u32(module.version);
+
+        let (dylink, custom_sections): (Vec<_>, Vec<_>) = module
+            .custom_sections
+            .iter()
+            .partition(|section| section.name == Dylink::SECTION_NAME);
+        for section in dylink {
+            self.write_custom_section(section);
+        }
+
+        let types = &module.type_section.0;
+        self.write_section(SectionId::Type, types, |encoder, ty| match ty {
+            TypeSectionTy::Sub(ty) => encoder.write_subtype(ty),
+            TypeSectionTy::Rec(group) => {
+                encoder.write_u8(0x4e);
+                encoder.write_vec(&group.0, Encoder::write_subtype);
+            }
+        });
+        let imports = &module.import_section.0;
+        self.write_section(SectionId::Import, imports, |encoder, import| {
+            encoder.write_str(&import.module_name);
+            encoder.write_str(&import.field_name);
+            match import.kind {
+                ImportKind::Func(idx) => {
+                    encoder.write_u8(0x00);
+                    encoder.write_var_u32(idx);
+                }
+                ImportKind::Table(ty) => {
+                    encoder.write_u8(0x01);
+                    encoder.write_tabletype(ty);
+                }
+                ImportKind::Memory(ty) => {
+                    encoder.write_u8(0x02);
+                    encoder.write_memtype(ty);
+                }
+                ImportKind::Global(ty) => {
+                    encoder.write_u8(0x03);
+                    encoder.write_globaltype(ty);
+                }
+                ImportKind::Tag(ty) => {
+                    encoder.write_u8(0x04);
+                    encoder.write_tagtype(ty);
+                }
+            }
+        });
+        let funcs = &module.func_section.0;
+        self.write_section(SectionId::Function, funcs, |encoder, idx| {
+            encoder.write_var_u32(*idx)
+        });
+        let tables = &module.table_section.0;
+        self.write_section(SectionId::Table, tables, |encoder, table| {
+            if let Some(init) = &table.init {
+                encoder.write_bytes(&[0x40, 0x00]);
+                encoder.write_tabletype(table.ty);
+                encoder.write_const_expr(init);
+            } else {
+                encoder.write_tabletype(table.ty);
+            }
+        });
+        let memories = &module.memory_section.0;
+        self.write_section(SectionId::Memory, memories, |encoder, ty| {
+            encoder.write_memtype(*ty)
+        });
+        let tags = &module.tag_section.0;
+        self.write_section(SectionId::Tag, tags, |encoder, ty| {
+            encoder.write_tagtype(*ty)
+        });
+        let globals = &module.global_section.0;
+        self.write_section(SectionId::Global, globals, |encoder, global| {
+            encoder.write_globaltype(global.ty);
+            encoder.write_const_expr(&global.expr);
+        });
+        let exports = &module.export_section.0;
+        self.write_section(SectionId::Export, exports, |encoder, export| {
+            encoder.write_str(&export.name);
+            let (kind, idx) = match export.kind {
+                ExportKind::Func(idx) => (0x00, idx),
+                ExportKind::Table(idx) => (0x01, idx),
+                ExportKind::Mem(idx) => (0x02, idx),
+                ExportKind::Global(idx) => (0x03, idx),
+                ExportKind::Tag(idx) => (0x04, idx),
+            };
+            encoder.write_u8(kind);
+            encoder.write_var_u32(idx);
+        });
+        if let Some(start) = module.start_section.0 {
+            self.write_u8(SectionId::Start as u8);
+            self.write_sized(|encoder| encoder.write_var_u32(start));
+        }
+        let elements = &module.element_section.0;
+        self.write_section(SectionId::Element, elements, Encoder::write_element);
+        if let Some(count) = module.data_count_section.0 {
+            self.write_u8(SectionId::DataCount as u8);
+            self.write_sized(|encoder| encoder.write_var_u32(count));
+        }
+        let codes = &module.code_section.0;
+        self.write_section(SectionId::Code, codes, |encoder, code| {
+            encoder.write_sized(|encoder| {
+                encoder.write_vec(&code.locals, |encoder, locals| {
+                    encoder.write_var_u32(locals.n);
+                    encoder.write_valtype(locals.ty);
+                });
+                encoder.write_bytes(&code.body);
+            });
+        });
+        let data = &module.data_section.0;
+        self.write_section(SectionId::Data, data, |encoder, data| {
+            match &data.kind {
+                DataKind::Passive => encoder.write_u8(0x01),
+                DataKind::Active { memory: 0, offset } => {
+                    encoder.write_u8(0x00);
+                    encoder.write_const_expr(offset);
+                }
+                DataKind::Active { memory, offset } => {
+                    encoder.write_u8(0x02);
+                    encoder.write_var_u32(*memory);
+                    encoder.write_const_expr(offset);
+                }
+            }
+            encoder.write_var_u32(data.init.len() as u32);
+            encoder.write_bytes(&data.init);
+        });
+
+        for section in custom_sections {
+            self.write_custom_section(section);
+        }
+    }
+
+    pub fn write_custom_section(&mut self, section: &CustomSection) {
+        self.write_u8(SectionId::Custom as u8);
+        self.write_sized(|encoder| {
+            encoder.write_str(&section.name);
+            encoder.write_bytes(&section.data);
+        });
+    }
+
+    /// Write a section holding a vector of `items`, nothing if there are none.
+    fn write_section<'a, T, F>(&mut self, id: SectionId, items: &'a [T], ele: F)
+    where
+        F: Fn(&mut Self, &'a T),
+    {
+        if items.is_empty() {
+            return;
+        }
+        self.write_u8(id as u8);
+        self.write_sized(|encoder| encoder.write_vec(items, ele));
+    }
+
+    /// Segments of function indices use the shorter encodings without expressions,
+    /// as the parser gives them the non-nullable `(ref func)` type.
+    fn write_element(&mut self, element: &Element) {
+        let indices = element
+            .init
+            .iter()
+            .map(|expr| match expr.0[..] {
+                [Instruction::RefFunc(idx), Instruction::End] => Some(idx),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .filter(|_| element.ty == RefType::non_null(HeapType::Func));
+        let flags = match (&element.kind, &indices) {
+            (ElementKind::Active { table: None, .. }, Some(_)) => 0,
+            (ElementKind::Passive, Some(_)) => 1,
+            (ElementKind::Active { table: Some(_), .. }, Some(_)) => 2,
+            (ElementKind::Declared, Some(_)) => 3,
+            (ElementKind::Active { table: None, .. }, None) if element.ty == RefType::FUNCREF => 4,
+            (ElementKind::Passive, None) => 5,
+            (ElementKind::Active { .. }, None) => 6,
+            (ElementKind::Declared, None) => 7,
+        };
+        self.write_var_u32(flags);
+        if let ElementKind::Active { table, offset } = &element.kind {
+            if flags == 2 || flags == 6 {
+                self.write_var_u32(table.unwrap_or(0));
+            }
+            self.write_const_expr(offset);
+        }
+        match indices {
+            Some(indices) => {
+                if flags != 0 {
+                    self.write_u8(0x00);
+                }
+                self.write_vec(indices, |encoder, idx| encoder.write_var_u32(idx));
+            }
+            None => {
+                if flags != 4 {
+                    self.write_reftype(element.ty);
+                }
+                self.write_vec(&element.init, Encoder::write_const_expr);
+            }
+        }
+    }
+}
+
+/// Bit 0 announces a maximum and bit 2 64-bit addresses, the shared bit is left to memories.
+fn limits_flags(limit: Limit, index_type: IndexType) -> u8 {
+    limit.max.is_some() as u8 | ((index_type == IndexType::I64) as u8) << 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Encoder;
+    use crate::instruction::InstructionReader;
+    use crate::module::Module;
+
+    fn round_trip(data: &[u8]) -> Vec<u8> {
+        let module = Module::from_bytes(data).parse().unwrap().into_owned();
+        let encoded = module.encode();
+        let decoded = Module::from_bytes(&encoded).parse().unwrap().into_owned();
+        assert_eq!(decoded, module);
+
+        // Bodies are copied as is, encode their instructions to cover every opcode.
+        for code in &module.code_section.0 {
+            let instrs = code.expr().unwrap().instrs;
+            let mut encoder = Encoder::new();
+            instrs
+                .iter()
+                .for_each(|instr| encoder.write_instruction(instr));
+            let decoded = InstructionReader::new(&encoder.bytes)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(decoded, instrs);
+        }
+        encoded
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        for data in [
+            include_bytes!("../tests/bz2.wasm").as_slice(),
+            include_bytes!("../tests/pulldown-cmark.wasm"),
+            include_bytes!("../tests/lots-of-types.wasm"),
+        ] {
+            assert_eq!(round_trip(data), data);
+        }
+
+        let data = wat::parse_str(
+            r#"
+            (module
+                (rec
+                    (type $node (sub (struct (field i32) (field (mut (ref null $node))))))
+                    (type $leaf (sub final $node (struct (field i32) (field (mut (ref null $node)))))))
+                (type $bytes (array (mut i8)))
+                (type $f (func (param i32) (result i32)))
+                (import "env" "f" (func $imported (type $f)))
+                (import "env" "t" (table 1 funcref))
+                (import "env" "m" (memory 1 2 shared))
+                (import "env" "g" (global (mut i64)))
+                (import "env" "e" (tag (param i32)))
+                (memory $m64 i64 1)
+                (table $refs 2 (ref null $node) (ref.null $node))
+                (tag $t (param i32))
+                (global $nan f32 (f32.const nan:0x1))
+                (global $v v128 (v128.const i32x4 1 -2 3 -4))
+                (global $i31 (ref i31) (ref.i31 (i32.const 7)))
+                (export "f" (func $imported))
+                (export "m64" (memory $m64))
+                (start $start)
+                (elem (table 0) (i32.const 0) func $start)
+                (elem funcref (ref.null func) (ref.func $start))
+                (elem declare func $imported)
+                (elem (ref $f) (ref.func $imported))
+                (data (i32.const 0) "shared")
+                (data (memory $m64) (i64.const 8) "\00\01")
+                (data "passive")
+                (func $start
+                    (local i32 i64 (ref null $node))
+                    (drop (i32.atomic.rmw.add offset=4 (i32.const 0) (i32.const 1)))
+                    atomic.fence
+                    (drop (i64.load32_u $m64 offset=0x100000000 (i64.const 0)))
+                    (drop (i8x16.shuffle 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 31
+                        (v128.const i64x2 1 2) (v128.load8_lane 3 (i32.const 0) (global.get $v))))
+                    (drop (f32x4.relaxed_madd (global.get $v) (global.get $v) (global.get $v)))
+                    (memory.init 2 (i32.const 0) (i32.const 0) (i32.const 0))
+                    (table.copy (i32.const 0) (i32.const 0) (i32.const 0))
+                    (drop (i64.trunc_sat_f64_u (f64.const -nan:0x8)))
+                    (drop (select (result i64) (i64.const -1) (i64.const 1) (i32.const 0)))
+                    (drop (call_indirect (type $f) (i32.const 0) (i32.const 0)))
+                    (drop (block $l (result (ref null $node))
+                        (br_on_cast_fail $l (ref null $node) (ref $leaf) (local.get 2))
+                        (struct.get $leaf 0)
+                        (array.new_default $bytes)
+                        (array.len)
+                        (br_table $l $l (ref.null $node) (i32.const 0))))
+                    (block $outer
+                        (try_table (catch $t $outer) (catch_all $outer)
+                            (throw $t (i32.const 1))))))
+            "#,
+        )
+        .unwrap();
+        round_trip(&data);
+    }
+}
@@ -1,6 +1,31 @@
 use thiserror::Error;
 
+use crate::component::section::ComponentSectionId;
 use crate::instruction::Instruction;
+use crate::section::SectionId;
+use crate::types::ValType;
+
+/// Where in a module an [Error] occurred.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Location {
+    /// The preamble or a section header.
+    Module,
+    Section(SectionId),
+    ComponentSection(ComponentSectionId),
+    /// A function body, by its index in the function index space.
+    Function(u32),
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Location::Module => write!(f, "module"),
+            Location::Section(id) => write!(f, "{id:?} section"),
+            Location::ComponentSection(id) => write!(f, "{id:?} component section"),
+            Location::Function(idx) => write!(f, "func[{idx}]"),
+        }
+    }
+}
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -13,6 +38,14 @@ pub enum Error {
 
     #[error("invalid wasm format")]
     InvalidWasm,
+    #[error("unsupported binary version: {0:#010x}")]
+    InvalidVersion(u32),
+    #[error("the binary is a component, parse it with `Component::from_bytes`")]
+    UnexpectedComponent,
+    #[error("the binary is a core module, parse it with `Module::from_bytes`")]
+    UnexpectedModule,
+    #[error("invalid leading byte {0:#04x} for {1}")]
+    InvalidLeadingByte(u8, &'static str),
     #[error("invalid block type")]
     InvalidBlockType,
     #[error("invalid section id: {0:#04x}")]
@@ -35,15 +68,152 @@ pub enum Error {
     InvalidConstExprOpcode(Instruction),
     #[error("invalid elemtype {0:#04x} of table, must be funcref")]
     InvalidElmType(u8),
+    #[error("unexpected 0xfb prefix: {0}")]
+    Unexpected0xfb(u32),
     #[error("unexpected 0xfc prefix: {0}")]
     Unexpected0xfc(u32),
+    #[error("unexpected 0xfd prefix: {0}")]
+    Unexpected0xfd(u32),
+    #[error("unexpected 0xfe prefix: {0}")]
+    Unexpected0xfe(u32),
+    #[error("invalid select arity: {0}, must be 1")]
+    InvalidSelectArity(u32),
+    #[error("invalid lane index {0}, must be less than {1}")]
+    InvalidLaneIndex(u8, u8),
+    #[error("alignment too large: 2^{0}")]
+    AlignmentTooLarge(u32),
 
     #[error("section {0:#04x} should be {1} bytes but the parser consumes {2}")]
     SectionOutOfBounds(u8, u32, u32),
+    #[error("unexpected end of input, {0} more bytes are needed")]
+    UnexpectedEof(u64),
+    #[error("invalid `{0}` custom section: {1}")]
+    InvalidCustomSection(&'static str, &'static str),
+    #[cfg(feature = "dwarf")]
+    #[error("invalid DWARF: {0}")]
+    Dwarf(#[from] addr2line::gimli::Error),
+    #[cfg(feature = "sourcemap")]
+    #[error("invalid source map: {0}")]
+    InvalidSourceMap(&'static str),
+    #[cfg(feature = "sourcemap")]
+    #[error("invalid source map JSON: {0}")]
+    SourceMapJson(#[from] serde_json::Error),
+    #[cfg(feature = "sourcemap")]
+    #[error("failed to read the source map: {0}")]
+    SourceMapIo(std::io::Error),
+    #[error("subsection {0:#04x} should be {1} bytes but the parser consumes {2}")]
+    SubsectionOutOfBounds(u8, u32, u32),
+    #[error("{0:?} section is out of order, it follows the {1:?} section")]
+    SectionOutOfOrder(SectionId, SectionId),
+    #[error("duplicate {0:?} section")]
+    DuplicateSection(SectionId),
+    #[error("function and code section have inconsistent lengths: {0} and {1}")]
+    FunctionCodeMismatch(u32, u32),
+    #[error("data count is {0} but the data section has {1} segments")]
+    DataCountMismatch(u32, u32),
 
-    #[error("BinaryReaderError: {0}")]
-    BinaryReaderErr(#[from] wasmparser::BinaryReaderError),
+    #[error("the {0} proposal is not enabled")]
+    FeatureDisabled(&'static str),
+    #[error("{0} exceeds the limit of {1}")]
+    LimitExceeded(&'static str, u64),
+    #[error("out of memory allocating {0} {1}")]
+    OutOfMemory(u64, &'static str),
+
+    #[error("type mismatch: expected {0:?}, found {1:?}")]
+    TypeMismatch(ValType, ValType),
+    #[error("type mismatch: expected a reference, found {0:?}")]
+    ExpectedRef(ValType),
+    #[error("type mismatch: the operand stack is empty")]
+    OperandStackUnderflow,
+    #[error("type mismatch: {0} values remain on the operand stack at the end of the block")]
+    ValuesRemaining(u32),
+    #[error("type mismatch: result types do not match")]
+    ResultTypeMismatch,
+    #[error("br_table targets have different arities: {0} and {1}")]
+    LabelArityMismatch(u32, u32),
+    #[error("invalid control flow: {0}")]
+    InvalidControlFlow(&'static str),
+    #[error("unknown {0} {1}")]
+    UnknownIndex(&'static str, u32),
+    #[error("uninitialized local {0}")]
+    UninitializedLocal(u32),
+    #[error("type {1} is not a {0} type")]
+    UnexpectedType(&'static str, u32),
+    #[error("type {0} does not match its supertype {1}")]
+    InvalidSubtype(u32, u32),
+    #[error("global {0} is immutable")]
+    ImmutableGlobal(u32),
+    #[error("invalid field access: {0}")]
+    InvalidFieldAccess(&'static str),
+    #[error("type {0} has fields without a default value")]
+    NotDefaultable(u32),
+    #[error("invalid alignment 2^{0}, the natural alignment is 2^{1}")]
+    InvalidAlignment(u8, u8),
+    #[error("size minimum {0} is greater than the maximum {1}")]
+    InvalidLimits(u64, u64),
+    #[error("size of {0} exceeds the limit of the memory or table")]
+    MemoryTooLarge(u64),
+    #[error("table {0} holds non-nullable references and needs an initializer")]
+    MissingTableInit(u32),
+    #[error("shared memory must have a maximum size")]
+    SharedMemoryWithoutMax,
+    #[error("offset {0} is out of range for a 32-bit memory")]
+    OffsetTooLarge(u64),
+    #[error("function {0} is referenced but not declared by an element segment, export or global")]
+    UndeclaredFunctionReference(u32),
+    #[error("memory.init and data.drop require a data count section")]
+    MissingDataCount,
+    #[error("start function {0} must have type [] -> []")]
+    InvalidStartFunction(u32),
+    #[error("duplicate export name `{0}`")]
+    DuplicateExport(String),
+    #[error("duplicate import `{0}` `{1}`")]
+    DuplicateImport(String, String),
 
     #[error("{0}")]
     Other(&'static str),
+
+    #[error("{source} (at offset {offset:#x} in {location})")]
+    Located {
+        offset: usize,
+        location: Location,
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Attaches where the error occurred, unless a more precise location is already known.
+    pub(crate) fn at(self, offset: usize, location: Location) -> Self {
+        match self {
+            Error::Located { .. } => self,
+            _ => Error::Located {
+                offset,
+                location,
+                source: Box::new(self),
+            },
+        }
+    }
+
+    /// Offset in the binary where decoding stopped.
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Error::Located { offset, .. } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    pub fn location(&self) -> Option<Location> {
+        match self {
+            Error::Located { location, .. } => Some(*location),
+            _ => None,
+        }
+    }
+
+    /// The error without its location.
+    pub fn kind(&self) -> &Error {
+        match self {
+            Error::Located { source, .. } => source,
+            _ => self,
+        }
+    }
 }
\ No newline at end of file
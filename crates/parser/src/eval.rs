@@ -0,0 +1,405 @@
+//! Evaluation of constant expressions, e.g. global initializers and segment offsets.
+
+use crate::error::Error;
+use crate::instruction::{ConstExpr, Instruction, F32, F64, I128};
+use crate::limits::{self, Limits};
+use crate::section::TypeSection;
+use crate::types::{CompositeType, FieldType, HeapType, RefType, StorageType, ValType};
+
+/// The result of a [ConstExpr].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    I32(i32),
+    I64(i64),
+    F32(F32),
+    F64(F64),
+    V128(I128),
+    Null(HeapType),
+    Func(u32),
+    /// The 31 bits of an `i31ref`.
+    I31(u32),
+    Struct {
+        ty: u32,
+        fields: Vec<Value>,
+    },
+    Array {
+        ty: u32,
+        elements: Vec<Value>,
+    },
+    /// An internal reference converted by `extern.convert_any`.
+    Extern(Box<Value>),
+}
+
+impl Value {
+    pub fn ty(&self) -> ValType {
+        match self {
+            Value::I32(_) => ValType::I32,
+            Value::I64(_) => ValType::I64,
+            Value::F32(_) => ValType::F32,
+            Value::F64(_) => ValType::F64,
+            Value::V128(_) => ValType::V128,
+            Value::Null(ty) => ValType::Ref(RefType::nullable(*ty)),
+            Value::Func(_) => ValType::Ref(RefType::non_null(HeapType::Func)),
+            Value::I31(_) => ValType::Ref(RefType::non_null(HeapType::I31)),
+            Value::Struct { ty, .. } | Value::Array { ty, .. } => {
+                ValType::Ref(RefType::non_null(HeapType::Concrete(*ty)))
+            }
+            Value::Extern(_) => ValType::Ref(RefType::non_null(HeapType::Extern)),
+        }
+    }
+
+    /// The number of values this one is made of, itself included.
+    fn size(&self) -> u64 {
+        match self {
+            Value::Struct { fields: values, .. }
+            | Value::Array {
+                elements: values, ..
+            } => 1 + values.iter().map(Value::size).sum::<u64>(),
+            Value::Extern(value) => 1 + value.size(),
+            _ => 1,
+        }
+    }
+
+    /// The zero or null value of a defaultable type.
+    fn default(ty: StorageType) -> Option<Value> {
+        Some(match ty {
+            StorageType::I8 | StorageType::I16 | StorageType::Val(ValType::I32) => Value::I32(0),
+            StorageType::Val(ValType::I64) => Value::I64(0),
+            StorageType::Val(ValType::F32) => Value::F32(F32(0.0)),
+            StorageType::Val(ValType::F64) => Value::F64(F64(0.0)),
+            StorageType::Val(ValType::V128) => Value::V128(I128(0)),
+            StorageType::Val(ValType::Ref(ty)) if ty.nullable => Value::Null(ty.heap_type),
+            StorageType::Val(ValType::Ref(_)) => return None,
+        })
+    }
+}
+
+impl ConstExpr {
+    /// Compute the value of the expression on its own,
+    /// see [Evaluator] to evaluate the expressions of a module with one budget.
+    pub fn eval(&self, types: &TypeSection, globals: &[Value]) -> Result<Value, Error> {
+        Evaluator::new(types).eval(self, globals)
+    }
+}
+
+/// Evaluates the constant expressions of a module, e.g. its global initializers in order.
+///
+/// The arrays all the expressions create share the budget of [Limits::array_elements],
+/// a module cannot allocate more by spreading them over several initializers.
+pub struct Evaluator<'t> {
+    types: &'t TypeSection,
+    limits: Limits,
+    /// Array elements allocated so far, nested values included.
+    allocated: u64,
+}
+
+impl<'t> Evaluator<'t> {
+    /// `types` describes the structs and arrays the expressions allocate.
+    pub fn new(types: &'t TypeSection) -> Self {
+        Evaluator {
+            types,
+            limits: Limits::default(),
+            allocated: 0,
+        }
+    }
+
+    /// Replace the default [Limits], only [Limits::array_elements] applies.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Compute the value of `expr`, `globals` are the values of the globals `global.get` may read.
+    ///
+    /// The expression is expected to be valid, but ill-typed operands are reported rather than trusted.
+    pub fn eval(&mut self, expr: &ConstExpr, globals: &[Value]) -> Result<Value, Error> {
+        let types = self.types;
+        let mut stack = Vec::new();
+        for instr in &expr.0 {
+            let value = match instr {
+                Instruction::I32Const(v) => Value::I32(*v),
+                Instruction::I64Const(v) => Value::I64(*v),
+                Instruction::F32Const(v) => Value::F32(*v),
+                Instruction::F64Const(v) => Value::F64(*v),
+                Instruction::V128Const(v) => Value::V128(*v),
+                Instruction::GlobalGet(idx) => globals
+                    .get(*idx as usize)
+                    .cloned()
+                    .ok_or(Error::UnknownIndex("global", *idx))?,
+                Instruction::RefNull(ty) => Value::Null(*ty),
+                Instruction::RefFunc(idx) => Value::Func(*idx),
+
+                Instruction::I32Add | Instruction::I32Sub | Instruction::I32Mul => {
+                    let b = pop_i32(&mut stack)?;
+                    let a = pop_i32(&mut stack)?;
+                    Value::I32(match instr {
+                        Instruction::I32Add => a.wrapping_add(b),
+                        Instruction::I32Sub => a.wrapping_sub(b),
+                        _ => a.wrapping_mul(b),
+                    })
+                }
+                Instruction::I64Add | Instruction::I64Sub | Instruction::I64Mul => {
+                    let b = pop_i64(&mut stack)?;
+                    let a = pop_i64(&mut stack)?;
+                    Value::I64(match instr {
+                        Instruction::I64Add => a.wrapping_add(b),
+                        Instruction::I64Sub => a.wrapping_sub(b),
+                        _ => a.wrapping_mul(b),
+                    })
+                }
+
+                Instruction::StructNew(ty) => {
+                    let n = struct_fields(types, *ty)?.len();
+                    let fields = pop_n(&mut stack, n)?;
+                    Value::Struct { ty: *ty, fields }
+                }
+                Instruction::StructNewDefault(ty) => {
+                    let fields = struct_fields(types, *ty)?
+                        .iter()
+                        .map(|field| Value::default(field.ty).ok_or(Error::NotDefaultable(*ty)))
+                        .collect::<Result<_, _>>()?;
+                    Value::Struct { ty: *ty, fields }
+                }
+                Instruction::ArrayNew(ty) => {
+                    array_field(types, *ty)?;
+                    let len = pop_i32(&mut stack)? as u32;
+                    let value = pop(&mut stack)?;
+                    Value::Array {
+                        ty: *ty,
+                        elements: self.repeat(value, len)?,
+                    }
+                }
+                Instruction::ArrayNewDefault(ty) => {
+                    let field = array_field(types, *ty)?;
+                    let len = pop_i32(&mut stack)? as u32;
+                    let value = Value::default(field.ty).ok_or(Error::NotDefaultable(*ty))?;
+                    Value::Array {
+                        ty: *ty,
+                        elements: self.repeat(value, len)?,
+                    }
+                }
+                Instruction::ArrayNewFixed(ty, n) => {
+                    array_field(types, *ty)?;
+                    let elements = pop_n(&mut stack, *n as usize)?;
+                    Value::Array { ty: *ty, elements }
+                }
+                Instruction::RefI31 => Value::I31(pop_i32(&mut stack)? as u32 & 0x7fff_ffff),
+                Instruction::AnyConvertExtern => match pop(&mut stack)? {
+                    Value::Null(_) => Value::Null(HeapType::None),
+                    Value::Extern(value) => *value,
+                    value => {
+                        return Err(Error::TypeMismatch(
+                            ValType::Ref(RefType::EXTERNREF),
+                            value.ty(),
+                        ))
+                    }
+                },
+                Instruction::ExternConvertAny => match pop(&mut stack)? {
+                    Value::Null(_) => Value::Null(HeapType::NoExtern),
+                    value => Value::Extern(Box::new(value)),
+                },
+
+                Instruction::End => break,
+                instr => return Err(Error::InvalidConstExprOpcode(instr.clone())),
+            };
+            stack.push(value);
+        }
+
+        let value = pop(&mut stack)?;
+        if !stack.is_empty() {
+            return Err(Error::ValuesRemaining(stack.len() as u32));
+        }
+        Ok(value)
+    }
+
+    /// The elements of an array of `len` copies of `value`,
+    /// counted against the budget before anything is allocated.
+    fn repeat(&mut self, value: Value, len: u32) -> Result<Vec<Value>, Error> {
+        let size = u64::from(len).saturating_mul(value.size());
+        self.allocated = self.allocated.saturating_add(size);
+        limits::check("array elements", self.allocated, self.limits.array_elements)?;
+        let mut elements = Vec::new();
+        elements
+            .try_reserve_exact(len as usize)
+            .map_err(|_| Error::OutOfMemory(len.into(), "array elements"))?;
+        elements.resize(len as usize, value);
+        Ok(elements)
+    }
+}
+
+fn pop(stack: &mut Vec<Value>) -> Result<Value, Error> {
+    stack.pop().ok_or(Error::OperandStackUnderflow)
+}
+
+fn pop_n(stack: &mut Vec<Value>, n: usize) -> Result<Vec<Value>, Error> {
+    let start = stack
+        .len()
+        .checked_sub(n)
+        .ok_or(Error::OperandStackUnderflow)?;
+    Ok(stack.split_off(start))
+}
+
+fn pop_i32(stack: &mut Vec<Value>) -> Result<i32, Error> {
+    match pop(stack)? {
+        Value::I32(v) => Ok(v),
+        value => Err(Error::TypeMismatch(ValType::I32, value.ty())),
+    }
+}
+
+fn pop_i64(stack: &mut Vec<Value>) -> Result<i64, Error> {
+    match pop(stack)? {
+        Value::I64(v) => Ok(v),
+        value => Err(Error::TypeMismatch(ValType::I64, value.ty())),
+    }
+}
+
+fn struct_fields(types: &TypeSection, ty: u32) -> Result<&[FieldType], Error> {
+    match types.get(ty).map(|sub| &sub.composite) {
+        Some(CompositeType::Struct(st)) => Ok(&st.fields),
+        _ => Err(Error::UnexpectedType("struct", ty)),
+    }
+}
+
+fn array_field(types: &TypeSection, ty: u32) -> Result<FieldType, Error> {
+    match types.get(ty).map(|sub| &sub.composite) {
+        Some(CompositeType::Array(at)) => Ok(at.0),
+        _ => Err(Error::UnexpectedType("array", ty)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::eval::{Evaluator, Value};
+    use crate::features::Features;
+    use crate::limits::Limits;
+    use crate::module::Module;
+    use crate::types::HeapType;
+
+    #[test]
+    fn test_eval_const_exprs() {
+        let data = wat::parse_str(
+            r#"
+            (module
+                (type $pair (struct (field i32) (field (mut i64))))
+                (type $bytes (array (mut i8)))
+                (import "env" "base" (global $base i32))
+                (global i32 (i32.add (global.get $base) (i32.mul (i32.const 4) (i32.const 3))))
+                (global i64 (i64.sub (i64.const 1) (i64.const 2)))
+                (global (ref $pair) (struct.new $pair (i32.const 1) (i64.const 2)))
+                (global (ref $pair) (struct.new_default $pair))
+                (global (ref $bytes) (array.new_fixed $bytes 2 (i32.const 7) (i32.const 8)))
+                (global (ref $bytes) (array.new $bytes (i32.const 9) (i32.const 3)))
+                (global i31ref (ref.i31 (i32.const -1)))
+                (global anyref (any.convert_extern (extern.convert_any (ref.i31 (i32.const 5)))))
+                (global externref (extern.convert_any (ref.null any))))
+            "#,
+        )
+        .unwrap();
+        let module = Module::from_bytes(&data).parse().unwrap();
+        module.validate().unwrap();
+
+        let types = &module.type_section;
+        let values = module
+            .global_section
+            .0
+            .iter()
+            .map(|global| global.expr.eval(types, &[Value::I32(100)]).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            [
+                Value::I32(112),
+                Value::I64(-1),
+                Value::Struct {
+                    ty: 0,
+                    fields: vec![Value::I32(1), Value::I64(2)],
+                },
+                Value::Struct {
+                    ty: 0,
+                    fields: vec![Value::I32(0), Value::I64(0)],
+                },
+                Value::Array {
+                    ty: 1,
+                    elements: vec![Value::I32(7), Value::I32(8)],
+                },
+                Value::Array {
+                    ty: 1,
+                    elements: vec![Value::I32(9); 3],
+                },
+                Value::I31(0x7fff_ffff),
+                Value::I31(5),
+                Value::Null(HeapType::NoExtern),
+            ]
+        );
+
+        let expr = &module.global_section.0[0].expr;
+        assert!(matches!(
+            expr.eval(types, &[]),
+            Err(Error::UnknownIndex("global", 0))
+        ));
+        assert!(matches!(
+            expr.eval(types, &[Value::I64(0)]),
+            Err(Error::TypeMismatch(..))
+        ));
+
+        let huge = wat::parse_str(
+            r#"
+            (module
+                (type $a (array i64))
+                (type $aa (array (ref $a)))
+                (global (ref $a) (array.new $a (i64.const 0) (i32.const -1)))
+                (global (ref $a) (array.new_default $a (i32.const 0x7fff_ffff)))
+                (global (ref $aa)
+                    (array.new $aa (array.new_default $a (i32.const 10_000)) (i32.const 10_000))))
+            "#,
+        )
+        .unwrap();
+        let module = Module::from_bytes(&huge).parse().unwrap();
+        module.validate().unwrap();
+        let limit = Limits::default().array_elements as u64;
+        for global in &module.global_section.0 {
+            assert!(matches!(
+                global.expr.eval(&module.type_section, &[]),
+                Err(Error::LimitExceeded("array elements", max)) if max == limit
+            ));
+        }
+
+        // The budget is shared by all the expressions of an evaluator.
+        let shared = wat::parse_str(
+            r#"
+            (module
+                (type $a (array i64))
+                (global (ref $a) (array.new_default $a (i32.const 60)))
+                (global (ref $a) (array.new_default $a (i32.const 60))))
+            "#,
+        )
+        .unwrap();
+        let module = Module::from_bytes(&shared).parse().unwrap();
+        let limits = Limits {
+            array_elements: 100,
+            ..Limits::default()
+        };
+        let mut evaluator = Evaluator::new(&module.type_section).limits(limits);
+        let [first, second] = &module.global_section.0[..] else {
+            unreachable!()
+        };
+        evaluator.eval(&first.expr, &[]).unwrap();
+        assert!(matches!(
+            evaluator.eval(&second.expr, &[]),
+            Err(Error::LimitExceeded("array elements", 100))
+        ));
+
+        let features = Features {
+            extended_const: false,
+            ..Features::default()
+        };
+        let err = Module::from_bytes_with(&data, features)
+            .parse()
+            .unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            Error::FeatureDisabled("extended-const")
+        ));
+    }
+}
@@ -0,0 +1,912 @@
+//! Proposals a module may use on top of the MVP, see [Features].
+
+use crate::error::{Error, Location};
+use crate::instruction::{ConstExpr, Instruction};
+use crate::module::Module;
+use crate::section::{DataKind, ElementKind, ExportKind, ImportKind, TypeSectionTy};
+use crate::types::{
+    BlockType, CompositeType, HeapType, IndexType, MemoryType, RefType, SubType, TableType, ValType,
+};
+
+/// The WebAssembly proposals a [ModuleParser](crate::parser::ModuleParser) accepts.
+///
+/// Everything is enabled by default. A module using a disabled proposal
+/// fails to parse with [Error::FeatureDisabled].
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct Features {
+    pub simd: bool,
+    pub relaxed_simd: bool,
+    /// Atomic instructions and shared memories.
+    pub threads: bool,
+    /// Functions and blocks with more than one result, blocks with parameters.
+    pub multi_value: bool,
+    /// `funcref` and `externref` values, table instructions and multiple tables.
+    pub reference_types: bool,
+    /// Passive segments and the `memory.*`, `table.*` bulk instructions.
+    pub bulk_memory: bool,
+    /// Tags, `exnref` and both the `try_table` and the legacy `try` instructions.
+    pub exceptions: bool,
+    /// Also covers typed function references.
+    pub gc: bool,
+    pub tail_call: bool,
+    /// 64-bit memories and tables.
+    pub memory64: bool,
+    pub multi_memory: bool,
+    pub sign_extension: bool,
+    pub saturating_float_to_int: bool,
+    /// Imports and exports of mutable globals.
+    pub mutable_global: bool,
+    /// `memory.discard`.
+    pub memory_control: bool,
+    /// `i32` and `i64` `add`, `sub` and `mul` in constant expressions.
+    pub extended_const: bool,
+}
+
+impl Default for Features {
+    fn default() -> Self {
+        Features::all()
+    }
+}
+
+impl Features {
+    pub const fn all() -> Self {
+        Features {
+            simd: true,
+            relaxed_simd: true,
+            threads: true,
+            multi_value: true,
+            reference_types: true,
+            bulk_memory: true,
+            exceptions: true,
+            gc: true,
+            tail_call: true,
+            memory64: true,
+            multi_memory: true,
+            sign_extension: true,
+            saturating_float_to_int: true,
+            mutable_global: true,
+            memory_control: true,
+            extended_const: true,
+        }
+    }
+
+    /// Only what the 1.0 spec defines.
+    pub const fn mvp() -> Self {
+        Features {
+            simd: false,
+            relaxed_simd: false,
+            threads: false,
+            multi_value: false,
+            reference_types: false,
+            bulk_memory: false,
+            exceptions: false,
+            gc: false,
+            tail_call: false,
+            memory64: false,
+            multi_memory: false,
+            sign_extension: false,
+            saturating_float_to_int: false,
+            mutable_global: false,
+            memory_control: false,
+            extended_const: false,
+        }
+    }
+
+    /// Reject anything in `module` that belongs to a disabled proposal.
+    ///
+    /// Errors in function bodies are located at the instruction,
+    /// the others are left to the caller.
+    pub(crate) fn check_module(&self, module: &Module) -> Result<(), Error> {
+        for ty in &module.type_section.0 {
+            match ty {
+                TypeSectionTy::Rec(_) => require(self.gc, "gc")?,
+                TypeSectionTy::Sub(ty) => self.sub_type(ty)?,
+            }
+        }
+
+        let mut tables = module.table_section.0.len();
+        let mut memories = module.memory_section.0.len();
+        let mut globals = Vec::new();
+        for import in &module.import_section.0 {
+            match &import.kind {
+                ImportKind::Func(_) => {}
+                ImportKind::Table(ty) => {
+                    tables += 1;
+                    self.table_type(ty)?;
+                }
+                ImportKind::Memory(ty) => {
+                    memories += 1;
+                    self.memory_type(ty)?;
+                }
+                ImportKind::Global(ty) => {
+                    globals.push(ty.mutable);
+                    self.val_type(ty.ty)?;
+                    if ty.mutable {
+                        require(self.mutable_global, "mutable-global")?;
+                    }
+                }
+                ImportKind::Tag(_) => require(self.exceptions, "exceptions")?,
+            }
+        }
+        if tables > 1 {
+            require(self.reference_types, "reference-types")?;
+        }
+        if memories > 1 {
+            require(self.multi_memory, "multi-memory")?;
+        }
+        for table in &module.table_section.0 {
+            self.table_type(&table.ty)?;
+            if let Some(init) = &table.init {
+                require(self.gc, "gc")?;
+                self.const_expr(init)?;
+            }
+        }
+        for ty in &module.memory_section.0 {
+            self.memory_type(ty)?;
+        }
+        if !module.tag_section.0.is_empty() {
+            require(self.exceptions, "exceptions")?;
+        }
+        for global in &module.global_section.0 {
+            globals.push(global.ty.mutable);
+            self.val_type(global.ty.ty)?;
+            self.const_expr(&global.expr)?;
+        }
+
+        for export in &module.export_section.0 {
+            match export.kind {
+                ExportKind::Global(idx) if globals.get(idx as usize) == Some(&true) => {
+                    require(self.mutable_global, "mutable-global")?
+                }
+                ExportKind::Tag(_) => require(self.exceptions, "exceptions")?,
+                _ => {}
+            }
+        }
+
+        // The encodings of segments are checked while parsing, see [Features::element_flags].
+        for element in &module.element_section.0 {
+            if let ElementKind::Active { table, offset } = &element.kind {
+                if table.is_some_and(|table| table != 0) {
+                    require(self.reference_types, "reference-types")?;
+                }
+                self.const_expr(offset)?;
+            }
+            // Function indices are `(ref func)`, expressions `funcref`.
+            if element.ty.heap_type != HeapType::Func {
+                self.ref_type(element.ty)?;
+            }
+            // Function indices are read as `ref.func` expressions.
+            for expr in &element.init {
+                if !matches!(expr.0[..], [Instruction::RefFunc(_), Instruction::End]) {
+                    self.const_expr(expr)?;
+                }
+            }
+        }
+
+        for data in &module.data_section.0 {
+            match &data.kind {
+                DataKind::Passive => require(self.bulk_memory, "bulk-memory")?,
+                DataKind::Active { memory, offset } => {
+                    if *memory != 0 {
+                        require(self.multi_memory, "multi-memory")?;
+                    }
+                    self.const_expr(offset)?;
+                }
+            }
+        }
+        if module.data_count_section.0.is_some() {
+            require(self.bulk_memory, "bulk-memory")?;
+        }
+
+        for code in &module.code_section.0 {
+            let location = Location::Function(code.index);
+            for local in &code.locals {
+                self.val_type(local.ty)
+                    .map_err(|err| err.at(code.offset, location))?;
+            }
+            let mut reader = code.instructions();
+            loop {
+                let offset = reader.offset();
+                match reader.next() {
+                    Some(instr) => self
+                        .instruction(&instr?)
+                        .map_err(|err| err.at(offset, location))?,
+                    None => break,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn sub_type(&self, ty: &SubType) -> Result<(), Error> {
+        if !ty.is_final || !ty.supertypes.is_empty() {
+            require(self.gc, "gc")?;
+        }
+        match &ty.composite {
+            CompositeType::Func(ty) => {
+                if ty.results.0.len() > 1 {
+                    require(self.multi_value, "multi-value")?;
+                }
+                ty.params
+                    .0
+                    .iter()
+                    .chain(&ty.results.0)
+                    .try_for_each(|ty| self.val_type(*ty))
+            }
+            CompositeType::Struct(_) | CompositeType::Array(_) => require(self.gc, "gc"),
+        }
+    }
+
+    /// `funcref` tables are part of the MVP.
+    fn table_type(&self, ty: &TableType) -> Result<(), Error> {
+        if ty.index_type == IndexType::I64 {
+            require(self.memory64, "memory64")?;
+        }
+        match ty.element {
+            RefType::FUNCREF => Ok(()),
+            element => self.ref_type(element),
+        }
+    }
+
+    fn memory_type(&self, ty: &MemoryType) -> Result<(), Error> {
+        if ty.shared {
+            require(self.threads, "threads")?;
+        }
+        if ty.index_type == IndexType::I64 {
+            require(self.memory64, "memory64")?;
+        }
+        Ok(())
+    }
+
+    fn val_type(&self, ty: ValType) -> Result<(), Error> {
+        match ty {
+            ValType::V128 => require(self.simd, "simd"),
+            ValType::Ref(ty) => self.ref_type(ty),
+            _ => Ok(()),
+        }
+    }
+
+    fn ref_type(&self, ty: RefType) -> Result<(), Error> {
+        if !ty.nullable {
+            require(self.gc, "gc")?;
+        }
+        self.heap_type(ty.heap_type)
+    }
+
+    fn heap_type(&self, ty: HeapType) -> Result<(), Error> {
+        match ty {
+            HeapType::Func | HeapType::Extern => require(self.reference_types, "reference-types"),
+            HeapType::Exn | HeapType::NoExn => require(self.exceptions, "exceptions"),
+            _ => require(self.gc, "gc"),
+        }
+    }
+
+    fn block_type(&self, ty: &BlockType) -> Result<(), Error> {
+        match ty {
+            BlockType::Empty => Ok(()),
+            BlockType::Type(ty) => self.val_type(*ty),
+            BlockType::FuncType(_) => require(self.multi_value, "multi-value"),
+        }
+    }
+
+    /// Only flags 0, an active segment of function indices for table 0, are in the MVP.
+    /// The others came with bulk memory, and declared segments with reference types.
+    pub(crate) fn element_flags(&self, flags: u32) -> Result<(), Error> {
+        match flags {
+            0 => Ok(()),
+            3 | 7 => require(self.reference_types, "reference-types"),
+            _ => require(self.bulk_memory, "bulk-memory"),
+        }
+    }
+
+    fn const_expr(&self, expr: &ConstExpr) -> Result<(), Error> {
+        expr.0
+            .iter()
+            .try_for_each(|instr| self.const_instruction(instr))
+    }
+
+    /// Check an instruction of a constant expression, where arithmetic needs extended-const.
+    pub(crate) fn const_instruction(&self, instr: &Instruction) -> Result<(), Error> {
+        match instr {
+            Instruction::I32Add
+            | Instruction::I32Sub
+            | Instruction::I32Mul
+            | Instruction::I64Add
+            | Instruction::I64Sub
+            | Instruction::I64Mul => require(self.extended_const, "extended-const"),
+            instr => self.instruction(instr),
+        }
+    }
+
+    fn instruction(&self, instr: &Instruction) -> Result<(), Error> {
+        if instr.memarg().is_some_and(|arg| arg.memory != 0) {
+            require(self.multi_memory, "multi-memory")?;
+        }
+        match instr {
+            Instruction::Block(ty) | Instruction::Loop(ty) | Instruction::If(ty) => {
+                self.block_type(ty)
+            }
+            Instruction::Try(ty) => {
+                require(self.exceptions, "exceptions")?;
+                self.block_type(ty)
+            }
+            Instruction::TryTable(table) => {
+                require(self.exceptions, "exceptions")?;
+                self.block_type(&table.ty)
+            }
+            Instruction::Throw(_)
+            | Instruction::ThrowRef
+            | Instruction::Delegate(_)
+            | Instruction::Catch(_)
+            | Instruction::CatchAll
+            | Instruction::Rethrow(_) => require(self.exceptions, "exceptions"),
+
+            Instruction::ReturnCall(_) | Instruction::ReturnCallIndirect(..) => {
+                require(self.tail_call, "tail-call")
+            }
+            Instruction::ReturnCallRef(_) => {
+                require(self.tail_call, "tail-call")?;
+                require(self.gc, "gc")
+            }
+            // The MVP reserves a single zero byte for the table index.
+            Instruction::CallIndirect(_, _, table_byte) if *table_byte != 0 => {
+                require(self.reference_types, "reference-types")
+            }
+
+            Instruction::TypedSelect(ty) => {
+                require(self.reference_types, "reference-types")?;
+                self.val_type(*ty)
+            }
+            Instruction::RefNull(ty) => {
+                require(self.reference_types, "reference-types")?;
+                self.heap_type(*ty)
+            }
+            Instruction::RefIsNull
+            | Instruction::RefFunc(_)
+            | Instruction::TableGet(_)
+            | Instruction::TableSet(_)
+            | Instruction::TableSize(_)
+            | Instruction::TableGrow(_)
+            | Instruction::TableFill(_) => require(self.reference_types, "reference-types"),
+
+            Instruction::MemorySize(mem) | Instruction::MemoryGrow(mem) if *mem != 0 => {
+                require(self.multi_memory, "multi-memory")
+            }
+            Instruction::MemoryInit(_, mem) | Instruction::MemoryFill(mem) => {
+                require(self.bulk_memory, "bulk-memory")?;
+                if *mem != 0 {
+                    require(self.multi_memory, "multi-memory")?;
+                }
+                Ok(())
+            }
+            Instruction::MemoryCopy(dst, src) => {
+                require(self.bulk_memory, "bulk-memory")?;
+                if *dst != 0 || *src != 0 {
+                    require(self.multi_memory, "multi-memory")?;
+                }
+                Ok(())
+            }
+            Instruction::DataDrop(_)
+            | Instruction::TableInit(..)
+            | Instruction::ElemDrop(_)
+            | Instruction::TableCopy(..) => require(self.bulk_memory, "bulk-memory"),
+            Instruction::MemoryDiscard(_) => require(self.memory_control, "memory-control"),
+
+            Instruction::I32Extend8S
+            | Instruction::I32Extend16S
+            | Instruction::I64Extend8S
+            | Instruction::I64Extend16S
+            | Instruction::I64Extend32S => require(self.sign_extension, "sign-extension"),
+            Instruction::I32TruncSatF32S
+            | Instruction::I32TruncSatF32U
+            | Instruction::I32TruncSatF64S
+            | Instruction::I32TruncSatF64U
+            | Instruction::I64TruncSatF32S
+            | Instruction::I64TruncSatF32U
+            | Instruction::I64TruncSatF64S
+            | Instruction::I64TruncSatF64U => {
+                require(self.saturating_float_to_int, "saturating-float-to-int")
+            }
+
+            Instruction::BrOnNull(_)
+            | Instruction::BrOnNonNull(_)
+            | Instruction::CallRef(_)
+            | Instruction::RefEq
+            | Instruction::RefAsNonNull
+            | Instruction::StructNew(_)
+            | Instruction::StructNewDefault(_)
+            | Instruction::StructGet(..)
+            | Instruction::StructGetS(..)
+            | Instruction::StructGetU(..)
+            | Instruction::StructSet(..)
+            | Instruction::ArrayNew(_)
+            | Instruction::ArrayNewDefault(_)
+            | Instruction::ArrayNewFixed(..)
+            | Instruction::ArrayNewData(..)
+            | Instruction::ArrayNewElem(..)
+            | Instruction::ArrayGet(_)
+            | Instruction::ArrayGetS(_)
+            | Instruction::ArrayGetU(_)
+            | Instruction::ArraySet(_)
+            | Instruction::ArrayLen
+            | Instruction::ArrayFill(_)
+            | Instruction::ArrayCopy(..)
+            | Instruction::ArrayInitData(..)
+            | Instruction::ArrayInitElem(..)
+            | Instruction::RefTestNonNull(_)
+            | Instruction::RefTestNullable(_)
+            | Instruction::RefCastNonNull(_)
+            | Instruction::RefCastNullable(_)
+            | Instruction::BrOnCast(..)
+            | Instruction::BrOnCastFail(..)
+            | Instruction::AnyConvertExtern
+            | Instruction::ExternConvertAny
+            | Instruction::RefI31
+            | Instruction::I31GetS
+            | Instruction::I31GetU => require(self.gc, "gc"),
+
+            Instruction::V128Load(..)
+            | Instruction::V128Load8x8S(..)
+            | Instruction::V128Load8x8U(..)
+            | Instruction::V128Load16x4S(..)
+            | Instruction::V128Load16x4U(..)
+            | Instruction::V128Load32x2S(..)
+            | Instruction::V128Load32x2U(..)
+            | Instruction::V128Load8Splat(..)
+            | Instruction::V128Load16Splat(..)
+            | Instruction::V128Load32Splat(..)
+            | Instruction::V128Load64Splat(..)
+            | Instruction::V128Load32Zero(..)
+            | Instruction::V128Load64Zero(..)
+            | Instruction::V128Store(..)
+            | Instruction::V128Load8Lane(..)
+            | Instruction::V128Load16Lane(..)
+            | Instruction::V128Load32Lane(..)
+            | Instruction::V128Load64Lane(..)
+            | Instruction::V128Store8Lane(..)
+            | Instruction::V128Store16Lane(..)
+            | Instruction::V128Store32Lane(..)
+            | Instruction::V128Store64Lane(..)
+            | Instruction::V128Const(..)
+            | Instruction::I8x16Shuffle(..)
+            | Instruction::I8x16ExtractLaneS(..)
+            | Instruction::I8x16ExtractLaneU(..)
+            | Instruction::I8x16ReplaceLane(..)
+            | Instruction::I16x8ExtractLaneS(..)
+            | Instruction::I16x8ExtractLaneU(..)
+            | Instruction::I16x8ReplaceLane(..)
+            | Instruction::I32x4ExtractLane(..)
+            | Instruction::I32x4ReplaceLane(..)
+            | Instruction::I64x2ExtractLane(..)
+            | Instruction::I64x2ReplaceLane(..)
+            | Instruction::F32x4ExtractLane(..)
+            | Instruction::F32x4ReplaceLane(..)
+            | Instruction::F64x2ExtractLane(..)
+            | Instruction::F64x2ReplaceLane(..)
+            | Instruction::I8x16Swizzle
+            | Instruction::I8x16Splat
+            | Instruction::I16x8Splat
+            | Instruction::I32x4Splat
+            | Instruction::I64x2Splat
+            | Instruction::F32x4Splat
+            | Instruction::F64x2Splat
+            | Instruction::I8x16Eq
+            | Instruction::I8x16Ne
+            | Instruction::I8x16LtS
+            | Instruction::I8x16LtU
+            | Instruction::I8x16GtS
+            | Instruction::I8x16GtU
+            | Instruction::I8x16LeS
+            | Instruction::I8x16LeU
+            | Instruction::I8x16GeS
+            | Instruction::I8x16GeU
+            | Instruction::I16x8Eq
+            | Instruction::I16x8Ne
+            | Instruction::I16x8LtS
+            | Instruction::I16x8LtU
+            | Instruction::I16x8GtS
+            | Instruction::I16x8GtU
+            | Instruction::I16x8LeS
+            | Instruction::I16x8LeU
+            | Instruction::I16x8GeS
+            | Instruction::I16x8GeU
+            | Instruction::I32x4Eq
+            | Instruction::I32x4Ne
+            | Instruction::I32x4LtS
+            | Instruction::I32x4LtU
+            | Instruction::I32x4GtS
+            | Instruction::I32x4GtU
+            | Instruction::I32x4LeS
+            | Instruction::I32x4LeU
+            | Instruction::I32x4GeS
+            | Instruction::I32x4GeU
+            | Instruction::I64x2Eq
+            | Instruction::I64x2Ne
+            | Instruction::I64x2LtS
+            | Instruction::I64x2GtS
+            | Instruction::I64x2LeS
+            | Instruction::I64x2GeS
+            | Instruction::F32x4Eq
+            | Instruction::F32x4Ne
+            | Instruction::F32x4Lt
+            | Instruction::F32x4Gt
+            | Instruction::F32x4Le
+            | Instruction::F32x4Ge
+            | Instruction::F64x2Eq
+            | Instruction::F64x2Ne
+            | Instruction::F64x2Lt
+            | Instruction::F64x2Gt
+            | Instruction::F64x2Le
+            | Instruction::F64x2Ge
+            | Instruction::V128Not
+            | Instruction::V128And
+            | Instruction::V128AndNot
+            | Instruction::V128Or
+            | Instruction::V128Xor
+            | Instruction::V128Bitselect
+            | Instruction::V128AnyTrue
+            | Instruction::I8x16Abs
+            | Instruction::I8x16Neg
+            | Instruction::I8x16Popcnt
+            | Instruction::I8x16AllTrue
+            | Instruction::I8x16Bitmask
+            | Instruction::I8x16NarrowI16x8S
+            | Instruction::I8x16NarrowI16x8U
+            | Instruction::I8x16Shl
+            | Instruction::I8x16ShrS
+            | Instruction::I8x16ShrU
+            | Instruction::I8x16Add
+            | Instruction::I8x16AddSatS
+            | Instruction::I8x16AddSatU
+            | Instruction::I8x16Sub
+            | Instruction::I8x16SubSatS
+            | Instruction::I8x16SubSatU
+            | Instruction::I8x16MinS
+            | Instruction::I8x16MinU
+            | Instruction::I8x16MaxS
+            | Instruction::I8x16MaxU
+            | Instruction::I8x16AvgrU
+            | Instruction::I16x8ExtAddPairwiseI8x16S
+            | Instruction::I16x8ExtAddPairwiseI8x16U
+            | Instruction::I16x8Abs
+            | Instruction::I16x8Neg
+            | Instruction::I16x8Q15MulrSatS
+            | Instruction::I16x8AllTrue
+            | Instruction::I16x8Bitmask
+            | Instruction::I16x8NarrowI32x4S
+            | Instruction::I16x8NarrowI32x4U
+            | Instruction::I16x8ExtendLowI8x16S
+            | Instruction::I16x8ExtendHighI8x16S
+            | Instruction::I16x8ExtendLowI8x16U
+            | Instruction::I16x8ExtendHighI8x16U
+            | Instruction::I16x8Shl
+            | Instruction::I16x8ShrS
+            | Instruction::I16x8ShrU
+            | Instruction::I16x8Add
+            | Instruction::I16x8AddSatS
+            | Instruction::I16x8AddSatU
+            | Instruction::I16x8Sub
+            | Instruction::I16x8SubSatS
+            | Instruction::I16x8SubSatU
+            | Instruction::I16x8Mul
+            | Instruction::I16x8MinS
+            | Instruction::I16x8MinU
+            | Instruction::I16x8MaxS
+            | Instruction::I16x8MaxU
+            | Instruction::I16x8AvgrU
+            | Instruction::I16x8ExtMulLowI8x16S
+            | Instruction::I16x8ExtMulHighI8x16S
+            | Instruction::I16x8ExtMulLowI8x16U
+            | Instruction::I16x8ExtMulHighI8x16U
+            | Instruction::I32x4ExtAddPairwiseI16x8S
+            | Instruction::I32x4ExtAddPairwiseI16x8U
+            | Instruction::I32x4Abs
+            | Instruction::I32x4Neg
+            | Instruction::I32x4AllTrue
+            | Instruction::I32x4Bitmask
+            | Instruction::I32x4ExtendLowI16x8S
+            | Instruction::I32x4ExtendHighI16x8S
+            | Instruction::I32x4ExtendLowI16x8U
+            | Instruction::I32x4ExtendHighI16x8U
+            | Instruction::I32x4Shl
+            | Instruction::I32x4ShrS
+            | Instruction::I32x4ShrU
+            | Instruction::I32x4Add
+            | Instruction::I32x4Sub
+            | Instruction::I32x4Mul
+            | Instruction::I32x4MinS
+            | Instruction::I32x4MinU
+            | Instruction::I32x4MaxS
+            | Instruction::I32x4MaxU
+            | Instruction::I32x4DotI16x8S
+            | Instruction::I32x4ExtMulLowI16x8S
+            | Instruction::I32x4ExtMulHighI16x8S
+            | Instruction::I32x4ExtMulLowI16x8U
+            | Instruction::I32x4ExtMulHighI16x8U
+            | Instruction::I64x2Abs
+            | Instruction::I64x2Neg
+            | Instruction::I64x2AllTrue
+            | Instruction::I64x2Bitmask
+            | Instruction::I64x2ExtendLowI32x4S
+            | Instruction::I64x2ExtendHighI32x4S
+            | Instruction::I64x2ExtendLowI32x4U
+            | Instruction::I64x2ExtendHighI32x4U
+            | Instruction::I64x2Shl
+            | Instruction::I64x2ShrS
+            | Instruction::I64x2ShrU
+            | Instruction::I64x2Add
+            | Instruction::I64x2Sub
+            | Instruction::I64x2Mul
+            | Instruction::I64x2ExtMulLowI32x4S
+            | Instruction::I64x2ExtMulHighI32x4S
+            | Instruction::I64x2ExtMulLowI32x4U
+            | Instruction::I64x2ExtMulHighI32x4U
+            | Instruction::F32x4Ceil
+            | Instruction::F32x4Floor
+            | Instruction::F32x4Trunc
+            | Instruction::F32x4Nearest
+            | Instruction::F32x4Abs
+            | Instruction::F32x4Neg
+            | Instruction::F32x4Sqrt
+            | Instruction::F32x4Add
+            | Instruction::F32x4Sub
+            | Instruction::F32x4Mul
+            | Instruction::F32x4Div
+            | Instruction::F32x4Min
+            | Instruction::F32x4Max
+            | Instruction::F32x4PMin
+            | Instruction::F32x4PMax
+            | Instruction::F64x2Ceil
+            | Instruction::F64x2Floor
+            | Instruction::F64x2Trunc
+            | Instruction::F64x2Nearest
+            | Instruction::F64x2Abs
+            | Instruction::F64x2Neg
+            | Instruction::F64x2Sqrt
+            | Instruction::F64x2Add
+            | Instruction::F64x2Sub
+            | Instruction::F64x2Mul
+            | Instruction::F64x2Div
+            | Instruction::F64x2Min
+            | Instruction::F64x2Max
+            | Instruction::F64x2PMin
+            | Instruction::F64x2PMax
+            | Instruction::I32x4TruncSatF32x4S
+            | Instruction::I32x4TruncSatF32x4U
+            | Instruction::F32x4ConvertI32x4S
+            | Instruction::F32x4ConvertI32x4U
+            | Instruction::I32x4TruncSatF64x2SZero
+            | Instruction::I32x4TruncSatF64x2UZero
+            | Instruction::F64x2ConvertLowI32x4S
+            | Instruction::F64x2ConvertLowI32x4U
+            | Instruction::F32x4DemoteF64x2Zero
+            | Instruction::F64x2PromoteLowF32x4 => require(self.simd, "simd"),
+            Instruction::I8x16RelaxedSwizzle
+            | Instruction::I32x4RelaxedTruncF32x4S
+            | Instruction::I32x4RelaxedTruncF32x4U
+            | Instruction::I32x4RelaxedTruncF64x2SZero
+            | Instruction::I32x4RelaxedTruncF64x2UZero
+            | Instruction::F32x4RelaxedMadd
+            | Instruction::F32x4RelaxedNmadd
+            | Instruction::F64x2RelaxedMadd
+            | Instruction::F64x2RelaxedNmadd
+            | Instruction::I8x16RelaxedLaneselect
+            | Instruction::I16x8RelaxedLaneselect
+            | Instruction::I32x4RelaxedLaneselect
+            | Instruction::I64x2RelaxedLaneselect
+            | Instruction::F32x4RelaxedMin
+            | Instruction::F32x4RelaxedMax
+            | Instruction::F64x2RelaxedMin
+            | Instruction::F64x2RelaxedMax
+            | Instruction::I16x8RelaxedQ15mulrS
+            | Instruction::I16x8RelaxedDotI8x16I7x16S
+            | Instruction::I32x4RelaxedDotI8x16I7x16AddS => {
+                require(self.simd, "simd")?;
+                require(self.relaxed_simd, "relaxed-simd")
+            }
+            Instruction::MemoryAtomicNotify(..)
+            | Instruction::MemoryAtomicWait32(..)
+            | Instruction::MemoryAtomicWait64(..)
+            | Instruction::AtomicFence
+            | Instruction::I32AtomicLoad(..)
+            | Instruction::I64AtomicLoad(..)
+            | Instruction::I32AtomicLoad8U(..)
+            | Instruction::I32AtomicLoad16U(..)
+            | Instruction::I64AtomicLoad8U(..)
+            | Instruction::I64AtomicLoad16U(..)
+            | Instruction::I64AtomicLoad32U(..)
+            | Instruction::I32AtomicStore(..)
+            | Instruction::I64AtomicStore(..)
+            | Instruction::I32AtomicStore8(..)
+            | Instruction::I32AtomicStore16(..)
+            | Instruction::I64AtomicStore8(..)
+            | Instruction::I64AtomicStore16(..)
+            | Instruction::I64AtomicStore32(..)
+            | Instruction::I32AtomicRmwAdd(..)
+            | Instruction::I64AtomicRmwAdd(..)
+            | Instruction::I32AtomicRmw8AddU(..)
+            | Instruction::I32AtomicRmw16AddU(..)
+            | Instruction::I64AtomicRmw8AddU(..)
+            | Instruction::I64AtomicRmw16AddU(..)
+            | Instruction::I64AtomicRmw32AddU(..)
+            | Instruction::I32AtomicRmwSub(..)
+            | Instruction::I64AtomicRmwSub(..)
+            | Instruction::I32AtomicRmw8SubU(..)
+            | Instruction::I32AtomicRmw16SubU(..)
+            | Instruction::I64AtomicRmw8SubU(..)
+            | Instruction::I64AtomicRmw16SubU(..)
+            | Instruction::I64AtomicRmw32SubU(..)
+            | Instruction::I32AtomicRmwAnd(..)
+            | Instruction::I64AtomicRmwAnd(..)
+            | Instruction::I32AtomicRmw8AndU(..)
+            | Instruction::I32AtomicRmw16AndU(..)
+            | Instruction::I64AtomicRmw8AndU(..)
+            | Instruction::I64AtomicRmw16AndU(..)
+            | Instruction::I64AtomicRmw32AndU(..)
+            | Instruction::I32AtomicRmwOr(..)
+            | Instruction::I64AtomicRmwOr(..)
+            | Instruction::I32AtomicRmw8OrU(..)
+            | Instruction::I32AtomicRmw16OrU(..)
+            | Instruction::I64AtomicRmw8OrU(..)
+            | Instruction::I64AtomicRmw16OrU(..)
+            | Instruction::I64AtomicRmw32OrU(..)
+            | Instruction::I32AtomicRmwXor(..)
+            | Instruction::I64AtomicRmwXor(..)
+            | Instruction::I32AtomicRmw8XorU(..)
+            | Instruction::I32AtomicRmw16XorU(..)
+            | Instruction::I64AtomicRmw8XorU(..)
+            | Instruction::I64AtomicRmw16XorU(..)
+            | Instruction::I64AtomicRmw32XorU(..)
+            | Instruction::I32AtomicRmwXchg(..)
+            | Instruction::I64AtomicRmwXchg(..)
+            | Instruction::I32AtomicRmw8XchgU(..)
+            | Instruction::I32AtomicRmw16XchgU(..)
+            | Instruction::I64AtomicRmw8XchgU(..)
+            | Instruction::I64AtomicRmw16XchgU(..)
+            | Instruction::I64AtomicRmw32XchgU(..)
+            | Instruction::I32AtomicRmwCmpxchg(..)
+            | Instruction::I64AtomicRmwCmpxchg(..)
+            | Instruction::I32AtomicRmw8CmpxchgU(..)
+            | Instruction::I32AtomicRmw16CmpxchgU(..)
+            | Instruction::I64AtomicRmw8CmpxchgU(..)
+            | Instruction::I64AtomicRmw16CmpxchgU(..)
+            | Instruction::I64AtomicRmw32CmpxchgU(..) => require(self.threads, "threads"),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn require(enabled: bool, proposal: &'static str) -> Result<(), Error> {
+    if enabled {
+        Ok(())
+    } else {
+        Err(Error::FeatureDisabled(proposal))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{Error, Location};
+    use crate::features::Features;
+    use crate::module::Module;
+    use crate::section::SectionId;
+
+    fn parse(wat: &str, features: Features) -> Result<(), Error> {
+        let data = wat::parse_str(wat).unwrap();
+        Module::from_bytes_with(&data, features).parse().map(|_| ())
+    }
+
+    fn disabled(wat: &str, features: Features) -> &'static str {
+        match parse(wat, features).unwrap_err().kind() {
+            Error::FeatureDisabled(proposal) => proposal,
+            err => panic!("expect a disabled feature, found {err}"),
+        }
+    }
+
+    #[test]
+    fn test_mvp_features() {
+        let mvp = Features::mvp();
+        parse(
+            r#"
+            (module
+                (import "env" "g" (global i32))
+                (memory 1)
+                (table 1 funcref)
+                (elem (i32.const 0) 0)
+                (data (i32.const 0) "a")
+                (global (mut i64) (i64.const 0))
+                (func (param i32) (result i32)
+                    (block (result i32)
+                        (call_indirect (param i32) (result i32) (local.get 0) (i32.const 0)))
+                    (memory.grow)))
+            "#,
+            mvp,
+        )
+        .unwrap();
+
+        let err = parse(r#"(module (func (drop (i8x16.splat (i32.const 0)))))"#, mvp).unwrap_err();
+        assert_eq!(err.location(), Some(Location::Function(0)));
+        assert!(matches!(err.kind(), Error::FeatureDisabled("simd")));
+
+        // Constant expressions are checked as they are decoded.
+        let wat = "(module (global i32 (i32.add (i32.const 1) (i32.const 2))))";
+        let err = parse(wat, mvp).unwrap_err();
+        assert_eq!(err.location(), Some(Location::Section(SectionId::Global)));
+        assert!(matches!(
+            err.kind(),
+            Error::FeatureDisabled("extended-const")
+        ));
+        let wat = "(module (global i31ref (ref.i31 (i32.const 0))))";
+        let features = Features {
+            gc: false,
+            ..Features::default()
+        };
+        let err = parse(wat, features).unwrap_err();
+        assert_eq!(err.location(), Some(Location::Section(SectionId::Global)));
+        assert!(matches!(err.kind(), Error::FeatureDisabled("gc")));
+
+        let cases = [
+            (
+                "(module (func (result i32 i32) (i32.const 0) (i32.const 1)))",
+                "multi-value",
+            ),
+            ("(module (memory 1) (data \"a\"))", "bulk-memory"),
+            ("(module (func) (elem func 0))", "bulk-memory"),
+            (
+                "(module (table 1 funcref) (func) (elem (i32.const 0) funcref (ref.func 0)))",
+                "bulk-memory",
+            ),
+            ("(module (func) (elem declare func 0))", "reference-types"),
+            (
+                "(module (global (mut i32) (i32.const 0)) (export \"g\" (global 0)))",
+                "mutable-global",
+            ),
+            ("(module (table 1 externref))", "reference-types"),
+            (
+                "(module (func (drop (i32.extend8_s (i32.const 0)))))",
+                "sign-extension",
+            ),
+            (
+                "(module (func (drop (i32.trunc_sat_f32_s (f32.const 0)))))",
+                "saturating-float-to-int",
+            ),
+            ("(module (tag))", "exceptions"),
+            ("(module (func (return_call 0)))", "tail-call"),
+            ("(module (type (struct)))", "gc"),
+            ("(module (memory 1) (memory 1))", "multi-memory"),
+            ("(module (memory i64 1))", "memory64"),
+            ("(module (memory 1 1 shared))", "threads"),
+            (
+                "(module (memory 1) (func (drop (i32.atomic.load (i32.const 0)))))",
+                "threads",
+            ),
+        ];
+        for (wat, proposal) in cases {
+            assert_eq!(disabled(wat, mvp), proposal, "{wat}");
+            parse(wat, Features::default()).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_relaxed_simd() {
+        let wat = r#"
+            (module
+                (func (param v128) (result v128)
+                    (i8x16.relaxed_swizzle (local.get 0) (local.get 0))))
+        "#;
+        let features = Features {
+            relaxed_simd: false,
+            ..Features::default()
+        };
+        assert_eq!(disabled(wat, features), "relaxed-simd");
+        parse(wat, Features::default()).unwrap();
+
+        let data = include_bytes!("../tests/pulldown-cmark.wasm");
+        let features = Features {
+            gc: false,
+            exceptions: false,
+            ..Features::default()
+        };
+        Module::from_bytes_with(data, features).parse().unwrap();
+    }
+}
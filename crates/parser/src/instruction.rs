@@ -1,10 +1,10 @@
-use wasmparser::{BinaryReader, VisitOperator};
-
 use crate::decode::Decoder;
+use crate::encode::Encoder;
 use crate::error::Error;
 use crate::types::{BlockType, HeapType, RefType, ValType};
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Instruction {
     Unreachable,
     Nop,
@@ -72,8 +72,8 @@ pub enum Instruction {
     I64Store8(MemArg),
     I64Store16(MemArg),
     I64Store32(MemArg),
-    MemorySize(u32, u8),
-    MemoryGrow(u32, u8),
+    MemorySize(u32),
+    MemoryGrow(u32),
     MemoryInit(u32, u32),
     DataDrop(u32),
     MemoryCopy(u32, u32),
@@ -605,13 +605,21 @@ pub enum Instruction {
     I64AtomicRmw32CmpxchgU(MemArg),
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct Expr(pub Vec<Instruction>);
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Expr {
+    pub instrs: Vec<Instruction>,
+    /// Offset of each instruction in the binary, only recorded on request,
+    /// see [Code::expr_with_offsets](crate::section::Code::expr_with_offsets).
+    pub offsets: Option<Vec<usize>>,
+}
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ConstExpr(pub Vec<Instruction>);
 
 impl Instruction {
+    /// Allowed in constant expressions, including the extended-const and gc additions.
     pub fn is_const(&self) -> bool {
         matches!(
             self,
@@ -623,63 +631,2394 @@ impl Instruction {
                 | Instruction::GlobalGet(_)
                 | Instruction::RefFunc(_)
                 | Instruction::RefNull(_)
-                | Instruction::RefIsNull
+                | Instruction::I32Add
+                | Instruction::I32Sub
+                | Instruction::I32Mul
+                | Instruction::I64Add
+                | Instruction::I64Sub
+                | Instruction::I64Mul
+                | Instruction::StructNew(_)
+                | Instruction::StructNewDefault(_)
+                | Instruction::ArrayNew(_)
+                | Instruction::ArrayNewDefault(_)
+                | Instruction::ArrayNewFixed(..)
+                | Instruction::RefI31
+                | Instruction::AnyConvertExtern
+                | Instruction::ExternConvertAny
         )
     }
+
+    /// The memory immediate of loads, stores, atomics and SIMD lane accesses.
+    pub fn memarg(&self) -> Option<&MemArg> {
+        match self {
+            Instruction::I32Load(arg)
+            | Instruction::I64Load(arg)
+            | Instruction::F32Load(arg)
+            | Instruction::F64Load(arg)
+            | Instruction::I32Load8S(arg)
+            | Instruction::I32Load8U(arg)
+            | Instruction::I32Load16S(arg)
+            | Instruction::I32Load16U(arg)
+            | Instruction::I64Load8S(arg)
+            | Instruction::I64Load8U(arg)
+            | Instruction::I64Load16S(arg)
+            | Instruction::I64Load16U(arg)
+            | Instruction::I64Load32S(arg)
+            | Instruction::I64Load32U(arg)
+            | Instruction::I32Store(arg)
+            | Instruction::I64Store(arg)
+            | Instruction::F32Store(arg)
+            | Instruction::F64Store(arg)
+            | Instruction::I32Store8(arg)
+            | Instruction::I32Store16(arg)
+            | Instruction::I64Store8(arg)
+            | Instruction::I64Store16(arg)
+            | Instruction::I64Store32(arg)
+            | Instruction::V128Load(arg)
+            | Instruction::V128Load8x8S(arg)
+            | Instruction::V128Load8x8U(arg)
+            | Instruction::V128Load16x4S(arg)
+            | Instruction::V128Load16x4U(arg)
+            | Instruction::V128Load32x2S(arg)
+            | Instruction::V128Load32x2U(arg)
+            | Instruction::V128Load8Splat(arg)
+            | Instruction::V128Load16Splat(arg)
+            | Instruction::V128Load32Splat(arg)
+            | Instruction::V128Load64Splat(arg)
+            | Instruction::V128Load32Zero(arg)
+            | Instruction::V128Load64Zero(arg)
+            | Instruction::V128Store(arg)
+            | Instruction::V128Load8Lane(arg, _)
+            | Instruction::V128Load16Lane(arg, _)
+            | Instruction::V128Load32Lane(arg, _)
+            | Instruction::V128Load64Lane(arg, _)
+            | Instruction::V128Store8Lane(arg, _)
+            | Instruction::V128Store16Lane(arg, _)
+            | Instruction::V128Store32Lane(arg, _)
+            | Instruction::V128Store64Lane(arg, _)
+            | Instruction::MemoryAtomicNotify(arg)
+            | Instruction::MemoryAtomicWait32(arg)
+            | Instruction::MemoryAtomicWait64(arg)
+            | Instruction::I32AtomicLoad(arg)
+            | Instruction::I64AtomicLoad(arg)
+            | Instruction::I32AtomicLoad8U(arg)
+            | Instruction::I32AtomicLoad16U(arg)
+            | Instruction::I64AtomicLoad8U(arg)
+            | Instruction::I64AtomicLoad16U(arg)
+            | Instruction::I64AtomicLoad32U(arg)
+            | Instruction::I32AtomicStore(arg)
+            | Instruction::I64AtomicStore(arg)
+            | Instruction::I32AtomicStore8(arg)
+            | Instruction::I32AtomicStore16(arg)
+            | Instruction::I64AtomicStore8(arg)
+            | Instruction::I64AtomicStore16(arg)
+            | Instruction::I64AtomicStore32(arg)
+            | Instruction::I32AtomicRmwAdd(arg)
+            | Instruction::I64AtomicRmwAdd(arg)
+            | Instruction::I32AtomicRmw8AddU(arg)
+            | Instruction::I32AtomicRmw16AddU(arg)
+            | Instruction::I64AtomicRmw8AddU(arg)
+            | Instruction::I64AtomicRmw16AddU(arg)
+            | Instruction::I64AtomicRmw32AddU(arg)
+            | Instruction::I32AtomicRmwSub(arg)
+            | Instruction::I64AtomicRmwSub(arg)
+            | Instruction::I32AtomicRmw8SubU(arg)
+            | Instruction::I32AtomicRmw16SubU(arg)
+            | Instruction::I64AtomicRmw8SubU(arg)
+            | Instruction::I64AtomicRmw16SubU(arg)
+            | Instruction::I64AtomicRmw32SubU(arg)
+            | Instruction::I32AtomicRmwAnd(arg)
+            | Instruction::I64AtomicRmwAnd(arg)
+            | Instruction::I32AtomicRmw8AndU(arg)
+            | Instruction::I32AtomicRmw16AndU(arg)
+            | Instruction::I64AtomicRmw8AndU(arg)
+            | Instruction::I64AtomicRmw16AndU(arg)
+            | Instruction::I64AtomicRmw32AndU(arg)
+            | Instruction::I32AtomicRmwOr(arg)
+            | Instruction::I64AtomicRmwOr(arg)
+            | Instruction::I32AtomicRmw8OrU(arg)
+            | Instruction::I32AtomicRmw16OrU(arg)
+            | Instruction::I64AtomicRmw8OrU(arg)
+            | Instruction::I64AtomicRmw16OrU(arg)
+            | Instruction::I64AtomicRmw32OrU(arg)
+            | Instruction::I32AtomicRmwXor(arg)
+            | Instruction::I64AtomicRmwXor(arg)
+            | Instruction::I32AtomicRmw8XorU(arg)
+            | Instruction::I32AtomicRmw16XorU(arg)
+            | Instruction::I64AtomicRmw8XorU(arg)
+            | Instruction::I64AtomicRmw16XorU(arg)
+            | Instruction::I64AtomicRmw32XorU(arg)
+            | Instruction::I32AtomicRmwXchg(arg)
+            | Instruction::I64AtomicRmwXchg(arg)
+            | Instruction::I32AtomicRmw8XchgU(arg)
+            | Instruction::I32AtomicRmw16XchgU(arg)
+            | Instruction::I64AtomicRmw8XchgU(arg)
+            | Instruction::I64AtomicRmw16XchgU(arg)
+            | Instruction::I64AtomicRmw32XchgU(arg)
+            | Instruction::I32AtomicRmwCmpxchg(arg)
+            | Instruction::I64AtomicRmwCmpxchg(arg)
+            | Instruction::I32AtomicRmw8CmpxchgU(arg)
+            | Instruction::I32AtomicRmw16CmpxchgU(arg)
+            | Instruction::I64AtomicRmw8CmpxchgU(arg)
+            | Instruction::I64AtomicRmw16CmpxchgU(arg)
+            | Instruction::I64AtomicRmw32CmpxchgU(arg) => Some(arg),
+            _ => None,
+        }
+    }
+
+    /// The natural alignment of a memory instruction, as a power of two of the bytes it accesses,
+    /// e.g. 1 for `i64.load16_s` or 3 for `v128.load8x8_s`.
+    pub fn natural_align(&self) -> Option<u8> {
+        match self {
+            Instruction::I32Load8S(_)
+            | Instruction::I32Load8U(_)
+            | Instruction::I64Load8S(_)
+            | Instruction::I64Load8U(_)
+            | Instruction::I32Store8(_)
+            | Instruction::I64Store8(_)
+            | Instruction::V128Load8Splat(_)
+            | Instruction::V128Load8Lane(_, _)
+            | Instruction::V128Store8Lane(_, _)
+            | Instruction::I32AtomicLoad8U(_)
+            | Instruction::I64AtomicLoad8U(_)
+            | Instruction::I32AtomicStore8(_)
+            | Instruction::I64AtomicStore8(_)
+            | Instruction::I32AtomicRmw8AddU(_)
+            | Instruction::I64AtomicRmw8AddU(_)
+            | Instruction::I32AtomicRmw8SubU(_)
+            | Instruction::I64AtomicRmw8SubU(_)
+            | Instruction::I32AtomicRmw8AndU(_)
+            | Instruction::I64AtomicRmw8AndU(_)
+            | Instruction::I32AtomicRmw8OrU(_)
+            | Instruction::I64AtomicRmw8OrU(_)
+            | Instruction::I32AtomicRmw8XorU(_)
+            | Instruction::I64AtomicRmw8XorU(_)
+            | Instruction::I32AtomicRmw8XchgU(_)
+            | Instruction::I64AtomicRmw8XchgU(_)
+            | Instruction::I32AtomicRmw8CmpxchgU(_)
+            | Instruction::I64AtomicRmw8CmpxchgU(_) => Some(0),
+            Instruction::I32Load16S(_)
+            | Instruction::I32Load16U(_)
+            | Instruction::I64Load16S(_)
+            | Instruction::I64Load16U(_)
+            | Instruction::I32Store16(_)
+            | Instruction::I64Store16(_)
+            | Instruction::V128Load16Splat(_)
+            | Instruction::V128Load16Lane(_, _)
+            | Instruction::V128Store16Lane(_, _)
+            | Instruction::I32AtomicLoad16U(_)
+            | Instruction::I64AtomicLoad16U(_)
+            | Instruction::I32AtomicStore16(_)
+            | Instruction::I64AtomicStore16(_)
+            | Instruction::I32AtomicRmw16AddU(_)
+            | Instruction::I64AtomicRmw16AddU(_)
+            | Instruction::I32AtomicRmw16SubU(_)
+            | Instruction::I64AtomicRmw16SubU(_)
+            | Instruction::I32AtomicRmw16AndU(_)
+            | Instruction::I64AtomicRmw16AndU(_)
+            | Instruction::I32AtomicRmw16OrU(_)
+            | Instruction::I64AtomicRmw16OrU(_)
+            | Instruction::I32AtomicRmw16XorU(_)
+            | Instruction::I64AtomicRmw16XorU(_)
+            | Instruction::I32AtomicRmw16XchgU(_)
+            | Instruction::I64AtomicRmw16XchgU(_)
+            | Instruction::I32AtomicRmw16CmpxchgU(_)
+            | Instruction::I64AtomicRmw16CmpxchgU(_) => Some(1),
+            Instruction::I32Load(_)
+            | Instruction::F32Load(_)
+            | Instruction::I64Load32S(_)
+            | Instruction::I64Load32U(_)
+            | Instruction::I32Store(_)
+            | Instruction::F32Store(_)
+            | Instruction::I64Store32(_)
+            | Instruction::V128Load32Splat(_)
+            | Instruction::V128Load32Zero(_)
+            | Instruction::V128Load32Lane(_, _)
+            | Instruction::V128Store32Lane(_, _)
+            | Instruction::MemoryAtomicNotify(_)
+            | Instruction::MemoryAtomicWait32(_)
+            | Instruction::I32AtomicLoad(_)
+            | Instruction::I64AtomicLoad32U(_)
+            | Instruction::I32AtomicStore(_)
+            | Instruction::I64AtomicStore32(_)
+            | Instruction::I32AtomicRmwAdd(_)
+            | Instruction::I64AtomicRmw32AddU(_)
+            | Instruction::I32AtomicRmwSub(_)
+            | Instruction::I64AtomicRmw32SubU(_)
+            | Instruction::I32AtomicRmwAnd(_)
+            | Instruction::I64AtomicRmw32AndU(_)
+            | Instruction::I32AtomicRmwOr(_)
+            | Instruction::I64AtomicRmw32OrU(_)
+            | Instruction::I32AtomicRmwXor(_)
+            | Instruction::I64AtomicRmw32XorU(_)
+            | Instruction::I32AtomicRmwXchg(_)
+            | Instruction::I64AtomicRmw32XchgU(_)
+            | Instruction::I32AtomicRmwCmpxchg(_)
+            | Instruction::I64AtomicRmw32CmpxchgU(_) => Some(2),
+            Instruction::I64Load(_)
+            | Instruction::F64Load(_)
+            | Instruction::I64Store(_)
+            | Instruction::F64Store(_)
+            | Instruction::V128Load8x8S(_)
+            | Instruction::V128Load8x8U(_)
+            | Instruction::V128Load16x4S(_)
+            | Instruction::V128Load16x4U(_)
+            | Instruction::V128Load32x2S(_)
+            | Instruction::V128Load32x2U(_)
+            | Instruction::V128Load64Splat(_)
+            | Instruction::V128Load64Zero(_)
+            | Instruction::V128Load64Lane(_, _)
+            | Instruction::V128Store64Lane(_, _)
+            | Instruction::MemoryAtomicWait64(_)
+            | Instruction::I64AtomicLoad(_)
+            | Instruction::I64AtomicStore(_)
+            | Instruction::I64AtomicRmwAdd(_)
+            | Instruction::I64AtomicRmwSub(_)
+            | Instruction::I64AtomicRmwAnd(_)
+            | Instruction::I64AtomicRmwOr(_)
+            | Instruction::I64AtomicRmwXor(_)
+            | Instruction::I64AtomicRmwXchg(_)
+            | Instruction::I64AtomicRmwCmpxchg(_) => Some(3),
+            Instruction::V128Load(_) | Instruction::V128Store(_) => Some(4),
+            _ => None,
+        }
+    }
 }
 
-macro_rules! define_visit_operator {
-    ($( @$proposal:ident $op:ident $({ $($arg:ident: $argty:ty),* })? => $visit:ident)*) => {
-        $(
-            fn $visit(&mut self $($(,$arg: $argty)*)?) -> Self::Output {
-                Instruction::$op $(( $($arg.into()),* ))?
+impl<'a, 'b> TryFrom<&'b mut Decoder<'a>> for Instruction {
+    type Error = Error;
+
+    fn try_from(decoder: &'b mut Decoder<'a>) -> Result<Self, Self::Error> {
+        Ok(match decoder.read_u8()? {
+            0x00 => Instruction::Unreachable,
+            0x01 => Instruction::Nop,
+            0x02 => Instruction::Block(decoder.read_block_type()?),
+            0x03 => Instruction::Loop(decoder.read_block_type()?),
+            0x04 => Instruction::If(decoder.read_block_type()?),
+            0x05 => Instruction::Else,
+            0x06 => Instruction::Try(decoder.read_block_type()?),
+            0x07 => Instruction::Catch(decoder.read_var_u32()?),
+            0x08 => Instruction::Throw(decoder.read_var_u32()?),
+            0x09 => Instruction::Rethrow(decoder.read_var_u32()?),
+            0x0a => Instruction::ThrowRef,
+            0x0b => Instruction::End,
+            0x0c => Instruction::Br(decoder.read_var_u32()?),
+            0x0d => Instruction::BrIf(decoder.read_var_u32()?),
+            0x0e => Instruction::BrTable(read_br_table(decoder)?),
+            0x0f => Instruction::Return,
+            0x10 => Instruction::Call(decoder.read_var_u32()?),
+            0x11 => {
+                let type_idx = decoder.read_var_u32()?;
+                let (table_byte, table) = read_first_byte_and_var_u32(decoder)?;
+                Instruction::CallIndirect(type_idx, table, table_byte as u32)
             }
-        )*
+            0x12 => Instruction::ReturnCall(decoder.read_var_u32()?),
+            0x13 => {
+                Instruction::ReturnCallIndirect(decoder.read_var_u32()?, decoder.read_var_u32()?)
+            }
+            0x14 => Instruction::CallRef(decoder.read_var_u32()?),
+            0x15 => Instruction::ReturnCallRef(decoder.read_var_u32()?),
+            0x18 => Instruction::Delegate(decoder.read_var_u32()?),
+            0x19 => Instruction::CatchAll,
+            0x1a => Instruction::Drop,
+            0x1b => Instruction::Select,
+            0x1c => {
+                let results = decoder.read_var_u32()?;
+                if results != 1 {
+                    return Err(Error::InvalidSelectArity(results));
+                }
+                Instruction::TypedSelect(decoder.read_valtype()?)
+            }
+            0x1f => Instruction::TryTable(read_try_table(decoder)?),
+            0x20 => Instruction::LocalGet(decoder.read_var_u32()?),
+            0x21 => Instruction::LocalSet(decoder.read_var_u32()?),
+            0x22 => Instruction::LocalTee(decoder.read_var_u32()?),
+            0x23 => Instruction::GlobalGet(decoder.read_var_u32()?),
+            0x24 => Instruction::GlobalSet(decoder.read_var_u32()?),
+            0x25 => Instruction::TableGet(decoder.read_var_u32()?),
+            0x26 => Instruction::TableSet(decoder.read_var_u32()?),
+            0x28 => Instruction::I32Load(read_memarg(decoder)?),
+            0x29 => Instruction::I64Load(read_memarg(decoder)?),
+            0x2a => Instruction::F32Load(read_memarg(decoder)?),
+            0x2b => Instruction::F64Load(read_memarg(decoder)?),
+            0x2c => Instruction::I32Load8S(read_memarg(decoder)?),
+            0x2d => Instruction::I32Load8U(read_memarg(decoder)?),
+            0x2e => Instruction::I32Load16S(read_memarg(decoder)?),
+            0x2f => Instruction::I32Load16U(read_memarg(decoder)?),
+            0x30 => Instruction::I64Load8S(read_memarg(decoder)?),
+            0x31 => Instruction::I64Load8U(read_memarg(decoder)?),
+            0x32 => Instruction::I64Load16S(read_memarg(decoder)?),
+            0x33 => Instruction::I64Load16U(read_memarg(decoder)?),
+            0x34 => Instruction::I64Load32S(read_memarg(decoder)?),
+            0x35 => Instruction::I64Load32U(read_memarg(decoder)?),
+            0x36 => Instruction::I32Store(read_memarg(decoder)?),
+            0x37 => Instruction::I64Store(read_memarg(decoder)?),
+            0x38 => Instruction::F32Store(read_memarg(decoder)?),
+            0x39 => Instruction::F64Store(read_memarg(decoder)?),
+            0x3a => Instruction::I32Store8(read_memarg(decoder)?),
+            0x3b => Instruction::I32Store16(read_memarg(decoder)?),
+            0x3c => Instruction::I64Store8(read_memarg(decoder)?),
+            0x3d => Instruction::I64Store16(read_memarg(decoder)?),
+            0x3e => Instruction::I64Store32(read_memarg(decoder)?),
+            0x3f => Instruction::MemorySize(decoder.read_var_u32()?),
+            0x40 => Instruction::MemoryGrow(decoder.read_var_u32()?),
+            0x41 => Instruction::I32Const(decoder.read_var_i32()?),
+            0x42 => Instruction::I64Const(decoder.read_var_i64()?),
+            0x43 => Instruction::F32Const(F32(f32::from_bits(decoder.read_u32()?))),
+            0x44 => {
+                Instruction::F64Const(F64(f64::from_bits(u64::from_le_bytes(decoder.read_n()?))))
+            }
+            0x45 => Instruction::I32Eqz,
+            0x46 => Instruction::I32Eq,
+            0x47 => Instruction::I32Ne,
+            0x48 => Instruction::I32LtS,
+            0x49 => Instruction::I32LtU,
+            0x4a => Instruction::I32GtS,
+            0x4b => Instruction::I32GtU,
+            0x4c => Instruction::I32LeS,
+            0x4d => Instruction::I32LeU,
+            0x4e => Instruction::I32GeS,
+            0x4f => Instruction::I32GeU,
+            0x50 => Instruction::I64Eqz,
+            0x51 => Instruction::I64Eq,
+            0x52 => Instruction::I64Ne,
+            0x53 => Instruction::I64LtS,
+            0x54 => Instruction::I64LtU,
+            0x55 => Instruction::I64GtS,
+            0x56 => Instruction::I64GtU,
+            0x57 => Instruction::I64LeS,
+            0x58 => Instruction::I64LeU,
+            0x59 => Instruction::I64GeS,
+            0x5a => Instruction::I64GeU,
+            0x5b => Instruction::F32Eq,
+            0x5c => Instruction::F32Ne,
+            0x5d => Instruction::F32Lt,
+            0x5e => Instruction::F32Gt,
+            0x5f => Instruction::F32Le,
+            0x60 => Instruction::F32Ge,
+            0x61 => Instruction::F64Eq,
+            0x62 => Instruction::F64Ne,
+            0x63 => Instruction::F64Lt,
+            0x64 => Instruction::F64Gt,
+            0x65 => Instruction::F64Le,
+            0x66 => Instruction::F64Ge,
+            0x67 => Instruction::I32Clz,
+            0x68 => Instruction::I32Ctz,
+            0x69 => Instruction::I32Popcnt,
+            0x6a => Instruction::I32Add,
+            0x6b => Instruction::I32Sub,
+            0x6c => Instruction::I32Mul,
+            0x6d => Instruction::I32DivS,
+            0x6e => Instruction::I32DivU,
+            0x6f => Instruction::I32RemS,
+            0x70 => Instruction::I32RemU,
+            0x71 => Instruction::I32And,
+            0x72 => Instruction::I32Or,
+            0x73 => Instruction::I32Xor,
+            0x74 => Instruction::I32Shl,
+            0x75 => Instruction::I32ShrS,
+            0x76 => Instruction::I32ShrU,
+            0x77 => Instruction::I32Rotl,
+            0x78 => Instruction::I32Rotr,
+            0x79 => Instruction::I64Clz,
+            0x7a => Instruction::I64Ctz,
+            0x7b => Instruction::I64Popcnt,
+            0x7c => Instruction::I64Add,
+            0x7d => Instruction::I64Sub,
+            0x7e => Instruction::I64Mul,
+            0x7f => Instruction::I64DivS,
+            0x80 => Instruction::I64DivU,
+            0x81 => Instruction::I64RemS,
+            0x82 => Instruction::I64RemU,
+            0x83 => Instruction::I64And,
+            0x84 => Instruction::I64Or,
+            0x85 => Instruction::I64Xor,
+            0x86 => Instruction::I64Shl,
+            0x87 => Instruction::I64ShrS,
+            0x88 => Instruction::I64ShrU,
+            0x89 => Instruction::I64Rotl,
+            0x8a => Instruction::I64Rotr,
+            0x8b => Instruction::F32Abs,
+            0x8c => Instruction::F32Neg,
+            0x8d => Instruction::F32Ceil,
+            0x8e => Instruction::F32Floor,
+            0x8f => Instruction::F32Trunc,
+            0x90 => Instruction::F32Nearest,
+            0x91 => Instruction::F32Sqrt,
+            0x92 => Instruction::F32Add,
+            0x93 => Instruction::F32Sub,
+            0x94 => Instruction::F32Mul,
+            0x95 => Instruction::F32Div,
+            0x96 => Instruction::F32Min,
+            0x97 => Instruction::F32Max,
+            0x98 => Instruction::F32Copysign,
+            0x99 => Instruction::F64Abs,
+            0x9a => Instruction::F64Neg,
+            0x9b => Instruction::F64Ceil,
+            0x9c => Instruction::F64Floor,
+            0x9d => Instruction::F64Trunc,
+            0x9e => Instruction::F64Nearest,
+            0x9f => Instruction::F64Sqrt,
+            0xa0 => Instruction::F64Add,
+            0xa1 => Instruction::F64Sub,
+            0xa2 => Instruction::F64Mul,
+            0xa3 => Instruction::F64Div,
+            0xa4 => Instruction::F64Min,
+            0xa5 => Instruction::F64Max,
+            0xa6 => Instruction::F64Copysign,
+            0xa7 => Instruction::I32WrapI64,
+            0xa8 => Instruction::I32TruncF32S,
+            0xa9 => Instruction::I32TruncF32U,
+            0xaa => Instruction::I32TruncF64S,
+            0xab => Instruction::I32TruncF64U,
+            0xac => Instruction::I64ExtendI32S,
+            0xad => Instruction::I64ExtendI32U,
+            0xae => Instruction::I64TruncF32S,
+            0xaf => Instruction::I64TruncF32U,
+            0xb0 => Instruction::I64TruncF64S,
+            0xb1 => Instruction::I64TruncF64U,
+            0xb2 => Instruction::F32ConvertI32S,
+            0xb3 => Instruction::F32ConvertI32U,
+            0xb4 => Instruction::F32ConvertI64S,
+            0xb5 => Instruction::F32ConvertI64U,
+            0xb6 => Instruction::F32DemoteF64,
+            0xb7 => Instruction::F64ConvertI32S,
+            0xb8 => Instruction::F64ConvertI32U,
+            0xb9 => Instruction::F64ConvertI64S,
+            0xba => Instruction::F64ConvertI64U,
+            0xbb => Instruction::F64PromoteF32,
+            0xbc => Instruction::I32ReinterpretF32,
+            0xbd => Instruction::I64ReinterpretF64,
+            0xbe => Instruction::F32ReinterpretI32,
+            0xbf => Instruction::F64ReinterpretI64,
+            0xc0 => Instruction::I32Extend8S,
+            0xc1 => Instruction::I32Extend16S,
+            0xc2 => Instruction::I64Extend8S,
+            0xc3 => Instruction::I64Extend16S,
+            0xc4 => Instruction::I64Extend32S,
+            0xd0 => Instruction::RefNull(decoder.read_heaptype()?),
+            0xd1 => Instruction::RefIsNull,
+            0xd2 => Instruction::RefFunc(decoder.read_var_u32()?),
+            0xd3 => Instruction::RefEq,
+            0xd4 => Instruction::RefAsNonNull,
+            0xd5 => Instruction::BrOnNull(decoder.read_var_u32()?),
+            0xd6 => Instruction::BrOnNonNull(decoder.read_var_u32()?),
+            0xfb => read_0xfb(decoder)?,
+            0xfc => read_0xfc(decoder)?,
+            0xfd => read_0xfd(decoder)?,
+            0xfe => read_0xfe(decoder)?,
+            byte => return Err(Error::InvalidInstruction(byte)),
+        })
+    }
+}
+
+/// GC instructions.
+fn read_0xfb(decoder: &mut Decoder) -> Result<Instruction, Error> {
+    Ok(match decoder.read_var_u32()? {
+        0x00 => Instruction::StructNew(decoder.read_var_u32()?),
+        0x01 => Instruction::StructNewDefault(decoder.read_var_u32()?),
+        0x02 => Instruction::StructGet(decoder.read_var_u32()?, decoder.read_var_u32()?),
+        0x03 => Instruction::StructGetS(decoder.read_var_u32()?, decoder.read_var_u32()?),
+        0x04 => Instruction::StructGetU(decoder.read_var_u32()?, decoder.read_var_u32()?),
+        0x05 => Instruction::StructSet(decoder.read_var_u32()?, decoder.read_var_u32()?),
+        0x06 => Instruction::ArrayNew(decoder.read_var_u32()?),
+        0x07 => Instruction::ArrayNewDefault(decoder.read_var_u32()?),
+        0x08 => Instruction::ArrayNewFixed(decoder.read_var_u32()?, decoder.read_var_u32()?),
+        0x09 => Instruction::ArrayNewData(decoder.read_var_u32()?, decoder.read_var_u32()?),
+        0x0a => Instruction::ArrayNewElem(decoder.read_var_u32()?, decoder.read_var_u32()?),
+        0x0b => Instruction::ArrayGet(decoder.read_var_u32()?),
+        0x0c => Instruction::ArrayGetS(decoder.read_var_u32()?),
+        0x0d => Instruction::ArrayGetU(decoder.read_var_u32()?),
+        0x0e => Instruction::ArraySet(decoder.read_var_u32()?),
+        0x0f => Instruction::ArrayLen,
+        0x10 => Instruction::ArrayFill(decoder.read_var_u32()?),
+        0x11 => Instruction::ArrayCopy(decoder.read_var_u32()?, decoder.read_var_u32()?),
+        0x12 => Instruction::ArrayInitData(decoder.read_var_u32()?, decoder.read_var_u32()?),
+        0x13 => Instruction::ArrayInitElem(decoder.read_var_u32()?, decoder.read_var_u32()?),
+        0x14 => Instruction::RefTestNonNull(decoder.read_heaptype()?),
+        0x15 => Instruction::RefTestNullable(decoder.read_heaptype()?),
+        0x16 => Instruction::RefCastNonNull(decoder.read_heaptype()?),
+        0x17 => Instruction::RefCastNullable(decoder.read_heaptype()?),
+        0x18 => {
+            let (label, from, to) = read_br_on_cast(decoder)?;
+            Instruction::BrOnCast(label, from, to)
+        }
+        0x19 => {
+            let (label, from, to) = read_br_on_cast(decoder)?;
+            Instruction::BrOnCastFail(label, from, to)
+        }
+        0x1a => Instruction::AnyConvertExtern,
+        0x1b => Instruction::ExternConvertAny,
+        0x1c => Instruction::RefI31,
+        0x1d => Instruction::I31GetS,
+        0x1e => Instruction::I31GetU,
+        op => return Err(Error::Unexpected0xfb(op)),
+    })
+}
+
+/// Saturating truncation, bulk memory and table instructions.
+fn read_0xfc(decoder: &mut Decoder) -> Result<Instruction, Error> {
+    Ok(match decoder.read_var_u32()? {
+        0x00 => Instruction::I32TruncSatF32S,
+        0x01 => Instruction::I32TruncSatF32U,
+        0x02 => Instruction::I32TruncSatF64S,
+        0x03 => Instruction::I32TruncSatF64U,
+        0x04 => Instruction::I64TruncSatF32S,
+        0x05 => Instruction::I64TruncSatF32U,
+        0x06 => Instruction::I64TruncSatF64S,
+        0x07 => Instruction::I64TruncSatF64U,
+        0x08 => Instruction::MemoryInit(decoder.read_var_u32()?, decoder.read_var_u32()?),
+        0x09 => Instruction::DataDrop(decoder.read_var_u32()?),
+        0x0a => Instruction::MemoryCopy(decoder.read_var_u32()?, decoder.read_var_u32()?),
+        0x0b => Instruction::MemoryFill(decoder.read_var_u32()?),
+        0x0c => Instruction::TableInit(decoder.read_var_u32()?, decoder.read_var_u32()?),
+        0x0d => Instruction::ElemDrop(decoder.read_var_u32()?),
+        0x0e => Instruction::TableCopy(decoder.read_var_u32()?, decoder.read_var_u32()?),
+        0x0f => Instruction::TableGrow(decoder.read_var_u32()?),
+        0x10 => Instruction::TableSize(decoder.read_var_u32()?),
+        0x11 => Instruction::TableFill(decoder.read_var_u32()?),
+        0x12 => Instruction::MemoryDiscard(decoder.read_var_u32()?),
+        op => return Err(Error::Unexpected0xfc(op)),
+    })
+}
+
+/// SIMD instructions.
+fn read_0xfd(decoder: &mut Decoder) -> Result<Instruction, Error> {
+    Ok(match decoder.read_var_u32()? {
+        0x00 => Instruction::V128Load(read_memarg(decoder)?),
+        0x01 => Instruction::V128Load8x8S(read_memarg(decoder)?),
+        0x02 => Instruction::V128Load8x8U(read_memarg(decoder)?),
+        0x03 => Instruction::V128Load16x4S(read_memarg(decoder)?),
+        0x04 => Instruction::V128Load16x4U(read_memarg(decoder)?),
+        0x05 => Instruction::V128Load32x2S(read_memarg(decoder)?),
+        0x06 => Instruction::V128Load32x2U(read_memarg(decoder)?),
+        0x07 => Instruction::V128Load8Splat(read_memarg(decoder)?),
+        0x08 => Instruction::V128Load16Splat(read_memarg(decoder)?),
+        0x09 => Instruction::V128Load32Splat(read_memarg(decoder)?),
+        0x0a => Instruction::V128Load64Splat(read_memarg(decoder)?),
+        0x0b => Instruction::V128Store(read_memarg(decoder)?),
+        0x0c => Instruction::V128Const(I128(i128::from_le_bytes(decoder.read_n()?))),
+        0x0d => {
+            let mut lanes = [0; 16];
+            for lane in &mut lanes {
+                *lane = read_lane(decoder, 32)?;
+            }
+            Instruction::I8x16Shuffle(lanes)
+        }
+        0x0e => Instruction::I8x16Swizzle,
+        0x0f => Instruction::I8x16Splat,
+        0x10 => Instruction::I16x8Splat,
+        0x11 => Instruction::I32x4Splat,
+        0x12 => Instruction::I64x2Splat,
+        0x13 => Instruction::F32x4Splat,
+        0x14 => Instruction::F64x2Splat,
+        0x15 => Instruction::I8x16ExtractLaneS(read_lane(decoder, 16)?),
+        0x16 => Instruction::I8x16ExtractLaneU(read_lane(decoder, 16)?),
+        0x17 => Instruction::I8x16ReplaceLane(read_lane(decoder, 16)?),
+        0x18 => Instruction::I16x8ExtractLaneS(read_lane(decoder, 8)?),
+        0x19 => Instruction::I16x8ExtractLaneU(read_lane(decoder, 8)?),
+        0x1a => Instruction::I16x8ReplaceLane(read_lane(decoder, 8)?),
+        0x1b => Instruction::I32x4ExtractLane(read_lane(decoder, 4)?),
+        0x1c => Instruction::I32x4ReplaceLane(read_lane(decoder, 4)?),
+        0x1d => Instruction::I64x2ExtractLane(read_lane(decoder, 2)?),
+        0x1e => Instruction::I64x2ReplaceLane(read_lane(decoder, 2)?),
+        0x1f => Instruction::F32x4ExtractLane(read_lane(decoder, 4)?),
+        0x20 => Instruction::F32x4ReplaceLane(read_lane(decoder, 4)?),
+        0x21 => Instruction::F64x2ExtractLane(read_lane(decoder, 2)?),
+        0x22 => Instruction::F64x2ReplaceLane(read_lane(decoder, 2)?),
+        0x23 => Instruction::I8x16Eq,
+        0x24 => Instruction::I8x16Ne,
+        0x25 => Instruction::I8x16LtS,
+        0x26 => Instruction::I8x16LtU,
+        0x27 => Instruction::I8x16GtS,
+        0x28 => Instruction::I8x16GtU,
+        0x29 => Instruction::I8x16LeS,
+        0x2a => Instruction::I8x16LeU,
+        0x2b => Instruction::I8x16GeS,
+        0x2c => Instruction::I8x16GeU,
+        0x2d => Instruction::I16x8Eq,
+        0x2e => Instruction::I16x8Ne,
+        0x2f => Instruction::I16x8LtS,
+        0x30 => Instruction::I16x8LtU,
+        0x31 => Instruction::I16x8GtS,
+        0x32 => Instruction::I16x8GtU,
+        0x33 => Instruction::I16x8LeS,
+        0x34 => Instruction::I16x8LeU,
+        0x35 => Instruction::I16x8GeS,
+        0x36 => Instruction::I16x8GeU,
+        0x37 => Instruction::I32x4Eq,
+        0x38 => Instruction::I32x4Ne,
+        0x39 => Instruction::I32x4LtS,
+        0x3a => Instruction::I32x4LtU,
+        0x3b => Instruction::I32x4GtS,
+        0x3c => Instruction::I32x4GtU,
+        0x3d => Instruction::I32x4LeS,
+        0x3e => Instruction::I32x4LeU,
+        0x3f => Instruction::I32x4GeS,
+        0x40 => Instruction::I32x4GeU,
+        0x41 => Instruction::F32x4Eq,
+        0x42 => Instruction::F32x4Ne,
+        0x43 => Instruction::F32x4Lt,
+        0x44 => Instruction::F32x4Gt,
+        0x45 => Instruction::F32x4Le,
+        0x46 => Instruction::F32x4Ge,
+        0x47 => Instruction::F64x2Eq,
+        0x48 => Instruction::F64x2Ne,
+        0x49 => Instruction::F64x2Lt,
+        0x4a => Instruction::F64x2Gt,
+        0x4b => Instruction::F64x2Le,
+        0x4c => Instruction::F64x2Ge,
+        0x4d => Instruction::V128Not,
+        0x4e => Instruction::V128And,
+        0x4f => Instruction::V128AndNot,
+        0x50 => Instruction::V128Or,
+        0x51 => Instruction::V128Xor,
+        0x52 => Instruction::V128Bitselect,
+        0x53 => Instruction::V128AnyTrue,
+        0x54 => Instruction::V128Load8Lane(read_memarg(decoder)?, read_lane(decoder, 16)?),
+        0x55 => Instruction::V128Load16Lane(read_memarg(decoder)?, read_lane(decoder, 8)?),
+        0x56 => Instruction::V128Load32Lane(read_memarg(decoder)?, read_lane(decoder, 4)?),
+        0x57 => Instruction::V128Load64Lane(read_memarg(decoder)?, read_lane(decoder, 2)?),
+        0x58 => Instruction::V128Store8Lane(read_memarg(decoder)?, read_lane(decoder, 16)?),
+        0x59 => Instruction::V128Store16Lane(read_memarg(decoder)?, read_lane(decoder, 8)?),
+        0x5a => Instruction::V128Store32Lane(read_memarg(decoder)?, read_lane(decoder, 4)?),
+        0x5b => Instruction::V128Store64Lane(read_memarg(decoder)?, read_lane(decoder, 2)?),
+        0x5c => Instruction::V128Load32Zero(read_memarg(decoder)?),
+        0x5d => Instruction::V128Load64Zero(read_memarg(decoder)?),
+        0x5e => Instruction::F32x4DemoteF64x2Zero,
+        0x5f => Instruction::F64x2PromoteLowF32x4,
+        0x60 => Instruction::I8x16Abs,
+        0x61 => Instruction::I8x16Neg,
+        0x62 => Instruction::I8x16Popcnt,
+        0x63 => Instruction::I8x16AllTrue,
+        0x64 => Instruction::I8x16Bitmask,
+        0x65 => Instruction::I8x16NarrowI16x8S,
+        0x66 => Instruction::I8x16NarrowI16x8U,
+        0x67 => Instruction::F32x4Ceil,
+        0x68 => Instruction::F32x4Floor,
+        0x69 => Instruction::F32x4Trunc,
+        0x6a => Instruction::F32x4Nearest,
+        0x6b => Instruction::I8x16Shl,
+        0x6c => Instruction::I8x16ShrS,
+        0x6d => Instruction::I8x16ShrU,
+        0x6e => Instruction::I8x16Add,
+        0x6f => Instruction::I8x16AddSatS,
+        0x70 => Instruction::I8x16AddSatU,
+        0x71 => Instruction::I8x16Sub,
+        0x72 => Instruction::I8x16SubSatS,
+        0x73 => Instruction::I8x16SubSatU,
+        0x74 => Instruction::F64x2Ceil,
+        0x75 => Instruction::F64x2Floor,
+        0x76 => Instruction::I8x16MinS,
+        0x77 => Instruction::I8x16MinU,
+        0x78 => Instruction::I8x16MaxS,
+        0x79 => Instruction::I8x16MaxU,
+        0x7a => Instruction::F64x2Trunc,
+        0x7b => Instruction::I8x16AvgrU,
+        0x7c => Instruction::I16x8ExtAddPairwiseI8x16S,
+        0x7d => Instruction::I16x8ExtAddPairwiseI8x16U,
+        0x7e => Instruction::I32x4ExtAddPairwiseI16x8S,
+        0x7f => Instruction::I32x4ExtAddPairwiseI16x8U,
+        0x80 => Instruction::I16x8Abs,
+        0x81 => Instruction::I16x8Neg,
+        0x82 => Instruction::I16x8Q15MulrSatS,
+        0x83 => Instruction::I16x8AllTrue,
+        0x84 => Instruction::I16x8Bitmask,
+        0x85 => Instruction::I16x8NarrowI32x4S,
+        0x86 => Instruction::I16x8NarrowI32x4U,
+        0x87 => Instruction::I16x8ExtendLowI8x16S,
+        0x88 => Instruction::I16x8ExtendHighI8x16S,
+        0x89 => Instruction::I16x8ExtendLowI8x16U,
+        0x8a => Instruction::I16x8ExtendHighI8x16U,
+        0x8b => Instruction::I16x8Shl,
+        0x8c => Instruction::I16x8ShrS,
+        0x8d => Instruction::I16x8ShrU,
+        0x8e => Instruction::I16x8Add,
+        0x8f => Instruction::I16x8AddSatS,
+        0x90 => Instruction::I16x8AddSatU,
+        0x91 => Instruction::I16x8Sub,
+        0x92 => Instruction::I16x8SubSatS,
+        0x93 => Instruction::I16x8SubSatU,
+        0x94 => Instruction::F64x2Nearest,
+        0x95 => Instruction::I16x8Mul,
+        0x96 => Instruction::I16x8MinS,
+        0x97 => Instruction::I16x8MinU,
+        0x98 => Instruction::I16x8MaxS,
+        0x99 => Instruction::I16x8MaxU,
+        0x9b => Instruction::I16x8AvgrU,
+        0x9c => Instruction::I16x8ExtMulLowI8x16S,
+        0x9d => Instruction::I16x8ExtMulHighI8x16S,
+        0x9e => Instruction::I16x8ExtMulLowI8x16U,
+        0x9f => Instruction::I16x8ExtMulHighI8x16U,
+        0xa0 => Instruction::I32x4Abs,
+        0xa1 => Instruction::I32x4Neg,
+        0xa3 => Instruction::I32x4AllTrue,
+        0xa4 => Instruction::I32x4Bitmask,
+        0xa7 => Instruction::I32x4ExtendLowI16x8S,
+        0xa8 => Instruction::I32x4ExtendHighI16x8S,
+        0xa9 => Instruction::I32x4ExtendLowI16x8U,
+        0xaa => Instruction::I32x4ExtendHighI16x8U,
+        0xab => Instruction::I32x4Shl,
+        0xac => Instruction::I32x4ShrS,
+        0xad => Instruction::I32x4ShrU,
+        0xae => Instruction::I32x4Add,
+        0xb1 => Instruction::I32x4Sub,
+        0xb5 => Instruction::I32x4Mul,
+        0xb6 => Instruction::I32x4MinS,
+        0xb7 => Instruction::I32x4MinU,
+        0xb8 => Instruction::I32x4MaxS,
+        0xb9 => Instruction::I32x4MaxU,
+        0xba => Instruction::I32x4DotI16x8S,
+        0xbc => Instruction::I32x4ExtMulLowI16x8S,
+        0xbd => Instruction::I32x4ExtMulHighI16x8S,
+        0xbe => Instruction::I32x4ExtMulLowI16x8U,
+        0xbf => Instruction::I32x4ExtMulHighI16x8U,
+        0xc0 => Instruction::I64x2Abs,
+        0xc1 => Instruction::I64x2Neg,
+        0xc3 => Instruction::I64x2AllTrue,
+        0xc4 => Instruction::I64x2Bitmask,
+        0xc7 => Instruction::I64x2ExtendLowI32x4S,
+        0xc8 => Instruction::I64x2ExtendHighI32x4S,
+        0xc9 => Instruction::I64x2ExtendLowI32x4U,
+        0xca => Instruction::I64x2ExtendHighI32x4U,
+        0xcb => Instruction::I64x2Shl,
+        0xcc => Instruction::I64x2ShrS,
+        0xcd => Instruction::I64x2ShrU,
+        0xce => Instruction::I64x2Add,
+        0xd1 => Instruction::I64x2Sub,
+        0xd5 => Instruction::I64x2Mul,
+        0xd6 => Instruction::I64x2Eq,
+        0xd7 => Instruction::I64x2Ne,
+        0xd8 => Instruction::I64x2LtS,
+        0xd9 => Instruction::I64x2GtS,
+        0xda => Instruction::I64x2LeS,
+        0xdb => Instruction::I64x2GeS,
+        0xdc => Instruction::I64x2ExtMulLowI32x4S,
+        0xdd => Instruction::I64x2ExtMulHighI32x4S,
+        0xde => Instruction::I64x2ExtMulLowI32x4U,
+        0xdf => Instruction::I64x2ExtMulHighI32x4U,
+        0xe0 => Instruction::F32x4Abs,
+        0xe1 => Instruction::F32x4Neg,
+        0xe3 => Instruction::F32x4Sqrt,
+        0xe4 => Instruction::F32x4Add,
+        0xe5 => Instruction::F32x4Sub,
+        0xe6 => Instruction::F32x4Mul,
+        0xe7 => Instruction::F32x4Div,
+        0xe8 => Instruction::F32x4Min,
+        0xe9 => Instruction::F32x4Max,
+        0xea => Instruction::F32x4PMin,
+        0xeb => Instruction::F32x4PMax,
+        0xec => Instruction::F64x2Abs,
+        0xed => Instruction::F64x2Neg,
+        0xef => Instruction::F64x2Sqrt,
+        0xf0 => Instruction::F64x2Add,
+        0xf1 => Instruction::F64x2Sub,
+        0xf2 => Instruction::F64x2Mul,
+        0xf3 => Instruction::F64x2Div,
+        0xf4 => Instruction::F64x2Min,
+        0xf5 => Instruction::F64x2Max,
+        0xf6 => Instruction::F64x2PMin,
+        0xf7 => Instruction::F64x2PMax,
+        0xf8 => Instruction::I32x4TruncSatF32x4S,
+        0xf9 => Instruction::I32x4TruncSatF32x4U,
+        0xfa => Instruction::F32x4ConvertI32x4S,
+        0xfb => Instruction::F32x4ConvertI32x4U,
+        0xfc => Instruction::I32x4TruncSatF64x2SZero,
+        0xfd => Instruction::I32x4TruncSatF64x2UZero,
+        0xfe => Instruction::F64x2ConvertLowI32x4S,
+        0xff => Instruction::F64x2ConvertLowI32x4U,
+        0x100 => Instruction::I8x16RelaxedSwizzle,
+        0x101 => Instruction::I32x4RelaxedTruncF32x4S,
+        0x102 => Instruction::I32x4RelaxedTruncF32x4U,
+        0x103 => Instruction::I32x4RelaxedTruncF64x2SZero,
+        0x104 => Instruction::I32x4RelaxedTruncF64x2UZero,
+        0x105 => Instruction::F32x4RelaxedMadd,
+        0x106 => Instruction::F32x4RelaxedNmadd,
+        0x107 => Instruction::F64x2RelaxedMadd,
+        0x108 => Instruction::F64x2RelaxedNmadd,
+        0x109 => Instruction::I8x16RelaxedLaneselect,
+        0x10a => Instruction::I16x8RelaxedLaneselect,
+        0x10b => Instruction::I32x4RelaxedLaneselect,
+        0x10c => Instruction::I64x2RelaxedLaneselect,
+        0x10d => Instruction::F32x4RelaxedMin,
+        0x10e => Instruction::F32x4RelaxedMax,
+        0x10f => Instruction::F64x2RelaxedMin,
+        0x110 => Instruction::F64x2RelaxedMax,
+        0x111 => Instruction::I16x8RelaxedQ15mulrS,
+        0x112 => Instruction::I16x8RelaxedDotI8x16I7x16S,
+        0x113 => Instruction::I32x4RelaxedDotI8x16I7x16AddS,
+        op => return Err(Error::Unexpected0xfd(op)),
+    })
+}
+
+/// Atomic instructions of the threads proposal.
+fn read_0xfe(decoder: &mut Decoder) -> Result<Instruction, Error> {
+    Ok(match decoder.read_var_u32()? {
+        0x00 => Instruction::MemoryAtomicNotify(read_memarg(decoder)?),
+        0x01 => Instruction::MemoryAtomicWait32(read_memarg(decoder)?),
+        0x02 => Instruction::MemoryAtomicWait64(read_memarg(decoder)?),
+        0x03 => match decoder.read_u8()? {
+            0x00 => Instruction::AtomicFence,
+            byte => return Err(Error::InvalidLeadingByte(byte, "atomic.fence")),
+        },
+        0x10 => Instruction::I32AtomicLoad(read_memarg(decoder)?),
+        0x11 => Instruction::I64AtomicLoad(read_memarg(decoder)?),
+        0x12 => Instruction::I32AtomicLoad8U(read_memarg(decoder)?),
+        0x13 => Instruction::I32AtomicLoad16U(read_memarg(decoder)?),
+        0x14 => Instruction::I64AtomicLoad8U(read_memarg(decoder)?),
+        0x15 => Instruction::I64AtomicLoad16U(read_memarg(decoder)?),
+        0x16 => Instruction::I64AtomicLoad32U(read_memarg(decoder)?),
+        0x17 => Instruction::I32AtomicStore(read_memarg(decoder)?),
+        0x18 => Instruction::I64AtomicStore(read_memarg(decoder)?),
+        0x19 => Instruction::I32AtomicStore8(read_memarg(decoder)?),
+        0x1a => Instruction::I32AtomicStore16(read_memarg(decoder)?),
+        0x1b => Instruction::I64AtomicStore8(read_memarg(decoder)?),
+        0x1c => Instruction::I64AtomicStore16(read_memarg(decoder)?),
+        0x1d => Instruction::I64AtomicStore32(read_memarg(decoder)?),
+        0x1e => Instruction::I32AtomicRmwAdd(read_memarg(decoder)?),
+        0x1f => Instruction::I64AtomicRmwAdd(read_memarg(decoder)?),
+        0x20 => Instruction::I32AtomicRmw8AddU(read_memarg(decoder)?),
+        0x21 => Instruction::I32AtomicRmw16AddU(read_memarg(decoder)?),
+        0x22 => Instruction::I64AtomicRmw8AddU(read_memarg(decoder)?),
+        0x23 => Instruction::I64AtomicRmw16AddU(read_memarg(decoder)?),
+        0x24 => Instruction::I64AtomicRmw32AddU(read_memarg(decoder)?),
+        0x25 => Instruction::I32AtomicRmwSub(read_memarg(decoder)?),
+        0x26 => Instruction::I64AtomicRmwSub(read_memarg(decoder)?),
+        0x27 => Instruction::I32AtomicRmw8SubU(read_memarg(decoder)?),
+        0x28 => Instruction::I32AtomicRmw16SubU(read_memarg(decoder)?),
+        0x29 => Instruction::I64AtomicRmw8SubU(read_memarg(decoder)?),
+        0x2a => Instruction::I64AtomicRmw16SubU(read_memarg(decoder)?),
+        0x2b => Instruction::I64AtomicRmw32SubU(read_memarg(decoder)?),
+        0x2c => Instruction::I32AtomicRmwAnd(read_memarg(decoder)?),
+        0x2d => Instruction::I64AtomicRmwAnd(read_memarg(decoder)?),
+        0x2e => Instruction::I32AtomicRmw8AndU(read_memarg(decoder)?),
+        0x2f => Instruction::I32AtomicRmw16AndU(read_memarg(decoder)?),
+        0x30 => Instruction::I64AtomicRmw8AndU(read_memarg(decoder)?),
+        0x31 => Instruction::I64AtomicRmw16AndU(read_memarg(decoder)?),
+        0x32 => Instruction::I64AtomicRmw32AndU(read_memarg(decoder)?),
+        0x33 => Instruction::I32AtomicRmwOr(read_memarg(decoder)?),
+        0x34 => Instruction::I64AtomicRmwOr(read_memarg(decoder)?),
+        0x35 => Instruction::I32AtomicRmw8OrU(read_memarg(decoder)?),
+        0x36 => Instruction::I32AtomicRmw16OrU(read_memarg(decoder)?),
+        0x37 => Instruction::I64AtomicRmw8OrU(read_memarg(decoder)?),
+        0x38 => Instruction::I64AtomicRmw16OrU(read_memarg(decoder)?),
+        0x39 => Instruction::I64AtomicRmw32OrU(read_memarg(decoder)?),
+        0x3a => Instruction::I32AtomicRmwXor(read_memarg(decoder)?),
+        0x3b => Instruction::I64AtomicRmwXor(read_memarg(decoder)?),
+        0x3c => Instruction::I32AtomicRmw8XorU(read_memarg(decoder)?),
+        0x3d => Instruction::I32AtomicRmw16XorU(read_memarg(decoder)?),
+        0x3e => Instruction::I64AtomicRmw8XorU(read_memarg(decoder)?),
+        0x3f => Instruction::I64AtomicRmw16XorU(read_memarg(decoder)?),
+        0x40 => Instruction::I64AtomicRmw32XorU(read_memarg(decoder)?),
+        0x41 => Instruction::I32AtomicRmwXchg(read_memarg(decoder)?),
+        0x42 => Instruction::I64AtomicRmwXchg(read_memarg(decoder)?),
+        0x43 => Instruction::I32AtomicRmw8XchgU(read_memarg(decoder)?),
+        0x44 => Instruction::I32AtomicRmw16XchgU(read_memarg(decoder)?),
+        0x45 => Instruction::I64AtomicRmw8XchgU(read_memarg(decoder)?),
+        0x46 => Instruction::I64AtomicRmw16XchgU(read_memarg(decoder)?),
+        0x47 => Instruction::I64AtomicRmw32XchgU(read_memarg(decoder)?),
+        0x48 => Instruction::I32AtomicRmwCmpxchg(read_memarg(decoder)?),
+        0x49 => Instruction::I64AtomicRmwCmpxchg(read_memarg(decoder)?),
+        0x4a => Instruction::I32AtomicRmw8CmpxchgU(read_memarg(decoder)?),
+        0x4b => Instruction::I32AtomicRmw16CmpxchgU(read_memarg(decoder)?),
+        0x4c => Instruction::I64AtomicRmw8CmpxchgU(read_memarg(decoder)?),
+        0x4d => Instruction::I64AtomicRmw16CmpxchgU(read_memarg(decoder)?),
+        0x4e => Instruction::I64AtomicRmw32CmpxchgU(read_memarg(decoder)?),
+        op => return Err(Error::Unexpected0xfe(op)),
+    })
+}
+
+/// `memarg ::= a:u32 o:u32`, bit 6 of `a` announces a memory index between the two.
+fn read_memarg(decoder: &mut Decoder) -> Result<MemArg, Error> {
+    let flags = decoder.read_var_u32()?;
+    let memory = if flags & (1 << 6) != 0 {
+        decoder.read_var_u32()?
+    } else {
+        0
     };
+    let align = flags & !(1 << 6);
+    if align >= 64 {
+        return Err(Error::AlignmentTooLarge(align));
+    }
+    Ok(MemArg {
+        align: align as u8,
+        // Always a u64 in the binary, only 64-bit memories may use offsets above `u32::MAX`.
+        offset: decoder.read_var_u64()?,
+        memory,
+    })
 }
 
-pub struct InstructionVisitor;
+fn read_lane(decoder: &mut Decoder, lanes: u8) -> Result<Lane, Error> {
+    let lane = decoder.read_u8()?;
+    if lane >= lanes {
+        return Err(Error::InvalidLaneIndex(lane, lanes));
+    }
+    Ok(lane)
+}
 
-impl<'a> VisitOperator<'a> for InstructionVisitor {
-    type Output = Instruction;
+fn read_br_table(decoder: &mut Decoder) -> Result<BrTable, Error> {
+    Ok(BrTable {
+        targets: decoder.read_svec(Decoder::read_var_u32)?,
+        default: decoder.read_var_u32()?,
+    })
+}
 
-    wasmparser::for_each_operator!(define_visit_operator);
+fn read_try_table(decoder: &mut Decoder) -> Result<TryTable, Error> {
+    let ty = decoder.read_block_type()?;
+    let catches = decoder.read_vec(|decoder| {
+        Ok(match decoder.read_u8()? {
+            0x00 => Catch::Catch {
+                tag: decoder.read_var_u32()?,
+                label: decoder.read_var_u32()?,
+            },
+            0x01 => Catch::CatchRef {
+                tag: decoder.read_var_u32()?,
+                label: decoder.read_var_u32()?,
+            },
+            0x02 => Catch::CatchAll {
+                label: decoder.read_var_u32()?,
+            },
+            0x03 => Catch::CatchAllRef {
+                label: decoder.read_var_u32()?,
+            },
+            byte => return Err(Error::InvalidLeadingByte(byte, "catch")),
+        })
+    })?;
+    Ok(TryTable { ty, catches })
 }
 
-impl<'a, 'b> TryFrom<&'b mut Decoder<'a>> for Instruction {
-    type Error = Error;
+/// `br_on_cast` and `br_on_cast_fail` carry the nullability of both types in a flags byte.
+fn read_br_on_cast(decoder: &mut Decoder) -> Result<(u32, RefType, RefType), Error> {
+    let flags = decoder.read_u8()?;
+    if flags > 0b11 {
+        return Err(Error::InvalidFlags(flags as u32, "br_on_cast"));
+    }
+    let label = decoder.read_var_u32()?;
+    let from = RefType::new(flags & 0b01 != 0, decoder.read_heaptype()?);
+    let to = RefType::new(flags & 0b10 != 0, decoder.read_heaptype()?);
+    Ok((label, from, to))
+}
 
-    fn try_from(decoder: &'b mut Decoder<'a>) -> Result<Self, Self::Error> {
-        let mut reader = BinaryReader::new_with_offset(
-            decoder.remaining_slice(),
-            decoder.reader.position() as usize,
-        );
-        let instr = reader.visit_operator(&mut InstructionVisitor)?;
-        decoder
-            .reader
-            .set_position(reader.original_position() as u64);
-        Ok(instr)
+/// The MVP encodes the table of `call_indirect` as a zero byte, the raw byte is kept
+/// so that the LEB128 table index of a reference-types binary can be told apart from it.
+fn read_first_byte_and_var_u32(decoder: &mut Decoder) -> Result<(u8, u32), Error> {
+    let byte = *decoder
+        .remaining_slice()
+        .first()
+        .ok_or(Error::UnexpectedEof(1))?;
+    Ok((byte, decoder.read_var_u32()?))
+}
+
+impl Instruction {
+    /// The opcode and, for the `0xfb`-`0xfe` prefixed instructions, the prefix byte before it.
+    pub fn opcode(&self) -> (Option<u8>, u32) {
+        match self {
+            Instruction::Unreachable => (None, 0x00),
+            Instruction::Nop => (None, 0x01),
+            Instruction::Block(_) => (None, 0x02),
+            Instruction::Loop(_) => (None, 0x03),
+            Instruction::If(_) => (None, 0x04),
+            Instruction::Else => (None, 0x05),
+            Instruction::Try(_) => (None, 0x06),
+            Instruction::Catch(_) => (None, 0x07),
+            Instruction::Throw(_) => (None, 0x08),
+            Instruction::Rethrow(_) => (None, 0x09),
+            Instruction::ThrowRef => (None, 0x0a),
+            Instruction::End => (None, 0x0b),
+            Instruction::Br(_) => (None, 0x0c),
+            Instruction::BrIf(_) => (None, 0x0d),
+            Instruction::BrTable(_) => (None, 0x0e),
+            Instruction::Return => (None, 0x0f),
+            Instruction::Call(_) => (None, 0x10),
+            Instruction::CallIndirect(..) => (None, 0x11),
+            Instruction::ReturnCall(_) => (None, 0x12),
+            Instruction::ReturnCallIndirect(..) => (None, 0x13),
+            Instruction::CallRef(_) => (None, 0x14),
+            Instruction::ReturnCallRef(_) => (None, 0x15),
+            Instruction::Delegate(_) => (None, 0x18),
+            Instruction::CatchAll => (None, 0x19),
+            Instruction::Drop => (None, 0x1a),
+            Instruction::Select => (None, 0x1b),
+            Instruction::TypedSelect(_) => (None, 0x1c),
+            Instruction::TryTable(_) => (None, 0x1f),
+            Instruction::LocalGet(_) => (None, 0x20),
+            Instruction::LocalSet(_) => (None, 0x21),
+            Instruction::LocalTee(_) => (None, 0x22),
+            Instruction::GlobalGet(_) => (None, 0x23),
+            Instruction::GlobalSet(_) => (None, 0x24),
+            Instruction::TableGet(_) => (None, 0x25),
+            Instruction::TableSet(_) => (None, 0x26),
+            Instruction::I32Load(_) => (None, 0x28),
+            Instruction::I64Load(_) => (None, 0x29),
+            Instruction::F32Load(_) => (None, 0x2a),
+            Instruction::F64Load(_) => (None, 0x2b),
+            Instruction::I32Load8S(_) => (None, 0x2c),
+            Instruction::I32Load8U(_) => (None, 0x2d),
+            Instruction::I32Load16S(_) => (None, 0x2e),
+            Instruction::I32Load16U(_) => (None, 0x2f),
+            Instruction::I64Load8S(_) => (None, 0x30),
+            Instruction::I64Load8U(_) => (None, 0x31),
+            Instruction::I64Load16S(_) => (None, 0x32),
+            Instruction::I64Load16U(_) => (None, 0x33),
+            Instruction::I64Load32S(_) => (None, 0x34),
+            Instruction::I64Load32U(_) => (None, 0x35),
+            Instruction::I32Store(_) => (None, 0x36),
+            Instruction::I64Store(_) => (None, 0x37),
+            Instruction::F32Store(_) => (None, 0x38),
+            Instruction::F64Store(_) => (None, 0x39),
+            Instruction::I32Store8(_) => (None, 0x3a),
+            Instruction::I32Store16(_) => (None, 0x3b),
+            Instruction::I64Store8(_) => (None, 0x3c),
+            Instruction::I64Store16(_) => (None, 0x3d),
+            Instruction::I64Store32(_) => (None, 0x3e),
+            Instruction::MemorySize(_) => (None, 0x3f),
+            Instruction::MemoryGrow(_) => (None, 0x40),
+            Instruction::I32Const(_) => (None, 0x41),
+            Instruction::I64Const(_) => (None, 0x42),
+            Instruction::F32Const(_) => (None, 0x43),
+            Instruction::F64Const(_) => (None, 0x44),
+            Instruction::I32Eqz => (None, 0x45),
+            Instruction::I32Eq => (None, 0x46),
+            Instruction::I32Ne => (None, 0x47),
+            Instruction::I32LtS => (None, 0x48),
+            Instruction::I32LtU => (None, 0x49),
+            Instruction::I32GtS => (None, 0x4a),
+            Instruction::I32GtU => (None, 0x4b),
+            Instruction::I32LeS => (None, 0x4c),
+            Instruction::I32LeU => (None, 0x4d),
+            Instruction::I32GeS => (None, 0x4e),
+            Instruction::I32GeU => (None, 0x4f),
+            Instruction::I64Eqz => (None, 0x50),
+            Instruction::I64Eq => (None, 0x51),
+            Instruction::I64Ne => (None, 0x52),
+            Instruction::I64LtS => (None, 0x53),
+            Instruction::I64LtU => (None, 0x54),
+            Instruction::I64GtS => (None, 0x55),
+            Instruction::I64GtU => (None, 0x56),
+            Instruction::I64LeS => (None, 0x57),
+            Instruction::I64LeU => (None, 0x58),
+            Instruction::I64GeS => (None, 0x59),
+            Instruction::I64GeU => (None, 0x5a),
+            Instruction::F32Eq => (None, 0x5b),
+            Instruction::F32Ne => (None, 0x5c),
+            Instruction::F32Lt => (None, 0x5d),
+            Instruction::F32Gt => (None, 0x5e),
+            Instruction::F32Le => (None, 0x5f),
+            Instruction::F32Ge => (None, 0x60),
+            Instruction::F64Eq => (None, 0x61),
+            Instruction::F64Ne => (None, 0x62),
+            Instruction::F64Lt => (None, 0x63),
+            Instruction::F64Gt => (None, 0x64),
+            Instruction::F64Le => (None, 0x65),
+            Instruction::F64Ge => (None, 0x66),
+            Instruction::I32Clz => (None, 0x67),
+            Instruction::I32Ctz => (None, 0x68),
+            Instruction::I32Popcnt => (None, 0x69),
+            Instruction::I32Add => (None, 0x6a),
+            Instruction::I32Sub => (None, 0x6b),
+            Instruction::I32Mul => (None, 0x6c),
+            Instruction::I32DivS => (None, 0x6d),
+            Instruction::I32DivU => (None, 0x6e),
+            Instruction::I32RemS => (None, 0x6f),
+            Instruction::I32RemU => (None, 0x70),
+            Instruction::I32And => (None, 0x71),
+            Instruction::I32Or => (None, 0x72),
+            Instruction::I32Xor => (None, 0x73),
+            Instruction::I32Shl => (None, 0x74),
+            Instruction::I32ShrS => (None, 0x75),
+            Instruction::I32ShrU => (None, 0x76),
+            Instruction::I32Rotl => (None, 0x77),
+            Instruction::I32Rotr => (None, 0x78),
+            Instruction::I64Clz => (None, 0x79),
+            Instruction::I64Ctz => (None, 0x7a),
+            Instruction::I64Popcnt => (None, 0x7b),
+            Instruction::I64Add => (None, 0x7c),
+            Instruction::I64Sub => (None, 0x7d),
+            Instruction::I64Mul => (None, 0x7e),
+            Instruction::I64DivS => (None, 0x7f),
+            Instruction::I64DivU => (None, 0x80),
+            Instruction::I64RemS => (None, 0x81),
+            Instruction::I64RemU => (None, 0x82),
+            Instruction::I64And => (None, 0x83),
+            Instruction::I64Or => (None, 0x84),
+            Instruction::I64Xor => (None, 0x85),
+            Instruction::I64Shl => (None, 0x86),
+            Instruction::I64ShrS => (None, 0x87),
+            Instruction::I64ShrU => (None, 0x88),
+            Instruction::I64Rotl => (None, 0x89),
+            Instruction::I64Rotr => (None, 0x8a),
+            Instruction::F32Abs => (None, 0x8b),
+            Instruction::F32Neg => (None, 0x8c),
+            Instruction::F32Ceil => (None, 0x8d),
+            Instruction::F32Floor => (None, 0x8e),
+            Instruction::F32Trunc => (None, 0x8f),
+            Instruction::F32Nearest => (None, 0x90),
+            Instruction::F32Sqrt => (None, 0x91),
+            Instruction::F32Add => (None, 0x92),
+            Instruction::F32Sub => (None, 0x93),
+            Instruction::F32Mul => (None, 0x94),
+            Instruction::F32Div => (None, 0x95),
+            Instruction::F32Min => (None, 0x96),
+            Instruction::F32Max => (None, 0x97),
+            Instruction::F32Copysign => (None, 0x98),
+            Instruction::F64Abs => (None, 0x99),
+            Instruction::F64Neg => (None, 0x9a),
+            Instruction::F64Ceil => (None, 0x9b),
+            Instruction::F64Floor => (None, 0x9c),
+            Instruction::F64Trunc => (None, 0x9d),
+            Instruction::F64Nearest => (None, 0x9e),
+            Instruction::F64Sqrt => (None, 0x9f),
+            Instruction::F64Add => (None, 0xa0),
+            Instruction::F64Sub => (None, 0xa1),
+            Instruction::F64Mul => (None, 0xa2),
+            Instruction::F64Div => (None, 0xa3),
+            Instruction::F64Min => (None, 0xa4),
+            Instruction::F64Max => (None, 0xa5),
+            Instruction::F64Copysign => (None, 0xa6),
+            Instruction::I32WrapI64 => (None, 0xa7),
+            Instruction::I32TruncF32S => (None, 0xa8),
+            Instruction::I32TruncF32U => (None, 0xa9),
+            Instruction::I32TruncF64S => (None, 0xaa),
+            Instruction::I32TruncF64U => (None, 0xab),
+            Instruction::I64ExtendI32S => (None, 0xac),
+            Instruction::I64ExtendI32U => (None, 0xad),
+            Instruction::I64TruncF32S => (None, 0xae),
+            Instruction::I64TruncF32U => (None, 0xaf),
+            Instruction::I64TruncF64S => (None, 0xb0),
+            Instruction::I64TruncF64U => (None, 0xb1),
+            Instruction::F32ConvertI32S => (None, 0xb2),
+            Instruction::F32ConvertI32U => (None, 0xb3),
+            Instruction::F32ConvertI64S => (None, 0xb4),
+            Instruction::F32ConvertI64U => (None, 0xb5),
+            Instruction::F32DemoteF64 => (None, 0xb6),
+            Instruction::F64ConvertI32S => (None, 0xb7),
+            Instruction::F64ConvertI32U => (None, 0xb8),
+            Instruction::F64ConvertI64S => (None, 0xb9),
+            Instruction::F64ConvertI64U => (None, 0xba),
+            Instruction::F64PromoteF32 => (None, 0xbb),
+            Instruction::I32ReinterpretF32 => (None, 0xbc),
+            Instruction::I64ReinterpretF64 => (None, 0xbd),
+            Instruction::F32ReinterpretI32 => (None, 0xbe),
+            Instruction::F64ReinterpretI64 => (None, 0xbf),
+            Instruction::I32Extend8S => (None, 0xc0),
+            Instruction::I32Extend16S => (None, 0xc1),
+            Instruction::I64Extend8S => (None, 0xc2),
+            Instruction::I64Extend16S => (None, 0xc3),
+            Instruction::I64Extend32S => (None, 0xc4),
+            Instruction::RefNull(_) => (None, 0xd0),
+            Instruction::RefIsNull => (None, 0xd1),
+            Instruction::RefFunc(_) => (None, 0xd2),
+            Instruction::RefEq => (None, 0xd3),
+            Instruction::RefAsNonNull => (None, 0xd4),
+            Instruction::BrOnNull(_) => (None, 0xd5),
+            Instruction::BrOnNonNull(_) => (None, 0xd6),
+            Instruction::StructNew(_) => (Some(0xfb), 0x00),
+            Instruction::StructNewDefault(_) => (Some(0xfb), 0x01),
+            Instruction::StructGet(..) => (Some(0xfb), 0x02),
+            Instruction::StructGetS(..) => (Some(0xfb), 0x03),
+            Instruction::StructGetU(..) => (Some(0xfb), 0x04),
+            Instruction::StructSet(..) => (Some(0xfb), 0x05),
+            Instruction::ArrayNew(_) => (Some(0xfb), 0x06),
+            Instruction::ArrayNewDefault(_) => (Some(0xfb), 0x07),
+            Instruction::ArrayNewFixed(..) => (Some(0xfb), 0x08),
+            Instruction::ArrayNewData(..) => (Some(0xfb), 0x09),
+            Instruction::ArrayNewElem(..) => (Some(0xfb), 0x0a),
+            Instruction::ArrayGet(_) => (Some(0xfb), 0x0b),
+            Instruction::ArrayGetS(_) => (Some(0xfb), 0x0c),
+            Instruction::ArrayGetU(_) => (Some(0xfb), 0x0d),
+            Instruction::ArraySet(_) => (Some(0xfb), 0x0e),
+            Instruction::ArrayLen => (Some(0xfb), 0x0f),
+            Instruction::ArrayFill(_) => (Some(0xfb), 0x10),
+            Instruction::ArrayCopy(..) => (Some(0xfb), 0x11),
+            Instruction::ArrayInitData(..) => (Some(0xfb), 0x12),
+            Instruction::ArrayInitElem(..) => (Some(0xfb), 0x13),
+            Instruction::RefTestNonNull(_) => (Some(0xfb), 0x14),
+            Instruction::RefTestNullable(_) => (Some(0xfb), 0x15),
+            Instruction::RefCastNonNull(_) => (Some(0xfb), 0x16),
+            Instruction::RefCastNullable(_) => (Some(0xfb), 0x17),
+            Instruction::BrOnCast(..) => (Some(0xfb), 0x18),
+            Instruction::BrOnCastFail(..) => (Some(0xfb), 0x19),
+            Instruction::AnyConvertExtern => (Some(0xfb), 0x1a),
+            Instruction::ExternConvertAny => (Some(0xfb), 0x1b),
+            Instruction::RefI31 => (Some(0xfb), 0x1c),
+            Instruction::I31GetS => (Some(0xfb), 0x1d),
+            Instruction::I31GetU => (Some(0xfb), 0x1e),
+            Instruction::I32TruncSatF32S => (Some(0xfc), 0x00),
+            Instruction::I32TruncSatF32U => (Some(0xfc), 0x01),
+            Instruction::I32TruncSatF64S => (Some(0xfc), 0x02),
+            Instruction::I32TruncSatF64U => (Some(0xfc), 0x03),
+            Instruction::I64TruncSatF32S => (Some(0xfc), 0x04),
+            Instruction::I64TruncSatF32U => (Some(0xfc), 0x05),
+            Instruction::I64TruncSatF64S => (Some(0xfc), 0x06),
+            Instruction::I64TruncSatF64U => (Some(0xfc), 0x07),
+            Instruction::MemoryInit(..) => (Some(0xfc), 0x08),
+            Instruction::DataDrop(_) => (Some(0xfc), 0x09),
+            Instruction::MemoryCopy(..) => (Some(0xfc), 0x0a),
+            Instruction::MemoryFill(_) => (Some(0xfc), 0x0b),
+            Instruction::TableInit(..) => (Some(0xfc), 0x0c),
+            Instruction::ElemDrop(_) => (Some(0xfc), 0x0d),
+            Instruction::TableCopy(..) => (Some(0xfc), 0x0e),
+            Instruction::TableGrow(_) => (Some(0xfc), 0x0f),
+            Instruction::TableSize(_) => (Some(0xfc), 0x10),
+            Instruction::TableFill(_) => (Some(0xfc), 0x11),
+            Instruction::MemoryDiscard(_) => (Some(0xfc), 0x12),
+            Instruction::V128Load(_) => (Some(0xfd), 0x00),
+            Instruction::V128Load8x8S(_) => (Some(0xfd), 0x01),
+            Instruction::V128Load8x8U(_) => (Some(0xfd), 0x02),
+            Instruction::V128Load16x4S(_) => (Some(0xfd), 0x03),
+            Instruction::V128Load16x4U(_) => (Some(0xfd), 0x04),
+            Instruction::V128Load32x2S(_) => (Some(0xfd), 0x05),
+            Instruction::V128Load32x2U(_) => (Some(0xfd), 0x06),
+            Instruction::V128Load8Splat(_) => (Some(0xfd), 0x07),
+            Instruction::V128Load16Splat(_) => (Some(0xfd), 0x08),
+            Instruction::V128Load32Splat(_) => (Some(0xfd), 0x09),
+            Instruction::V128Load64Splat(_) => (Some(0xfd), 0x0a),
+            Instruction::V128Store(_) => (Some(0xfd), 0x0b),
+            Instruction::V128Const(_) => (Some(0xfd), 0x0c),
+            Instruction::I8x16Shuffle(_) => (Some(0xfd), 0x0d),
+            Instruction::I8x16Swizzle => (Some(0xfd), 0x0e),
+            Instruction::I8x16Splat => (Some(0xfd), 0x0f),
+            Instruction::I16x8Splat => (Some(0xfd), 0x10),
+            Instruction::I32x4Splat => (Some(0xfd), 0x11),
+            Instruction::I64x2Splat => (Some(0xfd), 0x12),
+            Instruction::F32x4Splat => (Some(0xfd), 0x13),
+            Instruction::F64x2Splat => (Some(0xfd), 0x14),
+            Instruction::I8x16ExtractLaneS(_) => (Some(0xfd), 0x15),
+            Instruction::I8x16ExtractLaneU(_) => (Some(0xfd), 0x16),
+            Instruction::I8x16ReplaceLane(_) => (Some(0xfd), 0x17),
+            Instruction::I16x8ExtractLaneS(_) => (Some(0xfd), 0x18),
+            Instruction::I16x8ExtractLaneU(_) => (Some(0xfd), 0x19),
+            Instruction::I16x8ReplaceLane(_) => (Some(0xfd), 0x1a),
+            Instruction::I32x4ExtractLane(_) => (Some(0xfd), 0x1b),
+            Instruction::I32x4ReplaceLane(_) => (Some(0xfd), 0x1c),
+            Instruction::I64x2ExtractLane(_) => (Some(0xfd), 0x1d),
+            Instruction::I64x2ReplaceLane(_) => (Some(0xfd), 0x1e),
+            Instruction::F32x4ExtractLane(_) => (Some(0xfd), 0x1f),
+            Instruction::F32x4ReplaceLane(_) => (Some(0xfd), 0x20),
+            Instruction::F64x2ExtractLane(_) => (Some(0xfd), 0x21),
+            Instruction::F64x2ReplaceLane(_) => (Some(0xfd), 0x22),
+            Instruction::I8x16Eq => (Some(0xfd), 0x23),
+            Instruction::I8x16Ne => (Some(0xfd), 0x24),
+            Instruction::I8x16LtS => (Some(0xfd), 0x25),
+            Instruction::I8x16LtU => (Some(0xfd), 0x26),
+            Instruction::I8x16GtS => (Some(0xfd), 0x27),
+            Instruction::I8x16GtU => (Some(0xfd), 0x28),
+            Instruction::I8x16LeS => (Some(0xfd), 0x29),
+            Instruction::I8x16LeU => (Some(0xfd), 0x2a),
+            Instruction::I8x16GeS => (Some(0xfd), 0x2b),
+            Instruction::I8x16GeU => (Some(0xfd), 0x2c),
+            Instruction::I16x8Eq => (Some(0xfd), 0x2d),
+            Instruction::I16x8Ne => (Some(0xfd), 0x2e),
+            Instruction::I16x8LtS => (Some(0xfd), 0x2f),
+            Instruction::I16x8LtU => (Some(0xfd), 0x30),
+            Instruction::I16x8GtS => (Some(0xfd), 0x31),
+            Instruction::I16x8GtU => (Some(0xfd), 0x32),
+            Instruction::I16x8LeS => (Some(0xfd), 0x33),
+            Instruction::I16x8LeU => (Some(0xfd), 0x34),
+            Instruction::I16x8GeS => (Some(0xfd), 0x35),
+            Instruction::I16x8GeU => (Some(0xfd), 0x36),
+            Instruction::I32x4Eq => (Some(0xfd), 0x37),
+            Instruction::I32x4Ne => (Some(0xfd), 0x38),
+            Instruction::I32x4LtS => (Some(0xfd), 0x39),
+            Instruction::I32x4LtU => (Some(0xfd), 0x3a),
+            Instruction::I32x4GtS => (Some(0xfd), 0x3b),
+            Instruction::I32x4GtU => (Some(0xfd), 0x3c),
+            Instruction::I32x4LeS => (Some(0xfd), 0x3d),
+            Instruction::I32x4LeU => (Some(0xfd), 0x3e),
+            Instruction::I32x4GeS => (Some(0xfd), 0x3f),
+            Instruction::I32x4GeU => (Some(0xfd), 0x40),
+            Instruction::F32x4Eq => (Some(0xfd), 0x41),
+            Instruction::F32x4Ne => (Some(0xfd), 0x42),
+            Instruction::F32x4Lt => (Some(0xfd), 0x43),
+            Instruction::F32x4Gt => (Some(0xfd), 0x44),
+            Instruction::F32x4Le => (Some(0xfd), 0x45),
+            Instruction::F32x4Ge => (Some(0xfd), 0x46),
+            Instruction::F64x2Eq => (Some(0xfd), 0x47),
+            Instruction::F64x2Ne => (Some(0xfd), 0x48),
+            Instruction::F64x2Lt => (Some(0xfd), 0x49),
+            Instruction::F64x2Gt => (Some(0xfd), 0x4a),
+            Instruction::F64x2Le => (Some(0xfd), 0x4b),
+            Instruction::F64x2Ge => (Some(0xfd), 0x4c),
+            Instruction::V128Not => (Some(0xfd), 0x4d),
+            Instruction::V128And => (Some(0xfd), 0x4e),
+            Instruction::V128AndNot => (Some(0xfd), 0x4f),
+            Instruction::V128Or => (Some(0xfd), 0x50),
+            Instruction::V128Xor => (Some(0xfd), 0x51),
+            Instruction::V128Bitselect => (Some(0xfd), 0x52),
+            Instruction::V128AnyTrue => (Some(0xfd), 0x53),
+            Instruction::V128Load8Lane(..) => (Some(0xfd), 0x54),
+            Instruction::V128Load16Lane(..) => (Some(0xfd), 0x55),
+            Instruction::V128Load32Lane(..) => (Some(0xfd), 0x56),
+            Instruction::V128Load64Lane(..) => (Some(0xfd), 0x57),
+            Instruction::V128Store8Lane(..) => (Some(0xfd), 0x58),
+            Instruction::V128Store16Lane(..) => (Some(0xfd), 0x59),
+            Instruction::V128Store32Lane(..) => (Some(0xfd), 0x5a),
+            Instruction::V128Store64Lane(..) => (Some(0xfd), 0x5b),
+            Instruction::V128Load32Zero(_) => (Some(0xfd), 0x5c),
+            Instruction::V128Load64Zero(_) => (Some(0xfd), 0x5d),
+            Instruction::F32x4DemoteF64x2Zero => (Some(0xfd), 0x5e),
+            Instruction::F64x2PromoteLowF32x4 => (Some(0xfd), 0x5f),
+            Instruction::I8x16Abs => (Some(0xfd), 0x60),
+            Instruction::I8x16Neg => (Some(0xfd), 0x61),
+            Instruction::I8x16Popcnt => (Some(0xfd), 0x62),
+            Instruction::I8x16AllTrue => (Some(0xfd), 0x63),
+            Instruction::I8x16Bitmask => (Some(0xfd), 0x64),
+            Instruction::I8x16NarrowI16x8S => (Some(0xfd), 0x65),
+            Instruction::I8x16NarrowI16x8U => (Some(0xfd), 0x66),
+            Instruction::F32x4Ceil => (Some(0xfd), 0x67),
+            Instruction::F32x4Floor => (Some(0xfd), 0x68),
+            Instruction::F32x4Trunc => (Some(0xfd), 0x69),
+            Instruction::F32x4Nearest => (Some(0xfd), 0x6a),
+            Instruction::I8x16Shl => (Some(0xfd), 0x6b),
+            Instruction::I8x16ShrS => (Some(0xfd), 0x6c),
+            Instruction::I8x16ShrU => (Some(0xfd), 0x6d),
+            Instruction::I8x16Add => (Some(0xfd), 0x6e),
+            Instruction::I8x16AddSatS => (Some(0xfd), 0x6f),
+            Instruction::I8x16AddSatU => (Some(0xfd), 0x70),
+            Instruction::I8x16Sub => (Some(0xfd), 0x71),
+            Instruction::I8x16SubSatS => (Some(0xfd), 0x72),
+            Instruction::I8x16SubSatU => (Some(0xfd), 0x73),
+            Instruction::F64x2Ceil => (Some(0xfd), 0x74),
+            Instruction::F64x2Floor => (Some(0xfd), 0x75),
+            Instruction::I8x16MinS => (Some(0xfd), 0x76),
+            Instruction::I8x16MinU => (Some(0xfd), 0x77),
+            Instruction::I8x16MaxS => (Some(0xfd), 0x78),
+            Instruction::I8x16MaxU => (Some(0xfd), 0x79),
+            Instruction::F64x2Trunc => (Some(0xfd), 0x7a),
+            Instruction::I8x16AvgrU => (Some(0xfd), 0x7b),
+            Instruction::I16x8ExtAddPairwiseI8x16S => (Some(0xfd), 0x7c),
+            Instruction::I16x8ExtAddPairwiseI8x16U => (Some(0xfd), 0x7d),
+            Instruction::I32x4ExtAddPairwiseI16x8S => (Some(0xfd), 0x7e),
+            Instruction::I32x4ExtAddPairwiseI16x8U => (Some(0xfd), 0x7f),
+            Instruction::I16x8Abs => (Some(0xfd), 0x80),
+            Instruction::I16x8Neg => (Some(0xfd), 0x81),
+            Instruction::I16x8Q15MulrSatS => (Some(0xfd), 0x82),
+            Instruction::I16x8AllTrue => (Some(0xfd), 0x83),
+            Instruction::I16x8Bitmask => (Some(0xfd), 0x84),
+            Instruction::I16x8NarrowI32x4S => (Some(0xfd), 0x85),
+            Instruction::I16x8NarrowI32x4U => (Some(0xfd), 0x86),
+            Instruction::I16x8ExtendLowI8x16S => (Some(0xfd), 0x87),
+            Instruction::I16x8ExtendHighI8x16S => (Some(0xfd), 0x88),
+            Instruction::I16x8ExtendLowI8x16U => (Some(0xfd), 0x89),
+            Instruction::I16x8ExtendHighI8x16U => (Some(0xfd), 0x8a),
+            Instruction::I16x8Shl => (Some(0xfd), 0x8b),
+            Instruction::I16x8ShrS => (Some(0xfd), 0x8c),
+            Instruction::I16x8ShrU => (Some(0xfd), 0x8d),
+            Instruction::I16x8Add => (Some(0xfd), 0x8e),
+            Instruction::I16x8AddSatS => (Some(0xfd), 0x8f),
+            Instruction::I16x8AddSatU => (Some(0xfd), 0x90),
+            Instruction::I16x8Sub => (Some(0xfd), 0x91),
+            Instruction::I16x8SubSatS => (Some(0xfd), 0x92),
+            Instruction::I16x8SubSatU => (Some(0xfd), 0x93),
+            Instruction::F64x2Nearest => (Some(0xfd), 0x94),
+            Instruction::I16x8Mul => (Some(0xfd), 0x95),
+            Instruction::I16x8MinS => (Some(0xfd), 0x96),
+            Instruction::I16x8MinU => (Some(0xfd), 0x97),
+            Instruction::I16x8MaxS => (Some(0xfd), 0x98),
+            Instruction::I16x8MaxU => (Some(0xfd), 0x99),
+            Instruction::I16x8AvgrU => (Some(0xfd), 0x9b),
+            Instruction::I16x8ExtMulLowI8x16S => (Some(0xfd), 0x9c),
+            Instruction::I16x8ExtMulHighI8x16S => (Some(0xfd), 0x9d),
+            Instruction::I16x8ExtMulLowI8x16U => (Some(0xfd), 0x9e),
+            Instruction::I16x8ExtMulHighI8x16U => (Some(0xfd), 0x9f),
+            Instruction::I32x4Abs => (Some(0xfd), 0xa0),
+            Instruction::I32x4Neg => (Some(0xfd), 0xa1),
+            Instruction::I32x4AllTrue => (Some(0xfd), 0xa3),
+            Instruction::I32x4Bitmask => (Some(0xfd), 0xa4),
+            Instruction::I32x4ExtendLowI16x8S => (Some(0xfd), 0xa7),
+            Instruction::I32x4ExtendHighI16x8S => (Some(0xfd), 0xa8),
+            Instruction::I32x4ExtendLowI16x8U => (Some(0xfd), 0xa9),
+            Instruction::I32x4ExtendHighI16x8U => (Some(0xfd), 0xaa),
+            Instruction::I32x4Shl => (Some(0xfd), 0xab),
+            Instruction::I32x4ShrS => (Some(0xfd), 0xac),
+            Instruction::I32x4ShrU => (Some(0xfd), 0xad),
+            Instruction::I32x4Add => (Some(0xfd), 0xae),
+            Instruction::I32x4Sub => (Some(0xfd), 0xb1),
+            Instruction::I32x4Mul => (Some(0xfd), 0xb5),
+            Instruction::I32x4MinS => (Some(0xfd), 0xb6),
+            Instruction::I32x4MinU => (Some(0xfd), 0xb7),
+            Instruction::I32x4MaxS => (Some(0xfd), 0xb8),
+            Instruction::I32x4MaxU => (Some(0xfd), 0xb9),
+            Instruction::I32x4DotI16x8S => (Some(0xfd), 0xba),
+            Instruction::I32x4ExtMulLowI16x8S => (Some(0xfd), 0xbc),
+            Instruction::I32x4ExtMulHighI16x8S => (Some(0xfd), 0xbd),
+            Instruction::I32x4ExtMulLowI16x8U => (Some(0xfd), 0xbe),
+            Instruction::I32x4ExtMulHighI16x8U => (Some(0xfd), 0xbf),
+            Instruction::I64x2Abs => (Some(0xfd), 0xc0),
+            Instruction::I64x2Neg => (Some(0xfd), 0xc1),
+            Instruction::I64x2AllTrue => (Some(0xfd), 0xc3),
+            Instruction::I64x2Bitmask => (Some(0xfd), 0xc4),
+            Instruction::I64x2ExtendLowI32x4S => (Some(0xfd), 0xc7),
+            Instruction::I64x2ExtendHighI32x4S => (Some(0xfd), 0xc8),
+            Instruction::I64x2ExtendLowI32x4U => (Some(0xfd), 0xc9),
+            Instruction::I64x2ExtendHighI32x4U => (Some(0xfd), 0xca),
+            Instruction::I64x2Shl => (Some(0xfd), 0xcb),
+            Instruction::I64x2ShrS => (Some(0xfd), 0xcc),
+            Instruction::I64x2ShrU => (Some(0xfd), 0xcd),
+            Instruction::I64x2Add => (Some(0xfd), 0xce),
+            Instruction::I64x2Sub => (Some(0xfd), 0xd1),
+            Instruction::I64x2Mul => (Some(0xfd), 0xd5),
+            Instruction::I64x2Eq => (Some(0xfd), 0xd6),
+            Instruction::I64x2Ne => (Some(0xfd), 0xd7),
+            Instruction::I64x2LtS => (Some(0xfd), 0xd8),
+            Instruction::I64x2GtS => (Some(0xfd), 0xd9),
+            Instruction::I64x2LeS => (Some(0xfd), 0xda),
+            Instruction::I64x2GeS => (Some(0xfd), 0xdb),
+            Instruction::I64x2ExtMulLowI32x4S => (Some(0xfd), 0xdc),
+            Instruction::I64x2ExtMulHighI32x4S => (Some(0xfd), 0xdd),
+            Instruction::I64x2ExtMulLowI32x4U => (Some(0xfd), 0xde),
+            Instruction::I64x2ExtMulHighI32x4U => (Some(0xfd), 0xdf),
+            Instruction::F32x4Abs => (Some(0xfd), 0xe0),
+            Instruction::F32x4Neg => (Some(0xfd), 0xe1),
+            Instruction::F32x4Sqrt => (Some(0xfd), 0xe3),
+            Instruction::F32x4Add => (Some(0xfd), 0xe4),
+            Instruction::F32x4Sub => (Some(0xfd), 0xe5),
+            Instruction::F32x4Mul => (Some(0xfd), 0xe6),
+            Instruction::F32x4Div => (Some(0xfd), 0xe7),
+            Instruction::F32x4Min => (Some(0xfd), 0xe8),
+            Instruction::F32x4Max => (Some(0xfd), 0xe9),
+            Instruction::F32x4PMin => (Some(0xfd), 0xea),
+            Instruction::F32x4PMax => (Some(0xfd), 0xeb),
+            Instruction::F64x2Abs => (Some(0xfd), 0xec),
+            Instruction::F64x2Neg => (Some(0xfd), 0xed),
+            Instruction::F64x2Sqrt => (Some(0xfd), 0xef),
+            Instruction::F64x2Add => (Some(0xfd), 0xf0),
+            Instruction::F64x2Sub => (Some(0xfd), 0xf1),
+            Instruction::F64x2Mul => (Some(0xfd), 0xf2),
+            Instruction::F64x2Div => (Some(0xfd), 0xf3),
+            Instruction::F64x2Min => (Some(0xfd), 0xf4),
+            Instruction::F64x2Max => (Some(0xfd), 0xf5),
+            Instruction::F64x2PMin => (Some(0xfd), 0xf6),
+            Instruction::F64x2PMax => (Some(0xfd), 0xf7),
+            Instruction::I32x4TruncSatF32x4S => (Some(0xfd), 0xf8),
+            Instruction::I32x4TruncSatF32x4U => (Some(0xfd), 0xf9),
+            Instruction::F32x4ConvertI32x4S => (Some(0xfd), 0xfa),
+            Instruction::F32x4ConvertI32x4U => (Some(0xfd), 0xfb),
+            Instruction::I32x4TruncSatF64x2SZero => (Some(0xfd), 0xfc),
+            Instruction::I32x4TruncSatF64x2UZero => (Some(0xfd), 0xfd),
+            Instruction::F64x2ConvertLowI32x4S => (Some(0xfd), 0xfe),
+            Instruction::F64x2ConvertLowI32x4U => (Some(0xfd), 0xff),
+            Instruction::I8x16RelaxedSwizzle => (Some(0xfd), 0x100),
+            Instruction::I32x4RelaxedTruncF32x4S => (Some(0xfd), 0x101),
+            Instruction::I32x4RelaxedTruncF32x4U => (Some(0xfd), 0x102),
+            Instruction::I32x4RelaxedTruncF64x2SZero => (Some(0xfd), 0x103),
+            Instruction::I32x4RelaxedTruncF64x2UZero => (Some(0xfd), 0x104),
+            Instruction::F32x4RelaxedMadd => (Some(0xfd), 0x105),
+            Instruction::F32x4RelaxedNmadd => (Some(0xfd), 0x106),
+            Instruction::F64x2RelaxedMadd => (Some(0xfd), 0x107),
+            Instruction::F64x2RelaxedNmadd => (Some(0xfd), 0x108),
+            Instruction::I8x16RelaxedLaneselect => (Some(0xfd), 0x109),
+            Instruction::I16x8RelaxedLaneselect => (Some(0xfd), 0x10a),
+            Instruction::I32x4RelaxedLaneselect => (Some(0xfd), 0x10b),
+            Instruction::I64x2RelaxedLaneselect => (Some(0xfd), 0x10c),
+            Instruction::F32x4RelaxedMin => (Some(0xfd), 0x10d),
+            Instruction::F32x4RelaxedMax => (Some(0xfd), 0x10e),
+            Instruction::F64x2RelaxedMin => (Some(0xfd), 0x10f),
+            Instruction::F64x2RelaxedMax => (Some(0xfd), 0x110),
+            Instruction::I16x8RelaxedQ15mulrS => (Some(0xfd), 0x111),
+            Instruction::I16x8RelaxedDotI8x16I7x16S => (Some(0xfd), 0x112),
+            Instruction::I32x4RelaxedDotI8x16I7x16AddS => (Some(0xfd), 0x113),
+            Instruction::MemoryAtomicNotify(_) => (Some(0xfe), 0x00),
+            Instruction::MemoryAtomicWait32(_) => (Some(0xfe), 0x01),
+            Instruction::MemoryAtomicWait64(_) => (Some(0xfe), 0x02),
+            Instruction::AtomicFence => (Some(0xfe), 0x03),
+            Instruction::I32AtomicLoad(_) => (Some(0xfe), 0x10),
+            Instruction::I64AtomicLoad(_) => (Some(0xfe), 0x11),
+            Instruction::I32AtomicLoad8U(_) => (Some(0xfe), 0x12),
+            Instruction::I32AtomicLoad16U(_) => (Some(0xfe), 0x13),
+            Instruction::I64AtomicLoad8U(_) => (Some(0xfe), 0x14),
+            Instruction::I64AtomicLoad16U(_) => (Some(0xfe), 0x15),
+            Instruction::I64AtomicLoad32U(_) => (Some(0xfe), 0x16),
+            Instruction::I32AtomicStore(_) => (Some(0xfe), 0x17),
+            Instruction::I64AtomicStore(_) => (Some(0xfe), 0x18),
+            Instruction::I32AtomicStore8(_) => (Some(0xfe), 0x19),
+            Instruction::I32AtomicStore16(_) => (Some(0xfe), 0x1a),
+            Instruction::I64AtomicStore8(_) => (Some(0xfe), 0x1b),
+            Instruction::I64AtomicStore16(_) => (Some(0xfe), 0x1c),
+            Instruction::I64AtomicStore32(_) => (Some(0xfe), 0x1d),
+            Instruction::I32AtomicRmwAdd(_) => (Some(0xfe), 0x1e),
+            Instruction::I64AtomicRmwAdd(_) => (Some(0xfe), 0x1f),
+            Instruction::I32AtomicRmw8AddU(_) => (Some(0xfe), 0x20),
+            Instruction::I32AtomicRmw16AddU(_) => (Some(0xfe), 0x21),
+            Instruction::I64AtomicRmw8AddU(_) => (Some(0xfe), 0x22),
+            Instruction::I64AtomicRmw16AddU(_) => (Some(0xfe), 0x23),
+            Instruction::I64AtomicRmw32AddU(_) => (Some(0xfe), 0x24),
+            Instruction::I32AtomicRmwSub(_) => (Some(0xfe), 0x25),
+            Instruction::I64AtomicRmwSub(_) => (Some(0xfe), 0x26),
+            Instruction::I32AtomicRmw8SubU(_) => (Some(0xfe), 0x27),
+            Instruction::I32AtomicRmw16SubU(_) => (Some(0xfe), 0x28),
+            Instruction::I64AtomicRmw8SubU(_) => (Some(0xfe), 0x29),
+            Instruction::I64AtomicRmw16SubU(_) => (Some(0xfe), 0x2a),
+            Instruction::I64AtomicRmw32SubU(_) => (Some(0xfe), 0x2b),
+            Instruction::I32AtomicRmwAnd(_) => (Some(0xfe), 0x2c),
+            Instruction::I64AtomicRmwAnd(_) => (Some(0xfe), 0x2d),
+            Instruction::I32AtomicRmw8AndU(_) => (Some(0xfe), 0x2e),
+            Instruction::I32AtomicRmw16AndU(_) => (Some(0xfe), 0x2f),
+            Instruction::I64AtomicRmw8AndU(_) => (Some(0xfe), 0x30),
+            Instruction::I64AtomicRmw16AndU(_) => (Some(0xfe), 0x31),
+            Instruction::I64AtomicRmw32AndU(_) => (Some(0xfe), 0x32),
+            Instruction::I32AtomicRmwOr(_) => (Some(0xfe), 0x33),
+            Instruction::I64AtomicRmwOr(_) => (Some(0xfe), 0x34),
+            Instruction::I32AtomicRmw8OrU(_) => (Some(0xfe), 0x35),
+            Instruction::I32AtomicRmw16OrU(_) => (Some(0xfe), 0x36),
+            Instruction::I64AtomicRmw8OrU(_) => (Some(0xfe), 0x37),
+            Instruction::I64AtomicRmw16OrU(_) => (Some(0xfe), 0x38),
+            Instruction::I64AtomicRmw32OrU(_) => (Some(0xfe), 0x39),
+            Instruction::I32AtomicRmwXor(_) => (Some(0xfe), 0x3a),
+            Instruction::I64AtomicRmwXor(_) => (Some(0xfe), 0x3b),
+            Instruction::I32AtomicRmw8XorU(_) => (Some(0xfe), 0x3c),
+            Instruction::I32AtomicRmw16XorU(_) => (Some(0xfe), 0x3d),
+            Instruction::I64AtomicRmw8XorU(_) => (Some(0xfe), 0x3e),
+            Instruction::I64AtomicRmw16XorU(_) => (Some(0xfe), 0x3f),
+            Instruction::I64AtomicRmw32XorU(_) => (Some(0xfe), 0x40),
+            Instruction::I32AtomicRmwXchg(_) => (Some(0xfe), 0x41),
+            Instruction::I64AtomicRmwXchg(_) => (Some(0xfe), 0x42),
+            Instruction::I32AtomicRmw8XchgU(_) => (Some(0xfe), 0x43),
+            Instruction::I32AtomicRmw16XchgU(_) => (Some(0xfe), 0x44),
+            Instruction::I64AtomicRmw8XchgU(_) => (Some(0xfe), 0x45),
+            Instruction::I64AtomicRmw16XchgU(_) => (Some(0xfe), 0x46),
+            Instruction::I64AtomicRmw32XchgU(_) => (Some(0xfe), 0x47),
+            Instruction::I32AtomicRmwCmpxchg(_) => (Some(0xfe), 0x48),
+            Instruction::I64AtomicRmwCmpxchg(_) => (Some(0xfe), 0x49),
+            Instruction::I32AtomicRmw8CmpxchgU(_) => (Some(0xfe), 0x4a),
+            Instruction::I32AtomicRmw16CmpxchgU(_) => (Some(0xfe), 0x4b),
+            Instruction::I64AtomicRmw8CmpxchgU(_) => (Some(0xfe), 0x4c),
+            Instruction::I64AtomicRmw16CmpxchgU(_) => (Some(0xfe), 0x4d),
+            Instruction::I64AtomicRmw32CmpxchgU(_) => (Some(0xfe), 0x4e),
+        }
+    }
+
+    /// The name of the instruction in the text format, e.g. `i32.load8_u`.
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Unreachable => "unreachable",
+            Instruction::Nop => "nop",
+            Instruction::Block(_) => "block",
+            Instruction::Loop(_) => "loop",
+            Instruction::If(_) => "if",
+            Instruction::Else => "else",
+            Instruction::Try(_) => "try",
+            Instruction::Catch(_) => "catch",
+            Instruction::Throw(_) => "throw",
+            Instruction::Rethrow(_) => "rethrow",
+            Instruction::ThrowRef => "throw_ref",
+            Instruction::End => "end",
+            Instruction::Br(_) => "br",
+            Instruction::BrIf(_) => "br_if",
+            Instruction::BrTable(_) => "br_table",
+            Instruction::Return => "return",
+            Instruction::Call(_) => "call",
+            Instruction::CallIndirect(..) => "call_indirect",
+            Instruction::ReturnCall(_) => "return_call",
+            Instruction::ReturnCallIndirect(..) => "return_call_indirect",
+            Instruction::CallRef(_) => "call_ref",
+            Instruction::ReturnCallRef(_) => "return_call_ref",
+            Instruction::Delegate(_) => "delegate",
+            Instruction::CatchAll => "catch_all",
+            Instruction::Drop => "drop",
+            Instruction::TryTable(_) => "try_table",
+            Instruction::LocalGet(_) => "local.get",
+            Instruction::LocalSet(_) => "local.set",
+            Instruction::LocalTee(_) => "local.tee",
+            Instruction::GlobalGet(_) => "global.get",
+            Instruction::GlobalSet(_) => "global.set",
+            Instruction::TableGet(_) => "table.get",
+            Instruction::TableSet(_) => "table.set",
+            Instruction::I32Load(_) => "i32.load",
+            Instruction::I64Load(_) => "i64.load",
+            Instruction::F32Load(_) => "f32.load",
+            Instruction::F64Load(_) => "f64.load",
+            Instruction::I32Load8S(_) => "i32.load8_s",
+            Instruction::I32Load8U(_) => "i32.load8_u",
+            Instruction::I32Load16S(_) => "i32.load16_s",
+            Instruction::I32Load16U(_) => "i32.load16_u",
+            Instruction::I64Load8S(_) => "i64.load8_s",
+            Instruction::I64Load8U(_) => "i64.load8_u",
+            Instruction::I64Load16S(_) => "i64.load16_s",
+            Instruction::I64Load16U(_) => "i64.load16_u",
+            Instruction::I64Load32S(_) => "i64.load32_s",
+            Instruction::I64Load32U(_) => "i64.load32_u",
+            Instruction::I32Store(_) => "i32.store",
+            Instruction::I64Store(_) => "i64.store",
+            Instruction::F32Store(_) => "f32.store",
+            Instruction::F64Store(_) => "f64.store",
+            Instruction::I32Store8(_) => "i32.store8",
+            Instruction::I32Store16(_) => "i32.store16",
+            Instruction::I64Store8(_) => "i64.store8",
+            Instruction::I64Store16(_) => "i64.store16",
+            Instruction::I64Store32(_) => "i64.store32",
+            Instruction::MemorySize(_) => "memory.size",
+            Instruction::MemoryGrow(_) => "memory.grow",
+            Instruction::I32Const(_) => "i32.const",
+            Instruction::I64Const(_) => "i64.const",
+            Instruction::F32Const(_) => "f32.const",
+            Instruction::F64Const(_) => "f64.const",
+            Instruction::I32Eqz => "i32.eqz",
+            Instruction::I32Eq => "i32.eq",
+            Instruction::I32Ne => "i32.ne",
+            Instruction::I32LtS => "i32.lt_s",
+            Instruction::I32LtU => "i32.lt_u",
+            Instruction::I32GtS => "i32.gt_s",
+            Instruction::I32GtU => "i32.gt_u",
+            Instruction::I32LeS => "i32.le_s",
+            Instruction::I32LeU => "i32.le_u",
+            Instruction::I32GeS => "i32.ge_s",
+            Instruction::I32GeU => "i32.ge_u",
+            Instruction::I64Eqz => "i64.eqz",
+            Instruction::I64Eq => "i64.eq",
+            Instruction::I64Ne => "i64.ne",
+            Instruction::I64LtS => "i64.lt_s",
+            Instruction::I64LtU => "i64.lt_u",
+            Instruction::I64GtS => "i64.gt_s",
+            Instruction::I64GtU => "i64.gt_u",
+            Instruction::I64LeS => "i64.le_s",
+            Instruction::I64LeU => "i64.le_u",
+            Instruction::I64GeS => "i64.ge_s",
+            Instruction::I64GeU => "i64.ge_u",
+            Instruction::F32Eq => "f32.eq",
+            Instruction::F32Ne => "f32.ne",
+            Instruction::F32Lt => "f32.lt",
+            Instruction::F32Gt => "f32.gt",
+            Instruction::F32Le => "f32.le",
+            Instruction::F32Ge => "f32.ge",
+            Instruction::F64Eq => "f64.eq",
+            Instruction::F64Ne => "f64.ne",
+            Instruction::F64Lt => "f64.lt",
+            Instruction::F64Gt => "f64.gt",
+            Instruction::F64Le => "f64.le",
+            Instruction::F64Ge => "f64.ge",
+            Instruction::I32Clz => "i32.clz",
+            Instruction::I32Ctz => "i32.ctz",
+            Instruction::I32Popcnt => "i32.popcnt",
+            Instruction::I32Add => "i32.add",
+            Instruction::I32Sub => "i32.sub",
+            Instruction::I32Mul => "i32.mul",
+            Instruction::I32DivS => "i32.div_s",
+            Instruction::I32DivU => "i32.div_u",
+            Instruction::I32RemS => "i32.rem_s",
+            Instruction::I32RemU => "i32.rem_u",
+            Instruction::I32And => "i32.and",
+            Instruction::I32Or => "i32.or",
+            Instruction::I32Xor => "i32.xor",
+            Instruction::I32Shl => "i32.shl",
+            Instruction::I32ShrS => "i32.shr_s",
+            Instruction::I32ShrU => "i32.shr_u",
+            Instruction::I32Rotl => "i32.rotl",
+            Instruction::I32Rotr => "i32.rotr",
+            Instruction::I64Clz => "i64.clz",
+            Instruction::I64Ctz => "i64.ctz",
+            Instruction::I64Popcnt => "i64.popcnt",
+            Instruction::I64Add => "i64.add",
+            Instruction::I64Sub => "i64.sub",
+            Instruction::I64Mul => "i64.mul",
+            Instruction::I64DivS => "i64.div_s",
+            Instruction::I64DivU => "i64.div_u",
+            Instruction::I64RemS => "i64.rem_s",
+            Instruction::I64RemU => "i64.rem_u",
+            Instruction::I64And => "i64.and",
+            Instruction::I64Or => "i64.or",
+            Instruction::I64Xor => "i64.xor",
+            Instruction::I64Shl => "i64.shl",
+            Instruction::I64ShrS => "i64.shr_s",
+            Instruction::I64ShrU => "i64.shr_u",
+            Instruction::I64Rotl => "i64.rotl",
+            Instruction::I64Rotr => "i64.rotr",
+            Instruction::F32Abs => "f32.abs",
+            Instruction::F32Neg => "f32.neg",
+            Instruction::F32Ceil => "f32.ceil",
+            Instruction::F32Floor => "f32.floor",
+            Instruction::F32Trunc => "f32.trunc",
+            Instruction::F32Nearest => "f32.nearest",
+            Instruction::F32Sqrt => "f32.sqrt",
+            Instruction::F32Add => "f32.add",
+            Instruction::F32Sub => "f32.sub",
+            Instruction::F32Mul => "f32.mul",
+            Instruction::F32Div => "f32.div",
+            Instruction::F32Min => "f32.min",
+            Instruction::F32Max => "f32.max",
+            Instruction::F32Copysign => "f32.copysign",
+            Instruction::F64Abs => "f64.abs",
+            Instruction::F64Neg => "f64.neg",
+            Instruction::F64Ceil => "f64.ceil",
+            Instruction::F64Floor => "f64.floor",
+            Instruction::F64Trunc => "f64.trunc",
+            Instruction::F64Nearest => "f64.nearest",
+            Instruction::F64Sqrt => "f64.sqrt",
+            Instruction::F64Add => "f64.add",
+            Instruction::F64Sub => "f64.sub",
+            Instruction::F64Mul => "f64.mul",
+            Instruction::F64Div => "f64.div",
+            Instruction::F64Min => "f64.min",
+            Instruction::F64Max => "f64.max",
+            Instruction::F64Copysign => "f64.copysign",
+            Instruction::I32WrapI64 => "i32.wrap_i64",
+            Instruction::I32TruncF32S => "i32.trunc_f32_s",
+            Instruction::I32TruncF32U => "i32.trunc_f32_u",
+            Instruction::I32TruncF64S => "i32.trunc_f64_s",
+            Instruction::I32TruncF64U => "i32.trunc_f64_u",
+            Instruction::I64ExtendI32S => "i64.extend_i32_s",
+            Instruction::I64ExtendI32U => "i64.extend_i32_u",
+            Instruction::I64TruncF32S => "i64.trunc_f32_s",
+            Instruction::I64TruncF32U => "i64.trunc_f32_u",
+            Instruction::I64TruncF64S => "i64.trunc_f64_s",
+            Instruction::I64TruncF64U => "i64.trunc_f64_u",
+            Instruction::F32ConvertI32S => "f32.convert_i32_s",
+            Instruction::F32ConvertI32U => "f32.convert_i32_u",
+            Instruction::F32ConvertI64S => "f32.convert_i64_s",
+            Instruction::F32ConvertI64U => "f32.convert_i64_u",
+            Instruction::F32DemoteF64 => "f32.demote_f64",
+            Instruction::F64ConvertI32S => "f64.convert_i32_s",
+            Instruction::F64ConvertI32U => "f64.convert_i32_u",
+            Instruction::F64ConvertI64S => "f64.convert_i64_s",
+            Instruction::F64ConvertI64U => "f64.convert_i64_u",
+            Instruction::F64PromoteF32 => "f64.promote_f32",
+            Instruction::I32ReinterpretF32 => "i32.reinterpret_f32",
+            Instruction::I64ReinterpretF64 => "i64.reinterpret_f64",
+            Instruction::F32ReinterpretI32 => "f32.reinterpret_i32",
+            Instruction::F64ReinterpretI64 => "f64.reinterpret_i64",
+            Instruction::I32Extend8S => "i32.extend8_s",
+            Instruction::I32Extend16S => "i32.extend16_s",
+            Instruction::I64Extend8S => "i64.extend8_s",
+            Instruction::I64Extend16S => "i64.extend16_s",
+            Instruction::I64Extend32S => "i64.extend32_s",
+            Instruction::RefNull(_) => "ref.null",
+            Instruction::RefIsNull => "ref.is_null",
+            Instruction::RefFunc(_) => "ref.func",
+            Instruction::RefEq => "ref.eq",
+            Instruction::RefAsNonNull => "ref.as_non_null",
+            Instruction::BrOnNull(_) => "br_on_null",
+            Instruction::BrOnNonNull(_) => "br_on_non_null",
+            Instruction::StructNew(_) => "struct.new",
+            Instruction::StructNewDefault(_) => "struct.new_default",
+            Instruction::StructGet(..) => "struct.get",
+            Instruction::StructGetS(..) => "struct.get_s",
+            Instruction::StructGetU(..) => "struct.get_u",
+            Instruction::StructSet(..) => "struct.set",
+            Instruction::ArrayNew(_) => "array.new",
+            Instruction::ArrayNewDefault(_) => "array.new_default",
+            Instruction::ArrayNewFixed(..) => "array.new_fixed",
+            Instruction::ArrayNewData(..) => "array.new_data",
+            Instruction::ArrayNewElem(..) => "array.new_elem",
+            Instruction::ArrayGet(_) => "array.get",
+            Instruction::ArrayGetS(_) => "array.get_s",
+            Instruction::ArrayGetU(_) => "array.get_u",
+            Instruction::ArraySet(_) => "array.set",
+            Instruction::ArrayLen => "array.len",
+            Instruction::ArrayFill(_) => "array.fill",
+            Instruction::ArrayCopy(..) => "array.copy",
+            Instruction::ArrayInitData(..) => "array.init_data",
+            Instruction::ArrayInitElem(..) => "array.init_elem",
+            Instruction::AnyConvertExtern => "any.convert_extern",
+            Instruction::ExternConvertAny => "extern.convert_any",
+            Instruction::RefI31 => "ref.i31",
+            Instruction::I31GetS => "i31.get_s",
+            Instruction::I31GetU => "i31.get_u",
+            Instruction::I32TruncSatF32S => "i32.trunc_sat_f32_s",
+            Instruction::I32TruncSatF32U => "i32.trunc_sat_f32_u",
+            Instruction::I32TruncSatF64S => "i32.trunc_sat_f64_s",
+            Instruction::I32TruncSatF64U => "i32.trunc_sat_f64_u",
+            Instruction::I64TruncSatF32S => "i64.trunc_sat_f32_s",
+            Instruction::I64TruncSatF32U => "i64.trunc_sat_f32_u",
+            Instruction::I64TruncSatF64S => "i64.trunc_sat_f64_s",
+            Instruction::I64TruncSatF64U => "i64.trunc_sat_f64_u",
+            Instruction::MemoryInit(..) => "memory.init",
+            Instruction::DataDrop(_) => "data.drop",
+            Instruction::MemoryCopy(..) => "memory.copy",
+            Instruction::MemoryFill(_) => "memory.fill",
+            Instruction::TableInit(..) => "table.init",
+            Instruction::ElemDrop(_) => "elem.drop",
+            Instruction::TableCopy(..) => "table.copy",
+            Instruction::TableGrow(_) => "table.grow",
+            Instruction::TableSize(_) => "table.size",
+            Instruction::TableFill(_) => "table.fill",
+            Instruction::MemoryDiscard(_) => "memory.discard",
+            Instruction::V128Load(_) => "v128.load",
+            Instruction::V128Load8x8S(_) => "v128.load8x8_s",
+            Instruction::V128Load8x8U(_) => "v128.load8x8_u",
+            Instruction::V128Load16x4S(_) => "v128.load16x4_s",
+            Instruction::V128Load16x4U(_) => "v128.load16x4_u",
+            Instruction::V128Load32x2S(_) => "v128.load32x2_s",
+            Instruction::V128Load32x2U(_) => "v128.load32x2_u",
+            Instruction::V128Load8Splat(_) => "v128.load8_splat",
+            Instruction::V128Load16Splat(_) => "v128.load16_splat",
+            Instruction::V128Load32Splat(_) => "v128.load32_splat",
+            Instruction::V128Load64Splat(_) => "v128.load64_splat",
+            Instruction::V128Store(_) => "v128.store",
+            Instruction::V128Const(_) => "v128.const",
+            Instruction::I8x16Shuffle(_) => "i8x16.shuffle",
+            Instruction::I8x16Swizzle => "i8x16.swizzle",
+            Instruction::I8x16Splat => "i8x16.splat",
+            Instruction::I16x8Splat => "i16x8.splat",
+            Instruction::I32x4Splat => "i32x4.splat",
+            Instruction::I64x2Splat => "i64x2.splat",
+            Instruction::F32x4Splat => "f32x4.splat",
+            Instruction::F64x2Splat => "f64x2.splat",
+            Instruction::I8x16ExtractLaneS(_) => "i8x16.extract_lane_s",
+            Instruction::I8x16ExtractLaneU(_) => "i8x16.extract_lane_u",
+            Instruction::I8x16ReplaceLane(_) => "i8x16.replace_lane",
+            Instruction::I16x8ExtractLaneS(_) => "i16x8.extract_lane_s",
+            Instruction::I16x8ExtractLaneU(_) => "i16x8.extract_lane_u",
+            Instruction::I16x8ReplaceLane(_) => "i16x8.replace_lane",
+            Instruction::I32x4ExtractLane(_) => "i32x4.extract_lane",
+            Instruction::I32x4ReplaceLane(_) => "i32x4.replace_lane",
+            Instruction::I64x2ExtractLane(_) => "i64x2.extract_lane",
+            Instruction::I64x2ReplaceLane(_) => "i64x2.replace_lane",
+            Instruction::F32x4ExtractLane(_) => "f32x4.extract_lane",
+            Instruction::F32x4ReplaceLane(_) => "f32x4.replace_lane",
+            Instruction::F64x2ExtractLane(_) => "f64x2.extract_lane",
+            Instruction::F64x2ReplaceLane(_) => "f64x2.replace_lane",
+            Instruction::I8x16Eq => "i8x16.eq",
+            Instruction::I8x16Ne => "i8x16.ne",
+            Instruction::I8x16LtS => "i8x16.lt_s",
+            Instruction::I8x16LtU => "i8x16.lt_u",
+            Instruction::I8x16GtS => "i8x16.gt_s",
+            Instruction::I8x16GtU => "i8x16.gt_u",
+            Instruction::I8x16LeS => "i8x16.le_s",
+            Instruction::I8x16LeU => "i8x16.le_u",
+            Instruction::I8x16GeS => "i8x16.ge_s",
+            Instruction::I8x16GeU => "i8x16.ge_u",
+            Instruction::I16x8Eq => "i16x8.eq",
+            Instruction::I16x8Ne => "i16x8.ne",
+            Instruction::I16x8LtS => "i16x8.lt_s",
+            Instruction::I16x8LtU => "i16x8.lt_u",
+            Instruction::I16x8GtS => "i16x8.gt_s",
+            Instruction::I16x8GtU => "i16x8.gt_u",
+            Instruction::I16x8LeS => "i16x8.le_s",
+            Instruction::I16x8LeU => "i16x8.le_u",
+            Instruction::I16x8GeS => "i16x8.ge_s",
+            Instruction::I16x8GeU => "i16x8.ge_u",
+            Instruction::I32x4Eq => "i32x4.eq",
+            Instruction::I32x4Ne => "i32x4.ne",
+            Instruction::I32x4LtS => "i32x4.lt_s",
+            Instruction::I32x4LtU => "i32x4.lt_u",
+            Instruction::I32x4GtS => "i32x4.gt_s",
+            Instruction::I32x4GtU => "i32x4.gt_u",
+            Instruction::I32x4LeS => "i32x4.le_s",
+            Instruction::I32x4LeU => "i32x4.le_u",
+            Instruction::I32x4GeS => "i32x4.ge_s",
+            Instruction::I32x4GeU => "i32x4.ge_u",
+            Instruction::F32x4Eq => "f32x4.eq",
+            Instruction::F32x4Ne => "f32x4.ne",
+            Instruction::F32x4Lt => "f32x4.lt",
+            Instruction::F32x4Gt => "f32x4.gt",
+            Instruction::F32x4Le => "f32x4.le",
+            Instruction::F32x4Ge => "f32x4.ge",
+            Instruction::F64x2Eq => "f64x2.eq",
+            Instruction::F64x2Ne => "f64x2.ne",
+            Instruction::F64x2Lt => "f64x2.lt",
+            Instruction::F64x2Gt => "f64x2.gt",
+            Instruction::F64x2Le => "f64x2.le",
+            Instruction::F64x2Ge => "f64x2.ge",
+            Instruction::V128Not => "v128.not",
+            Instruction::V128And => "v128.and",
+            Instruction::V128AndNot => "v128.andnot",
+            Instruction::V128Or => "v128.or",
+            Instruction::V128Xor => "v128.xor",
+            Instruction::V128Bitselect => "v128.bitselect",
+            Instruction::V128AnyTrue => "v128.any_true",
+            Instruction::V128Load8Lane(..) => "v128.load8_lane",
+            Instruction::V128Load16Lane(..) => "v128.load16_lane",
+            Instruction::V128Load32Lane(..) => "v128.load32_lane",
+            Instruction::V128Load64Lane(..) => "v128.load64_lane",
+            Instruction::V128Store8Lane(..) => "v128.store8_lane",
+            Instruction::V128Store16Lane(..) => "v128.store16_lane",
+            Instruction::V128Store32Lane(..) => "v128.store32_lane",
+            Instruction::V128Store64Lane(..) => "v128.store64_lane",
+            Instruction::V128Load32Zero(_) => "v128.load32_zero",
+            Instruction::V128Load64Zero(_) => "v128.load64_zero",
+            Instruction::F32x4DemoteF64x2Zero => "f32x4.demote_f64x2_zero",
+            Instruction::F64x2PromoteLowF32x4 => "f64x2.promote_low_f32x4",
+            Instruction::I8x16Abs => "i8x16.abs",
+            Instruction::I8x16Neg => "i8x16.neg",
+            Instruction::I8x16Popcnt => "i8x16.popcnt",
+            Instruction::I8x16AllTrue => "i8x16.all_true",
+            Instruction::I8x16Bitmask => "i8x16.bitmask",
+            Instruction::I8x16NarrowI16x8S => "i8x16.narrow_i16x8_s",
+            Instruction::I8x16NarrowI16x8U => "i8x16.narrow_i16x8_u",
+            Instruction::F32x4Ceil => "f32x4.ceil",
+            Instruction::F32x4Floor => "f32x4.floor",
+            Instruction::F32x4Trunc => "f32x4.trunc",
+            Instruction::F32x4Nearest => "f32x4.nearest",
+            Instruction::I8x16Shl => "i8x16.shl",
+            Instruction::I8x16ShrS => "i8x16.shr_s",
+            Instruction::I8x16ShrU => "i8x16.shr_u",
+            Instruction::I8x16Add => "i8x16.add",
+            Instruction::I8x16AddSatS => "i8x16.add_sat_s",
+            Instruction::I8x16AddSatU => "i8x16.add_sat_u",
+            Instruction::I8x16Sub => "i8x16.sub",
+            Instruction::I8x16SubSatS => "i8x16.sub_sat_s",
+            Instruction::I8x16SubSatU => "i8x16.sub_sat_u",
+            Instruction::F64x2Ceil => "f64x2.ceil",
+            Instruction::F64x2Floor => "f64x2.floor",
+            Instruction::I8x16MinS => "i8x16.min_s",
+            Instruction::I8x16MinU => "i8x16.min_u",
+            Instruction::I8x16MaxS => "i8x16.max_s",
+            Instruction::I8x16MaxU => "i8x16.max_u",
+            Instruction::F64x2Trunc => "f64x2.trunc",
+            Instruction::I8x16AvgrU => "i8x16.avgr_u",
+            Instruction::I16x8ExtAddPairwiseI8x16S => "i16x8.extadd_pairwise_i8x16_s",
+            Instruction::I16x8ExtAddPairwiseI8x16U => "i16x8.extadd_pairwise_i8x16_u",
+            Instruction::I32x4ExtAddPairwiseI16x8S => "i32x4.extadd_pairwise_i16x8_s",
+            Instruction::I32x4ExtAddPairwiseI16x8U => "i32x4.extadd_pairwise_i16x8_u",
+            Instruction::I16x8Abs => "i16x8.abs",
+            Instruction::I16x8Neg => "i16x8.neg",
+            Instruction::I16x8Q15MulrSatS => "i16x8.q15mulr_sat_s",
+            Instruction::I16x8AllTrue => "i16x8.all_true",
+            Instruction::I16x8Bitmask => "i16x8.bitmask",
+            Instruction::I16x8NarrowI32x4S => "i16x8.narrow_i32x4_s",
+            Instruction::I16x8NarrowI32x4U => "i16x8.narrow_i32x4_u",
+            Instruction::I16x8ExtendLowI8x16S => "i16x8.extend_low_i8x16_s",
+            Instruction::I16x8ExtendHighI8x16S => "i16x8.extend_high_i8x16_s",
+            Instruction::I16x8ExtendLowI8x16U => "i16x8.extend_low_i8x16_u",
+            Instruction::I16x8ExtendHighI8x16U => "i16x8.extend_high_i8x16_u",
+            Instruction::I16x8Shl => "i16x8.shl",
+            Instruction::I16x8ShrS => "i16x8.shr_s",
+            Instruction::I16x8ShrU => "i16x8.shr_u",
+            Instruction::I16x8Add => "i16x8.add",
+            Instruction::I16x8AddSatS => "i16x8.add_sat_s",
+            Instruction::I16x8AddSatU => "i16x8.add_sat_u",
+            Instruction::I16x8Sub => "i16x8.sub",
+            Instruction::I16x8SubSatS => "i16x8.sub_sat_s",
+            Instruction::I16x8SubSatU => "i16x8.sub_sat_u",
+            Instruction::F64x2Nearest => "f64x2.nearest",
+            Instruction::I16x8Mul => "i16x8.mul",
+            Instruction::I16x8MinS => "i16x8.min_s",
+            Instruction::I16x8MinU => "i16x8.min_u",
+            Instruction::I16x8MaxS => "i16x8.max_s",
+            Instruction::I16x8MaxU => "i16x8.max_u",
+            Instruction::I16x8AvgrU => "i16x8.avgr_u",
+            Instruction::I16x8ExtMulLowI8x16S => "i16x8.extmul_low_i8x16_s",
+            Instruction::I16x8ExtMulHighI8x16S => "i16x8.extmul_high_i8x16_s",
+            Instruction::I16x8ExtMulLowI8x16U => "i16x8.extmul_low_i8x16_u",
+            Instruction::I16x8ExtMulHighI8x16U => "i16x8.extmul_high_i8x16_u",
+            Instruction::I32x4Abs => "i32x4.abs",
+            Instruction::I32x4Neg => "i32x4.neg",
+            Instruction::I32x4AllTrue => "i32x4.all_true",
+            Instruction::I32x4Bitmask => "i32x4.bitmask",
+            Instruction::I32x4ExtendLowI16x8S => "i32x4.extend_low_i16x8_s",
+            Instruction::I32x4ExtendHighI16x8S => "i32x4.extend_high_i16x8_s",
+            Instruction::I32x4ExtendLowI16x8U => "i32x4.extend_low_i16x8_u",
+            Instruction::I32x4ExtendHighI16x8U => "i32x4.extend_high_i16x8_u",
+            Instruction::I32x4Shl => "i32x4.shl",
+            Instruction::I32x4ShrS => "i32x4.shr_s",
+            Instruction::I32x4ShrU => "i32x4.shr_u",
+            Instruction::I32x4Add => "i32x4.add",
+            Instruction::I32x4Sub => "i32x4.sub",
+            Instruction::I32x4Mul => "i32x4.mul",
+            Instruction::I32x4MinS => "i32x4.min_s",
+            Instruction::I32x4MinU => "i32x4.min_u",
+            Instruction::I32x4MaxS => "i32x4.max_s",
+            Instruction::I32x4MaxU => "i32x4.max_u",
+            Instruction::I32x4DotI16x8S => "i32x4.dot_i16x8_s",
+            Instruction::I32x4ExtMulLowI16x8S => "i32x4.extmul_low_i16x8_s",
+            Instruction::I32x4ExtMulHighI16x8S => "i32x4.extmul_high_i16x8_s",
+            Instruction::I32x4ExtMulLowI16x8U => "i32x4.extmul_low_i16x8_u",
+            Instruction::I32x4ExtMulHighI16x8U => "i32x4.extmul_high_i16x8_u",
+            Instruction::I64x2Abs => "i64x2.abs",
+            Instruction::I64x2Neg => "i64x2.neg",
+            Instruction::I64x2AllTrue => "i64x2.all_true",
+            Instruction::I64x2Bitmask => "i64x2.bitmask",
+            Instruction::I64x2ExtendLowI32x4S => "i64x2.extend_low_i32x4_s",
+            Instruction::I64x2ExtendHighI32x4S => "i64x2.extend_high_i32x4_s",
+            Instruction::I64x2ExtendLowI32x4U => "i64x2.extend_low_i32x4_u",
+            Instruction::I64x2ExtendHighI32x4U => "i64x2.extend_high_i32x4_u",
+            Instruction::I64x2Shl => "i64x2.shl",
+            Instruction::I64x2ShrS => "i64x2.shr_s",
+            Instruction::I64x2ShrU => "i64x2.shr_u",
+            Instruction::I64x2Add => "i64x2.add",
+            Instruction::I64x2Sub => "i64x2.sub",
+            Instruction::I64x2Mul => "i64x2.mul",
+            Instruction::I64x2Eq => "i64x2.eq",
+            Instruction::I64x2Ne => "i64x2.ne",
+            Instruction::I64x2LtS => "i64x2.lt_s",
+            Instruction::I64x2GtS => "i64x2.gt_s",
+            Instruction::I64x2LeS => "i64x2.le_s",
+            Instruction::I64x2GeS => "i64x2.ge_s",
+            Instruction::I64x2ExtMulLowI32x4S => "i64x2.extmul_low_i32x4_s",
+            Instruction::I64x2ExtMulHighI32x4S => "i64x2.extmul_high_i32x4_s",
+            Instruction::I64x2ExtMulLowI32x4U => "i64x2.extmul_low_i32x4_u",
+            Instruction::I64x2ExtMulHighI32x4U => "i64x2.extmul_high_i32x4_u",
+            Instruction::F32x4Abs => "f32x4.abs",
+            Instruction::F32x4Neg => "f32x4.neg",
+            Instruction::F32x4Sqrt => "f32x4.sqrt",
+            Instruction::F32x4Add => "f32x4.add",
+            Instruction::F32x4Sub => "f32x4.sub",
+            Instruction::F32x4Mul => "f32x4.mul",
+            Instruction::F32x4Div => "f32x4.div",
+            Instruction::F32x4Min => "f32x4.min",
+            Instruction::F32x4Max => "f32x4.max",
+            Instruction::F32x4PMin => "f32x4.pmin",
+            Instruction::F32x4PMax => "f32x4.pmax",
+            Instruction::F64x2Abs => "f64x2.abs",
+            Instruction::F64x2Neg => "f64x2.neg",
+            Instruction::F64x2Sqrt => "f64x2.sqrt",
+            Instruction::F64x2Add => "f64x2.add",
+            Instruction::F64x2Sub => "f64x2.sub",
+            Instruction::F64x2Mul => "f64x2.mul",
+            Instruction::F64x2Div => "f64x2.div",
+            Instruction::F64x2Min => "f64x2.min",
+            Instruction::F64x2Max => "f64x2.max",
+            Instruction::F64x2PMin => "f64x2.pmin",
+            Instruction::F64x2PMax => "f64x2.pmax",
+            Instruction::I32x4TruncSatF32x4S => "i32x4.trunc_sat_f32x4_s",
+            Instruction::I32x4TruncSatF32x4U => "i32x4.trunc_sat_f32x4_u",
+            Instruction::F32x4ConvertI32x4S => "f32x4.convert_i32x4_s",
+            Instruction::F32x4ConvertI32x4U => "f32x4.convert_i32x4_u",
+            Instruction::I32x4TruncSatF64x2SZero => "i32x4.trunc_sat_f64x2_s_zero",
+            Instruction::I32x4TruncSatF64x2UZero => "i32x4.trunc_sat_f64x2_u_zero",
+            Instruction::F64x2ConvertLowI32x4S => "f64x2.convert_low_i32x4_s",
+            Instruction::F64x2ConvertLowI32x4U => "f64x2.convert_low_i32x4_u",
+            Instruction::I8x16RelaxedSwizzle => "i8x16.relaxed_swizzle",
+            Instruction::I32x4RelaxedTruncF32x4S => "i32x4.relaxed_trunc_f32x4_s",
+            Instruction::I32x4RelaxedTruncF32x4U => "i32x4.relaxed_trunc_f32x4_u",
+            Instruction::I32x4RelaxedTruncF64x2SZero => "i32x4.relaxed_trunc_f64x2_s_zero",
+            Instruction::I32x4RelaxedTruncF64x2UZero => "i32x4.relaxed_trunc_f64x2_u_zero",
+            Instruction::F32x4RelaxedMadd => "f32x4.relaxed_madd",
+            Instruction::F32x4RelaxedNmadd => "f32x4.relaxed_nmadd",
+            Instruction::F64x2RelaxedMadd => "f64x2.relaxed_madd",
+            Instruction::F64x2RelaxedNmadd => "f64x2.relaxed_nmadd",
+            Instruction::I8x16RelaxedLaneselect => "i8x16.relaxed_laneselect",
+            Instruction::I16x8RelaxedLaneselect => "i16x8.relaxed_laneselect",
+            Instruction::I32x4RelaxedLaneselect => "i32x4.relaxed_laneselect",
+            Instruction::I64x2RelaxedLaneselect => "i64x2.relaxed_laneselect",
+            Instruction::F32x4RelaxedMin => "f32x4.relaxed_min",
+            Instruction::F32x4RelaxedMax => "f32x4.relaxed_max",
+            Instruction::F64x2RelaxedMin => "f64x2.relaxed_min",
+            Instruction::F64x2RelaxedMax => "f64x2.relaxed_max",
+            Instruction::I16x8RelaxedQ15mulrS => "i16x8.relaxed_q15mulr_s",
+            Instruction::I16x8RelaxedDotI8x16I7x16S => "i16x8.relaxed_dot_i8x16_i7x16_s",
+            Instruction::I32x4RelaxedDotI8x16I7x16AddS => "i32x4.relaxed_dot_i8x16_i7x16_add_s",
+            Instruction::MemoryAtomicNotify(_) => "memory.atomic.notify",
+            Instruction::MemoryAtomicWait32(_) => "memory.atomic.wait32",
+            Instruction::MemoryAtomicWait64(_) => "memory.atomic.wait64",
+            Instruction::AtomicFence => "atomic.fence",
+            Instruction::I32AtomicLoad(_) => "i32.atomic.load",
+            Instruction::I64AtomicLoad(_) => "i64.atomic.load",
+            Instruction::I32AtomicLoad8U(_) => "i32.atomic.load8_u",
+            Instruction::I32AtomicLoad16U(_) => "i32.atomic.load16_u",
+            Instruction::I64AtomicLoad8U(_) => "i64.atomic.load8_u",
+            Instruction::I64AtomicLoad16U(_) => "i64.atomic.load16_u",
+            Instruction::I64AtomicLoad32U(_) => "i64.atomic.load32_u",
+            Instruction::I32AtomicStore(_) => "i32.atomic.store",
+            Instruction::I64AtomicStore(_) => "i64.atomic.store",
+            Instruction::I32AtomicStore8(_) => "i32.atomic.store8",
+            Instruction::I32AtomicStore16(_) => "i32.atomic.store16",
+            Instruction::I64AtomicStore8(_) => "i64.atomic.store8",
+            Instruction::I64AtomicStore16(_) => "i64.atomic.store16",
+            Instruction::I64AtomicStore32(_) => "i64.atomic.store32",
+            Instruction::I32AtomicRmwAdd(_) => "i32.atomic.rmw.add",
+            Instruction::I64AtomicRmwAdd(_) => "i64.atomic.rmw.add",
+            Instruction::I32AtomicRmw8AddU(_) => "i32.atomic.rmw8.add_u",
+            Instruction::I32AtomicRmw16AddU(_) => "i32.atomic.rmw16.add_u",
+            Instruction::I64AtomicRmw8AddU(_) => "i64.atomic.rmw8.add_u",
+            Instruction::I64AtomicRmw16AddU(_) => "i64.atomic.rmw16.add_u",
+            Instruction::I64AtomicRmw32AddU(_) => "i64.atomic.rmw32.add_u",
+            Instruction::I32AtomicRmwSub(_) => "i32.atomic.rmw.sub",
+            Instruction::I64AtomicRmwSub(_) => "i64.atomic.rmw.sub",
+            Instruction::I32AtomicRmw8SubU(_) => "i32.atomic.rmw8.sub_u",
+            Instruction::I32AtomicRmw16SubU(_) => "i32.atomic.rmw16.sub_u",
+            Instruction::I64AtomicRmw8SubU(_) => "i64.atomic.rmw8.sub_u",
+            Instruction::I64AtomicRmw16SubU(_) => "i64.atomic.rmw16.sub_u",
+            Instruction::I64AtomicRmw32SubU(_) => "i64.atomic.rmw32.sub_u",
+            Instruction::I32AtomicRmwAnd(_) => "i32.atomic.rmw.and",
+            Instruction::I64AtomicRmwAnd(_) => "i64.atomic.rmw.and",
+            Instruction::I32AtomicRmw8AndU(_) => "i32.atomic.rmw8.and_u",
+            Instruction::I32AtomicRmw16AndU(_) => "i32.atomic.rmw16.and_u",
+            Instruction::I64AtomicRmw8AndU(_) => "i64.atomic.rmw8.and_u",
+            Instruction::I64AtomicRmw16AndU(_) => "i64.atomic.rmw16.and_u",
+            Instruction::I64AtomicRmw32AndU(_) => "i64.atomic.rmw32.and_u",
+            Instruction::I32AtomicRmwOr(_) => "i32.atomic.rmw.or",
+            Instruction::I64AtomicRmwOr(_) => "i64.atomic.rmw.or",
+            Instruction::I32AtomicRmw8OrU(_) => "i32.atomic.rmw8.or_u",
+            Instruction::I32AtomicRmw16OrU(_) => "i32.atomic.rmw16.or_u",
+            Instruction::I64AtomicRmw8OrU(_) => "i64.atomic.rmw8.or_u",
+            Instruction::I64AtomicRmw16OrU(_) => "i64.atomic.rmw16.or_u",
+            Instruction::I64AtomicRmw32OrU(_) => "i64.atomic.rmw32.or_u",
+            Instruction::I32AtomicRmwXor(_) => "i32.atomic.rmw.xor",
+            Instruction::I64AtomicRmwXor(_) => "i64.atomic.rmw.xor",
+            Instruction::I32AtomicRmw8XorU(_) => "i32.atomic.rmw8.xor_u",
+            Instruction::I32AtomicRmw16XorU(_) => "i32.atomic.rmw16.xor_u",
+            Instruction::I64AtomicRmw8XorU(_) => "i64.atomic.rmw8.xor_u",
+            Instruction::I64AtomicRmw16XorU(_) => "i64.atomic.rmw16.xor_u",
+            Instruction::I64AtomicRmw32XorU(_) => "i64.atomic.rmw32.xor_u",
+            Instruction::I32AtomicRmwXchg(_) => "i32.atomic.rmw.xchg",
+            Instruction::I64AtomicRmwXchg(_) => "i64.atomic.rmw.xchg",
+            Instruction::I32AtomicRmw8XchgU(_) => "i32.atomic.rmw8.xchg_u",
+            Instruction::I32AtomicRmw16XchgU(_) => "i32.atomic.rmw16.xchg_u",
+            Instruction::I64AtomicRmw8XchgU(_) => "i64.atomic.rmw8.xchg_u",
+            Instruction::I64AtomicRmw16XchgU(_) => "i64.atomic.rmw16.xchg_u",
+            Instruction::I64AtomicRmw32XchgU(_) => "i64.atomic.rmw32.xchg_u",
+            Instruction::I32AtomicRmwCmpxchg(_) => "i32.atomic.rmw.cmpxchg",
+            Instruction::I64AtomicRmwCmpxchg(_) => "i64.atomic.rmw.cmpxchg",
+            Instruction::I32AtomicRmw8CmpxchgU(_) => "i32.atomic.rmw8.cmpxchg_u",
+            Instruction::I32AtomicRmw16CmpxchgU(_) => "i32.atomic.rmw16.cmpxchg_u",
+            Instruction::I64AtomicRmw8CmpxchgU(_) => "i64.atomic.rmw8.cmpxchg_u",
+            Instruction::I64AtomicRmw16CmpxchgU(_) => "i64.atomic.rmw16.cmpxchg_u",
+            Instruction::I64AtomicRmw32CmpxchgU(_) => "i64.atomic.rmw32.cmpxchg_u",
+            Instruction::Select | Instruction::TypedSelect(_) => "select",
+            Instruction::RefTestNonNull(_) | Instruction::RefTestNullable(_) => "ref.test",
+            Instruction::RefCastNonNull(_) | Instruction::RefCastNullable(_) => "ref.cast",
+            Instruction::BrOnCast(..) => "br_on_cast",
+            Instruction::BrOnCastFail(..) => "br_on_cast_fail",
+        }
+    }
+
+    /// Encode the instruction, the inverse of [Decoder::read_instruction].
+    ///
+    /// Immediates are written in their shortest form, `call_indirect` ignores the raw table byte.
+    pub fn encode(&self, encoder: &mut Encoder) {
+        match self.opcode() {
+            (None, op) => encoder.write_u8(op as u8),
+            (Some(prefix), op) => {
+                encoder.write_u8(prefix);
+                encoder.write_var_u32(op);
+            }
+        }
+        match self {
+            Instruction::Block(ty)
+            | Instruction::Loop(ty)
+            | Instruction::If(ty)
+            | Instruction::Try(ty) => encoder.write_block_type(*ty),
+            Instruction::Catch(idx)
+            | Instruction::Throw(idx)
+            | Instruction::Rethrow(idx)
+            | Instruction::Br(idx)
+            | Instruction::BrIf(idx)
+            | Instruction::Call(idx)
+            | Instruction::ReturnCall(idx)
+            | Instruction::CallRef(idx)
+            | Instruction::ReturnCallRef(idx)
+            | Instruction::Delegate(idx)
+            | Instruction::LocalGet(idx)
+            | Instruction::LocalSet(idx)
+            | Instruction::LocalTee(idx)
+            | Instruction::GlobalGet(idx)
+            | Instruction::GlobalSet(idx)
+            | Instruction::TableGet(idx)
+            | Instruction::TableSet(idx)
+            | Instruction::MemorySize(idx)
+            | Instruction::MemoryGrow(idx)
+            | Instruction::RefFunc(idx)
+            | Instruction::BrOnNull(idx)
+            | Instruction::BrOnNonNull(idx)
+            | Instruction::StructNew(idx)
+            | Instruction::StructNewDefault(idx)
+            | Instruction::ArrayNew(idx)
+            | Instruction::ArrayNewDefault(idx)
+            | Instruction::ArrayGet(idx)
+            | Instruction::ArrayGetS(idx)
+            | Instruction::ArrayGetU(idx)
+            | Instruction::ArraySet(idx)
+            | Instruction::ArrayFill(idx)
+            | Instruction::DataDrop(idx)
+            | Instruction::MemoryFill(idx)
+            | Instruction::ElemDrop(idx)
+            | Instruction::TableGrow(idx)
+            | Instruction::TableSize(idx)
+            | Instruction::TableFill(idx)
+            | Instruction::MemoryDiscard(idx) => encoder.write_var_u32(*idx),
+            Instruction::StructGet(a, b)
+            | Instruction::StructGetS(a, b)
+            | Instruction::StructGetU(a, b)
+            | Instruction::StructSet(a, b)
+            | Instruction::ArrayNewFixed(a, b)
+            | Instruction::ArrayNewData(a, b)
+            | Instruction::ArrayNewElem(a, b)
+            | Instruction::ArrayCopy(a, b)
+            | Instruction::ArrayInitData(a, b)
+            | Instruction::ArrayInitElem(a, b)
+            | Instruction::MemoryInit(a, b)
+            | Instruction::MemoryCopy(a, b)
+            | Instruction::TableInit(a, b)
+            | Instruction::TableCopy(a, b)
+            | Instruction::ReturnCallIndirect(a, b)
+            | Instruction::CallIndirect(a, b, _) => {
+                encoder.write_var_u32(*a);
+                encoder.write_var_u32(*b);
+            }
+            Instruction::RefNull(ty)
+            | Instruction::RefTestNonNull(ty)
+            | Instruction::RefTestNullable(ty)
+            | Instruction::RefCastNonNull(ty)
+            | Instruction::RefCastNullable(ty) => encoder.write_heaptype(*ty),
+            Instruction::I8x16ExtractLaneS(lane)
+            | Instruction::I8x16ExtractLaneU(lane)
+            | Instruction::I8x16ReplaceLane(lane)
+            | Instruction::I16x8ExtractLaneS(lane)
+            | Instruction::I16x8ExtractLaneU(lane)
+            | Instruction::I16x8ReplaceLane(lane)
+            | Instruction::I32x4ExtractLane(lane)
+            | Instruction::I32x4ReplaceLane(lane)
+            | Instruction::I64x2ExtractLane(lane)
+            | Instruction::I64x2ReplaceLane(lane)
+            | Instruction::F32x4ExtractLane(lane)
+            | Instruction::F32x4ReplaceLane(lane)
+            | Instruction::F64x2ExtractLane(lane)
+            | Instruction::F64x2ReplaceLane(lane) => encoder.write_u8(*lane),
+            Instruction::V128Load8Lane(memarg, lane)
+            | Instruction::V128Load16Lane(memarg, lane)
+            | Instruction::V128Load32Lane(memarg, lane)
+            | Instruction::V128Load64Lane(memarg, lane)
+            | Instruction::V128Store8Lane(memarg, lane)
+            | Instruction::V128Store16Lane(memarg, lane)
+            | Instruction::V128Store32Lane(memarg, lane)
+            | Instruction::V128Store64Lane(memarg, lane) => {
+                write_memarg(encoder, memarg);
+                encoder.write_u8(*lane);
+            }
+            Instruction::BrTable(table) => {
+                encoder.write_vec(&table.targets, |encoder, target| {
+                    encoder.write_var_u32(*target)
+                });
+                encoder.write_var_u32(table.default);
+            }
+            Instruction::TypedSelect(ty) => {
+                encoder.write_var_u32(1);
+                encoder.write_valtype(*ty);
+            }
+            Instruction::TryTable(try_table) => write_try_table(encoder, try_table),
+            Instruction::I32Const(value) => encoder.write_var_i32(*value),
+            Instruction::I64Const(value) => encoder.write_var_i64(*value),
+            Instruction::F32Const(value) => encoder.write_u32(value.0.to_bits()),
+            Instruction::F64Const(value) => encoder.write_bytes(&value.0.to_bits().to_le_bytes()),
+            Instruction::V128Const(value) => encoder.write_bytes(&value.0.to_le_bytes()),
+            Instruction::BrOnCast(label, from, to) | Instruction::BrOnCastFail(label, from, to) => {
+                encoder.write_u8(from.nullable as u8 | (to.nullable as u8) << 1);
+                encoder.write_var_u32(*label);
+                encoder.write_heaptype(from.heap_type);
+                encoder.write_heaptype(to.heap_type);
+            }
+            Instruction::I8x16Shuffle(lanes) => encoder.write_bytes(lanes),
+            Instruction::AtomicFence => encoder.write_u8(0x00),
+            _ => {
+                if let Some(memarg) = self.memarg() {
+                    write_memarg(encoder, memarg);
+                }
+            }
+        }
     }
 }
 
-#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
-pub struct MemArg {
-    align: u8,
-    offset: u64,
+fn write_memarg(encoder: &mut Encoder, memarg: &MemArg) {
+    if memarg.memory == 0 {
+        encoder.write_var_u32(memarg.align as u32);
+    } else {
+        encoder.write_var_u32(memarg.align as u32 | (1 << 6));
+        encoder.write_var_u32(memarg.memory);
+    }
+    encoder.write_var_u64(memarg.offset);
 }
 
-impl From<wasmparser::MemArg> for MemArg {
-    fn from(value: wasmparser::MemArg) -> Self {
-        MemArg {
-            align: value.align,
-            offset: value.offset,
+fn write_try_table(encoder: &mut Encoder, try_table: &TryTable) {
+    encoder.write_block_type(try_table.ty);
+    encoder.write_vec(&try_table.catches, |encoder, catch| match *catch {
+        Catch::Catch { tag, label } => {
+            encoder.write_u8(0x00);
+            encoder.write_var_u32(tag);
+            encoder.write_var_u32(label);
+        }
+        Catch::CatchRef { tag, label } => {
+            encoder.write_u8(0x01);
+            encoder.write_var_u32(tag);
+            encoder.write_var_u32(label);
+        }
+        Catch::CatchAll { label } => {
+            encoder.write_u8(0x02);
+            encoder.write_var_u32(label);
+        }
+        Catch::CatchAllRef { label } => {
+            encoder.write_u8(0x03);
+            encoder.write_var_u32(label);
         }
+    });
+}
+
+/// Decodes an expression one instruction at a time, stopping after the `end` that closes it.
+///
+/// Nothing is allocated besides what an [Instruction] itself holds, e.g. `br_table` targets.
+pub struct InstructionReader<'a> {
+    decoder: Decoder<'a>,
+    depth: u32,
+    done: bool,
+}
+
+impl<'a> InstructionReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self::new_with_offset(bytes, 0)
+    }
+
+    /// A reader over an expression found at `offset` in a binary.
+    pub fn new_with_offset(bytes: &'a [u8], offset: usize) -> Self {
+        InstructionReader {
+            decoder: Decoder::new_with_offset(bytes, offset),
+            depth: 0,
+            done: false,
+        }
+    }
+
+    /// Number of bytes consumed so far.
+    pub fn position(&self) -> usize {
+        self.decoder.reader.position() as usize
+    }
+
+    /// Offset in the binary of the next instruction, or of the one that failed to decode.
+    pub fn offset(&self) -> usize {
+        self.decoder.offset()
+    }
+
+    /// Bytes not yet decoded, any left after the final `end` are not part of the expression.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.decoder.remaining_slice()
+    }
+}
+
+impl Iterator for InstructionReader<'_> {
+    type Item = Result<Instruction, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let start = self.decoder.reader.position();
+        let instr = match self.decoder.read_instruction() {
+            Ok(instr) => instr,
+            Err(err) => {
+                self.decoder.reader.set_position(start);
+                self.done = true;
+                return Some(Err(err));
+            }
+        };
+        match instr {
+            Instruction::Block(_)
+            | Instruction::Loop(_)
+            | Instruction::If(_)
+            | Instruction::Try(_)
+            | Instruction::TryTable(_) => self.depth += 1,
+            Instruction::End if self.depth == 0 => self.done = true,
+            Instruction::End => self.depth -= 1,
+            // `delegate` ends its `try` like an `end`.
+            Instruction::Delegate(_) => self.depth = self.depth.saturating_sub(1),
+            _ => {}
+        }
+        Some(Ok(instr))
     }
 }
 
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemArg {
+    /// Alignment as a power of two.
+    pub align: u8,
+    pub offset: u64,
+    /// Index of the accessed memory, encoded only when bit 6 of the alignment flags is set.
+    pub memory: u32,
+}
+
 pub type Lane = u8;
 
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Catch {
     Catch { tag: u32, label: u32 },
     CatchRef { tag: u32, label: u32 },
@@ -687,91 +3026,238 @@ pub enum Catch {
     CatchAllRef { label: u32 },
 }
 
-impl From<wasmparser::Catch> for Catch {
-    fn from(value: wasmparser::Catch) -> Self {
-        match value {
-            wasmparser::Catch::One { tag, label } => Catch::Catch { tag, label },
-            wasmparser::Catch::OneRef { tag, label } => Catch::CatchRef { tag, label },
-            wasmparser::Catch::All { label } => Catch::CatchAll { label },
-            wasmparser::Catch::AllRef { label } => Catch::CatchAllRef { label },
-        }
-    }
-}
-
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TryTable {
     pub ty: BlockType,
     pub catches: Vec<Catch>,
 }
 
-impl From<wasmparser::TryTable> for TryTable {
-    fn from(value: wasmparser::TryTable) -> Self {
-        TryTable {
-            ty: value.ty.into(),
-            catches: value.catches.into_iter().map(Into::into).collect(),
-        }
-    }
-}
-
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BrTable {
-    targets: crate::SVec<u32>,
-    default: u32,
-}
-
-impl<'a> From<wasmparser::BrTable<'a>> for BrTable {
-    fn from(value: wasmparser::BrTable<'a>) -> Self {
-        let targets = value.targets().collect::<Result<crate::SVec<_>, _>>().unwrap();
-        BrTable {
-            targets,
-            default: value.default(),
-        }
-    }
+    pub(crate) targets: crate::SVec<u32>,
+    pub(crate) default: u32,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// Compared by bits, so that NaNs with equal payloads are equal and `0.0` differs from `-0.0`.
+#[derive(Clone, Copy, Debug)]
 pub struct F32(pub f32);
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// Compared by bits, like [F32].
+#[derive(Clone, Copy, Debug)]
 pub struct F64(pub f64);
 
-impl From<wasmparser::Ieee32> for F32 {
-    fn from(value: wasmparser::Ieee32) -> Self {
-        F32(f32::from_le_bytes(value.bits().to_le_bytes()))
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct I128(pub i128);
+
+impl PartialEq for F32 {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
     }
 }
 
-impl From<wasmparser::Ieee64> for F64 {
-    fn from(value: wasmparser::Ieee64) -> Self {
-        F64(f64::from_le_bytes(value.bits().to_le_bytes()))
+impl PartialEq for F64 {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct I128(pub i128);
+// Constants are serialized as their bits in a hex string, e.g. `"0x7fc00001"` for an `F32`,
+// keeping NaN payloads and staying exact in formats with double-only numbers such as JSON.
+#[cfg(feature = "serde")]
+macro_rules! impl_serde_bits {
+    ($($ty:ident($bits:ty, $width:literal, $to:expr, $from:expr)),*) => {$(
+        impl serde::Serialize for $ty {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let bits: $bits = $to(self.0);
+                serializer.collect_str(&format_args!("{:#0width$x}", bits, width = $width + 2))
+            }
+        }
 
-impl From<wasmparser::V128> for I128 {
-    fn from(value: wasmparser::V128) -> Self {
-        I128(value.i128())
-    }
+        impl<'de> serde::Deserialize<'de> for $ty {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+                s.strip_prefix("0x")
+                    .and_then(|hex| <$bits>::from_str_radix(hex, 16).ok())
+                    .map(|bits| $ty($from(bits)))
+                    .ok_or_else(|| {
+                        serde::de::Error::invalid_value(
+                            serde::de::Unexpected::Str(&s),
+                            &concat!("the bits of ", stringify!($ty), " as a 0x prefixed hex string"),
+                        )
+                    })
+            }
+        }
+    )*};
 }
 
-impl From<wasmparser::HeapType> for HeapType {
-    fn from(value: wasmparser::HeapType) -> Self {
-        match value {
-            wasmparser::HeapType::Concrete(ty) => HeapType::Concrete(ty.as_module_index().unwrap()),
-            wasmparser::HeapType::Func => HeapType::Func,
-            wasmparser::HeapType::Extern => HeapType::Extern,
-            wasmparser::HeapType::Any => HeapType::Any,
-            wasmparser::HeapType::None => HeapType::None,
-            wasmparser::HeapType::NoExtern => HeapType::NoExtern,
-            wasmparser::HeapType::NoFunc => HeapType::NoFunc,
-            wasmparser::HeapType::Eq => HeapType::Eq,
-            wasmparser::HeapType::Struct => HeapType::Struct,
-            wasmparser::HeapType::Array => HeapType::Array,
-            wasmparser::HeapType::I31 => HeapType::I31,
-            wasmparser::HeapType::Exn => HeapType::Exn,
-            wasmparser::HeapType::NoExn => HeapType::NoExn,
+#[cfg(feature = "serde")]
+impl_serde_bits!(
+    F32(u32, 8, f32::to_bits, f32::from_bits),
+    F64(u64, 16, f64::to_bits, f64::from_bits),
+    I128(u128, 32, |v: i128| v as u128, |bits: u128| bits as i128)
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::section::DataKind;
+
+    fn decode(bytes: &[u8]) -> Result<Vec<Instruction>, Error> {
+        InstructionReader::new(bytes).collect()
+    }
+
+    #[test]
+    fn test_decode_prefixed_instructions() {
+        let data = wat::parse_str(
+            r#"
+            (module
+                (type $s (struct (field i32)))
+                (memory 1)
+                (table 1 funcref)
+                (func (param i32 anyref) (result i32)
+                    (drop (call_indirect (param i32) (result i32) (local.get 0) (i32.const 0)))
+                    (drop (select (result i32) (i32.const 1) (i32.const 2) (local.get 0)))
+                    (drop (i8x16.shuffle 0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15
+                        (v128.const i64x2 1 2) (v128.load offset=8 align=4 (i32.const 0))))
+                    (drop (i32.atomic.rmw.add offset=4 (i32.const 0) (i32.const 1)))
+                    atomic.fence
+                    (memory.fill (i32.const 0) (i32.const 0) (i32.const 0))
+                    (drop (i32.trunc_sat_f32_s (f32.const 1.5)))
+                    (drop (block (result anyref)
+                        (br_on_cast 0 anyref (ref $s) (local.get 1))))
+                    (br_table 0 0 (local.get 0) (local.get 0))))
+            "#,
+        )
+        .unwrap();
+        let module = crate::module::Module::from_bytes(&data).parse().unwrap();
+        let instrs = module.code_section.0[0].expr().unwrap().instrs;
+
+        assert_eq!(instrs[2], Instruction::CallIndirect(2, 0, 0));
+        assert_eq!(instrs[7], Instruction::TypedSelect(ValType::I32));
+        assert!(instrs.contains(&Instruction::I8x16Shuffle(std::array::from_fn(|i| i as u8))));
+        assert!(instrs.contains(&Instruction::V128Load(MemArg {
+            align: 2,
+            offset: 8,
+            memory: 0,
+        })));
+        assert!(instrs.contains(&Instruction::I32AtomicRmwAdd(MemArg {
+            align: 2,
+            offset: 4,
+            memory: 0,
+        })));
+        assert!(instrs.contains(&Instruction::AtomicFence));
+        assert!(instrs.contains(&Instruction::MemoryFill(0)));
+        assert!(instrs.contains(&Instruction::I32TruncSatF32S));
+        assert!(instrs.contains(&Instruction::BrOnCast(
+            0,
+            RefType::ANYREF,
+            RefType::non_null(HeapType::Concrete(0))
+        )));
+        assert!(instrs.contains(&Instruction::BrTable(BrTable {
+            targets: [0].into_iter().collect(),
+            default: 0,
+        })));
+    }
+
+    #[test]
+    fn test_decode_multi_memory() {
+        let data = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (memory $m 1 1 shared)
+                (data (memory $m) (i32.const 0) "a")
+                (func (param v128) (result i32)
+                    (drop (i32.load $m offset=4 align=2 (i32.const 0)))
+                    (drop (v128.load8_lane $m 3 (i32.const 0) (local.get 0)))
+                    (drop (i32.atomic.load $m (i32.const 0)))
+                    (drop (memory.grow $m (i32.const 1)))
+                    (memory.size $m)))
+            "#,
+        )
+        .unwrap();
+        let module = crate::module::Module::from_bytes(&data).parse().unwrap();
+        module.validate().unwrap();
+        let instrs = module.code_section.0[0].expr().unwrap().instrs;
+
+        let arg = MemArg {
+            align: 1,
+            offset: 4,
+            memory: 1,
+        };
+        assert_eq!(instrs[1], Instruction::I32Load(arg));
+        assert_eq!(
+            instrs[5],
+            Instruction::V128Load8Lane(
+                MemArg {
+                    align: 0,
+                    offset: 0,
+                    memory: 1
+                },
+                3
+            )
+        );
+        assert_eq!(instrs[8].memarg().map(|arg| arg.memory), Some(1));
+        assert_eq!(instrs[11], Instruction::MemoryGrow(1));
+        assert_eq!(instrs[13], Instruction::MemorySize(1));
+        assert_eq!(instrs[13].memarg(), None);
+        assert!(matches!(
+            module.data_section.0[0].kind,
+            DataKind::Active { memory: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn test_natural_align() {
+        let arg = MemArg {
+            align: 0,
+            offset: 0,
+            memory: 0,
+        };
+        let cases = [
+            (Instruction::I32Load8U(arg), 0),
+            (Instruction::I64Load16S(arg), 1),
+            (Instruction::I64Load32U(arg), 2),
+            (Instruction::F64Store(arg), 3),
+            (Instruction::V128Load8x8S(arg), 3),
+            (Instruction::V128Load(arg), 4),
+            (Instruction::V128Store16Lane(arg, 1), 1),
+            (Instruction::MemoryAtomicWait64(arg), 3),
+            (Instruction::I64AtomicRmw32CmpxchgU(arg), 2),
+        ];
+        for (instr, natural) in cases {
+            assert_eq!(instr.natural_align(), Some(natural), "{instr:?}");
         }
+        assert_eq!(Instruction::MemorySize(0).natural_align(), None);
+    }
+
+    #[test]
+    fn test_decode_invalid_instructions() {
+        assert!(matches!(
+            decode(&[0xff]),
+            Err(Error::InvalidInstruction(0xff))
+        ));
+        assert!(matches!(
+            decode(&[0xfc, 0x7f]),
+            Err(Error::Unexpected0xfc(0x7f))
+        ));
+        assert!(matches!(
+            decode(&[0xfd, 0x15, 16]),
+            Err(Error::InvalidLaneIndex(16, 16))
+        ));
+        assert!(matches!(
+            decode(&[0x28, 0x80, 0x01, 0]),
+            Err(Error::AlignmentTooLarge(128))
+        ));
+        assert!(matches!(
+            decode(&[0x1c, 2, 0x7f, 0x7f]),
+            Err(Error::InvalidSelectArity(2))
+        ));
+        assert!(matches!(
+            decode(&[0xfe, 0x03, 0x01]),
+            Err(Error::InvalidLeadingByte(0x01, "atomic.fence"))
+        ));
     }
 }
@@ -1,9 +1,27 @@
+pub mod component;
 pub mod decode;
+#[cfg_attr(docsrs, doc(cfg(feature = "dwarf")))]
+#[cfg(feature = "dwarf")]
+pub mod dwarf;
+pub mod encode;
 pub mod error;
+pub mod eval;
+pub mod features;
 pub mod instruction;
+pub mod limits;
+pub mod linking;
 pub mod module;
+pub mod names;
 pub mod parser;
+pub mod print;
+pub mod producers;
 pub mod section;
+#[cfg_attr(docsrs, doc(cfg(feature = "sourcemap")))]
+#[cfg(feature = "sourcemap")]
+pub mod sourcemap;
+pub mod streaming;
+pub mod target_features;
 pub mod types;
+pub mod validate;
 
 type SVec<T> = smallvec::SmallVec<[T; 4]>;
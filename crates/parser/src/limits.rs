@@ -0,0 +1,291 @@
+//! Resource limits for parsing untrusted binaries, see [Limits].
+
+use crate::decode::Decoder;
+use crate::error::{Error, Location};
+use crate::instruction::Instruction;
+use crate::module::Module;
+use crate::parser::imported_functions;
+use crate::section::{Code, DataSection, ElementSection, SectionId};
+
+/// Upper bounds a [ModuleParser](crate::parser::ModuleParser) or a
+/// [StreamingParser](crate::streaming::StreamingParser) enforces,
+/// a binary over any of them fails to parse with [Error::LimitExceeded].
+///
+/// The defaults follow the limits of JavaScript embeddings, and bound nesting depth
+/// so that code recursing on blocks, e.g. the printer, is safe on the bodies of a parsed binary.
+/// Checking nesting depth decodes every function body while parsing,
+/// [Limits::unlimited] turns it off along with the other limits.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct Limits {
+    /// Bytes in the binary.
+    pub module_size: usize,
+    /// Sections, custom sections included.
+    pub sections: u32,
+    /// Imported and defined functions.
+    pub functions: u32,
+    /// Locals declared by a function body, parameters excluded.
+    pub locals: u32,
+    /// Blocks, loops, ifs and trys open at once in a function body,
+    /// `u32::MAX` turns the check off.
+    pub nesting_depth: u32,
+    /// Bytes of a data segment, or items of an element segment.
+    pub segment_size: u32,
+    /// Elements of the arrays an [Evaluator](crate::eval::Evaluator) creates
+    /// over all the expressions it evaluates, nested values included.
+    pub array_elements: u32,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            module_size: 1 << 30,
+            sections: 100_000,
+            functions: 1_000_000,
+            locals: 50_000,
+            nesting_depth: 1024,
+            segment_size: 1 << 30,
+            array_elements: 1 << 20,
+        }
+    }
+}
+
+impl Limits {
+    pub const fn unlimited() -> Self {
+        Limits {
+            module_size: usize::MAX,
+            sections: u32::MAX,
+            functions: u32::MAX,
+            locals: u32::MAX,
+            nesting_depth: u32::MAX,
+            segment_size: u32::MAX,
+            array_elements: u32::MAX,
+        }
+    }
+
+    /// Reject a section from the length its vector starts with, before it is decoded.
+    pub(crate) fn check_section(&self, id: SectionId, decoder: &Decoder) -> Result<(), Error> {
+        match id {
+            SectionId::Function | SectionId::Code => {
+                let count = decoder.clone().read_var_u32()?;
+                check("function count", count, self.functions)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Check what is only known once the module is parsed.
+    ///
+    /// Errors in function bodies are located at the body or instruction,
+    /// the others are left to the caller.
+    pub(crate) fn check_module(&self, module: &Module) -> Result<(), Error> {
+        let imported = imported_functions(&module.import_section) as u64;
+        let defined = module.func_section.0.len().max(module.code_section.0.len()) as u64;
+        check("function count", imported + defined, self.functions)?;
+
+        self.check_elements(&module.element_section)?;
+        self.check_data(&module.data_section)?;
+        for code in &module.code_section.0 {
+            self.check_code(code)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_elements(&self, section: &ElementSection) -> Result<(), Error> {
+        for element in &section.0 {
+            check(
+                "element segment size",
+                element.init.len(),
+                self.segment_size,
+            )?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn check_data(&self, section: &DataSection) -> Result<(), Error> {
+        for data in &section.0 {
+            check("data segment size", data.init.len(), self.segment_size)?;
+        }
+        Ok(())
+    }
+
+    /// Check the locals and, if limited, the nesting depth of a function body,
+    /// errors are located in the body.
+    pub(crate) fn check_code(&self, code: &Code) -> Result<(), Error> {
+        let location = Location::Function(code.index);
+        let locals = code.locals.iter().map(|local| local.n as u64).sum::<u64>();
+        check("locals count", locals, self.locals).map_err(|err| err.at(code.offset, location))?;
+        if self.nesting_depth != u32::MAX {
+            self.nesting(code)?;
+        }
+        Ok(())
+    }
+
+    fn nesting(&self, code: &Code) -> Result<(), Error> {
+        let location = Location::Function(code.index);
+        let mut reader = code.instructions();
+        let mut depth = 0u32;
+        loop {
+            let offset = reader.offset();
+            let Some(instr) = reader.next() else {
+                return Ok(());
+            };
+            match instr.map_err(|err| err.at(offset, location))? {
+                Instruction::Block(_)
+                | Instruction::Loop(_)
+                | Instruction::If(_)
+                | Instruction::Try(_)
+                | Instruction::TryTable(_) => {
+                    depth += 1;
+                    check("nesting depth", depth, self.nesting_depth)
+                        .map_err(|err| err.at(offset, location))?;
+                }
+                Instruction::End | Instruction::Delegate(_) => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+    }
+}
+
+pub(crate) fn check(
+    what: &'static str,
+    value: impl TryInto<u64>,
+    limit: impl TryInto<u64>,
+) -> Result<(), Error> {
+    let value = value.try_into().unwrap_or(u64::MAX);
+    let limit = limit.try_into().unwrap_or(u64::MAX);
+    if value > limit {
+        return Err(Error::LimitExceeded(what, limit));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Limits;
+    use crate::error::{Error, Location};
+    use crate::module::Module;
+
+    fn parse_err(data: &[u8], limits: Limits) -> Error {
+        Module::from_bytes(data).limits(limits).parse().unwrap_err()
+    }
+
+    #[test]
+    fn test_limits() {
+        let data = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (func (local i32 i32 i32)
+                    (block (loop (block (br 2))))
+                    (block))
+                (func)
+                (data (i32.const 0) "abcd"))
+            "#,
+        )
+        .unwrap();
+        Module::from_bytes(&data).parse().unwrap();
+        let limits = Limits {
+            nesting_depth: 3,
+            ..Limits::default()
+        };
+        Module::from_bytes(&data).limits(limits).parse().unwrap();
+
+        let cases = [
+            (
+                Limits {
+                    module_size: 16,
+                    ..Limits::default()
+                },
+                "module size",
+            ),
+            (
+                Limits {
+                    sections: 2,
+                    ..Limits::default()
+                },
+                "section count",
+            ),
+            (
+                Limits {
+                    functions: 1,
+                    ..Limits::default()
+                },
+                "function count",
+            ),
+            (
+                Limits {
+                    locals: 2,
+                    ..Limits::default()
+                },
+                "locals count",
+            ),
+            (
+                Limits {
+                    nesting_depth: 2,
+                    ..Limits::default()
+                },
+                "nesting depth",
+            ),
+            (
+                Limits {
+                    segment_size: 3,
+                    ..Limits::default()
+                },
+                "data segment size",
+            ),
+        ];
+        for (limits, what) in cases {
+            let err = parse_err(&data, limits);
+            assert!(
+                matches!(err.kind(), Error::LimitExceeded(name, _) if *name == what),
+                "{what}: {err}"
+            );
+        }
+        let err = parse_err(
+            &data,
+            Limits {
+                nesting_depth: 2,
+                ..Limits::default()
+            },
+        );
+        assert_eq!(err.location(), Some(Location::Function(0)));
+
+        // Deeply nested bodies are rejected by default.
+        let depth = 100_000;
+        let wat = format!(
+            "(module (func {} {}))",
+            "(block ".repeat(depth),
+            ")".repeat(depth)
+        );
+        let data = wat::parse_str(wat).unwrap();
+        let err = Module::from_bytes(&data).parse().unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            Error::LimitExceeded("nesting depth", 1024)
+        ));
+        Module::from_bytes(&data)
+            .limits(Limits::unlimited())
+            .parse()
+            .unwrap();
+
+        // Length prefixes larger than the input fail without allocating for them.
+        let mut data = b"\0asm\x01\0\0\0".to_vec();
+        data.extend([0x01, 0x05, 0xff, 0xff, 0xff, 0xff, 0x0f]);
+        assert!(Module::from_bytes(&data).parse().is_err());
+        let mut data = b"\0asm\x01\0\0\0".to_vec();
+        data.extend([0x00, 0x06, 0xff, 0xff, 0xff, 0xff, 0x0f, b'a']);
+        assert!(Module::from_bytes(&data).parse().is_err());
+
+        // A body cut short after `block` fails to decode instead of reading past the end.
+        let mut data = b"\0asm\x01\0\0\0".to_vec();
+        data.extend([0x01, 0x04, 0x01, 0x60, 0x00, 0x00]);
+        data.extend([0x03, 0x02, 0x01, 0x00]);
+        data.extend([0x0a, 0x04, 0x01, 0x02, 0x00, 0x02]);
+        assert!(Module::from_bytes(&data).parse().is_err());
+        let module = Module::from_bytes(&data)
+            .limits(Limits::unlimited())
+            .parse()
+            .unwrap();
+        assert!(module.code_section.0[0].expr().is_err());
+    }
+}
@@ -0,0 +1,782 @@
+//! The `linking`, `reloc.*` and `dylink.0` custom sections of object files and shared libraries.
+//!
+//! https://github.com/WebAssembly/tool-conventions/blob/main/Linking.md
+//! and https://github.com/WebAssembly/tool-conventions/blob/main/DynamicLinking.md
+
+use smol_str::SmolStr;
+
+use crate::decode::Decoder;
+use crate::error::Error;
+
+/// The `linking` section, which describes the symbols of a relocatable object file.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Linking {
+    pub version: u32,
+    pub segments: Vec<SegmentInfo>,
+    pub init_funcs: Vec<InitFunc>,
+    pub comdats: Vec<Comdat>,
+    pub symbols: Vec<Symbol>,
+}
+
+/// Extra data about a data segment, in data section order.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SegmentInfo {
+    pub name: SmolStr,
+    /// The alignment as a power of two.
+    pub alignment: u32,
+    /// `0x1` strings, `0x2` thread local, `0x4` retained by the linker.
+    pub flags: u32,
+}
+
+/// A constructor to run at startup, lower priorities run first.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InitFunc {
+    pub priority: u32,
+    /// Index in the symbol table, the symbol is a function.
+    pub symbol: u32,
+}
+
+/// A group of symbols the linker keeps or discards together, deduplicated by name.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Comdat {
+    pub name: SmolStr,
+    pub flags: u32,
+    pub symbols: Vec<ComdatSymbol>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComdatSymbol {
+    pub kind: ComdatSymbolKind,
+    /// Index in the index space of `kind`, e.g. a data segment or function index.
+    pub index: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComdatSymbolKind {
+    Data = 0x00,
+    Function = 0x01,
+    Global = 0x02,
+    Tag = 0x03,
+    Table = 0x04,
+    Section = 0x05,
+}
+
+impl TryFrom<u8> for ComdatSymbolKind {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x00 => Ok(ComdatSymbolKind::Data),
+            0x01 => Ok(ComdatSymbolKind::Function),
+            0x02 => Ok(ComdatSymbolKind::Global),
+            0x03 => Ok(ComdatSymbolKind::Tag),
+            0x04 => Ok(ComdatSymbolKind::Table),
+            0x05 => Ok(ComdatSymbolKind::Section),
+            _ => Err(Error::InvalidType("ComdatSymbolKind", value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub flags: SymbolFlags,
+    pub kind: SymbolKind,
+}
+
+/// Symbols of wasm entities refer to them by index, `name` is only present
+/// for defined symbols or with [SymbolFlags::EXPLICIT_NAME], the import name is used otherwise.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SymbolKind {
+    Function {
+        index: u32,
+        name: Option<SmolStr>,
+    },
+    Data {
+        name: SmolStr,
+        definition: Option<DataDefinition>,
+    },
+    Global {
+        index: u32,
+        name: Option<SmolStr>,
+    },
+    /// A custom section, by its index among all sections of the binary.
+    Section {
+        section: u32,
+    },
+    Tag {
+        index: u32,
+        name: Option<SmolStr>,
+    },
+    Table {
+        index: u32,
+        name: Option<SmolStr>,
+    },
+}
+
+/// Where a defined data symbol lives, `offset` and `size` are in bytes within the segment.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DataDefinition {
+    pub segment: u32,
+    pub offset: u64,
+    pub size: u64,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SymbolFlags(pub u32);
+
+impl SymbolFlags {
+    pub const BINDING_WEAK: u32 = 0x01;
+    pub const BINDING_LOCAL: u32 = 0x02;
+    pub const VISIBILITY_HIDDEN: u32 = 0x04;
+    pub const UNDEFINED: u32 = 0x10;
+    pub const EXPORTED: u32 = 0x20;
+    pub const EXPLICIT_NAME: u32 = 0x40;
+    pub const NO_STRIP: u32 = 0x80;
+    pub const TLS: u32 = 0x100;
+    pub const ABSOLUTE: u32 = 0x200;
+
+    pub fn contains(&self, flag: u32) -> bool {
+        self.0 & flag == flag
+    }
+
+    pub fn is_undefined(&self) -> bool {
+        self.contains(Self::UNDEFINED)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LinkingSubsectionId {
+    SegmentInfo = 0x05,
+    InitFuncs = 0x06,
+    ComdatInfo = 0x07,
+    SymbolTable = 0x08,
+}
+
+impl TryFrom<u8> for LinkingSubsectionId {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x05 => Ok(LinkingSubsectionId::SegmentInfo),
+            0x06 => Ok(LinkingSubsectionId::InitFuncs),
+            0x07 => Ok(LinkingSubsectionId::ComdatInfo),
+            0x08 => Ok(LinkingSubsectionId::SymbolTable),
+            _ => Err(Error::InvalidSectionId(value)),
+        }
+    }
+}
+
+impl Linking {
+    pub const SECTION_NAME: &'static str = "linking";
+    pub const VERSION: u32 = 2;
+
+    /// Decode the payload of a `linking` custom section, unknown subsections are skipped.
+    pub fn parse(data: &[u8]) -> Result<Linking, Error> {
+        let mut decoder = Decoder::new(data);
+        let mut linking = Linking {
+            version: decoder.read_var_u32()?,
+            ..Default::default()
+        };
+        if linking.version != Self::VERSION {
+            return Err(Error::InvalidCustomSection(
+                Self::SECTION_NAME,
+                "unsupported version",
+            ));
+        }
+
+        read_subsections(&mut decoder, |id, sub| {
+            let Ok(id) = LinkingSubsectionId::try_from(id) else {
+                return Ok(false);
+            };
+            match id {
+                LinkingSubsectionId::SegmentInfo => {
+                    linking.segments = sub.read_vec(|decoder| {
+                        Ok(SegmentInfo {
+                            name: decoder.read_str()?,
+                            alignment: decoder.read_var_u32()?,
+                            flags: decoder.read_var_u32()?,
+                        })
+                    })?;
+                }
+                LinkingSubsectionId::InitFuncs => {
+                    linking.init_funcs = sub.read_vec(|decoder| {
+                        Ok(InitFunc {
+                            priority: decoder.read_var_u32()?,
+                            symbol: decoder.read_var_u32()?,
+                        })
+                    })?;
+                }
+                LinkingSubsectionId::ComdatInfo => {
+                    linking.comdats = sub.read_vec(|decoder| {
+                        Ok(Comdat {
+                            name: decoder.read_str()?,
+                            flags: decoder.read_var_u32()?,
+                            symbols: decoder.read_vec(|decoder| {
+                                Ok(ComdatSymbol {
+                                    kind: ComdatSymbolKind::try_from(decoder.read_u8()?)?,
+                                    index: decoder.read_var_u32()?,
+                                })
+                            })?,
+                        })
+                    })?;
+                }
+                LinkingSubsectionId::SymbolTable => {
+                    linking.symbols = sub.read_vec(read_symbol)?;
+                }
+            }
+            Ok(true)
+        })?;
+
+        Ok(linking)
+    }
+
+    /// The name of the symbol at `idx`, if it has one in the symbol table.
+    pub fn symbol_name(&self, idx: u32) -> Option<&str> {
+        match &self.symbols.get(idx as usize)?.kind {
+            SymbolKind::Function { name, .. }
+            | SymbolKind::Global { name, .. }
+            | SymbolKind::Tag { name, .. }
+            | SymbolKind::Table { name, .. } => name.as_deref(),
+            SymbolKind::Data { name, .. } => Some(name),
+            SymbolKind::Section { .. } => None,
+        }
+    }
+}
+
+fn read_symbol(decoder: &mut Decoder) -> Result<Symbol, Error> {
+    let kind = decoder.read_u8()?;
+    let flags = SymbolFlags(decoder.read_var_u32()?);
+    let has_name = !flags.is_undefined() || flags.contains(SymbolFlags::EXPLICIT_NAME);
+    let entity = |decoder: &mut Decoder| -> Result<_, Error> {
+        let index = decoder.read_var_u32()?;
+        let name = has_name.then(|| decoder.read_str()).transpose()?;
+        Ok((index, name))
+    };
+
+    let kind = match kind {
+        0x00 => {
+            let (index, name) = entity(decoder)?;
+            SymbolKind::Function { index, name }
+        }
+        0x01 => {
+            let name = decoder.read_str()?;
+            let definition = if flags.is_undefined() {
+                None
+            } else {
+                Some(DataDefinition {
+                    segment: decoder.read_var_u32()?,
+                    offset: decoder.read_var_u64()?,
+                    size: decoder.read_var_u64()?,
+                })
+            };
+            SymbolKind::Data { name, definition }
+        }
+        0x02 => {
+            let (index, name) = entity(decoder)?;
+            SymbolKind::Global { index, name }
+        }
+        0x03 => SymbolKind::Section {
+            section: decoder.read_var_u32()?,
+        },
+        0x04 => {
+            let (index, name) = entity(decoder)?;
+            SymbolKind::Tag { index, name }
+        }
+        0x05 => {
+            let (index, name) = entity(decoder)?;
+            SymbolKind::Table { index, name }
+        }
+        _ => return Err(Error::InvalidType("SymbolKind", kind)),
+    };
+    Ok(Symbol { flags, kind })
+}
+
+/// The relocations of one section, from a `reloc.*` custom section.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Relocations {
+    /// The index of the patched section among all sections of the binary, custom sections included.
+    pub section: u32,
+    pub entries: Vec<Relocation>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Relocation {
+    pub ty: RelocationType,
+    /// Offset of the patched value, relative to the start of the section contents.
+    pub offset: u32,
+    /// A symbol index, or a type index for [RelocationType::TypeIndexLeb].
+    pub index: u32,
+    /// Only memory address and offset relocations have an addend, it is 0 for the others.
+    pub addend: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RelocationType {
+    FunctionIndexLeb = 0,
+    TableIndexSleb = 1,
+    TableIndexI32 = 2,
+    MemoryAddrLeb = 3,
+    MemoryAddrSleb = 4,
+    MemoryAddrI32 = 5,
+    TypeIndexLeb = 6,
+    GlobalIndexLeb = 7,
+    FunctionOffsetI32 = 8,
+    SectionOffsetI32 = 9,
+    TagIndexLeb = 10,
+    MemoryAddrRelSleb = 11,
+    TableIndexRelSleb = 12,
+    GlobalIndexI32 = 13,
+    MemoryAddrLeb64 = 14,
+    MemoryAddrSleb64 = 15,
+    MemoryAddrI64 = 16,
+    MemoryAddrRelSleb64 = 17,
+    TableIndexSleb64 = 18,
+    TableIndexI64 = 19,
+    TableNumberLeb = 20,
+    MemoryAddrTlsSleb = 21,
+    FunctionOffsetI64 = 22,
+    MemoryAddrLocrelI32 = 23,
+    TableIndexRelSleb64 = 24,
+    MemoryAddrTlsSleb64 = 25,
+    FunctionIndexI32 = 26,
+}
+
+impl TryFrom<u8> for RelocationType {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        use RelocationType::*;
+        Ok(match value {
+            0 => FunctionIndexLeb,
+            1 => TableIndexSleb,
+            2 => TableIndexI32,
+            3 => MemoryAddrLeb,
+            4 => MemoryAddrSleb,
+            5 => MemoryAddrI32,
+            6 => TypeIndexLeb,
+            7 => GlobalIndexLeb,
+            8 => FunctionOffsetI32,
+            9 => SectionOffsetI32,
+            10 => TagIndexLeb,
+            11 => MemoryAddrRelSleb,
+            12 => TableIndexRelSleb,
+            13 => GlobalIndexI32,
+            14 => MemoryAddrLeb64,
+            15 => MemoryAddrSleb64,
+            16 => MemoryAddrI64,
+            17 => MemoryAddrRelSleb64,
+            18 => TableIndexSleb64,
+            19 => TableIndexI64,
+            20 => TableNumberLeb,
+            21 => MemoryAddrTlsSleb,
+            22 => FunctionOffsetI64,
+            23 => MemoryAddrLocrelI32,
+            24 => TableIndexRelSleb64,
+            25 => MemoryAddrTlsSleb64,
+            26 => FunctionIndexI32,
+            _ => return Err(Error::InvalidType("RelocationType", value)),
+        })
+    }
+}
+
+impl RelocationType {
+    pub fn has_addend(&self) -> bool {
+        use RelocationType::*;
+        matches!(
+            self,
+            MemoryAddrLeb
+                | MemoryAddrSleb
+                | MemoryAddrI32
+                | FunctionOffsetI32
+                | SectionOffsetI32
+                | MemoryAddrRelSleb
+                | MemoryAddrLeb64
+                | MemoryAddrSleb64
+                | MemoryAddrI64
+                | MemoryAddrRelSleb64
+                | MemoryAddrTlsSleb
+                | FunctionOffsetI64
+                | MemoryAddrLocrelI32
+                | MemoryAddrTlsSleb64
+        )
+    }
+}
+
+impl Relocations {
+    /// Relocation sections are named after the patched section, e.g. `reloc.CODE` or `reloc.DATA`.
+    pub const SECTION_PREFIX: &'static str = "reloc.";
+
+    pub fn parse(data: &[u8]) -> Result<Relocations, Error> {
+        let mut decoder = Decoder::new(data);
+        let section = decoder.read_var_u32()?;
+        let entries = decoder.read_vec(|decoder| {
+            let ty = RelocationType::try_from(decoder.read_u8()?)?;
+            Ok(Relocation {
+                ty,
+                offset: decoder.read_var_u32()?,
+                index: decoder.read_var_u32()?,
+                addend: if ty.has_addend() {
+                    decoder.read_var_i64()?
+                } else {
+                    0
+                },
+            })
+        })?;
+
+        if !decoder.remaining_slice().is_empty() {
+            return Err(Error::InvalidCustomSection("reloc", "trailing bytes"));
+        }
+
+        Ok(Relocations { section, entries })
+    }
+}
+
+/// The `dylink.0` section, which describes what a shared library needs to be loaded.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Dylink {
+    pub mem_info: Option<DylinkMemInfo>,
+    /// Shared libraries this one depends on.
+    pub needed: Vec<SmolStr>,
+    pub exports: Vec<DylinkExport>,
+    pub imports: Vec<DylinkImport>,
+    pub runtime_paths: Vec<SmolStr>,
+}
+
+/// Memory and table space to reserve for the library, alignments are powers of two.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DylinkMemInfo {
+    pub memory_size: u32,
+    pub memory_alignment: u32,
+    pub table_size: u32,
+    pub table_alignment: u32,
+}
+
+/// Symbol flags of an export, using the values of [SymbolFlags].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DylinkExport {
+    pub name: SmolStr,
+    pub flags: SymbolFlags,
+}
+
+/// Symbol flags of an import, using the values of [SymbolFlags].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DylinkImport {
+    pub module: SmolStr,
+    pub field: SmolStr,
+    pub flags: SymbolFlags,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DylinkSubsectionId {
+    MemInfo = 0x01,
+    Needed = 0x02,
+    ExportInfo = 0x03,
+    ImportInfo = 0x04,
+    RuntimePath = 0x05,
+}
+
+impl TryFrom<u8> for DylinkSubsectionId {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x01 => Ok(DylinkSubsectionId::MemInfo),
+            0x02 => Ok(DylinkSubsectionId::Needed),
+            0x03 => Ok(DylinkSubsectionId::ExportInfo),
+            0x04 => Ok(DylinkSubsectionId::ImportInfo),
+            0x05 => Ok(DylinkSubsectionId::RuntimePath),
+            _ => Err(Error::InvalidSectionId(value)),
+        }
+    }
+}
+
+impl Dylink {
+    pub const SECTION_NAME: &'static str = "dylink.0";
+
+    /// Decode the payload of a `dylink.0` custom section, unknown subsections are skipped.
+    pub fn parse(data: &[u8]) -> Result<Dylink, Error> {
+        let mut dylink = Dylink::default();
+        let mut decoder = Decoder::new(data);
+
+        read_subsections(&mut decoder, |id, sub| {
+            let Ok(id) = DylinkSubsectionId::try_from(id) else {
+                return Ok(false);
+            };
+            match id {
+                DylinkSubsectionId::MemInfo => {
+                    dylink.mem_info = Some(DylinkMemInfo {
+                        memory_size: sub.read_var_u32()?,
+                        memory_alignment: sub.read_var_u32()?,
+                        table_size: sub.read_var_u32()?,
+                        table_alignment: sub.read_var_u32()?,
+                    });
+                }
+                DylinkSubsectionId::Needed => dylink.needed = sub.read_vec(Decoder::read_str)?,
+                DylinkSubsectionId::ExportInfo => {
+                    dylink.exports = sub.read_vec(|decoder| {
+                        Ok(DylinkExport {
+                            name: decoder.read_str()?,
+                            flags: SymbolFlags(decoder.read_var_u32()?),
+                        })
+                    })?;
+                }
+                DylinkSubsectionId::ImportInfo => {
+                    dylink.imports = sub.read_vec(|decoder| {
+                        Ok(DylinkImport {
+                            module: decoder.read_str()?,
+                            field: decoder.read_str()?,
+                            flags: SymbolFlags(decoder.read_var_u32()?),
+                        })
+                    })?;
+                }
+                DylinkSubsectionId::RuntimePath => {
+                    dylink.runtime_paths = sub.read_vec(Decoder::read_str)?;
+                }
+            }
+            Ok(true)
+        })?;
+
+        Ok(dylink)
+    }
+}
+
+/// Calls `f` with the id and contents of each subsection.
+///
+/// `f` returns false to skip an unknown subsection, known ones must be consumed entirely.
+fn read_subsections<F>(decoder: &mut Decoder, mut f: F) -> Result<(), Error>
+where
+    F: FnMut(u8, &mut Decoder) -> Result<bool, Error>,
+{
+    while !decoder.remaining_slice().is_empty() {
+        let id = decoder.read_u8()?;
+        let size = decoder.read_var_u32()?;
+        if size as usize > decoder.remaining_slice().len() {
+            return Err(Error::SubsectionOutOfBounds(
+                id,
+                size,
+                decoder.remaining_slice().len() as u32,
+            ));
+        }
+        let mut sub = decoder.slice_with(size as u64);
+        if !f(id, &mut sub)? {
+            continue;
+        }
+
+        if !sub.remaining_slice().is_empty() {
+            return Err(Error::SubsectionOutOfBounds(
+                id,
+                size,
+                size - sub.remaining_slice().len() as u32,
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        Comdat, ComdatSymbol, ComdatSymbolKind, DataDefinition, Dylink, DylinkImport,
+        DylinkMemInfo, InitFunc, Linking, Relocation, RelocationType, Relocations, SegmentInfo,
+        Symbol, SymbolFlags, SymbolKind,
+    };
+    use crate::encode::Encoder;
+    use crate::module::Module;
+    use crate::section::CustomSection;
+
+    #[test]
+    fn test_parse_linking_sections() {
+        let mut linking = Encoder::new();
+        linking.write_var_u32(2);
+        // Symbol table: `main`, an undefined `puts` and the string it prints.
+        linking.write_u8(0x08);
+        linking.write_sized(|encoder| {
+            encoder.write_var_u32(3);
+            encoder.write_u8(0x00);
+            encoder.write_var_u32(0);
+            encoder.write_var_u32(1);
+            encoder.write_str("main");
+            encoder.write_u8(0x00);
+            encoder.write_var_u32(SymbolFlags::UNDEFINED);
+            encoder.write_var_u32(0);
+            encoder.write_u8(0x01);
+            encoder.write_var_u32(SymbolFlags::BINDING_LOCAL);
+            encoder.write_str(".L.str");
+            encoder.write_var_u32(0);
+            encoder.write_var_u32(0);
+            encoder.write_var_u32(6);
+        });
+        // An unknown subsection is skipped.
+        linking.write_u8(0x7f);
+        linking.write_sized(|encoder| encoder.write_bytes(&[1, 2, 3]));
+        linking.write_u8(0x05);
+        linking.write_sized(|encoder| {
+            encoder.write_var_u32(1);
+            encoder.write_str(".rodata.str");
+            encoder.write_var_u32(0);
+            encoder.write_var_u32(0x1);
+        });
+        linking.write_u8(0x06);
+        linking.write_sized(|encoder| {
+            encoder.write_var_u32(1);
+            encoder.write_var_u32(65535);
+            encoder.write_var_u32(0);
+        });
+        linking.write_u8(0x07);
+        linking.write_sized(|encoder| {
+            encoder.write_var_u32(1);
+            encoder.write_str("inline_fn");
+            encoder.write_var_u32(0);
+            encoder.write_var_u32(1);
+            encoder.write_u8(0x01);
+            encoder.write_var_u32(1);
+        });
+
+        let mut reloc = Encoder::new();
+        reloc.write_var_u32(3);
+        reloc.write_var_u32(2);
+        reloc.write_u8(RelocationType::MemoryAddrSleb as u8);
+        reloc.write_var_u32(0x0a);
+        reloc.write_var_u32(2);
+        reloc.write_var_i64(-4);
+        reloc.write_u8(RelocationType::FunctionIndexLeb as u8);
+        reloc.write_var_u32(0x10);
+        reloc.write_var_u32(1);
+
+        let mut dylink = Encoder::new();
+        dylink.write_u8(0x01);
+        dylink.write_sized(|encoder| {
+            encoder.write_var_u32(16);
+            encoder.write_var_u32(2);
+            encoder.write_var_u32(1);
+            encoder.write_var_u32(0);
+        });
+        dylink.write_u8(0x02);
+        dylink.write_sized(|encoder| {
+            encoder.write_vec(["libc.so"], |encoder, lib| encoder.write_str(lib));
+        });
+        dylink.write_u8(0x04);
+        dylink.write_sized(|encoder| {
+            encoder.write_var_u32(1);
+            encoder.write_str("env");
+            encoder.write_str("errno");
+            encoder.write_var_u32(SymbolFlags::TLS);
+        });
+
+        let mut module = Module::default();
+        assert_eq!(module.linking().unwrap(), None);
+        module.custom_sections.extend([
+            CustomSection::new(Linking::SECTION_NAME, linking.finish()),
+            CustomSection::new("reloc.CODE", reloc.finish()),
+            CustomSection::new(Dylink::SECTION_NAME, dylink.finish()),
+        ]);
+
+        let linking = module.linking().unwrap().unwrap();
+        assert_eq!(
+            linking.symbols,
+            [
+                Symbol {
+                    flags: SymbolFlags(0),
+                    kind: SymbolKind::Function {
+                        index: 1,
+                        name: Some("main".into()),
+                    },
+                },
+                Symbol {
+                    flags: SymbolFlags(SymbolFlags::UNDEFINED),
+                    kind: SymbolKind::Function {
+                        index: 0,
+                        name: None,
+                    },
+                },
+                Symbol {
+                    flags: SymbolFlags(SymbolFlags::BINDING_LOCAL),
+                    kind: SymbolKind::Data {
+                        name: ".L.str".into(),
+                        definition: Some(DataDefinition {
+                            segment: 0,
+                            offset: 0,
+                            size: 6,
+                        }),
+                    },
+                },
+            ]
+        );
+        assert_eq!(linking.symbol_name(2), Some(".L.str"));
+        assert_eq!(linking.symbol_name(1), None);
+        assert_eq!(
+            linking.segments,
+            [SegmentInfo {
+                name: ".rodata.str".into(),
+                alignment: 0,
+                flags: 0x1,
+            }]
+        );
+        assert_eq!(
+            linking.init_funcs,
+            [InitFunc {
+                priority: 65535,
+                symbol: 0,
+            }]
+        );
+        assert_eq!(
+            linking.comdats,
+            [Comdat {
+                name: "inline_fn".into(),
+                flags: 0,
+                symbols: vec![ComdatSymbol {
+                    kind: ComdatSymbolKind::Function,
+                    index: 1,
+                }],
+            }]
+        );
+
+        let relocations = module.relocations().unwrap();
+        assert_eq!(
+            relocations,
+            [(
+                "CODE",
+                Relocations {
+                    section: 3,
+                    entries: vec![
+                        Relocation {
+                            ty: RelocationType::MemoryAddrSleb,
+                            offset: 0x0a,
+                            index: 2,
+                            addend: -4,
+                        },
+                        Relocation {
+                            ty: RelocationType::FunctionIndexLeb,
+                            offset: 0x10,
+                            index: 1,
+                            addend: 0,
+                        },
+                    ],
+                },
+            )]
+        );
+
+        let dylink = module.dylink().unwrap().unwrap();
+        assert_eq!(
+            dylink.mem_info,
+            Some(DylinkMemInfo {
+                memory_size: 16,
+                memory_alignment: 2,
+                table_size: 1,
+                table_alignment: 0,
+            })
+        );
+        assert_eq!(dylink.needed, ["libc.so"]);
+        assert_eq!(
+            dylink.imports,
+            [DylinkImport {
+                module: "env".into(),
+                field: "errno".into(),
+                flags: SymbolFlags(SymbolFlags::TLS),
+            }]
+        );
+        assert!(dylink.exports.is_empty());
+
+        assert!(Linking::parse(&[1]).is_err());
+    }
+}
@@ -1,33 +1,308 @@
+use smol_str::SmolStr;
+
 use crate::decode::Decoder;
+use crate::encode::Encoder;
+use crate::error::Error;
+use crate::features::Features;
+use crate::limits::Limits;
+use crate::linking::{Dylink, Linking, Relocations};
+use crate::names::Names;
 use crate::parser::ModuleParser;
+use crate::producers::Producers;
 use crate::section::{
     CodeSection, CustomSection, DataCountSection, DataSection, ElementSection, ExportSection,
     FunctionSection, GlobalSection, ImportSection, MemorySection, StartSection, TableSection,
-    TypeSection,
+    TagSection, TypeSection,
 };
+use crate::target_features::TargetFeatures;
 
+/// A parsed core module.
+///
+/// Names, custom sections and data segments borrow from the input binary,
+/// use [Module::into_owned] to get a `Module<'static>`.
+///
+/// Modules are equal if their contents are, wherever they are found in the binary,
+/// e.g. a module is equal to the result of parsing its [Module::encode] output.
 #[derive(Debug, Default)]
-pub struct Module {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Module<'a> {
     pub version: u32,
-    pub custom_sections: crate::SVec<CustomSection>,
+    pub custom_sections: crate::SVec<CustomSection<'a>>,
     pub type_section: TypeSection,
-    pub import_section: ImportSection,
+    pub import_section: ImportSection<'a>,
     pub func_section: FunctionSection,
     pub table_section: TableSection,
     pub memory_section: MemorySection,
+    pub tag_section: TagSection,
     pub global_section: GlobalSection,
-    pub export_section: ExportSection,
+    pub export_section: ExportSection<'a>,
     pub start_section: StartSection,
     pub element_section: ElementSection,
-    pub code_section: CodeSection,
-    pub data_section: DataSection,
+    pub code_section: CodeSection<'a>,
+    /// Offset of the code section contents in the binary, DWARF addresses are relative to it.
+    pub code_section_offset: usize,
+    pub data_section: DataSection<'a>,
     pub data_count_section: DataCountSection,
 }
 
-impl Module {
-    pub fn from_bytes(bytes: &[u8]) -> ModuleParser {
-        let decoder = Decoder::new(bytes);
+impl PartialEq for Module<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        let Module {
+            version,
+            custom_sections,
+            type_section,
+            import_section,
+            func_section,
+            table_section,
+            memory_section,
+            tag_section,
+            global_section,
+            export_section,
+            start_section,
+            element_section,
+            code_section,
+            code_section_offset: _,
+            data_section,
+            data_count_section,
+        } = self;
+        *version == other.version
+            && *custom_sections == other.custom_sections
+            && *type_section == other.type_section
+            && *import_section == other.import_section
+            && *func_section == other.func_section
+            && *table_section == other.table_section
+            && *memory_section == other.memory_section
+            && *tag_section == other.tag_section
+            && *global_section == other.global_section
+            && *export_section == other.export_section
+            && *start_section == other.start_section
+            && *element_section == other.element_section
+            && *code_section == other.code_section
+            && *data_section == other.data_section
+            && *data_count_section == other.data_count_section
+    }
+}
+
+impl<'a> Module<'a> {
+    pub fn from_bytes(bytes: &'a [u8]) -> ModuleParser<'a> {
+        Self::from_bytes_with(bytes, Features::default())
+    }
+
+    /// Like [Module::from_bytes], but rejects proposals disabled in `features`.
+    ///
+    /// Unless every proposal is enabled, function bodies are decoded once while parsing to be checked.
+    pub fn from_bytes_with(bytes: &'a [u8], features: Features) -> ModuleParser<'a> {
+        let decoder = Decoder::new(bytes).with_features(features);
+
+        ModuleParser {
+            decoder,
+            strict: false,
+            features,
+            limits: Limits::default(),
+        }
+    }
+
+    /// Encode the module back to a binary, see [Encoder::write_module].
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        encoder.write_module(self);
+        encoder.finish()
+    }
+
+    /// Copy all borrowed names and bytes, detaching the module from the input binary.
+    pub fn into_owned(self) -> Module<'static> {
+        Module {
+            version: self.version,
+            custom_sections: self
+                .custom_sections
+                .into_iter()
+                .map(CustomSection::into_owned)
+                .collect(),
+            type_section: self.type_section,
+            import_section: self.import_section.into_owned(),
+            func_section: self.func_section,
+            table_section: self.table_section,
+            memory_section: self.memory_section,
+            tag_section: self.tag_section,
+            global_section: self.global_section,
+            export_section: self.export_section.into_owned(),
+            start_section: self.start_section,
+            element_section: self.element_section,
+            code_section: self.code_section.into_owned(),
+            code_section_offset: self.code_section_offset,
+            data_section: self.data_section.into_owned(),
+            data_count_section: self.data_count_section,
+        }
+    }
+
+    /// Find the first custom section called `name`.
+    pub fn custom_section(&self, name: &str) -> Option<&CustomSection<'a>> {
+        self.custom_sections
+            .iter()
+            .find(|section| section.name == name)
+    }
+
+    /// Decode the `name` custom section, returns empty [Names] if the module has none.
+    pub fn names(&self) -> Result<Names, Error> {
+        self.custom_section(Names::SECTION_NAME).map_or_else(
+            || Ok(Names::default()),
+            |section| Names::parse(&section.data),
+        )
+    }
+
+    /// Decode the `producers` custom section, returns empty [Producers] if the module has none.
+    pub fn producers(&self) -> Result<Producers, Error> {
+        self.custom_section(Producers::SECTION_NAME).map_or_else(
+            || Ok(Producers::default()),
+            |section| Producers::parse(&section.data),
+        )
+    }
+
+    /// Decode the `target_features` custom section,
+    /// returns empty [TargetFeatures] if the module has none.
+    pub fn target_features(&self) -> Result<TargetFeatures, Error> {
+        self.custom_section(TargetFeatures::SECTION_NAME)
+            .map_or_else(
+                || Ok(TargetFeatures::default()),
+                |section| TargetFeatures::parse(&section.data),
+            )
+    }
+
+    /// The URL of the source map in the `sourceMappingURL` custom section, if any.
+    pub fn source_mapping_url(&self) -> Result<Option<SmolStr>, Error> {
+        let Some(section) = self.custom_section("sourceMappingURL") else {
+            return Ok(None);
+        };
+        let mut decoder = Decoder::new(&section.data);
+        let url = decoder.read_str()?;
+        if !decoder.remaining_slice().is_empty() {
+            return Err(Error::InvalidCustomSection(
+                "sourceMappingURL",
+                "trailing bytes",
+            ));
+        }
+        Ok(Some(url))
+    }
+
+    /// Decode the `linking` custom section, only relocatable object files have one.
+    pub fn linking(&self) -> Result<Option<Linking>, Error> {
+        self.custom_section(Linking::SECTION_NAME)
+            .map(|section| Linking::parse(&section.data))
+            .transpose()
+    }
+
+    /// Decode every `reloc.*` custom section, along with the name that follows the prefix.
+    pub fn relocations(&self) -> Result<Vec<(&str, Relocations)>, Error> {
+        self.custom_sections
+            .iter()
+            .filter_map(|section| {
+                let target = section.name.strip_prefix(Relocations::SECTION_PREFIX)?;
+                Some(Relocations::parse(&section.data).map(|relocations| (target, relocations)))
+            })
+            .collect()
+    }
+
+    /// Decode the `dylink.0` custom section, only shared libraries have one.
+    pub fn dylink(&self) -> Result<Option<Dylink>, Error> {
+        self.custom_section(Dylink::SECTION_NAME)
+            .map(|section| Dylink::parse(&section.data))
+            .transpose()
+    }
+
+    /// Load the DWARF custom sections for source lookups, see [DebugInfo](crate::dwarf::DebugInfo).
+    #[cfg_attr(docsrs, doc(cfg(feature = "dwarf")))]
+    #[cfg(feature = "dwarf")]
+    pub fn debug_info(&self) -> Result<crate::dwarf::DebugInfo<'_>, Error> {
+        crate::dwarf::DebugInfo::new(self)
+    }
+
+    /// Check the module against the spec, see [validate](crate::validate::validate).
+    pub fn validate(&self) -> Result<(), Error> {
+        crate::validate::validate(self)
+    }
+
+    /// Check the module against the spec and reject duplicate imports,
+    /// see [validate_strict](crate::validate::validate_strict).
+    pub fn validate_strict(&self) -> Result<(), Error> {
+        crate::validate::validate_strict(self)
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::Module;
+    use crate::instruction::{Instruction, F32, F64};
+    use crate::section::{DataKind, ExportKind};
+
+    #[test]
+    fn test_serde_round_trip() {
+        let data = wat::parse_str(
+            r#"
+            (module
+                (import "env" "f" (func $f (param f32)))
+                (memory (export "mem") 1)
+                (table 1 funcref)
+                (global (export "g") f32 (f32.const nan:0x200001))
+                (global f64 (f64.const -nan:0xfffffffffffff))
+                (global v128 (v128.const i64x2 -1 1))
+                (func (export "h") (param f32) (local i32)
+                    (call $f (f32.const nan:0x1)))
+                (elem (i32.const 0) func $f)
+                (data (i32.const 8) "\ff\00"))
+            "#,
+        )
+        .unwrap();
+        let module = Module::from_bytes(&data).parse().unwrap();
+        let json = serde_json::to_value(&module).unwrap();
+
+        assert_eq!(
+            json["export_section"][0]["kind"],
+            serde_json::json!({ "kind": "memory", "index": 0 })
+        );
+        assert_eq!(
+            json["import_section"][0]["kind"],
+            serde_json::json!({ "kind": "func", "type": 0 })
+        );
+        assert_eq!(json["element_section"][0]["kind"]["mode"], "active");
+        assert_eq!(json["data_section"][0]["kind"]["memory"], 0);
+        assert_eq!(
+            json["global_section"][0]["expr"][0],
+            serde_json::json!({ "F32Const": "0x7fa00001" })
+        );
+        assert_eq!(
+            json["global_section"][2]["expr"][0],
+            serde_json::json!({ "V128Const": "0x0000000000000001ffffffffffffffff" })
+        );
+        assert_eq!(
+            json["code_section"][0]["instrs"],
+            serde_json::json!([{ "F32Const": "0x7f800001" }, { "Call": 0 }, "End"])
+        );
+        assert!(json["code_section"][0].get("body").is_none());
+
+        let text = serde_json::to_string(&module).unwrap();
+        let decoded: Module<'static> = serde_json::from_str(&text).unwrap();
+        assert_eq!(serde_json::to_string(&decoded).unwrap(), text);
+        let Instruction::F32Const(F32(nan)) = decoded.global_section.0[0].expr.0[0] else {
+            panic!("expected f32.const");
+        };
+        assert_eq!(nan.to_bits(), 0x7fa0_0001);
+        let Instruction::F64Const(F64(nan)) = decoded.global_section.0[1].expr.0[0] else {
+            panic!("expected f64.const");
+        };
+        assert_eq!(nan.to_bits(), 0xffff_ffff_ffff_ffff);
+        assert!(matches!(
+            decoded.export_section.0[0].kind,
+            ExportKind::Mem(0)
+        ));
+        assert!(matches!(
+            decoded.data_section.0[0].kind,
+            DataKind::Active { memory: 0, .. }
+        ));
+        assert_eq!(decoded.data_section.0[0].init, &b"\xff\0"[..]);
+        assert_eq!(decoded.code_section, module.code_section);
 
-        ModuleParser { decoder }
+        assert!(serde_json::from_str::<F32>(r#""0x1""#).is_ok());
+        assert!(serde_json::from_str::<F32>("1.5").is_err());
+        assert!(serde_json::from_str::<F32>(r#""7fc00000""#).is_err());
     }
 }
@@ -0,0 +1,188 @@
+//! Decoding of the `name` custom section.
+//!
+//! https://webassembly.github.io/spec/core/appendix/custom.html#name-section
+//! and the [extended name section](https://github.com/WebAssembly/extended-name-section) proposal.
+
+use std::collections::BTreeMap;
+
+use smol_str::SmolStr;
+
+use crate::decode::Decoder;
+use crate::error::Error;
+
+pub type NameMap = BTreeMap<u32, SmolStr>;
+
+pub type IndirectNameMap = BTreeMap<u32, NameMap>;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Names {
+    pub module: Option<SmolStr>,
+    pub functions: NameMap,
+    /// Function index -> local index -> name.
+    pub locals: IndirectNameMap,
+    /// Function index -> label index -> name.
+    pub labels: IndirectNameMap,
+    pub types: NameMap,
+    pub tables: NameMap,
+    pub memories: NameMap,
+    pub globals: NameMap,
+    pub elems: NameMap,
+    pub datas: NameMap,
+    /// Type index -> field index -> name.
+    pub fields: IndirectNameMap,
+    pub tags: NameMap,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum NameSubsectionId {
+    Module = 0x00,
+    Function = 0x01,
+    Local = 0x02,
+    Label = 0x03,
+    Type = 0x04,
+    Table = 0x05,
+    Memory = 0x06,
+    Global = 0x07,
+    Elem = 0x08,
+    Data = 0x09,
+    Field = 0x0a,
+    Tag = 0x0b,
+}
+
+impl TryFrom<u8> for NameSubsectionId {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x00 => Ok(NameSubsectionId::Module),
+            0x01 => Ok(NameSubsectionId::Function),
+            0x02 => Ok(NameSubsectionId::Local),
+            0x03 => Ok(NameSubsectionId::Label),
+            0x04 => Ok(NameSubsectionId::Type),
+            0x05 => Ok(NameSubsectionId::Table),
+            0x06 => Ok(NameSubsectionId::Memory),
+            0x07 => Ok(NameSubsectionId::Global),
+            0x08 => Ok(NameSubsectionId::Elem),
+            0x09 => Ok(NameSubsectionId::Data),
+            0x0a => Ok(NameSubsectionId::Field),
+            0x0b => Ok(NameSubsectionId::Tag),
+            _ => Err(Error::InvalidSectionId(value)),
+        }
+    }
+}
+
+impl Names {
+    pub const SECTION_NAME: &'static str = "name";
+
+    /// Decode the payload of a `name` custom section (without the section name).
+    ///
+    /// Unknown subsections are skipped, as the spec requires.
+    pub fn parse(data: &[u8]) -> Result<Names, Error> {
+        let mut names = Names::default();
+        let mut decoder = Decoder::new(data);
+
+        while !decoder.remaining_slice().is_empty() {
+            let id = decoder.read_u8()?;
+            let size = decoder.read_var_u32()?;
+            if size as usize > decoder.remaining_slice().len() {
+                return Err(Error::SubsectionOutOfBounds(
+                    id,
+                    size,
+                    decoder.remaining_slice().len() as u32,
+                ));
+            }
+            let mut sub = decoder.slice_with(size as u64);
+
+            let Ok(id) = NameSubsectionId::try_from(id) else {
+                continue;
+            };
+            match id {
+                NameSubsectionId::Module => names.module = Some(sub.read_str()?),
+                NameSubsectionId::Function => names.functions = read_name_map(&mut sub)?,
+                NameSubsectionId::Local => names.locals = read_indirect_name_map(&mut sub)?,
+                NameSubsectionId::Label => names.labels = read_indirect_name_map(&mut sub)?,
+                NameSubsectionId::Type => names.types = read_name_map(&mut sub)?,
+                NameSubsectionId::Table => names.tables = read_name_map(&mut sub)?,
+                NameSubsectionId::Memory => names.memories = read_name_map(&mut sub)?,
+                NameSubsectionId::Global => names.globals = read_name_map(&mut sub)?,
+                NameSubsectionId::Elem => names.elems = read_name_map(&mut sub)?,
+                NameSubsectionId::Data => names.datas = read_name_map(&mut sub)?,
+                NameSubsectionId::Field => names.fields = read_indirect_name_map(&mut sub)?,
+                NameSubsectionId::Tag => names.tags = read_name_map(&mut sub)?,
+            }
+
+            if !sub.remaining_slice().is_empty() {
+                return Err(Error::SubsectionOutOfBounds(
+                    id as u8,
+                    size,
+                    size - sub.remaining_slice().len() as u32,
+                ));
+            }
+        }
+
+        Ok(names)
+    }
+
+    pub fn function(&self, idx: u32) -> Option<&str> {
+        self.functions.get(&idx).map(SmolStr::as_str)
+    }
+
+    pub fn local(&self, func: u32, idx: u32) -> Option<&str> {
+        self.locals.get(&func)?.get(&idx).map(SmolStr::as_str)
+    }
+
+    pub fn label(&self, func: u32, idx: u32) -> Option<&str> {
+        self.labels.get(&func)?.get(&idx).map(SmolStr::as_str)
+    }
+
+    pub fn field(&self, ty: u32, idx: u32) -> Option<&str> {
+        self.fields.get(&ty)?.get(&idx).map(SmolStr::as_str)
+    }
+}
+
+fn read_name_map(decoder: &mut Decoder) -> Result<NameMap, Error> {
+    let len = decoder.read_var_u32()?;
+    (0..len)
+        .map(|_| Ok((decoder.read_var_u32()?, decoder.read_str()?)))
+        .collect()
+}
+
+fn read_indirect_name_map(decoder: &mut Decoder) -> Result<IndirectNameMap, Error> {
+    let len = decoder.read_var_u32()?;
+    (0..len)
+        .map(|_| Ok((decoder.read_var_u32()?, read_name_map(decoder)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_parse_names() {
+        let data = wat::parse_str(
+            r#"
+            (module $demo
+                (type $sig (func (param i32)))
+                (global $counter (mut i32) (i32.const 0))
+                (memory $heap 1)
+                (func $imported_like (type $sig) (param $value i32)
+                    (local $tmp i64)
+                    (block $exit
+                        br $exit))
+                (func $second))
+            "#,
+        )
+        .unwrap();
+        let module = crate::module::Module::from_bytes(&data).parse().unwrap();
+        let names = module.names().unwrap();
+
+        assert_eq!(names.module.as_deref(), Some("demo"));
+        assert_eq!(names.function(0), Some("imported_like"));
+        assert_eq!(names.function(1), Some("second"));
+        assert_eq!(names.local(0, 0), Some("value"));
+        assert_eq!(names.local(0, 1), Some("tmp"));
+        assert_eq!(names.label(0, 0), Some("exit"));
+        assert_eq!(names.types.get(&0).map(|s| s.as_str()), Some("sig"));
+        assert_eq!(names.globals.get(&0).map(|s| s.as_str()), Some("counter"));
+        assert_eq!(names.memories.get(&0).map(|s| s.as_str()), Some("heap"));
+    }
+}
@@ -1,25 +1,59 @@
 use crate::decode::Decoder;
-use crate::error::Error;
+use crate::error::{Error, Location};
+use crate::features::Features;
 use crate::instruction::{ConstExpr, Instruction};
+use crate::limits::{self, Limits};
 use crate::module::Module;
 use crate::section::{
     Code, CodeSection, CustomSection, Data, DataCountSection, DataKind, DataSection, Element,
     ElementKind, ElementSection, Export, ExportKind, ExportSection, FunctionSection, GlobalSection,
     Import, ImportKind, ImportSection, Locals, MemorySection, SectionId, StartSection,
-    TableSection, TypeSection, TypeSectionTy,
+    TableSection, TagSection, TypeSection, TypeSectionTy,
 };
-use crate::types::{FuncType, Global, RefType};
+use crate::types::{Global, HeapType, RecGroup, RefType, Table};
+
+/// `version 0x01 0x00` followed by `layer 0x00 0x00`.
+pub const MODULE_VERSION: u32 = 0x0000_0001;
+
+/// `version 0x0d 0x00` followed by `layer 0x01 0x00`.
+pub const COMPONENT_VERSION: u32 = 0x0001_000d;
 
 pub struct ModuleParser<'a> {
     pub decoder: Decoder<'a>,
+    pub(crate) strict: bool,
+    pub(crate) features: Features,
+    pub(crate) limits: Limits,
 }
 
 impl<'a> ModuleParser<'a> {
-    fn parse_header(&mut self) -> Result<u32, Error> {
+    /// Reject sections out of the spec order or repeated, function and code sections
+    /// of different lengths, and a data count that does not match the data section.
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Replace the default [Limits], e.g. to parse untrusted binaries with tighter bounds.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    fn check_module_size(&self) -> Result<(), Error> {
+        let size = self.decoder.remaining_slice().len();
+        limits::check("module size", size, self.limits.module_size)
+            .map_err(|err| err.at(self.decoder.offset(), Location::Module))
+    }
+
+    pub(crate) fn parse_header(&mut self) -> Result<u32, Error> {
         let magic = self.decoder.read_n::<4>()?;
         let version = self.decoder.read_u32()?;
         if &magic != b"\0asm" {
             Err(Error::InvalidWasm)
+        } else if version == COMPONENT_VERSION {
+            Err(Error::UnexpectedComponent)
+        } else if version != MODULE_VERSION {
+            Err(Error::InvalidVersion(version))
         } else {
             Ok(version)
         }
@@ -31,33 +65,50 @@ impl<'a> ModuleParser<'a> {
         Ok((SectionId::try_from(id)?, size))
     }
 
-    /// Parse modules and code sections in parallel, 
-    /// this should speed up (2-5x) the parsing of large wasm files(large code sections), 
-    /// based on the benchmark results, it is recommended to only use this method on wasm files >100kb
+    /// Parse module sections in parallel, function bodies are left to be decoded by [Code::expr].
     ///
-    /// ```
-    /// test big_1900kb_wasm               ... bench:   2,928,385 ns/iter (+/- 664,241) = 677 MB/s
-    /// test big_1900kb_wasm_parallel      ... bench:     640,027 ns/iter (+/- 73,691) = 3099 MB/s
-    /// test medium_180kb_wasm             ... bench:     903,667 ns/iter (+/- 15,438) = 207 MB/s
-    /// test medium_180kb_wasm_parallel    ... bench:     380,636 ns/iter (+/- 17,981) = 493 MB/s
-    /// test small_30kb_wasm               ... bench:     558,580 ns/iter (+/- 77,136) = 53 MB/s
-    /// test small_30kb_wasm_parallel      ... bench:     561,020 ns/iter (+/- 9,287) = 53 MB/s
-    /// test very_big_5270kb_wasm          ... bench:  99,321,540 ns/iter (+/- 7,110,395) = 54 MB/s
-    /// test very_big_5270kb_wasm_parallel ... bench:  37,908,100 ns/iter (+/- 8,038,568) = 142 MB/s
-    /// ```
+    /// Each section is parsed on its own thread and function bodies are not decoded here,
+    /// so the speedup depends on the binary, measure before preferring it over [ModuleParser::parse].
     #[cfg_attr(docsrs, doc(cfg(feature = "parallel")))]
     #[cfg(feature = "parallel")]
-    pub fn par_parse(mut self) -> Result<Module, Error> {
-        let mut module = Module::default();
+    pub fn par_parse(mut self) -> Result<Module<'a>, Error> {
+        self.check_module_size()?;
+        let mut module = Module {
+            version: self
+                .parse_header()
+                .map_err(|err| err.at(self.decoder.offset(), Location::Module))?,
+            ..Default::default()
+        };
         let mut sections = Vec::new();
-
-        module.version = self.parse_header()?;
+        let mut count = 0;
+        let mut order = SectionOrder::default();
 
         while !self.decoder.is_empty() {
-            let (id, size) = self.read_section_header()?;
+            let (id, size) = self
+                .read_section_header()
+                .map_err(|err| err.at(self.decoder.offset(), Location::Module))?;
+            if self.strict {
+                order
+                    .check(id)
+                    .map_err(|err| err.at(self.decoder.offset(), Location::Section(id)))?;
+            }
+            count += 1;
+            limits::check("section count", count, self.limits.sections)
+                .map_err(|err| err.at(self.decoder.offset(), Location::Section(id)))?;
+            let remaining = self.decoder.remaining_slice().len();
+            if size as usize > remaining {
+                return Err(Error::SectionOutOfBounds(id as u8, size, remaining as u32)
+                    .at(self.decoder.offset(), Location::Section(id)));
+            }
             let start = self.decoder.reader.position();
             let end = self.decoder.reader.position() + size as u64;
-            sections.push((id, start as usize, end as usize));
+            // Custom sections are cheap to read, reading them here keeps their order.
+            if id == SectionId::Custom {
+                self.parse_section(&mut module, id, end)
+                    .map_err(|err| err.at(self.decoder.offset(), Location::Section(id)))?;
+            } else {
+                sections.push((id, start as usize, end as usize));
+            }
             self.decoder.reader.set_position(end);
         }
 
@@ -66,185 +117,271 @@ impl<'a> ModuleParser<'a> {
         use std::sync::Arc;
         let packed = Arc::new(spin::Mutex::new(module));
         sections.into_par_iter().try_for_each(|(id, start, end)| {
-            let data = &self.decoder.reader.get_ref()[start..end];
-            let mut decoder = Decoder::new(&data);
-            match id {
-                SectionId::Custom => {
-                    let name = decoder.read_str()?;
-                    let data =
-                        decoder.read_bytes(data.len() - decoder.reader.position() as usize)?;
-                    packed
-                        .lock()
-                        .custom_sections
-                        .push(CustomSection { name, data });
-                }
-                SectionId::Type => {
-                    packed.lock().type_section = Self::parse_type_section(&mut decoder)?;
-                }
-                SectionId::Import => {
-                    packed.lock().import_section = Self::parse_import_section(&mut decoder)?;
-                }
-                SectionId::Function => {
-                    packed.lock().func_section = Self::parse_function_section(&mut decoder)?;
-                }
-                SectionId::Table => {
-                    packed.lock().table_section = Self::parse_table_section(&mut decoder)?;
-                }
-                SectionId::Memory => {
-                    packed.lock().memory_section = Self::parse_memory_section(&mut decoder)?;
-                }
-                SectionId::Global => {
-                    packed.lock().global_section = Self::parse_global_section(&mut decoder)?;
-                }
-                SectionId::Export => {
-                    packed.lock().export_section = Self::parse_export_section(&mut decoder)?;
-                }
-                SectionId::Start => {
-                    packed.lock().start_section = Self::parse_start_section(&mut decoder)?;
-                }
-                SectionId::Element => {
-                    packed.lock().element_section = Self::parse_element_section(&mut decoder)?;
-                }
-                SectionId::Code => {
-                    packed.lock().code_section = Self::par_parse_code_section(&mut decoder)?;
-                }
-                SectionId::Data => {
-                    packed.lock().data_section = Self::parse_data_section(&mut decoder)?;
-                }
-                SectionId::DataCount => {
-                    packed.lock().data_count_section =
-                        Self::parse_data_count_section(&mut decoder)?;
+            let bytes: &'a [u8] = self.decoder.reader.get_ref();
+            let mut decoder =
+                Decoder::new_with_offset(&bytes[start..end], start).with_features(self.features);
+            let mut parse = || {
+                self.limits.check_section(id, &decoder)?;
+                match id {
+                    SectionId::Custom => unreachable!("custom sections are read in order"),
+                    SectionId::Type => {
+                        packed.lock().type_section = Self::parse_type_section(&mut decoder)?;
+                    }
+                    SectionId::Import => {
+                        packed.lock().import_section = Self::parse_import_section(&mut decoder)?;
+                    }
+                    SectionId::Function => {
+                        packed.lock().func_section = Self::parse_function_section(&mut decoder)?;
+                    }
+                    SectionId::Table => {
+                        packed.lock().table_section = Self::parse_table_section(&mut decoder)?;
+                    }
+                    SectionId::Memory => {
+                        packed.lock().memory_section = Self::parse_memory_section(&mut decoder)?;
+                    }
+                    SectionId::Global => {
+                        packed.lock().global_section = Self::parse_global_section(&mut decoder)?;
+                    }
+                    SectionId::Export => {
+                        packed.lock().export_section = Self::parse_export_section(&mut decoder)?;
+                    }
+                    SectionId::Start => {
+                        packed.lock().start_section = Self::parse_start_section(&mut decoder)?;
+                    }
+                    SectionId::Element => {
+                        packed.lock().element_section = Self::parse_element_section(&mut decoder)?;
+                    }
+                    SectionId::Code => {
+                        let code_section = Self::parse_code_section(&mut decoder)?;
+                        let mut module = packed.lock();
+                        module.code_section = code_section;
+                        module.code_section_offset = start;
+                    }
+                    SectionId::Data => {
+                        packed.lock().data_section = Self::parse_data_section(&mut decoder)?;
+                    }
+                    SectionId::DataCount => {
+                        packed.lock().data_count_section =
+                            Self::parse_data_count_section(&mut decoder)?;
+                    }
+                    SectionId::Tag => {
+                        packed.lock().tag_section = Self::parse_tag_section(&mut decoder)?;
+                    }
                 }
-            }
-            Result::<(), Error>::Ok(())
+                Result::<(), Error>::Ok(())
+            };
+            parse().map_err(|err| err.at(decoder.offset(), Location::Section(id)))
         })?;
 
-        Ok(spin::Mutex::into_inner(Arc::into_inner(packed).unwrap()))
+        let mut module = spin::Mutex::into_inner(Arc::into_inner(packed).unwrap());
+        if self.strict {
+            check_counts(&module).map_err(|err| err.at(self.decoder.offset(), Location::Module))?;
+        }
+        index_functions(&mut module);
+        self.limits
+            .check_module(&module)
+            .map_err(|err| err.at(self.decoder.offset(), Location::Module))?;
+        if self.features != Features::all() {
+            self.features
+                .check_module(&module)
+                .map_err(|err| err.at(self.decoder.offset(), Location::Module))?;
+        }
+        Ok(module)
     }
 
-    pub fn parse(mut self) -> Result<Module, Error> {
-        let mut module = Module::default();
-
-        module.version = self.parse_header()?;
+    pub fn parse(mut self) -> Result<Module<'a>, Error> {
+        self.check_module_size()?;
+        let mut module = Module {
+            version: self
+                .parse_header()
+                .map_err(|err| err.at(self.decoder.offset(), Location::Module))?,
+            ..Default::default()
+        };
+        let mut order = SectionOrder::default();
+        let mut sections = 0u32;
 
         while !self.decoder.is_empty() {
-            let (id, size) = self.read_section_header()?;
+            let (id, size) = self
+                .read_section_header()
+                .map_err(|err| err.at(self.decoder.offset(), Location::Module))?;
+            if self.strict {
+                order
+                    .check(id)
+                    .map_err(|err| err.at(self.decoder.offset(), Location::Section(id)))?;
+            }
+            sections += 1;
+            limits::check("section count", sections, self.limits.sections)
+                .map_err(|err| err.at(self.decoder.offset(), Location::Section(id)))?;
+            self.limits
+                .check_section(id, &self.decoder)
+                .map_err(|err| err.at(self.decoder.offset(), Location::Section(id)))?;
             let end = self.decoder.reader.position() + size as u64;
 
-            match id {
-                SectionId::Custom => {
-                    let name = self.decoder.read_str()?;
-                    let data = self
-                        .decoder
-                        .read_bytes((end - self.decoder.reader.position()) as usize)?;
-                    module.custom_sections.push(CustomSection { name, data });
-                }
-                SectionId::Type => {
-                    module.type_section = Self::parse_type_section(&mut self.decoder)?;
-                }
-                SectionId::Import => {
-                    module.import_section = Self::parse_import_section(&mut self.decoder)?;
-                }
-                SectionId::Function => {
-                    module.func_section = Self::parse_function_section(&mut self.decoder)?;
-                }
-                SectionId::Table => {
-                    module.table_section = Self::parse_table_section(&mut self.decoder)?;
-                }
-                SectionId::Memory => {
-                    module.memory_section = Self::parse_memory_section(&mut self.decoder)?;
-                }
-                SectionId::Global => {
-                    module.global_section = Self::parse_global_section(&mut self.decoder)?;
-                }
-                SectionId::Export => {
-                    module.export_section = Self::parse_export_section(&mut self.decoder)?;
-                }
-                SectionId::Start => {
-                    module.start_section = Self::parse_start_section(&mut self.decoder)?;
-                }
-                SectionId::Element => {
-                    module.element_section = Self::parse_element_section(&mut self.decoder)?;
-                }
-                SectionId::Code => {
-                    module.code_section = Self::parse_code_section(&mut self.decoder)?;
-                }
-                SectionId::Data => {
-                    module.data_section = Self::parse_data_section(&mut self.decoder)?;
-                }
-                SectionId::DataCount => {
-                    module.data_count_section = Self::parse_data_count_section(&mut self.decoder)?;
-                }
-            }
+            self.parse_section(&mut module, id, end)
+                .map_err(|err| err.at(self.decoder.offset(), Location::Section(id)))?;
 
             if self.decoder.reader.position() != end {
                 return Err(Error::SectionOutOfBounds(
                     id as u8,
                     size,
                     (self.decoder.reader.position() - (end - size as u64)) as u32,
-                ));
+                )
+                .at(self.decoder.offset(), Location::Section(id)));
             }
         }
 
+        if self.strict {
+            check_counts(&module).map_err(|err| err.at(self.decoder.offset(), Location::Module))?;
+        }
+        index_functions(&mut module);
+        self.limits
+            .check_module(&module)
+            .map_err(|err| err.at(self.decoder.offset(), Location::Module))?;
+        if self.features != Features::all() {
+            self.features
+                .check_module(&module)
+                .map_err(|err| err.at(self.decoder.offset(), Location::Module))?;
+        }
         Ok(module)
     }
 
-    fn parse_type_section(decoder: &mut Decoder) -> Result<TypeSection, Error> {
+    fn parse_section(
+        &mut self,
+        module: &mut Module<'a>,
+        id: SectionId,
+        end: u64,
+    ) -> Result<(), Error> {
+        match id {
+            SectionId::Custom => {
+                let name = self.decoder.read_name()?.into();
+                let len = end.saturating_sub(self.decoder.reader.position());
+                let data = self.decoder.read_slice(len as usize)?.into();
+                module.custom_sections.push(CustomSection { name, data });
+            }
+            SectionId::Type => {
+                module.type_section = Self::parse_type_section(&mut self.decoder)?;
+            }
+            SectionId::Import => {
+                module.import_section = Self::parse_import_section(&mut self.decoder)?;
+            }
+            SectionId::Function => {
+                module.func_section = Self::parse_function_section(&mut self.decoder)?;
+            }
+            SectionId::Table => {
+                module.table_section = Self::parse_table_section(&mut self.decoder)?;
+            }
+            SectionId::Memory => {
+                module.memory_section = Self::parse_memory_section(&mut self.decoder)?;
+            }
+            SectionId::Global => {
+                module.global_section = Self::parse_global_section(&mut self.decoder)?;
+            }
+            SectionId::Export => {
+                module.export_section = Self::parse_export_section(&mut self.decoder)?;
+            }
+            SectionId::Start => {
+                module.start_section = Self::parse_start_section(&mut self.decoder)?;
+            }
+            SectionId::Element => {
+                module.element_section = Self::parse_element_section(&mut self.decoder)?;
+            }
+            SectionId::Code => {
+                module.code_section_offset = self.decoder.offset();
+                module.code_section = Self::parse_code_section(&mut self.decoder)?;
+            }
+            SectionId::Data => {
+                module.data_section = Self::parse_data_section(&mut self.decoder)?;
+            }
+            SectionId::DataCount => {
+                module.data_count_section = Self::parse_data_count_section(&mut self.decoder)?;
+            }
+            SectionId::Tag => {
+                module.tag_section = Self::parse_tag_section(&mut self.decoder)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn parse_type_section(decoder: &mut Decoder) -> Result<TypeSection, Error> {
         decoder
             .read_vec(|decoder| {
-                let type_tag = decoder.read_u8()?;
-                match type_tag {
-                    0x60 => {
-                        let params = decoder.read_resulttype()?;
-                        let results = decoder.read_resulttype()?;
-
-                        let func_type = FuncType { params, results };
-                        Ok(TypeSectionTy::Func(func_type))
-                    }
-                    _ => Err(Error::InvalidTypeTag(type_tag)),
+                if decoder.peek()? == 0x4e {
+                    decoder.read_u8()?;
+                    decoder
+                        .read_vec(Decoder::read_subtype)
+                        .map(RecGroup)
+                        .map(TypeSectionTy::Rec)
+                } else {
+                    decoder.read_subtype().map(TypeSectionTy::Sub)
                 }
             })
             .map(TypeSection)
     }
 
-    fn parse_import_section(decoder: &mut Decoder) -> Result<ImportSection, Error> {
-        decoder
-            .read_vec(|decoder| {
-                let module_name = decoder.read_str()?;
-                let field_name = decoder.read_str()?;
-                let kind = decoder.read_u8()?;
-                let kind = match kind {
-                    0x00 => ImportKind::Func(decoder.read_var_u32()?),
-                    0x01 => ImportKind::Table(decoder.read_tabletype()?),
-                    0x02 => ImportKind::Memory(decoder.read_memtype()?),
-                    0x03 => ImportKind::Global(decoder.read_globaltype()?),
-                    _ => return Err(Error::InvalidImportKind(kind)),
-                };
+    pub(crate) fn parse_import_section(
+        decoder: &mut Decoder<'a>,
+    ) -> Result<ImportSection<'a>, Error> {
+        decoder.read_vec(Self::parse_import).map(ImportSection)
+    }
 
-                Ok(Import {
-                    module_name,
-                    field_name,
-                    kind,
-                })
-            })
-            .map(ImportSection)
+    pub(crate) fn parse_import(decoder: &mut Decoder<'a>) -> Result<Import<'a>, Error> {
+        let module_name = decoder.read_name()?.into();
+        let field_name = decoder.read_name()?.into();
+        let kind = Self::parse_import_kind(decoder)?;
+
+        Ok(Import {
+            module_name,
+            field_name,
+            kind,
+        })
+    }
+
+    pub(crate) fn parse_import_kind(decoder: &mut Decoder) -> Result<ImportKind, Error> {
+        let kind = decoder.read_u8()?;
+        Ok(match kind {
+            0x00 => ImportKind::Func(decoder.read_var_u32()?),
+            0x01 => ImportKind::Table(decoder.read_tabletype()?),
+            0x02 => ImportKind::Memory(decoder.read_memtype()?),
+            0x03 => ImportKind::Global(decoder.read_globaltype()?),
+            0x04 => ImportKind::Tag(decoder.read_tagtype()?),
+            _ => return Err(Error::InvalidImportKind(kind)),
+        })
     }
 
-    fn parse_function_section(decoder: &mut Decoder) -> Result<FunctionSection, Error> {
+    pub(crate) fn parse_function_section(decoder: &mut Decoder) -> Result<FunctionSection, Error> {
         decoder
             .read_svec(Decoder::read_var_u32)
             .map(FunctionSection)
     }
 
-    fn parse_table_section(decoder: &mut Decoder) -> Result<TableSection, Error> {
-        decoder.read_svec(Decoder::read_tabletype).map(TableSection)
+    pub(crate) fn parse_table_section(decoder: &mut Decoder) -> Result<TableSection, Error> {
+        decoder
+            .read_svec(|decoder| {
+                // `0x40 0x00 tabletype expr` declares a table with an initializer.
+                if decoder.peek()? != 0x40 {
+                    let ty = decoder.read_tabletype()?;
+                    return Ok(Table { ty, init: None });
+                }
+                decoder.read_u8()?;
+                let reserved = decoder.read_u8()?;
+                if reserved != 0x00 {
+                    return Err(Error::InvalidLeadingByte(reserved, "table"));
+                }
+                let ty = decoder.read_tabletype()?;
+                let init = decoder.read_const_expr()?;
+                Ok(Table {
+                    ty,
+                    init: Some(init),
+                })
+            })
+            .map(TableSection)
     }
 
-    fn parse_memory_section(decoder: &mut Decoder) -> Result<MemorySection, Error> {
+    pub(crate) fn parse_memory_section(decoder: &mut Decoder) -> Result<MemorySection, Error> {
         decoder.read_svec(Decoder::read_memtype).map(MemorySection)
     }
 
-    fn parse_global_section(decoder: &mut Decoder) -> Result<GlobalSection, Error> {
+    pub(crate) fn parse_global_section(decoder: &mut Decoder) -> Result<GlobalSection, Error> {
         decoder
             .read_vec(|decoder| {
                 let ty = decoder.read_globaltype()?;
@@ -254,10 +391,12 @@ impl<'a> ModuleParser<'a> {
             .map(GlobalSection)
     }
 
-    fn parse_export_section(decoder: &mut Decoder) -> Result<ExportSection, Error> {
+    pub(crate) fn parse_export_section(
+        decoder: &mut Decoder<'a>,
+    ) -> Result<ExportSection<'a>, Error> {
         decoder
             .read_vec(|decoder| {
-                let name = decoder.read_str()?;
+                let name = decoder.read_name()?.into();
                 let kind = decoder.read_u8()?;
                 let idx = decoder.read_var_u32()?;
                 let kind = match kind {
@@ -265,6 +404,7 @@ impl<'a> ModuleParser<'a> {
                     0x01 => ExportKind::Table(idx),
                     0x02 => ExportKind::Mem(idx),
                     0x03 => ExportKind::Global(idx),
+                    0x04 => ExportKind::Tag(idx),
                     _ => return Err(Error::InvalidExportKind(kind)),
                 };
                 Ok(Export { name, kind })
@@ -272,14 +412,15 @@ impl<'a> ModuleParser<'a> {
             .map(ExportSection)
     }
 
-    fn parse_start_section(decoder: &mut Decoder) -> Result<StartSection, Error> {
-        decoder.read_var_u32().map(StartSection)
+    pub(crate) fn parse_start_section(decoder: &mut Decoder) -> Result<StartSection, Error> {
+        decoder.read_var_u32().map(|idx| StartSection(Some(idx)))
     }
 
-    fn parse_element_section(decoder: &mut Decoder) -> Result<ElementSection, Error> {
+    pub(crate) fn parse_element_section(decoder: &mut Decoder) -> Result<ElementSection, Error> {
         decoder
             .read_vec(|decoder| {
                 let flags = decoder.read_var_u32()?;
+                decoder.features.element_flags(flags)?;
                 let kind = match flags {
                     0 | 2 | 4 | 6 => {
                         let tabidx = if matches!(flags, 2 | 6) {
@@ -296,15 +437,11 @@ impl<'a> ModuleParser<'a> {
                     3 | 7 => ElementKind::Declared,
                     _ => return Err(Error::InvalidFlags(flags, "element segment")),
                 };
+                // Function indices are never null, expressions may be.
                 let ty = match flags {
-                    0 | 4 => RefType::FuncRef,
-                    1..=3 => {
-                        let elemkind = decoder.read_u8()?;
-                        match elemkind {
-                            0x00 => RefType::FuncRef,
-                            _ => return Err(Error::InvalidElmType(elemkind)),
-                        }
-                    }
+                    0 => RefType::non_null(HeapType::Func),
+                    4 => RefType::FUNCREF,
+                    1..=3 => decoder.read_elemkind()?,
                     5..=7 => decoder.read_reftype()?,
                     _ => return Err(Error::InvalidFlags(flags, "element segment")),
                 };
@@ -323,55 +460,30 @@ impl<'a> ModuleParser<'a> {
             .map(ElementSection)
     }
 
-    fn parse_code_section(decoder: &mut Decoder) -> Result<CodeSection, Error> {
-        decoder
-            .read_vec(|decoder| {
-                let size = decoder.read_var_u32()?;
-                let locals = decoder.read_svec(|decoder| {
-                    let n = decoder.read_var_u32()?;
-                    let valtype = decoder.read_valtype()?;
-                    Ok(Locals { n, ty: valtype })
-                })?;
-                let expr = decoder.read_expr()?;
-                Ok(Code { size, locals, expr })
-            })
-            .map(CodeSection)
+    pub(crate) fn parse_code_section(decoder: &mut Decoder<'a>) -> Result<CodeSection<'a>, Error> {
+        decoder.read_vec(Self::parse_code).map(CodeSection)
     }
 
-    #[cfg_attr(docsrs, doc(cfg(feature = "parallel")))]
-    #[cfg(feature = "parallel")]
-    fn par_parse_code_section(decoder: &mut Decoder) -> Result<CodeSection, Error> {
-        let codes = decoder.read_vec(|decoder| {
-            let size = decoder.read_var_u32()?;
-            let start = decoder.reader.position();
-            let end = decoder.reader.position() + size as u64;
-            decoder.reader.set_position(end);
-            Ok((start as usize, end as usize))
+    /// Function indices are assigned once the import section is known, see [index_functions].
+    pub(crate) fn parse_code(decoder: &mut Decoder<'a>) -> Result<Code<'a>, Error> {
+        let size = decoder.read_var_u32()?;
+        let offset = decoder.offset();
+        let mut body = Decoder::new_with_offset(decoder.read_slice(size as usize)?, offset);
+        let locals = body.read_svec(|decoder| {
+            let n = decoder.read_var_u32()?;
+            let valtype = decoder.read_valtype()?;
+            Ok(Locals { n, ty: valtype })
         })?;
-
-        use rayon::iter::IntoParallelIterator;
-        use rayon::iter::ParallelIterator;
-        codes
-            .into_par_iter()
-            .map(|(start, end)| {
-                let mut decoder = Decoder::new(&decoder.reader.get_ref()[start..end]);
-                let locals = decoder.read_svec(|decoder| {
-                    let n = decoder.read_var_u32()?;
-                    let valtype = decoder.read_valtype()?;
-                    Ok(Locals { n, ty: valtype })
-                })?;
-                let expr = decoder.read_expr()?;
-                Ok(Code {
-                    size: (end - start) as u32,
-                    locals,
-                    expr,
-                })
-            })
-            .collect::<Result<Vec<_>, _>>()
-            .map(CodeSection)
+        Ok(Code {
+            index: 0,
+            size,
+            locals,
+            body: body.remaining_slice().into(),
+            offset: body.offset(),
+        })
     }
 
-    fn parse_data_section(decoder: &mut Decoder) -> Result<DataSection, Error> {
+    pub(crate) fn parse_data_section(decoder: &mut Decoder<'a>) -> Result<DataSection<'a>, Error> {
         decoder
             .read_vec(|decoder| {
                 let flags = decoder.read_var_u32()?;
@@ -392,23 +504,93 @@ impl<'a> ModuleParser<'a> {
                     _ => return Err(Error::InvalidFlags(flags, "dats segment")),
                 };
                 let len = decoder.read_var_u32()? as usize;
-                let init = decoder.read_bytes(len)?;
+                let init = decoder.read_slice(len)?.into();
 
                 Ok(Data { init, kind })
             })
             .map(DataSection)
     }
 
-    fn parse_data_count_section(decoder: &mut Decoder) -> Result<DataCountSection, Error> {
+    pub(crate) fn parse_data_count_section(
+        decoder: &mut Decoder,
+    ) -> Result<DataCountSection, Error> {
         decoder.read_var_u32().map(Some).map(DataCountSection)
     }
+
+    pub(crate) fn parse_tag_section(decoder: &mut Decoder) -> Result<TagSection, Error> {
+        decoder.read_svec(Decoder::read_tagtype).map(TagSection)
+    }
+}
+
+/// Number of function imports, the first defined function comes right after them.
+pub(crate) fn imported_functions(imports: &ImportSection) -> u32 {
+    imports
+        .0
+        .iter()
+        .filter(|import| matches!(import.kind, ImportKind::Func(_)))
+        .count() as u32
+}
+
+fn index_functions(module: &mut Module) {
+    let imported = imported_functions(&module.import_section);
+    for (code, index) in module.code_section.0.iter_mut().zip(imported..) {
+        code.index = index;
+    }
+}
+
+/// Tracks the last non-custom section to enforce the spec order, which also rules out duplicates.
+#[derive(Default)]
+pub(crate) struct SectionOrder(Option<SectionId>);
+
+impl SectionOrder {
+    pub(crate) fn check(&mut self, id: SectionId) -> Result<(), Error> {
+        if id == SectionId::Custom {
+            return Ok(());
+        }
+        if let Some(last) = self.0 {
+            if id.order() == last.order() {
+                return Err(Error::DuplicateSection(id));
+            }
+            if id.order() < last.order() {
+                return Err(Error::SectionOutOfOrder(id, last));
+            }
+        }
+        self.0 = Some(id);
+        Ok(())
+    }
+}
+
+/// Sections that declare the same items must agree on how many there are.
+pub(crate) fn check_counts(module: &Module) -> Result<(), Error> {
+    let funcs = module.func_section.0.len() as u32;
+    let codes = module.code_section.0.len() as u32;
+    if funcs != codes {
+        return Err(Error::FunctionCodeMismatch(funcs, codes));
+    }
+    if let Some(count) = module.data_count_section.0 {
+        let data = module.data_section.0.len() as u32;
+        if count != data {
+            return Err(Error::DataCountMismatch(count, data));
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
+    use std::borrow::Cow;
+
+    use crate::error::{Error, Location};
+    use crate::instruction::{ConstExpr, Instruction};
+    use crate::limits::Limits;
+    use crate::section::{Code, ExportKind, ImportKind, SectionId, TypeSectionTy};
+    use crate::types::{
+        BlockType, CompositeType, HeapType, IndexType, RefType, StorageType, TagAttribute, ValType,
+    };
+
     #[test]
     fn test_parse() {
-        let data = include_bytes!("../tests/spidermonkey.wasm");
+        let data = include_bytes!("../tests/pulldown-cmark.wasm");
         let parser = crate::module::Module::from_bytes(data);
         match parser.parse() {
             Ok(_) => {}
@@ -419,11 +601,414 @@ mod tests {
     #[cfg(feature = "parallel")]
     #[test]
     fn test_par_parse() {
-        let data = include_bytes!("../tests/spidermonkey.wasm");
+        let data = include_bytes!("../tests/pulldown-cmark.wasm");
         let parser = crate::module::Module::from_bytes(data);
-        match parser.par_parse() {
-            Ok(_) => {}
+        let module = match parser.par_parse() {
+            Ok(module) => module,
             Err(err) => panic!("{}", err),
         };
+        // Custom sections included, in their order in the binary.
+        assert!(module == crate::module::Module::from_bytes(data).parse().unwrap());
+    }
+
+    #[test]
+    fn test_parse_code_lazily() {
+        let data = wat::parse_str(
+            r#"
+            (module
+                (func (param i32) (result i32)
+                    (local i64 i64)
+                    (block (result i32)
+                        (local.get 0))))
+            "#,
+        )
+        .unwrap();
+        let module = crate::module::Module::from_bytes(&data).parse().unwrap();
+
+        let code = &module.code_section.0[0];
+        assert_eq!(code.locals[0].n, 2);
+        let instrs = code.instructions().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(
+            instrs,
+            [
+                Instruction::Block(BlockType::Type(ValType::I32)),
+                Instruction::LocalGet(0),
+                Instruction::End,
+                Instruction::End,
+            ]
+        );
+        assert_eq!(code.expr().unwrap().instrs, instrs);
+
+        let mut body = code.body.to_vec();
+        body.push(0x01);
+        let code = Code {
+            body: body.into(),
+            ..code.clone()
+        };
+        assert!(matches!(
+            code.expr().unwrap_err().kind(),
+            Error::InvalidExprEnd(0x01)
+        ));
+
+        let data = include_bytes!("../tests/pulldown-cmark.wasm");
+        let module = crate::module::Module::from_bytes(data).parse().unwrap();
+        for code in &module.code_section.0 {
+            code.expr().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_error_offsets() {
+        let mut data = wat::parse_str(
+            r#"
+            (module
+                (import "env" "f" (func))
+                (func (nop) (nop)))
+            "#,
+        )
+        .unwrap();
+        let module = crate::module::Module::from_bytes(&data).parse().unwrap();
+        let code = &module.code_section.0[0];
+        assert_eq!(code.index, 1);
+        let offset = code.offset;
+        let expr = code.expr_with_offsets().unwrap();
+        assert_eq!(expr.offsets, Some(vec![offset, offset + 1, offset + 2]));
+        drop(module);
+
+        data[offset + 1] = 0xff;
+        let module = crate::module::Module::from_bytes(&data)
+            .limits(Limits::unlimited())
+            .parse()
+            .unwrap();
+        let err = module.code_section.0[0].expr().unwrap_err();
+        assert_eq!(err.offset(), Some(offset + 1));
+        assert_eq!(err.location(), Some(Location::Function(1)));
+        // Checking nesting depth decodes the body while parsing, with the same error.
+        let err = crate::module::Module::from_bytes(&data)
+            .parse()
+            .unwrap_err();
+        assert_eq!(err.offset(), Some(offset + 1));
+        assert_eq!(err.location(), Some(Location::Function(1)));
+
+        // A type section holding `(func (param <0x7a>))`.
+        let data = b"\0asm\x01\0\0\0\x01\x05\x01\x60\x01\x7a\x00";
+        let err = crate::module::Module::from_bytes(data).parse().unwrap_err();
+        assert_eq!(err.location(), Some(Location::Section(SectionId::Type)));
+        assert!((10..15).contains(&err.offset().unwrap()));
+        assert!(matches!(err.kind(), Error::InvalidType(..)));
+    }
+
+    #[test]
+    fn test_parse_borrows_input() {
+        let data = wat::parse_str(
+            r#"
+            (module
+                (import "env" "memory" (memory 1))
+                (export "mem" (memory 0))
+                (data (i32.const 0) "hello")
+                (@custom "meta" "world"))
+            "#,
+        )
+        .unwrap();
+        let module = crate::module::Module::from_bytes(&data).parse().unwrap();
+
+        let range = data.as_ptr_range();
+        let init = &module.data_section.0[0].init;
+        assert!(matches!(init, Cow::Borrowed(_)));
+        assert!(range.contains(&init.as_ptr()));
+        let custom = module.custom_section("meta").unwrap();
+        assert!(range.contains(&custom.data.as_ptr()));
+        assert!(matches!(
+            module.import_section.0[0].field_name,
+            Cow::Borrowed("memory")
+        ));
+
+        let module = module.into_owned();
+        drop(data);
+        assert_eq!(module.import_section.0[0].module_name, "env");
+        assert_eq!(module.export_section.0[0].name, "mem");
+        assert_eq!(&*module.data_section.0[0].init, b"hello");
+        assert_eq!(&*module.custom_section("meta").unwrap().data, b"world");
+    }
+
+    #[test]
+    fn test_parse_tag_section() {
+        let data = wat::parse_str(
+            r#"
+            (module
+                (type $t (func (param i32)))
+                (import "env" "imported" (tag $imported (type $t)))
+                (tag $local (type $t))
+                (export "local" (tag $local))
+                (func (param i32)
+                    local.get 0
+                    throw $local))
+            "#,
+        )
+        .unwrap();
+        let module = crate::module::Module::from_bytes(&data).parse().unwrap();
+
+        assert_eq!(module.tag_section.0.len(), 1);
+        assert_eq!(module.tag_section.0[0].attribute, TagAttribute::Exception);
+        assert_eq!(module.tag_section.0[0].type_idx, 0);
+        assert!(matches!(
+            module.import_section.0[0].kind,
+            ImportKind::Tag(ty) if ty.type_idx == 0
+        ));
+        assert!(matches!(
+            module.export_section.0[0].kind,
+            ExportKind::Tag(1)
+        ));
+    }
+
+    #[test]
+    fn test_parse_gc_types() {
+        let data = wat::parse_str(
+            r#"
+            (module
+                (type $f (func (param i32) (result i64)))
+                (rec
+                    (type $base (sub (struct (field (mut i8)) (field i16))))
+                    (type $derived (sub final $base (struct (field (mut i8)) (field i16) (field f64))))
+                )
+                (type $bytes (array (mut i8))))
+            "#,
+        )
+        .unwrap();
+        let module = crate::module::Module::from_bytes(&data).parse().unwrap();
+        let types = &module.type_section;
+
+        assert_eq!(types.0.len(), 3);
+        assert!(matches!(&types.0[1], TypeSectionTy::Rec(group) if group.0.len() == 2));
+        assert_eq!(types.sub_types().count(), 4);
+        assert!(types.func_type(0).is_some());
+
+        let base = types.get(1).unwrap();
+        assert!(!base.is_final);
+        assert!(base.supertypes.is_empty());
+
+        let derived = types.get(2).unwrap();
+        assert!(derived.is_final);
+        assert_eq!(derived.supertypes.as_slice(), &[1]);
+        match &derived.composite {
+            CompositeType::Struct(ty) => {
+                assert_eq!(ty.fields.len(), 3);
+                assert_eq!(ty.fields[0].ty, StorageType::I8);
+                assert!(ty.fields[0].mutable);
+                assert_eq!(ty.fields[1].ty, StorageType::I16);
+                assert!(!ty.fields[1].mutable);
+                assert_eq!(ty.fields[2].ty, StorageType::Val(ValType::F64));
+            }
+            ty => panic!("expect struct type, found {ty:?}"),
+        }
+
+        match &types.get(3).unwrap().composite {
+            CompositeType::Array(ty) => assert_eq!(ty.0.ty, StorageType::I8),
+            ty => panic!("expect array type, found {ty:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_typed_references() {
+        let data = wat::parse_str(
+            r#"
+            (module
+                (type $s (struct (field (ref null $s))))
+                (table 1 (ref null $s))
+                (global anyref (ref.null any))
+                (global i31ref (ref.null i31))
+                (func (param (ref $s)) (result (ref null $s))
+                    (local eqref)
+                    (block (result (ref $s))
+                        local.get 0)
+                    ref.null $s
+                    i32.const 0
+                    select (result (ref null $s))))
+            "#,
+        )
+        .unwrap();
+        let module = crate::module::Module::from_bytes(&data).parse().unwrap();
+        let s = RefType::nullable(HeapType::Concrete(0));
+
+        match &module.type_section.get(0).unwrap().composite {
+            CompositeType::Struct(ty) => {
+                assert_eq!(ty.fields[0].ty, StorageType::Val(ValType::Ref(s)))
+            }
+            ty => panic!("expect struct type, found {ty:?}"),
+        }
+        let func = module.type_section.func_type(1).unwrap();
+        assert_eq!(
+            func.params.0[0],
+            ValType::Ref(RefType::non_null(HeapType::Concrete(0)))
+        );
+        assert_eq!(func.results.0[0], ValType::Ref(s));
+
+        assert_eq!(module.table_section.0[0].ty.element, s);
+        assert_eq!(
+            module.global_section.0[0].ty.ty,
+            ValType::Ref(RefType::ANYREF)
+        );
+        assert_eq!(
+            module.global_section.0[1].ty.ty,
+            ValType::Ref(RefType::I31REF)
+        );
+
+        let code = &module.code_section.0[0];
+        assert_eq!(code.locals[0].ty, ValType::Ref(RefType::EQREF));
+        let expr = code.expr().unwrap();
+        assert_eq!(
+            expr.instrs[0],
+            Instruction::Block(BlockType::Type(ValType::Ref(RefType::non_null(
+                HeapType::Concrete(0)
+            ))))
+        );
+        assert!(expr
+            .instrs
+            .contains(&Instruction::TypedSelect(ValType::Ref(s))));
+    }
+
+    #[test]
+    fn test_parse_memory_types() {
+        let data = wat::parse_str(
+            r#"
+            (module
+                (import "env" "memory" (memory 1 16 shared))
+                (memory i64 1)
+                (func (param i64) (result i32)
+                    (i32.load 1 offset=0x1_0000_0000 (local.get 0))))
+            "#,
+        )
+        .unwrap();
+        let module = crate::module::Module::from_bytes(&data).parse().unwrap();
+
+        let ImportKind::Memory(shared) = module.import_section.0[0].kind else {
+            panic!("expect a memory import");
+        };
+        assert!(shared.shared);
+        assert_eq!(shared.index_type, IndexType::I32);
+        assert_eq!(shared.limit.max, Some(16));
+        let memory = module.memory_section.0[0];
+        assert!(!memory.shared);
+        assert_eq!(memory.index_type, IndexType::I64);
+        assert_eq!(memory.limit.max, None);
+
+        let expr = module.code_section.0[0].expr().unwrap();
+        match &expr.instrs[1] {
+            Instruction::I32Load(arg) => assert_eq!(arg.offset, 0x1_0000_0000),
+            instr => panic!("expect i32.load, found {instr:?}"),
+        }
+
+        // A memory section with a limits flag byte of 0x08.
+        let data = b"\0asm\x01\0\0\0\x05\x03\x01\x08\x01";
+        let err = crate::module::Module::from_bytes(data).parse().unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            Error::InvalidFlags(0x08, "memory limits")
+        ));
+    }
+
+    #[test]
+    fn test_parse_tables() {
+        let data = wat::parse_str(
+            r#"
+            (module
+                (import "env" "t" (table $imported i64 1 funcref))
+                (table $init 2 10 (ref func) (ref.func $f))
+                (table $t64 i64 1 externref)
+                (elem (table $init) (i32.const 0) func $f)
+                (func $f (param i64) (result externref)
+                    (call_indirect $imported (local.get 0))
+                    (table.get $t64 (local.get 0))))
+            "#,
+        )
+        .unwrap();
+        let module = crate::module::Module::from_bytes(&data).parse().unwrap();
+        module.validate().unwrap();
+
+        let ImportKind::Table(imported) = module.import_section.0[0].kind else {
+            panic!("expect a table import");
+        };
+        assert_eq!(imported.index_type, IndexType::I64);
+        let init = &module.table_section.0[0];
+        assert_eq!(init.ty.element, RefType::non_null(HeapType::Func));
+        assert_eq!(init.ty.limit.max, Some(10));
+        assert_eq!(
+            init.init,
+            Some(ConstExpr(vec![Instruction::RefFunc(0), Instruction::End]))
+        );
+        let t64 = &module.table_section.0[1];
+        assert_eq!(t64.ty.index_type, IndexType::I64);
+        assert_eq!(t64.init, None);
+        assert_eq!(
+            module.element_section.0[0].ty,
+            RefType::non_null(HeapType::Func)
+        );
+
+        let data = wat::parse_str("(module (table 1 (ref func)) (func))").unwrap();
+        let err = crate::module::Module::from_bytes(&data)
+            .parse()
+            .unwrap()
+            .validate()
+            .unwrap_err();
+        assert!(matches!(err, Error::MissingTableInit(0)));
+    }
+
+    #[test]
+    fn test_strict_sections() {
+        let parse = |sections: &[u8]| {
+            let data = [b"\0asm\x01\0\0\0".as_slice(), sections].concat();
+            crate::module::Module::from_bytes(&data)
+                .strict()
+                .parse()
+                .map(|_| ())
+        };
+
+        let data = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (data "a")
+                (func (memory.init 0 (i32.const 0) (i32.const 0) (i32.const 1))))
+            "#,
+        )
+        .unwrap();
+        let module = crate::module::Module::from_bytes(&data)
+            .strict()
+            .parse()
+            .unwrap();
+        assert_eq!(module.data_count_section.0, Some(1));
+
+        // Two empty type sections.
+        let err = parse(b"\x01\x01\x00\x01\x01\x00").unwrap_err();
+        assert_eq!(err.location(), Some(Location::Section(SectionId::Type)));
+        assert!(matches!(
+            err.kind(),
+            Error::DuplicateSection(SectionId::Type)
+        ));
+
+        // An empty export section, then an empty memory section.
+        let err = parse(b"\x07\x01\x00\x05\x01\x00").unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            Error::SectionOutOfOrder(SectionId::Memory, SectionId::Export)
+        ));
+
+        // Custom sections may appear anywhere.
+        parse(b"\x07\x01\x00\x00\x02\x01a\x0b\x01\x00").unwrap();
+
+        // `(func)` declared without a code section.
+        let sections = b"\x01\x04\x01\x60\x00\x00\x03\x02\x01\x00";
+        assert!(matches!(
+            parse(sections).unwrap_err().kind(),
+            Error::FunctionCodeMismatch(1, 0)
+        ));
+        let data = [b"\0asm\x01\0\0\0".as_slice(), sections].concat();
+        crate::module::Module::from_bytes(&data).parse().unwrap();
+
+        // A data count of 1 without a data section.
+        assert!(matches!(
+            parse(b"\x0c\x01\x01").unwrap_err().kind(),
+            Error::DataCountMismatch(1, 0)
+        ));
     }
 }
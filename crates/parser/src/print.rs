@@ -0,0 +1,1088 @@
+//! Printing modules in the WebAssembly text format, see [Printer].
+//!
+//! https://webassembly.github.io/spec/core/text/index.html
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use crate::error::Error;
+use crate::instruction::{Catch, ConstExpr, Instruction, MemArg, I128};
+use crate::module::Module;
+use crate::names::{NameMap, Names};
+use crate::section::{Code, DataKind, Element, ElementKind, ExportKind, ImportKind, TypeSectionTy};
+use crate::types::{
+    BlockType, CompositeType, FieldType, FuncType, HeapType, IndexType, Limit, RefType,
+    StorageType, SubType, ValType,
+};
+
+/// Renders a [Module] as WAT, e.g. `Printer::new().folded().print(&module)`.
+///
+/// Items are referred to by the identifiers of the `name` section, unnamed ones get identifiers
+/// such as `$f3` for function 3 or `$l0` for local 0. Custom sections are not printed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Printer {
+    folded: bool,
+}
+
+impl Printer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Nest the body of blocks in parentheses, e.g. `(if (then ...) (else ...))`
+    /// instead of `if ... else ... end`. Other instructions stay flat, operands are not folded,
+    /// nor is the `try` of the legacy exception handling proposal.
+    pub fn folded(mut self) -> Self {
+        self.folded = true;
+        self
+    }
+
+    pub fn print(&self, module: &Module) -> Result<String, Error> {
+        let names = module.names()?;
+        let mut writer = Writer {
+            folded: self.folded,
+            ids: Ids::new(&names),
+            module,
+            out: String::new(),
+            indent: 0,
+        };
+        writer.module(names.module.as_deref())?;
+        Ok(writer.out)
+    }
+}
+
+/// The flat text of a single instruction, e.g. `i32.load offset=8` or `call $f3`.
+///
+/// Indices are printed as the identifiers a [Printer] gives unnamed items, labels as depths.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&Ids::new(&Names::default()).instr(self, None, &[]))
+    }
+}
+
+/// Levels of indentation deeper blocks are printed at,
+/// so that the text of deeply nested bodies stays linear in their size.
+const MAX_INDENT: usize = 32;
+
+struct Writer<'a, 'b> {
+    folded: bool,
+    ids: Ids,
+    module: &'a Module<'b>,
+    out: String,
+    indent: usize,
+}
+
+impl Writer<'_, '_> {
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.indent.min(MAX_INDENT) {
+            self.out.push_str("  ");
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn open(&mut self, text: &str) {
+        self.line(&format!("({text}"));
+        self.indent += 1;
+    }
+
+    /// Close the innermost parenthesis at the end of the last line.
+    fn close(&mut self) {
+        self.indent = self.indent.saturating_sub(1);
+        if self.out.ends_with('\n') {
+            self.out.pop();
+        }
+        self.out.push_str(")\n");
+    }
+
+    fn module(&mut self, name: Option<&str>) -> Result<(), Error> {
+        let module = self.module;
+        match name {
+            Some(name) => self.open(&format!("module {}", ident(name))),
+            None => self.open("module"),
+        }
+
+        let mut type_idx = 0;
+        for ty in &module.type_section.0 {
+            match ty {
+                TypeSectionTy::Sub(sub) => {
+                    self.line(&self.ids.type_def(type_idx, sub));
+                    type_idx += 1;
+                }
+                TypeSectionTy::Rec(group) => {
+                    self.open("rec");
+                    for sub in &group.0 {
+                        self.line(&self.ids.type_def(type_idx, sub));
+                        type_idx += 1;
+                    }
+                    self.close();
+                }
+            }
+        }
+
+        let (mut funcs, mut tables, mut memories, mut globals, mut tags) = (0, 0, 0, 0, 0);
+        for import in &module.import_section.0 {
+            let desc = match &import.kind {
+                ImportKind::Func(ty) => {
+                    funcs += 1;
+                    let id = self.ids.funcs.id(funcs - 1);
+                    format!("func {id} {}", self.signature(*ty, None))
+                }
+                ImportKind::Table(ty) => {
+                    tables += 1;
+                    let id = self.ids.tables.id(tables - 1);
+                    let limit = limit(ty.index_type, &ty.limit);
+                    format!("table {id} {limit} {}", self.ids.ref_type(&ty.element))
+                }
+                ImportKind::Memory(ty) => {
+                    memories += 1;
+                    let id = self.ids.memories.id(memories - 1);
+                    let shared = if ty.shared { " shared" } else { "" };
+                    format!("memory {id} {}{shared}", limit(ty.index_type, &ty.limit))
+                }
+                ImportKind::Global(ty) => {
+                    globals += 1;
+                    let id = self.ids.globals.id(globals - 1);
+                    format!("global {id} {}", self.ids.global_type(&ty.ty, ty.mutable))
+                }
+                ImportKind::Tag(ty) => {
+                    tags += 1;
+                    let id = self.ids.tags.id(tags - 1);
+                    format!("tag {id} {}", self.signature(ty.type_idx, None))
+                }
+            };
+            let module_name = string(import.module_name.as_bytes());
+            let field_name = string(import.field_name.as_bytes());
+            self.line(&format!("(import {module_name} {field_name} ({desc}))"));
+        }
+
+        for (i, &ty) in module.func_section.0.iter().enumerate() {
+            self.func(funcs + i as u32, ty, module.code_section.0.get(i))?;
+        }
+        for (i, table) in module.table_section.0.iter().enumerate() {
+            let id = self.ids.tables.id(tables + i as u32);
+            let limit = limit(table.ty.index_type, &table.ty.limit);
+            let element = self.ids.ref_type(&table.ty.element);
+            let init = match &table.init {
+                Some(init) => format!(" {}", self.ids.const_expr(init)),
+                None => String::new(),
+            };
+            self.line(&format!("(table {id} {limit} {element}{init})"));
+        }
+        for (i, memory) in module.memory_section.0.iter().enumerate() {
+            let id = self.ids.memories.id(memories + i as u32);
+            let shared = if memory.shared { " shared" } else { "" };
+            let limit = limit(memory.index_type, &memory.limit);
+            self.line(&format!("(memory {id} {limit}{shared})"));
+        }
+        for (i, tag) in module.tag_section.0.iter().enumerate() {
+            let id = self.ids.tags.id(tags + i as u32);
+            self.line(&format!(
+                "(tag {id} {})",
+                self.signature(tag.type_idx, None)
+            ));
+        }
+        for (i, global) in module.global_section.0.iter().enumerate() {
+            let id = self.ids.globals.id(globals + i as u32);
+            let ty = self.ids.global_type(&global.ty.ty, global.ty.mutable);
+            let init = self.ids.const_expr(&global.expr);
+            self.line(&format!("(global {id} {ty} {init})"));
+        }
+        for export in &module.export_section.0 {
+            let desc = match export.kind {
+                ExportKind::Func(idx) => format!("func {}", self.ids.funcs.id(idx)),
+                ExportKind::Table(idx) => format!("table {}", self.ids.tables.id(idx)),
+                ExportKind::Mem(idx) => format!("memory {}", self.ids.memories.id(idx)),
+                ExportKind::Global(idx) => format!("global {}", self.ids.globals.id(idx)),
+                ExportKind::Tag(idx) => format!("tag {}", self.ids.tags.id(idx)),
+            };
+            let name = string(export.name.as_bytes());
+            self.line(&format!("(export {name} ({desc}))"));
+        }
+        if let Some(start) = module.start_section.0 {
+            self.line(&format!("(start {})", self.ids.funcs.id(start)));
+        }
+        for (i, element) in module.element_section.0.iter().enumerate() {
+            self.line(&self.element(i as u32, element));
+        }
+        for (i, data) in module.data_section.0.iter().enumerate() {
+            let mut text = format!("(data {}", self.ids.datas.id(i as u32));
+            if let DataKind::Active { memory, offset } = &data.kind {
+                if *memory != 0 {
+                    text += &format!(" (memory {})", self.ids.memories.id(*memory));
+                }
+                text += &format!(" (offset {})", self.ids.const_expr(offset));
+            }
+            text += &format!(" {})", string(&data.init));
+            self.line(&text);
+        }
+
+        self.close();
+        Ok(())
+    }
+
+    /// `(type $t) (param ...) (result ...)`, parameters are given identifiers in a definition.
+    fn signature(&self, type_idx: u32, func: Option<u32>) -> String {
+        let mut text = format!("(type {})", self.ids.types.id(type_idx));
+        let Some(ty) = self.module.type_section.func_type(type_idx) else {
+            return text;
+        };
+        match func {
+            Some(func) => {
+                for (i, param) in ty.params.0.iter().enumerate() {
+                    let id = self.ids.local(Some(func), i as u32);
+                    text += &format!(" (param {id} {})", self.ids.val_type(param));
+                }
+                text += &self.ids.results(ty);
+            }
+            None => text += &self.ids.params_results(ty),
+        }
+        text
+    }
+
+    fn func(&mut self, idx: u32, type_idx: u32, code: Option<&Code>) -> Result<(), Error> {
+        let expr = code.map(Code::expr).transpose()?;
+        let signature = self.signature(type_idx, Some(idx));
+        self.open(&format!("func {} {signature}", self.ids.funcs.id(idx)));
+
+        let params = self
+            .module
+            .type_section
+            .func_type(type_idx)
+            .map_or(0, |ty| ty.params.0.len() as u32);
+        let mut local_idx = params;
+        for locals in code.map_or(&[][..], |code| &code.locals) {
+            for _ in 0..locals.n {
+                let id = self.ids.local(Some(idx), local_idx);
+                self.line(&format!("(local {id} {})", self.ids.val_type(&locals.ty)));
+                local_idx += 1;
+            }
+        }
+        if let Some(expr) = expr {
+            self.body(idx, &expr.instrs);
+        }
+
+        self.close();
+        Ok(())
+    }
+
+    fn body(&mut self, func: u32, instrs: &[Instruction]) {
+        let instrs = instrs.strip_suffix(&[Instruction::End]).unwrap_or(instrs);
+        let mut labels: Vec<Option<String>> = Vec::new();
+        let mut frames: Vec<Frame> = Vec::new();
+        let mut label_idx = 0;
+        for instr in instrs {
+            let mut text = self.ids.instr(instr, Some(func), &labels);
+            match instr {
+                Instruction::Block(_)
+                | Instruction::Loop(_)
+                | Instruction::If(_)
+                | Instruction::Try(_)
+                | Instruction::TryTable(_) => {
+                    let label = self.ids.label(func, label_idx);
+                    label_idx += 1;
+                    if let Some(label) = &label {
+                        text.insert_str(instr.mnemonic().len(), &format!(" {label}"));
+                    }
+                    labels.push(label);
+                    let frame = match instr {
+                        _ if !self.folded => Frame::Flat,
+                        Instruction::Try(_) => Frame::Flat,
+                        Instruction::If(_) => Frame::Clause,
+                        _ => Frame::Folded,
+                    };
+                    match frame {
+                        Frame::Flat => {
+                            self.line(&text);
+                            self.indent += 1;
+                        }
+                        Frame::Folded => self.open(&text),
+                        Frame::Clause => {
+                            self.open(&text);
+                            self.open("then");
+                        }
+                    }
+                    frames.push(frame);
+                }
+                Instruction::Else if frames.last() == Some(&Frame::Clause) => {
+                    self.close();
+                    self.open("else");
+                }
+                Instruction::Else | Instruction::Catch(_) | Instruction::CatchAll => {
+                    self.indent = self.indent.saturating_sub(1);
+                    self.line(&text);
+                    self.indent += 1;
+                }
+                Instruction::End | Instruction::Delegate(_) => {
+                    labels.pop();
+                    if let Instruction::Delegate(label) = instr {
+                        text = format!("delegate {}", relative_label(&labels, *label));
+                    }
+                    match frames.pop().unwrap_or(Frame::Flat) {
+                        Frame::Flat => {
+                            self.indent = self.indent.saturating_sub(1);
+                            self.line(&text);
+                        }
+                        Frame::Folded => self.close(),
+                        Frame::Clause => {
+                            self.close();
+                            self.close();
+                        }
+                    }
+                }
+                _ => self.line(&text),
+            }
+        }
+    }
+
+    fn element(&self, idx: u32, element: &Element) -> String {
+        let mut text = format!("(elem {}", self.ids.elems.id(idx));
+        match &element.kind {
+            ElementKind::Passive => {}
+            ElementKind::Active { table, offset } => {
+                if let Some(table) = table {
+                    text += &format!(" (table {})", self.ids.tables.id(*table));
+                }
+                text += &format!(" (offset {})", self.ids.const_expr(offset));
+            }
+            ElementKind::Declared => text += " declare",
+        }
+        // Function indices are printed as such, so that the segment is encoded the same way.
+        let funcs = (element.ty == RefType::non_null(HeapType::Func))
+            .then(|| {
+                element
+                    .init
+                    .iter()
+                    .map(|expr| match expr.0.as_slice() {
+                        [Instruction::RefFunc(idx), Instruction::End] => Some(*idx),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()
+            })
+            .flatten();
+        match funcs {
+            Some(funcs) => {
+                text += " func";
+                for func in funcs {
+                    text += &format!(" {}", self.ids.funcs.id(func));
+                }
+            }
+            None => {
+                text += &format!(" {}", self.ids.ref_type(&element.ty));
+                for expr in &element.init {
+                    text += &format!(" (item {})", self.ids.const_expr(expr));
+                }
+            }
+        }
+        text + ")"
+    }
+}
+
+/// How an open block is printed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Frame {
+    /// `block ... end`
+    Flat,
+    /// `(block ...)`
+    Folded,
+    /// `(if (then ...) (else ...))`, with `then` or `else` open.
+    Clause,
+}
+
+/// Identifiers of one index space.
+struct Space {
+    prefix: &'static str,
+    ids: BTreeMap<u32, String>,
+    /// The identifiers of `ids`, that generated ones must not repeat.
+    taken: HashSet<String>,
+}
+
+impl Space {
+    fn new(prefix: &'static str, names: Option<&NameMap>) -> Self {
+        let ids = names.map(idents).unwrap_or_default();
+        Space {
+            prefix,
+            taken: ids.values().cloned().collect(),
+            ids,
+        }
+    }
+
+    /// The identifier from the `name` section, or `$<prefix><idx>`,
+    /// suffixed with `_1`, `_2`, ... while a name already has it.
+    fn id(&self, idx: u32) -> String {
+        if let Some(id) = self.ids.get(&idx) {
+            return id.clone();
+        }
+        let id = format!("${}{idx}", self.prefix);
+        if !self.taken.contains(&id) {
+            return id;
+        }
+        (1..)
+            .map(|n| format!("{id}_{n}"))
+            .find(|id| !self.taken.contains(id))
+            .unwrap()
+    }
+}
+
+/// The identifiers of every index space, from the `name` section.
+struct Ids {
+    types: Space,
+    funcs: Space,
+    tables: Space,
+    memories: Space,
+    globals: Space,
+    elems: Space,
+    datas: Space,
+    tags: Space,
+    locals: BTreeMap<u32, Space>,
+    labels: BTreeMap<u32, BTreeMap<u32, String>>,
+    fields: BTreeMap<u32, BTreeMap<u32, String>>,
+}
+
+impl Ids {
+    fn new(names: &Names) -> Self {
+        Ids {
+            types: Space::new("t", Some(&names.types)),
+            funcs: Space::new("f", Some(&names.functions)),
+            tables: Space::new("table", Some(&names.tables)),
+            memories: Space::new("mem", Some(&names.memories)),
+            globals: Space::new("g", Some(&names.globals)),
+            elems: Space::new("elem", Some(&names.elems)),
+            datas: Space::new("data", Some(&names.datas)),
+            tags: Space::new("tag", Some(&names.tags)),
+            locals: (names.locals.iter())
+                .map(|(&func, names)| (func, Space::new("l", Some(names))))
+                .collect(),
+            labels: (names.labels.iter())
+                .map(|(&func, names)| (func, idents(names)))
+                .collect(),
+            fields: (names.fields.iter())
+                .map(|(&ty, names)| (ty, idents(names)))
+                .collect(),
+        }
+    }
+
+    fn local(&self, func: Option<u32>, idx: u32) -> String {
+        match func.and_then(|func| self.locals.get(&func)) {
+            Some(locals) => locals.id(idx),
+            None => Space::new("l", None).id(idx),
+        }
+    }
+
+    /// Labels without a name are referred to by depth.
+    fn label(&self, func: u32, idx: u32) -> Option<String> {
+        self.labels.get(&func)?.get(&idx).cloned()
+    }
+
+    fn field(&self, ty: u32, idx: u32) -> Option<String> {
+        self.fields.get(&ty)?.get(&idx).cloned()
+    }
+
+    fn type_def(&self, idx: u32, sub: &SubType) -> String {
+        let composite = match &sub.composite {
+            CompositeType::Func(ty) => format!("(func{})", self.params_results(ty)),
+            CompositeType::Struct(ty) => {
+                let mut text = "(struct".to_string();
+                for (i, field) in ty.fields.iter().enumerate() {
+                    text += " (field ";
+                    if let Some(id) = self.field(idx, i as u32) {
+                        text += &format!("{id} ");
+                    }
+                    text += &self.field_type(field);
+                    text += ")";
+                }
+                text + ")"
+            }
+            CompositeType::Array(ty) => format!("(array {})", self.field_type(&ty.0)),
+        };
+        let id = self.types.id(idx);
+        if sub.is_final && sub.supertypes.is_empty() {
+            return format!("(type {id} {composite})");
+        }
+        let mut text = "(sub".to_string();
+        if sub.is_final {
+            text += " final";
+        }
+        for &supertype in &sub.supertypes {
+            text += &format!(" {}", self.types.id(supertype));
+        }
+        format!("(type {id} {text} {composite}))")
+    }
+
+    fn params_results(&self, ty: &FuncType) -> String {
+        let mut text = String::new();
+        if !ty.params.0.is_empty() {
+            text += " (param";
+            for param in &ty.params.0 {
+                text += &format!(" {}", self.val_type(param));
+            }
+            text += ")";
+        }
+        text + &self.results(ty)
+    }
+
+    fn results(&self, ty: &FuncType) -> String {
+        let mut text = String::new();
+        if !ty.results.0.is_empty() {
+            text += " (result";
+            for result in &ty.results.0 {
+                text += &format!(" {}", self.val_type(result));
+            }
+            text += ")";
+        }
+        text
+    }
+
+    fn field_type(&self, field: &FieldType) -> String {
+        let ty = match &field.ty {
+            StorageType::I8 => "i8".to_string(),
+            StorageType::I16 => "i16".to_string(),
+            StorageType::Val(ty) => self.val_type(ty),
+        };
+        if field.mutable {
+            format!("(mut {ty})")
+        } else {
+            ty
+        }
+    }
+
+    fn global_type(&self, ty: &ValType, mutable: bool) -> String {
+        if mutable {
+            format!("(mut {})", self.val_type(ty))
+        } else {
+            self.val_type(ty)
+        }
+    }
+
+    fn val_type(&self, ty: &ValType) -> String {
+        match ty {
+            ValType::I32 => "i32".to_string(),
+            ValType::I64 => "i64".to_string(),
+            ValType::F32 => "f32".to_string(),
+            ValType::F64 => "f64".to_string(),
+            ValType::V128 => "v128".to_string(),
+            ValType::Ref(ty) => self.ref_type(ty),
+        }
+    }
+
+    fn ref_type(&self, ty: &RefType) -> String {
+        let shorthand = match ty.heap_type {
+            _ if !ty.nullable => None,
+            HeapType::Func => Some("funcref"),
+            HeapType::Extern => Some("externref"),
+            HeapType::Any => Some("anyref"),
+            HeapType::None => Some("nullref"),
+            HeapType::NoExtern => Some("nullexternref"),
+            HeapType::NoFunc => Some("nullfuncref"),
+            HeapType::Eq => Some("eqref"),
+            HeapType::Struct => Some("structref"),
+            HeapType::Array => Some("arrayref"),
+            HeapType::I31 => Some("i31ref"),
+            HeapType::Exn => Some("exnref"),
+            HeapType::NoExn => Some("nullexnref"),
+            HeapType::Concrete(_) => None,
+        };
+        match shorthand {
+            Some(shorthand) => shorthand.to_string(),
+            None if ty.nullable => format!("(ref null {})", self.heap_type(ty.heap_type)),
+            None => format!("(ref {})", self.heap_type(ty.heap_type)),
+        }
+    }
+
+    fn heap_type(&self, ty: HeapType) -> String {
+        let name = match ty {
+            HeapType::Func => "func",
+            HeapType::Extern => "extern",
+            HeapType::Any => "any",
+            HeapType::None => "none",
+            HeapType::NoExtern => "noextern",
+            HeapType::NoFunc => "nofunc",
+            HeapType::Eq => "eq",
+            HeapType::Struct => "struct",
+            HeapType::Array => "array",
+            HeapType::I31 => "i31",
+            HeapType::Exn => "exn",
+            HeapType::NoExn => "noexn",
+            HeapType::Concrete(idx) => return self.types.id(idx),
+        };
+        name.to_string()
+    }
+
+    fn block_type(&self, ty: BlockType) -> String {
+        match ty {
+            BlockType::Empty => String::new(),
+            BlockType::Type(ty) => format!(" (result {})", self.val_type(&ty)),
+            BlockType::FuncType(idx) => format!(" (type {})", self.types.id(idx)),
+        }
+    }
+
+    /// Each instruction in parentheses, without the final `end`.
+    fn const_expr(&self, expr: &ConstExpr) -> String {
+        let instrs = expr.0.strip_suffix(&[Instruction::End]).unwrap_or(&expr.0);
+        (instrs.iter())
+            .map(|instr| format!("({})", self.instr(instr, None, &[])))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The flat text of an instruction, `labels` are the labels of the enclosing blocks.
+    fn instr(&self, instr: &Instruction, func: Option<u32>, labels: &[Option<String>]) -> String {
+        let label = |depth: &u32| relative_label(labels, *depth);
+        let immediates = match instr {
+            Instruction::Block(ty)
+            | Instruction::Loop(ty)
+            | Instruction::If(ty)
+            | Instruction::Try(ty) => self.block_type(*ty),
+            Instruction::TryTable(try_table) => {
+                let mut text = self.block_type(try_table.ty);
+                for catch in &try_table.catches {
+                    text += &match catch {
+                        Catch::Catch { tag, label: l } => {
+                            format!(" (catch {} {})", self.tags.id(*tag), label(l))
+                        }
+                        Catch::CatchRef { tag, label: l } => {
+                            format!(" (catch_ref {} {})", self.tags.id(*tag), label(l))
+                        }
+                        Catch::CatchAll { label: l } => format!(" (catch_all {})", label(l)),
+                        Catch::CatchAllRef { label: l } => {
+                            format!(" (catch_all_ref {})", label(l))
+                        }
+                    };
+                }
+                text
+            }
+            Instruction::Br(l)
+            | Instruction::BrIf(l)
+            | Instruction::BrOnNull(l)
+            | Instruction::BrOnNonNull(l)
+            | Instruction::Rethrow(l)
+            | Instruction::Delegate(l) => format!(" {}", label(l)),
+            Instruction::BrTable(table) => {
+                let mut text = String::new();
+                for target in table.targets.iter().chain([&table.default]) {
+                    text += &format!(" {}", label(target));
+                }
+                text
+            }
+            Instruction::BrOnCast(l, from, to) | Instruction::BrOnCastFail(l, from, to) => {
+                let (from, to) = (self.ref_type(from), self.ref_type(to));
+                format!(" {} {from} {to}", label(l))
+            }
+            Instruction::Call(idx) | Instruction::ReturnCall(idx) | Instruction::RefFunc(idx) => {
+                format!(" {}", self.funcs.id(*idx))
+            }
+            Instruction::CallIndirect(ty, table, _)
+            | Instruction::ReturnCallIndirect(ty, table) => {
+                format!(" {} (type {})", self.tables.id(*table), self.types.id(*ty))
+            }
+            Instruction::Throw(tag) | Instruction::Catch(tag) => format!(" {}", self.tags.id(*tag)),
+            Instruction::TypedSelect(ty) => format!(" (result {})", self.val_type(ty)),
+            Instruction::LocalGet(idx)
+            | Instruction::LocalSet(idx)
+            | Instruction::LocalTee(idx) => {
+                format!(" {}", self.local(func, *idx))
+            }
+            Instruction::GlobalGet(idx) | Instruction::GlobalSet(idx) => {
+                format!(" {}", self.globals.id(*idx))
+            }
+            Instruction::MemorySize(memory)
+            | Instruction::MemoryGrow(memory)
+            | Instruction::MemoryFill(memory)
+            | Instruction::MemoryDiscard(memory) => self.memory(*memory),
+            Instruction::MemoryInit(data, memory) => {
+                format!("{} {}", self.memory(*memory), self.datas.id(*data))
+            }
+            Instruction::MemoryCopy(0, 0) => String::new(),
+            Instruction::MemoryCopy(dst, src) => {
+                format!(" {} {}", self.memories.id(*dst), self.memories.id(*src))
+            }
+            Instruction::DataDrop(data) => format!(" {}", self.datas.id(*data)),
+            Instruction::I32Const(value) => format!(" {value}"),
+            Instruction::I64Const(value) => format!(" {value}"),
+            Instruction::F32Const(value) => {
+                let payload = (value.0.to_bits() & 0x7f_ffff) as u64;
+                format!(" {}", float(value.0, payload, 1 << 22))
+            }
+            Instruction::F64Const(value) => {
+                let payload = value.0.to_bits() & 0xf_ffff_ffff_ffff;
+                format!(" {}", float(value.0, payload, 1 << 51))
+            }
+            Instruction::V128Const(I128(value)) => {
+                let mut text = " i32x4".to_string();
+                for lane in value.to_le_bytes().chunks(4) {
+                    let lane = u32::from_le_bytes(lane.try_into().unwrap());
+                    text += &format!(" {lane:#010x}");
+                }
+                text
+            }
+            Instruction::RefNull(ty) => format!(" {}", self.heap_type(*ty)),
+            Instruction::RefTestNonNull(ty) | Instruction::RefCastNonNull(ty) => {
+                format!(" {}", self.ref_type(&RefType::non_null(*ty)))
+            }
+            Instruction::RefTestNullable(ty) | Instruction::RefCastNullable(ty) => {
+                format!(" {}", self.ref_type(&RefType::new(true, *ty)))
+            }
+            Instruction::StructNew(ty)
+            | Instruction::StructNewDefault(ty)
+            | Instruction::ArrayNew(ty)
+            | Instruction::ArrayNewDefault(ty)
+            | Instruction::ArrayGet(ty)
+            | Instruction::ArrayGetS(ty)
+            | Instruction::ArrayGetU(ty)
+            | Instruction::ArraySet(ty)
+            | Instruction::ArrayFill(ty)
+            | Instruction::CallRef(ty)
+            | Instruction::ReturnCallRef(ty) => format!(" {}", self.types.id(*ty)),
+            Instruction::StructGet(ty, field)
+            | Instruction::StructGetS(ty, field)
+            | Instruction::StructGetU(ty, field)
+            | Instruction::StructSet(ty, field) => {
+                let field = self.field(*ty, *field).unwrap_or(field.to_string());
+                format!(" {} {field}", self.types.id(*ty))
+            }
+            Instruction::ArrayNewFixed(ty, n) => format!(" {} {n}", self.types.id(*ty)),
+            Instruction::ArrayNewData(ty, data) | Instruction::ArrayInitData(ty, data) => {
+                format!(" {} {}", self.types.id(*ty), self.datas.id(*data))
+            }
+            Instruction::ArrayNewElem(ty, elem) | Instruction::ArrayInitElem(ty, elem) => {
+                format!(" {} {}", self.types.id(*ty), self.elems.id(*elem))
+            }
+            Instruction::ArrayCopy(dst, src) => {
+                format!(" {} {}", self.types.id(*dst), self.types.id(*src))
+            }
+            Instruction::TableInit(elem, table) => {
+                format!(" {} {}", self.tables.id(*table), self.elems.id(*elem))
+            }
+            Instruction::ElemDrop(elem) => format!(" {}", self.elems.id(*elem)),
+            Instruction::TableFill(table)
+            | Instruction::TableSet(table)
+            | Instruction::TableGet(table)
+            | Instruction::TableGrow(table)
+            | Instruction::TableSize(table) => format!(" {}", self.tables.id(*table)),
+            Instruction::TableCopy(dst, src) => {
+                format!(" {} {}", self.tables.id(*dst), self.tables.id(*src))
+            }
+            Instruction::V128Load8Lane(memarg, lane)
+            | Instruction::V128Load16Lane(memarg, lane)
+            | Instruction::V128Load32Lane(memarg, lane)
+            | Instruction::V128Load64Lane(memarg, lane)
+            | Instruction::V128Store8Lane(memarg, lane)
+            | Instruction::V128Store16Lane(memarg, lane)
+            | Instruction::V128Store32Lane(memarg, lane)
+            | Instruction::V128Store64Lane(memarg, lane) => {
+                format!("{} {lane}", self.memarg(instr, memarg))
+            }
+            Instruction::I8x16Shuffle(lanes) => {
+                let mut text = String::new();
+                for lane in lanes {
+                    text += &format!(" {lane}");
+                }
+                text
+            }
+            Instruction::I8x16ExtractLaneS(lane)
+            | Instruction::I8x16ExtractLaneU(lane)
+            | Instruction::I8x16ReplaceLane(lane)
+            | Instruction::I16x8ExtractLaneS(lane)
+            | Instruction::I16x8ExtractLaneU(lane)
+            | Instruction::I16x8ReplaceLane(lane)
+            | Instruction::I32x4ExtractLane(lane)
+            | Instruction::I32x4ReplaceLane(lane)
+            | Instruction::I64x2ExtractLane(lane)
+            | Instruction::I64x2ReplaceLane(lane)
+            | Instruction::F32x4ExtractLane(lane)
+            | Instruction::F32x4ReplaceLane(lane)
+            | Instruction::F64x2ExtractLane(lane)
+            | Instruction::F64x2ReplaceLane(lane) => format!(" {lane}"),
+            _ => match instr.memarg() {
+                Some(memarg) => self.memarg(instr, memarg),
+                None => String::new(),
+            },
+        };
+        format!("{}{immediates}", instr.mnemonic())
+    }
+
+    /// Memory 0 is left implicit.
+    fn memory(&self, idx: u32) -> String {
+        match idx {
+            0 => String::new(),
+            _ => format!(" {}", self.memories.id(idx)),
+        }
+    }
+
+    /// The alignment is only printed if it differs from the natural alignment of `instr`.
+    fn memarg(&self, instr: &Instruction, memarg: &MemArg) -> String {
+        let mut text = self.memory(memarg.memory);
+        if memarg.offset != 0 {
+            text += &format!(" offset={}", memarg.offset);
+        }
+        if instr.natural_align() != Some(memarg.align) {
+            text += &format!(" align={}", 1u64 << memarg.align);
+        }
+        text
+    }
+}
+
+/// The shortest decimal that parses back to the same value, NaN payloads in hex,
+/// e.g. `nan:0x1` for an f32 with the bits `0x7f800001`.
+fn float<T: fmt::Debug + Into<f64> + Copy>(value: T, payload: u64, canonical: u64) -> String {
+    let wide: f64 = value.into();
+    let sign = if wide.is_sign_negative() { "-" } else { "" };
+    if wide.is_nan() {
+        if payload == canonical {
+            format!("{sign}nan")
+        } else {
+            format!("{sign}nan:{payload:#x}")
+        }
+    } else if wide.is_infinite() {
+        format!("{sign}inf")
+    } else {
+        format!("{value:?}")
+    }
+}
+
+fn limit(index_type: IndexType, limit: &Limit) -> String {
+    let mut text = match index_type {
+        IndexType::I32 => String::new(),
+        IndexType::I64 => "i64 ".to_string(),
+    };
+    text += &limit.min.to_string();
+    if let Some(max) = limit.max {
+        text += &format!(" {max}");
+    }
+    text
+}
+
+/// A label by name if it has one, by depth otherwise.
+fn relative_label(labels: &[Option<String>], depth: u32) -> String {
+    (labels.len().checked_sub(depth as usize + 1))
+        .and_then(|idx| labels[idx].clone())
+        .unwrap_or_else(|| depth.to_string())
+}
+
+/// Identifiers for the names of an index space, a repeated name is suffixed with the index,
+/// then with `_1`, `_2`, ... while another name already has the suffixed one.
+fn idents(names: &NameMap) -> BTreeMap<u32, String> {
+    let mut taken = names
+        .values()
+        .map(|name| ident(name))
+        .collect::<HashSet<_>>();
+    let mut seen = HashSet::new();
+    (names.iter())
+        .map(|(&idx, name)| {
+            if seen.insert(name.as_str()) {
+                return (idx, ident(name));
+            }
+            let name = format!("{name}#{idx}");
+            let id = (0..)
+                .map(|n| match n {
+                    0 => ident(&name),
+                    n => ident(&format!("{name}_{n}")),
+                })
+                .find(|id| !taken.contains(id))
+                .unwrap();
+            taken.insert(id.clone());
+            (idx, id)
+        })
+        .collect()
+}
+
+/// `$name`, or `$"name"` if the name has characters not allowed in an identifier.
+fn ident(name: &str) -> String {
+    let idchar = |c: u8| c.is_ascii_alphanumeric() || b"!#$%&'*+-./:<=>?@\\^_`|~".contains(&c);
+    if !name.is_empty() && name.bytes().all(idchar) {
+        format!("${name}")
+    } else {
+        format!("${}", string(name.as_bytes()))
+    }
+}
+
+/// A string literal, bytes other than printable ASCII are escaped as `\hh`.
+fn string(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len() + 2);
+    text.push('"');
+    for &byte in bytes {
+        match byte {
+            b'"' => text.push_str("\\\""),
+            b'\\' => text.push_str("\\\\"),
+            b'\t' => text.push_str("\\t"),
+            b'\n' => text.push_str("\\n"),
+            0x20..=0x7e => text.push(byte as char),
+            _ => text.push_str(&format!("\\{byte:02x}")),
+        }
+    }
+    text.push('"');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Printer, MAX_INDENT};
+    use crate::instruction::Instruction;
+    use crate::limits::Limits;
+    use crate::module::Module;
+
+    /// Print `data` and parse the text back, everything but custom sections must be equal.
+    /// Bodies are compared by instructions, binaries may pad LEB128 immediates.
+    fn round_trip(data: &[u8], printer: Printer) -> String {
+        let mut module = Module::from_bytes(data).parse().unwrap();
+        let text = printer.print(&module).unwrap();
+        let printed = wat::parse_str(&text).unwrap_or_else(|err| panic!("{err}\n{text}"));
+        let mut reparsed = Module::from_bytes(&printed).parse().unwrap();
+
+        let code = std::mem::take(&mut module.code_section.0);
+        let reparsed_code = std::mem::take(&mut reparsed.code_section.0);
+        assert_eq!(code.len(), reparsed_code.len());
+        for (code, reparsed) in code.iter().zip(&reparsed_code) {
+            assert_eq!(code.locals, reparsed.locals);
+            assert_eq!(code.expr().unwrap(), reparsed.expr().unwrap());
+        }
+        module.custom_sections.clear();
+        reparsed.custom_sections.clear();
+        assert_eq!(module.into_owned(), reparsed.into_owned());
+        text
+    }
+
+    #[test]
+    fn test_print_round_trip() {
+        for data in [
+            include_bytes!("../tests/bz2.wasm").as_slice(),
+            include_bytes!("../tests/pulldown-cmark.wasm"),
+            include_bytes!("../tests/lots-of-types.wasm"),
+        ] {
+            round_trip(data, Printer::new());
+            round_trip(data, Printer::new().folded());
+        }
+
+        let data = wat::parse_str(
+            r#"
+            (module $m
+                (rec
+                    (type $node (sub (struct (field i32) (field (mut (ref null $node))))))
+                    (type $leaf (sub final $node (struct (field i32) (field (mut (ref null $node)))))))
+                (type $bytes (array (mut i8)))
+                (type $f (func (param i32) (result i32)))
+                (import "env" "f" (func $imported (type $f)))
+                (import "env" "t" (table 1 funcref))
+                (import "env" "m" (memory 1 2 shared))
+                (import "env" "g" (global (mut i64)))
+                (import "env" "e" (tag (param i32)))
+                (memory $m64 i64 1)
+                (table $refs 2 (ref null $node) (ref.null $node))
+                (tag $t (param i32))
+                (global $nan f32 (f32.const nan:0x1))
+                (global $neg f64 (f64.const -0x1p-1074))
+                (global $v v128 (v128.const i32x4 1 -2 3 -4))
+                (export "f" (func $imported))
+                (export "m64" (memory $m64))
+                (start $start)
+                (elem (table 0) (i32.const 0) func $start)
+                (elem funcref (ref.null func) (ref.func $start))
+                (elem declare func $imported)
+                (data (i32.const 0) "quote\" tab\t \ff")
+                (data (memory $m64) (i64.const 8) "\00\01")
+                (func $start
+                    (local $x i32) (local i64 (ref null $node))
+                    (drop (i32.atomic.rmw.add offset=4 (i32.const 0) (i32.const 1)))
+                    (drop (i64.load32_u $m64 offset=8 align=1 (i64.const 0)))
+                    (v128.store8_lane 3 (i32.const 0) (global.get $v))
+                    (memory.copy $m64 $m64 (i64.const 0) (i64.const 0) (i64.const 0))
+                    (drop (select (result i64) (i64.const -1) (i64.const 1) (i32.const 0)))
+                    (drop (call_indirect (type $f) (i32.const 0) (i32.const 0)))
+                    (drop (block $l (result (ref null $node))
+                        (br_on_cast_fail $l (ref null $node) (ref $leaf) (local.get 2))
+                        (struct.get $leaf 0)
+                        (array.new_default $bytes)
+                        (array.len)
+                        (br_table $l $l (ref.null $node) (local.get $x))))
+                    (if (local.get $x)
+                        (then (loop $again (br_if $again (i32.const 0))))
+                        (else nop))
+                    try
+                        try
+                            (throw $t (i32.const 1))
+                        delegate 0
+                    catch $t
+                        drop
+                        rethrow 0
+                    catch_all
+                    end
+                    (block $outer
+                        (try_table (catch $t $outer) (catch_all $outer)
+                            (throw $t (i32.const 1))))))
+            "#,
+        )
+        .unwrap();
+        let text = round_trip(&data, Printer::new());
+        for expected in [
+            "(module $m\n  (rec\n    (type $node (sub (struct (field i32) (field (mut (ref null $node))))))",
+            "(import \"env\" \"f\" (func $imported (type $f) (param i32) (result i32)))",
+            "(import \"env\" \"t\" (table $table0 1 funcref))",
+            "(func $start (type $t5)\n    (local $x i32)\n    (local $l1 i64)",
+            "i64.load32_u $m64 offset=8 align=1\n",
+            "i32.atomic.rmw.add offset=4\n",
+            "br_on_cast_fail $l (ref null $node) (ref $leaf)\n",
+            "call_indirect $table0 (type $f)\n",
+            "    if\n      loop $again\n        i32.const 0\n        br_if $again\n      end\n    else\n",
+            "      delegate 0\n    catch $t\n      drop\n      rethrow 0\n    catch_all\n    end\n",
+            "try_table (catch $t $outer) (catch_all $outer)\n",
+            "(global $nan f32 (f32.const nan:0x1))",
+            "(global $neg f64 (f64.const -5e-324))",
+            "(v128.const i32x4 0x00000001 0xfffffffe 0x00000003 0xfffffffc)",
+            "(elem $elem0 (table $table0) (offset (i32.const 0)) func $start)",
+            "(elem $elem1 funcref (item (ref.null func)) (item (ref.func $start)))",
+            "(data $data0 (offset (i32.const 0)) \"quote\\\" tab\\t \\ff\")",
+        ] {
+            assert!(text.contains(expected), "{expected}\n{text}");
+        }
+
+        let folded = round_trip(&data, Printer::new().folded());
+        assert!(
+            folded.contains("    (if\n      (then\n        (loop $again\n          i32.const 0\n          br_if $again))\n      (else\n        nop))\n"),
+            "{folded}"
+        );
+
+        // Indentation stops growing past a depth.
+        let depth = 10_000;
+        let wat = format!(
+            "(module (func {} {}))",
+            "(block ".repeat(depth),
+            ")".repeat(depth)
+        );
+        let data = wat::parse_str(wat).unwrap();
+        let module = Module::from_bytes(&data)
+            .limits(Limits::unlimited())
+            .parse()
+            .unwrap();
+        for printer in [Printer::new(), Printer::new().folded()] {
+            let text = printer.print(&module).unwrap();
+            let indent = |line: &str| line.len() - line.trim_start().len();
+            assert_eq!(text.lines().map(indent).max(), Some(2 * MAX_INDENT));
+        }
+
+        // Generated identifiers skip those the `name` section already gives.
+        let data = wat::parse_str(
+            r#"
+            (module
+                (func $f1 (call 1))
+                (func (call 0))
+                (func $a (local $l1 i32) (local i32))
+                (func (@name "a"))
+                (func $a#3))
+            "#,
+        )
+        .unwrap();
+        let text = round_trip(&data, Printer::new());
+        for expected in [
+            "(func $f1 (type $t0)\n    call $f1_1)",
+            "(func $f1_1 (type $t0)\n    call $f1)",
+            "(local $l1 i32)\n    (local $l1_1 i32))",
+            "(func $a#3_1 (type $t0))",
+            "(func $a#3 (type $t0))",
+        ] {
+            assert!(text.contains(expected), "{expected}\n{text}");
+        }
+
+        assert_eq!(Instruction::Call(3).to_string(), "call $f3");
+        assert_eq!(Instruction::Br(1).to_string(), "br 1");
+    }
+}
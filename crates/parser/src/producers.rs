@@ -0,0 +1,135 @@
+//! The `producers` custom section.
+//!
+//! https://github.com/WebAssembly/tool-conventions/blob/main/ProducersSection.md
+
+use smol_str::SmolStr;
+
+use crate::decode::Decoder;
+use crate::encode::Encoder;
+use crate::error::Error;
+use crate::section::CustomSection;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Producers {
+    /// Source languages, e.g. `Rust`, `C++`.
+    pub language: Vec<VersionedName>,
+    /// Tools that processed the module, e.g. `rustc`, `wasm-bindgen`.
+    pub processed_by: Vec<VersionedName>,
+    /// SDKs the module was built with, e.g. `Emscripten`.
+    pub sdk: Vec<VersionedName>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VersionedName {
+    pub name: SmolStr,
+    pub version: SmolStr,
+}
+
+impl VersionedName {
+    pub fn new(name: impl Into<SmolStr>, version: impl Into<SmolStr>) -> Self {
+        VersionedName {
+            name: name.into(),
+            version: version.into(),
+        }
+    }
+}
+
+impl Producers {
+    pub const SECTION_NAME: &'static str = "producers";
+
+    pub fn parse(data: &[u8]) -> Result<Producers, Error> {
+        let mut producers = Producers::default();
+        let mut decoder = Decoder::new(data);
+
+        let fields = decoder.read_var_u32()?;
+        for _ in 0..fields {
+            let field = decoder.read_str()?;
+            let values = decoder.read_vec(|decoder| {
+                Ok(VersionedName {
+                    name: decoder.read_str()?,
+                    version: decoder.read_str()?,
+                })
+            })?;
+            match field.as_str() {
+                "language" => producers.language = values,
+                "processed-by" => producers.processed_by = values,
+                "sdk" => producers.sdk = values,
+                _ => {
+                    return Err(Error::InvalidCustomSection(
+                        Self::SECTION_NAME,
+                        "unknown field name",
+                    ))
+                }
+            }
+        }
+
+        if !decoder.remaining_slice().is_empty() {
+            return Err(Error::InvalidCustomSection(
+                Self::SECTION_NAME,
+                "trailing bytes",
+            ));
+        }
+
+        Ok(producers)
+    }
+
+    /// Encode the section payload, fields without values are omitted.
+    pub fn encode(&self) -> Vec<u8> {
+        let fields = [
+            ("language", &self.language),
+            ("processed-by", &self.processed_by),
+            ("sdk", &self.sdk),
+        ]
+        .into_iter()
+        .filter(|(_, values)| !values.is_empty())
+        .collect::<Vec<_>>();
+
+        let mut encoder = Encoder::new();
+        encoder.write_vec(fields, |encoder, (field, values)| {
+            encoder.write_str(field);
+            encoder.write_vec(values, |encoder, value| {
+                encoder.write_str(&value.name);
+                encoder.write_str(&value.version);
+            });
+        });
+        encoder.finish()
+    }
+
+    pub fn to_custom_section(&self) -> CustomSection<'static> {
+        CustomSection::new(Self::SECTION_NAME, self.encode())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Producers, VersionedName};
+
+    #[test]
+    fn test_producers_round_trip() {
+        let data = wat::parse_str(
+            r#"
+            (module
+                (@producers
+                    (language "Rust" "")
+                    (processed-by "rustc" "1.77.0")
+                    (processed-by "wasm-bindgen" "0.2.92")))
+            "#,
+        )
+        .unwrap();
+        let module = crate::module::Module::from_bytes(&data).parse().unwrap();
+        let producers = module.producers().unwrap();
+
+        assert_eq!(producers.language, vec![VersionedName::new("Rust", "")]);
+        assert_eq!(
+            producers.processed_by,
+            vec![
+                VersionedName::new("rustc", "1.77.0"),
+                VersionedName::new("wasm-bindgen", "0.2.92")
+            ]
+        );
+        assert!(producers.sdk.is_empty());
+
+        let section = producers.to_custom_section();
+        assert_eq!(Producers::parse(&section.data).unwrap(), producers);
+    }
+}
@@ -1,11 +1,14 @@
-use smol_str::SmolStr;
+use std::borrow::Cow;
 
-use crate::error::Error;
-use crate::instruction::{ConstExpr, Expr};
-use crate::types::{FuncType, Global, GlobalType, MemoryType, RefType, TableType, ValType};
+use crate::error::{Error, Location};
+use crate::instruction::{ConstExpr, Expr, InstructionReader};
+use crate::types::{
+    CompositeType, FuncType, Global, GlobalType, MemoryType, RecGroup, RefType, SubType, Table,
+    TableType, TagType, ValType,
+};
 
-
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SectionId {
     Custom = 0x00,
     Type = 0x01,
@@ -20,6 +23,7 @@ pub enum SectionId {
     Code = 0x0a,
     Data = 0x0b,
     DataCount = 0x0c,
+    Tag = 0x0d,
 }
 
 impl TryFrom<u8> for SectionId {
@@ -40,95 +44,245 @@ impl TryFrom<u8> for SectionId {
             0x0a => Ok(SectionId::Code),
             0x0b => Ok(SectionId::Data),
             0x0c => Ok(SectionId::DataCount),
+            0x0d => Ok(SectionId::Tag),
             _ => Err(Error::InvalidSectionId(value)),
         }
     }
 }
 
-#[derive(Debug, Default)]
-pub struct CustomSection {
-    pub name: SmolStr,
-    pub data: Vec<u8>,
+impl SectionId {
+    /// Rank of the section in the order required by the spec,
+    /// custom sections may appear anywhere.
+    pub(crate) fn order(self) -> u8 {
+        match self {
+            SectionId::Custom => 0,
+            SectionId::Type => 1,
+            SectionId::Import => 2,
+            SectionId::Function => 3,
+            SectionId::Table => 4,
+            SectionId::Memory => 5,
+            SectionId::Tag => 6,
+            SectionId::Global => 7,
+            SectionId::Export => 8,
+            SectionId::Start => 9,
+            SectionId::Element => 10,
+            SectionId::DataCount => 11,
+            SectionId::Code => 12,
+            SectionId::Data => 13,
+        }
+    }
+}
+
+/// `name` and `data` borrow from the input binary when parsed,
+/// use [CustomSection::into_owned] to detach them.
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CustomSection<'a> {
+    pub name: Cow<'a, str>,
+    pub data: Cow<'a, [u8]>,
 }
 
-#[derive(Debug, Default)]
+impl<'a> CustomSection<'a> {
+    pub fn new(name: impl Into<Cow<'a, str>>, data: impl Into<Cow<'a, [u8]>>) -> Self {
+        CustomSection {
+            name: name.into(),
+            data: data.into(),
+        }
+    }
+
+    pub fn into_owned(self) -> CustomSection<'static> {
+        CustomSection {
+            name: Cow::Owned(self.name.into_owned()),
+            data: Cow::Owned(self.data.into_owned()),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TypeSection(pub Vec<TypeSectionTy>);
 
-#[derive(Debug, Default)]
-pub struct ImportSection(pub Vec<Import>);
+impl TypeSection {
+    /// Iterate all defined types in index order, flattening `rec` groups.
+    pub fn sub_types(&self) -> impl Iterator<Item = &SubType> {
+        self.0.iter().flat_map(|ty| match ty {
+            TypeSectionTy::Sub(sub) => std::slice::from_ref(sub),
+            TypeSectionTy::Rec(group) => group.0.as_slice(),
+        })
+    }
+
+    pub fn get(&self, idx: u32) -> Option<&SubType> {
+        self.sub_types().nth(idx as usize)
+    }
+
+    pub fn func_type(&self, idx: u32) -> Option<&FuncType> {
+        match &self.get(idx)?.composite {
+            CompositeType::Func(ty) => Some(ty),
+            _ => None,
+        }
+    }
+}
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImportSection<'a>(pub Vec<Import<'a>>);
+
+impl ImportSection<'_> {
+    pub fn into_owned(self) -> ImportSection<'static> {
+        ImportSection(self.0.into_iter().map(Import::into_owned).collect())
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FunctionSection(pub crate::SVec<u32>);
 
-#[derive(Debug, Default)]
-pub struct TableSection(pub crate::SVec<TableType>);
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TableSection(pub crate::SVec<Table>);
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MemorySection(pub crate::SVec<MemoryType>);
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GlobalSection(pub Vec<Global>);
 
-#[derive(Debug, Default)]
-pub struct ExportSection(pub Vec<Export>);
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExportSection<'a>(pub Vec<Export<'a>>);
 
-#[derive(Debug, Default)]
-pub struct StartSection(pub u32);
+impl ExportSection<'_> {
+    pub fn into_owned(self) -> ExportSection<'static> {
+        ExportSection(self.0.into_iter().map(Export::into_owned).collect())
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StartSection(pub Option<u32>);
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ElementSection(pub Vec<Element>);
 
-#[derive(Debug, Default)]
-pub struct CodeSection(pub Vec<Code>);
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CodeSection<'a>(pub Vec<Code<'a>>);
+
+impl CodeSection<'_> {
+    pub fn into_owned(self) -> CodeSection<'static> {
+        CodeSection(self.0.into_iter().map(Code::into_owned).collect())
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DataSection<'a>(pub Vec<Data<'a>>);
 
-#[derive(Debug, Default)]
-pub struct DataSection(pub Vec<Data>);
+impl DataSection<'_> {
+    pub fn into_owned(self) -> DataSection<'static> {
+        DataSection(self.0.into_iter().map(Data::into_owned).collect())
+    }
+}
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DataCountSection(pub Option<u32>);
 
-#[derive(Debug)]
+#[derive(Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TagSection(pub crate::SVec<TagType>);
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TypeSectionTy {
-    Func(FuncType),
+    /// A single type, forming an implicit recursion group of its own.
+    Sub(SubType),
+    Rec(RecGroup),
 }
 
-#[derive(Debug)]
-pub struct Import {
-    pub module_name: SmolStr,
-    pub field_name: SmolStr,
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Import<'a> {
+    pub module_name: Cow<'a, str>,
+    pub field_name: Cow<'a, str>,
     pub kind: ImportKind,
 }
 
-#[derive(Debug)]
+impl Import<'_> {
+    pub fn into_owned(self) -> Import<'static> {
+        Import {
+            module_name: Cow::Owned(self.module_name.into_owned()),
+            field_name: Cow::Owned(self.field_name.into_owned()),
+            kind: self.kind,
+        }
+    }
+}
+
+/// Serialized as `{"kind": "func", "type": 0}`, the kind is one of
+/// `func`, `table`, `memory`, `global` and `tag`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "type", rename_all = "snake_case")
+)]
 pub enum ImportKind {
     Func(u32),
     Table(TableType),
     Memory(MemoryType),
     Global(GlobalType),
+    Tag(TagType),
 }
 
-#[derive(Debug)]
-pub struct Export {
-    pub name: SmolStr,
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Export<'a> {
+    pub name: Cow<'a, str>,
     pub kind: ExportKind,
 }
 
-#[derive(Debug)]
+impl Export<'_> {
+    pub fn into_owned(self) -> Export<'static> {
+        Export {
+            name: Cow::Owned(self.name.into_owned()),
+            kind: self.kind,
+        }
+    }
+}
+
+/// Serialized as `{"kind": "func", "index": 0}`, the kind is one of
+/// `func`, `table`, `memory`, `global` and `tag`.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "index", rename_all = "snake_case")
+)]
 pub enum ExportKind {
     Func(u32),
     Table(u32),
+    #[cfg_attr(feature = "serde", serde(rename = "memory"))]
     Mem(u32),
     Global(u32),
+    Tag(u32),
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Element {
     pub ty: RefType,
     pub init: crate::SVec<ConstExpr>,
     pub kind: ElementKind,
 }
 
-#[derive(Debug)]
+/// Serialized as `{"mode": "passive"}`, `{"mode": "declared"}`
+/// or `{"mode": "active", "table": 0, "offset": [...]}`.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "mode", rename_all = "snake_case"))]
 pub enum ElementKind {
     Passive,
     Active {
@@ -138,30 +292,145 @@ pub enum ElementKind {
     Declared,
 }
 
-#[derive(Debug)]
-pub struct Code {
+/// A function body, its instructions are only decoded on demand.
+///
+/// `size` and `offset` are positions in the binary and are not compared by `==`.
+/// Serialized with the decoded instructions in place of `body`, as `"instrs": [...]`,
+/// a body that fails to decode fails to serialize.
+#[derive(Clone, Debug)]
+pub struct Code<'a> {
+    /// Index of the function in the function index space, imported functions included.
+    pub index: u32,
     pub size: u32,
     pub locals: crate::SVec<Locals>,
-    pub expr: Expr,
+    /// The encoded expression following the locals, up to and including its final `end`.
+    pub body: Cow<'a, [u8]>,
+    /// Offset of `body` in the binary.
+    pub offset: usize,
+}
+
+impl PartialEq for Code<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.locals == other.locals && self.body == other.body
+    }
 }
 
-#[derive(Debug)]
+impl Code<'_> {
+    pub fn instructions(&self) -> InstructionReader<'_> {
+        InstructionReader::new_with_offset(&self.body, self.offset)
+    }
+
+    pub fn expr(&self) -> Result<Expr, Error> {
+        self.decode_expr(false)
+    }
+
+    /// Like [Code::expr], but also records the offset of every instruction.
+    pub fn expr_with_offsets(&self) -> Result<Expr, Error> {
+        self.decode_expr(true)
+    }
+
+    fn decode_expr(&self, record_offsets: bool) -> Result<Expr, Error> {
+        let location = Location::Function(self.index);
+        let mut reader = self.instructions();
+        let mut instrs = Vec::new();
+        let mut offsets = record_offsets.then(Vec::new);
+        loop {
+            let offset = reader.offset();
+            match reader.next() {
+                Some(Ok(instr)) => instrs.push(instr),
+                Some(Err(err)) => return Err(err.at(offset, location)),
+                None => break,
+            }
+            if let Some(offsets) = &mut offsets {
+                offsets.push(offset);
+            }
+        }
+        match reader.remaining().first() {
+            Some(&byte) => Err(Error::InvalidExprEnd(byte).at(reader.offset(), location)),
+            None => Ok(Expr { instrs, offsets }),
+        }
+    }
+
+    pub fn into_owned(self) -> Code<'static> {
+        Code {
+            body: Cow::Owned(self.body.into_owned()),
+            ..self
+        }
+    }
+}
+
+/// The serialized form of [Code].
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerdeCode<'c> {
+    index: u32,
+    size: u32,
+    locals: Cow<'c, [Locals]>,
+    instrs: Vec<crate::instruction::Instruction>,
+    offset: usize,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Code<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let expr = self.expr().map_err(serde::ser::Error::custom)?;
+        SerdeCode {
+            index: self.index,
+            size: self.size,
+            locals: Cow::Borrowed(&self.locals),
+            instrs: expr.instrs,
+            offset: self.offset,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Code<'_> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = SerdeCode::deserialize(deserializer)?;
+        let mut encoder = crate::encode::Encoder::new();
+        code.instrs
+            .iter()
+            .for_each(|instr| encoder.write_instruction(instr));
+        Ok(Code {
+            index: code.index,
+            size: code.size,
+            locals: code.locals.into_owned().into(),
+            body: Cow::Owned(encoder.finish()),
+            offset: code.offset,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Locals {
     pub n: u32,
     pub ty: ValType,
 }
 
-#[derive(Debug)]
-pub struct Data {
-    pub init: Vec<u8>,
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Data<'a> {
+    pub init: Cow<'a, [u8]>,
     pub kind: DataKind,
 }
 
-#[derive(Debug)]
+impl Data<'_> {
+    pub fn into_owned(self) -> Data<'static> {
+        Data {
+            init: Cow::Owned(self.init.into_owned()),
+            kind: self.kind,
+        }
+    }
+}
+
+/// Serialized as `{"mode": "passive"}` or `{"mode": "active", "memory": 0, "offset": [...]}`.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "mode", rename_all = "snake_case"))]
 pub enum DataKind {
     Passive,
-    Active {
-        memory: u32,
-        offset: ConstExpr,
-    },
+    Active { memory: u32, offset: ConstExpr },
 }
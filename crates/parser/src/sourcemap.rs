@@ -0,0 +1,374 @@
+//! Source maps referenced by the `sourceMappingURL` custom section.
+//!
+//! https://sourcemaps.info/spec.html
+//!
+//! Wasm source maps have a single generated line, whose columns are offsets in the binary,
+//! the same offsets as [InstructionReader::offset](crate::instruction::InstructionReader::offset).
+
+use std::path::{Component, Path};
+
+use serde::Deserialize;
+
+use crate::error::Error;
+use crate::module::Module;
+
+/// A decoded version 3 source map.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct SourceMap {
+    pub file: Option<String>,
+    /// Source file names, prefixed with the `sourceRoot`.
+    pub sources: Vec<String>,
+    /// The embedded content of each source, if any.
+    pub sources_content: Vec<Option<String>>,
+    pub names: Vec<String>,
+    /// Sorted by offset.
+    pub mappings: Vec<Mapping>,
+}
+
+/// The code from `offset` up to the next mapping comes from `original`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Mapping {
+    pub offset: usize,
+    /// `None` for code without a source, e.g. generated by the compiler.
+    pub original: Option<Original>,
+}
+
+/// A position in a source file, lines and columns are zero-based as in the format.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Original {
+    /// Index in [SourceMap::sources].
+    pub source: u32,
+    pub line: u32,
+    pub column: u32,
+    /// Index in [SourceMap::names].
+    pub name: Option<u32>,
+}
+
+/// The result of [SourceMap::lookup], lines and columns are one-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceLocation<'a> {
+    pub file: &'a str,
+    pub line: u32,
+    pub column: u32,
+    /// The original name of the symbol, e.g. before minification.
+    pub name: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawSourceMap {
+    version: u32,
+    file: Option<String>,
+    source_root: Option<String>,
+    #[serde(default)]
+    sources: Vec<Option<String>>,
+    #[serde(default)]
+    sources_content: Vec<Option<String>>,
+    #[serde(default)]
+    names: Vec<String>,
+    mappings: String,
+}
+
+impl SourceMap {
+    /// Parse the JSON text of a source map.
+    pub fn parse(json: &str) -> Result<SourceMap, Error> {
+        let raw: RawSourceMap = serde_json::from_str(json)?;
+        if raw.version != 3 {
+            return Err(Error::InvalidSourceMap("unsupported version"));
+        }
+
+        let root = raw.source_root.unwrap_or_default();
+        let sources = raw
+            .sources
+            .into_iter()
+            .map(|source| {
+                let source = source.unwrap_or_default();
+                if root.is_empty() || root.ends_with('/') {
+                    format!("{root}{source}")
+                } else {
+                    format!("{root}/{source}")
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut sources_content = raw.sources_content;
+        sources_content.resize(sources.len(), None);
+
+        let mappings = decode_mappings(&raw.mappings)?;
+        if mappings.iter().any(|mapping| {
+            mapping.original.is_some_and(|original| {
+                original.source as usize >= sources.len()
+                    || original
+                        .name
+                        .is_some_and(|name| name as usize >= raw.names.len())
+            })
+        }) {
+            return Err(Error::InvalidSourceMap("mapping index out of range"));
+        }
+
+        Ok(SourceMap {
+            file: raw.file,
+            sources,
+            sources_content,
+            names: raw.names,
+            mappings,
+        })
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<SourceMap, Error> {
+        let json = std::fs::read_to_string(path).map_err(Error::SourceMapIo)?;
+        Self::parse(&json)
+    }
+
+    /// Load the source map named by the `sourceMappingURL` section of `module`,
+    /// a relative URL is resolved against `dir`, the directory of the binary.
+    ///
+    /// The URL comes from the binary, so it may not leave `dir`:
+    /// absolute paths, including `file:///` URLs, and `..` segments are rejected.
+    /// Returns `None` if the module has no such section.
+    pub fn load(module: &Module, dir: impl AsRef<Path>) -> Result<Option<SourceMap>, Error> {
+        let Some(url) = module.source_mapping_url()? else {
+            return Ok(None);
+        };
+        let path = match url.split_once("://") {
+            Some(("file", path)) => path,
+            Some(_) => return Err(Error::InvalidSourceMap("not a local file URL")),
+            None => &url,
+        };
+        let path = Path::new(path);
+        if !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(Error::InvalidSourceMap(
+                "URL outside of the binary's directory",
+            ));
+        }
+        Self::from_file(dir.as_ref().join(path)).map(Some)
+    }
+
+    /// The source of the instruction at `offset`.
+    pub fn lookup(&self, offset: usize) -> Option<SourceLocation<'_>> {
+        let idx = self
+            .mappings
+            .partition_point(|mapping| mapping.offset <= offset);
+        let original = self.mappings[idx.checked_sub(1)?].original?;
+        Some(SourceLocation {
+            file: &self.sources[original.source as usize],
+            line: original.line + 1,
+            column: original.column + 1,
+            name: original.name.map(|name| self.names[name as usize].as_str()),
+        })
+    }
+}
+
+/// Decode the `mappings` field, fields other than the offset are relative to the previous segment.
+fn decode_mappings(mappings: &str) -> Result<Vec<Mapping>, Error> {
+    let mut result = Vec::new();
+    let (mut offset, mut source, mut line, mut column, mut name) = (0i64, 0i64, 0i64, 0i64, 0i64);
+    let mut lines = mappings.split(';');
+    for segment in lines.next().unwrap_or_default().split(',') {
+        if segment.is_empty() {
+            continue;
+        }
+        let fields = decode_vlq(segment)?;
+        offset += fields[0];
+        let original = match fields[1..] {
+            [] => None,
+            [d_source, d_line, d_column, ref d_name @ ..] if d_name.len() <= 1 => {
+                source += d_source;
+                line += d_line;
+                column += d_column;
+                let name = match d_name {
+                    [d_name] => {
+                        name += d_name;
+                        Some(to_u32(name)?)
+                    }
+                    _ => None,
+                };
+                Some(Original {
+                    source: to_u32(source)?,
+                    line: to_u32(line)?,
+                    column: to_u32(column)?,
+                    name,
+                })
+            }
+            _ => {
+                return Err(Error::InvalidSourceMap(
+                    "segment with 2, 3 or more than 5 fields",
+                ))
+            }
+        };
+        result.push(Mapping {
+            offset: usize::try_from(offset)
+                .map_err(|_| Error::InvalidSourceMap("negative offset"))?,
+            original,
+        });
+    }
+    if lines.any(|line| !line.is_empty()) {
+        return Err(Error::InvalidSourceMap("mappings span more than one line"));
+    }
+
+    result.sort_by_key(|mapping| mapping.offset);
+    Ok(result)
+}
+
+fn to_u32(value: i64) -> Result<u32, Error> {
+    u32::try_from(value).map_err(|_| Error::InvalidSourceMap("mapping index out of range"))
+}
+
+/// Decode the base64 VLQ values of a segment,
+/// each digit holds 5 bits and a continuation bit, the first bit of a value is its sign.
+/// Values are limited to 32 bits by the format.
+fn decode_vlq(segment: &str) -> Result<Vec<i64>, Error> {
+    let mut values = Vec::new();
+    let (mut value, mut shift) = (0u64, 0);
+    for byte in segment.bytes() {
+        let digit = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return Err(Error::InvalidSourceMap("invalid base64 digit")),
+        };
+        if shift > 30 {
+            return Err(Error::InvalidSourceMap("VLQ value too large"));
+        }
+        value |= u64::from(digit & 0x1f) << shift;
+        shift += 5;
+        if digit & 0x20 == 0 {
+            let magnitude = (value >> 1) as i64;
+            values.push(if value & 1 != 0 {
+                -magnitude
+            } else {
+                magnitude
+            });
+            (value, shift) = (0, 0);
+        }
+    }
+    if shift != 0 {
+        return Err(Error::InvalidSourceMap("truncated VLQ value"));
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SourceLocation, SourceMap};
+    use crate::encode::Encoder;
+    use crate::error::Error;
+    use crate::module::Module;
+    use crate::section::CustomSection;
+
+    fn encode_vlq(values: &[i64]) -> String {
+        const DIGITS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut segment = String::new();
+        for &value in values {
+            let mut value = (value.unsigned_abs() << 1) | u64::from(value < 0);
+            loop {
+                let digit = (value & 0x1f) as usize;
+                value >>= 5;
+                let continued = if value != 0 { 0x20 } else { 0 };
+                segment.push(DIGITS[digit | continued] as char);
+                if value == 0 {
+                    break;
+                }
+            }
+        }
+        segment
+    }
+
+    #[test]
+    fn test_source_map_lookup() {
+        let data = wat::parse_str(
+            r#"
+            (module
+                (func (export "add") (param i32 i32) (result i32)
+                    local.get 0
+                    local.get 1
+                    i32.add
+                    drop
+                    i32.const 0))
+            "#,
+        )
+        .unwrap();
+        let mut module = Module::from_bytes(&data).parse().unwrap();
+        let offsets = module.code_section.0[0]
+            .expr_with_offsets()
+            .unwrap()
+            .offsets
+            .unwrap();
+
+        // `add` at src/lib.rs:2:5, `+` at 3:7, then compiler generated code.
+        let segments = [
+            vec![offsets[0] as i64, 0, 1, 4, 0],
+            vec![(offsets[2] - offsets[0]) as i64, 0, 1, 2],
+            vec![(offsets[3] - offsets[2]) as i64],
+        ];
+        let mappings = segments
+            .iter()
+            .map(|fields| encode_vlq(fields))
+            .collect::<Vec<_>>()
+            .join(",");
+        let json = format!(
+            r#"{{"version": 3, "sourceRoot": "src", "sources": ["lib.rs"], "names": ["add"], "mappings": "{mappings}"}}"#
+        );
+
+        let dir = std::env::temp_dir().join(format!("wasmcat-sourcemap-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("add.wasm.map"), &json).unwrap();
+        let mut url = Encoder::new();
+        url.write_str("add.wasm.map");
+        module
+            .custom_sections
+            .push(CustomSection::new("sourceMappingURL", url.finish()));
+
+        let source_map = SourceMap::load(&module, &dir).unwrap().unwrap();
+        for url in [
+            "/etc/passwd",
+            "file:///etc/passwd",
+            "../add.wasm.map",
+            "a/../../b",
+        ] {
+            let mut encoder = Encoder::new();
+            encoder.write_str(url);
+            *module.custom_sections.last_mut().unwrap() =
+                CustomSection::new("sourceMappingURL", encoder.finish());
+            assert!(matches!(
+                SourceMap::load(&module, &dir),
+                Err(Error::InvalidSourceMap(_))
+            ));
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(source_map, SourceMap::parse(&json).unwrap());
+        assert_eq!(source_map.sources, ["src/lib.rs"]);
+
+        let add = SourceLocation {
+            file: "src/lib.rs",
+            line: 2,
+            column: 5,
+            name: Some("add"),
+        };
+        assert_eq!(source_map.lookup(offsets[0]), Some(add));
+        assert_eq!(source_map.lookup(offsets[1]), Some(add));
+        assert_eq!(
+            source_map.lookup(offsets[2]),
+            Some(SourceLocation {
+                line: 3,
+                column: 7,
+                name: None,
+                ..add
+            })
+        );
+        assert_eq!(source_map.lookup(offsets[4]), None);
+        assert_eq!(source_map.lookup(0), None);
+
+        let err = SourceMap::parse(r#"{"version": 3, "sources": [], "mappings": "AAAA"}"#);
+        assert!(matches!(err, Err(Error::InvalidSourceMap(_))));
+        let err = SourceMap::parse(r#"{"version": 3, "sources": ["a"], "mappings": "Ag"}"#);
+        assert!(matches!(
+            err,
+            Err(Error::InvalidSourceMap("truncated VLQ value"))
+        ));
+    }
+}
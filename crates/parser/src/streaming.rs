@@ -0,0 +1,558 @@
+//! Push-style parsing of a module received in chunks.
+
+use crate::decode::Decoder;
+use crate::error::{Error, Location};
+use crate::limits::{self, Limits};
+use crate::module::Module;
+use crate::parser::{imported_functions, ModuleParser};
+use crate::section::{
+    Code, CustomSection, DataCountSection, DataSection, ElementSection, ExportSection,
+    FunctionSection, GlobalSection, ImportSection, MemorySection, SectionId, StartSection,
+    TableSection, TagSection, TypeSection,
+};
+
+/// What the [StreamingParser] decoded from the buffered input.
+#[derive(Debug)]
+pub enum Event<'a> {
+    /// The buffered input ends in the middle of an item,
+    /// at least this many more bytes are needed to make progress.
+    NeedMoreData(u64),
+    Header {
+        version: u32,
+    },
+    CustomSection(CustomSection<'a>),
+    TypeSection(TypeSection),
+    ImportSection(ImportSection<'a>),
+    FunctionSection(FunctionSection),
+    TableSection(TableSection),
+    MemorySection(MemorySection),
+    TagSection(TagSection),
+    GlobalSection(GlobalSection),
+    ExportSection(ExportSection<'a>),
+    StartSection(StartSection),
+    ElementSection(ElementSection),
+    DataCountSection(DataCountSection),
+    /// The code section is not buffered as a whole,
+    /// its `count` function bodies follow as separate events.
+    CodeSectionStart {
+        count: u32,
+        size: u32,
+    },
+    FunctionBody(Code<'a>),
+    DataSection(DataSection<'a>),
+    /// [StreamingParser::finish] was called and every byte was consumed.
+    End,
+}
+
+#[derive(Debug)]
+enum State {
+    Header,
+    SectionHeader,
+    /// Inside the code section whose contents start at `start`.
+    FunctionBody {
+        count: u32,
+        start: usize,
+        size: u32,
+    },
+    End,
+}
+
+/// Parses a module from chunks of bytes as they arrive,
+/// reusing the section parsers of [ModuleParser].
+///
+/// The [Limits] of [StreamingParser::limits] are checked as each event is parsed,
+/// a section over the module size fails before it is buffered.
+///
+/// Events borrow from the internal buffer, which is only compacted by [StreamingParser::push].
+///
+/// ```
+/// use wasmcat_parser::streaming::{Event, StreamingParser};
+///
+/// let mut parser = StreamingParser::new();
+/// for chunk in [&b"\0as"[..], &b"m\x01\0\0\0"[..]] {
+///     parser.push(chunk);
+///     loop {
+///         match parser.next_event().unwrap() {
+///             Event::NeedMoreData(_) => break,
+///             event => println!("{event:?}"),
+///         }
+///     }
+/// }
+/// parser.finish();
+/// assert!(matches!(parser.next_event().unwrap(), Event::End));
+/// ```
+#[derive(Debug)]
+pub struct StreamingParser {
+    buf: Vec<u8>,
+    /// Position of the next unconsumed byte in `buf`.
+    pos: usize,
+    /// Number of bytes drained from the front of `buf`.
+    drained: usize,
+    eof: bool,
+    state: State,
+    /// Index of the next function body, imported functions come first.
+    function: u32,
+    /// Sections parsed so far, custom sections included.
+    sections: u32,
+    limits: Limits,
+}
+
+impl Default for StreamingParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StreamingParser {
+    pub fn new() -> Self {
+        StreamingParser {
+            buf: Vec::new(),
+            pos: 0,
+            drained: 0,
+            eof: false,
+            state: State::Header,
+            function: 0,
+            sections: 0,
+            limits: Limits::default(),
+        }
+    }
+
+    /// Replace the default [Limits], e.g. to parse untrusted binaries with tighter bounds.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Appends a chunk of input, dropping the bytes already consumed.
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.buf.drain(..self.pos);
+        self.drained += self.pos;
+        self.pos = 0;
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// Marks the end of the input, a module cut short is then reported as an error.
+    pub fn finish(&mut self) {
+        self.eof = true;
+    }
+
+    /// Offset in the whole input of the next byte to be parsed.
+    pub fn offset(&self) -> usize {
+        self.drained + self.pos
+    }
+
+    pub fn next_event(&mut self) -> Result<Event<'_>, Error> {
+        let available = self.buf.len() - self.pos;
+        limits::check(
+            "module size",
+            self.drained + self.buf.len(),
+            self.limits.module_size,
+        )
+        .map_err(|err| err.at(self.offset(), Location::Module))?;
+
+        match self.state {
+            State::Header => {
+                if available < 8 {
+                    return self.need((8 - available) as u64);
+                }
+                let version = Module::from_bytes(&self.buf[self.pos..self.pos + 8])
+                    .parse_header()
+                    .map_err(|err| err.at(self.offset(), Location::Module))?;
+                self.pos += 8;
+                self.state = State::SectionHeader;
+                Ok(Event::Header { version })
+            }
+            State::SectionHeader => {
+                if available == 0 {
+                    if !self.eof {
+                        return Ok(Event::NeedMoreData(1));
+                    }
+                    self.state = State::End;
+                    return Ok(Event::End);
+                }
+
+                let bytes = &self.buf[self.pos..];
+                let Some(size_len) = leb128_len(&bytes[1..]) else {
+                    return self.need(1);
+                };
+                let mut decoder = Decoder::new_with_offset(bytes, self.offset());
+                let (id, size) = decoder
+                    .read_u8()
+                    .and_then(|id| Ok((SectionId::try_from(id)?, decoder.read_var_u32()?)))
+                    .map_err(|err| err.at(self.offset(), Location::Module))?;
+                let header_len = 1 + size_len;
+                let location = Location::Section(id);
+                limits::check(
+                    "module size",
+                    self.offset() + header_len + size as usize,
+                    self.limits.module_size,
+                )
+                .and_then(|()| {
+                    limits::check("section count", self.sections + 1, self.limits.sections)
+                })
+                .map_err(|err| err.at(self.offset(), location))?;
+
+                if id == SectionId::Code {
+                    let Some(count_len) = leb128_len(&bytes[header_len..]) else {
+                        return self.need(1);
+                    };
+                    let count = decoder
+                        .read_var_u32()
+                        .map_err(|err| err.at(decoder.offset(), location))?;
+                    if count_len > size as usize {
+                        return Err(Error::SectionOutOfBounds(id as u8, size, count_len as u32)
+                            .at(decoder.offset(), location));
+                    }
+                    limits::check(
+                        "function count",
+                        u64::from(self.function) + u64::from(count),
+                        self.limits.functions,
+                    )
+                    .map_err(|err| err.at(decoder.offset(), location))?;
+                    self.sections += 1;
+                    self.pos += header_len;
+                    self.state = State::FunctionBody {
+                        count,
+                        start: self.offset(),
+                        size,
+                    };
+                    self.pos += count_len;
+                    return Ok(Event::CodeSectionStart { count, size });
+                }
+
+                if available < header_len + size as usize {
+                    return self.need((header_len + size as usize - available) as u64);
+                }
+                let start = self.pos + header_len;
+                let end = start + size as usize;
+                let offset = self.drained + start;
+                self.limits
+                    .check_section(id, &Decoder::new(&self.buf[start..end]))
+                    .map_err(|err| err.at(offset, location))?;
+                self.sections += 1;
+                self.pos = end;
+                let event = parse_section(id, size, &self.buf[start..end], offset)?;
+                self.check_event(&event)
+                    .map_err(|err| err.at(offset, location))?;
+                if let Event::ImportSection(imports) = &event {
+                    self.function += imported_functions(imports);
+                }
+                Ok(event)
+            }
+            State::FunctionBody {
+                count: 0,
+                start,
+                size,
+            } => {
+                let consumed = (self.offset() - start) as u32;
+                if consumed != size {
+                    return Err(
+                        Error::SectionOutOfBounds(SectionId::Code as u8, size, consumed)
+                            .at(self.offset(), Location::Section(SectionId::Code)),
+                    );
+                }
+                self.state = State::SectionHeader;
+                self.next_event()
+            }
+            State::FunctionBody { count, start, size } => {
+                let bytes = &self.buf[self.pos..];
+                let Some(size_len) = leb128_len(bytes) else {
+                    return self.need(1);
+                };
+                let location = Location::Function(self.function);
+                let body_size = Decoder::new(bytes)
+                    .read_var_u32()
+                    .map_err(|err| err.at(self.offset(), location))?;
+                let len = size_len + body_size as usize;
+                let consumed = self.offset() + len - start;
+                if consumed > size as usize {
+                    return Err(Error::SectionOutOfBounds(
+                        SectionId::Code as u8,
+                        size,
+                        consumed as u32,
+                    )
+                    .at(self.offset(), Location::Section(SectionId::Code)));
+                }
+                if available < len {
+                    return self.need((len - available) as u64);
+                }
+
+                let start_pos = self.pos;
+                self.pos += len;
+                self.state = State::FunctionBody {
+                    count: count - 1,
+                    start,
+                    size,
+                };
+                let index = self.function;
+                self.function += 1;
+                let mut decoder = Decoder::new_with_offset(
+                    &self.buf[start_pos..self.pos],
+                    self.drained + start_pos,
+                );
+                let mut code = ModuleParser::parse_code(&mut decoder)
+                    .map_err(|err| err.at(decoder.offset(), location))?;
+                code.index = index;
+                self.limits.check_code(&code)?;
+                Ok(Event::FunctionBody(code))
+            }
+            State::End => Ok(Event::End),
+        }
+    }
+
+    /// The checks [Limits] makes once a section is parsed, function bodies are checked one by one.
+    fn check_event(&self, event: &Event) -> Result<(), Error> {
+        let functions = match event {
+            Event::ImportSection(imports) => imported_functions(imports),
+            Event::FunctionSection(section) => section.0.len() as u32,
+            Event::ElementSection(section) => return self.limits.check_elements(section),
+            Event::DataSection(section) => return self.limits.check_data(section),
+            _ => return Ok(()),
+        };
+        limits::check(
+            "function count",
+            u64::from(self.function) + u64::from(functions),
+            self.limits.functions,
+        )
+    }
+
+    fn need(&self, hint: u64) -> Result<Event<'static>, Error> {
+        if self.eof {
+            Err(Error::UnexpectedEof(hint).at(self.drained + self.buf.len(), Location::Module))
+        } else {
+            Ok(Event::NeedMoreData(hint))
+        }
+    }
+}
+
+/// Length of the LEB128 number at the start of `bytes`, `None` if it is not complete yet.
+fn leb128_len(bytes: &[u8]) -> Option<usize> {
+    match bytes.iter().take(5).position(|byte| byte & 0x80 == 0) {
+        Some(idx) => Some(idx + 1),
+        None if bytes.len() >= 5 => Some(5),
+        None => None,
+    }
+}
+
+fn parse_section(id: SectionId, size: u32, data: &[u8], offset: usize) -> Result<Event<'_>, Error> {
+    let mut decoder = Decoder::new_with_offset(data, offset);
+    let event = parse_section_contents(id, &mut decoder)
+        .map_err(|err| err.at(decoder.offset(), Location::Section(id)))?;
+
+    if !decoder.remaining_slice().is_empty() {
+        return Err(Error::SectionOutOfBounds(
+            id as u8,
+            size,
+            size - decoder.remaining_slice().len() as u32,
+        )
+        .at(decoder.offset(), Location::Section(id)));
+    }
+    Ok(event)
+}
+
+fn parse_section_contents<'a>(
+    id: SectionId,
+    decoder: &mut Decoder<'a>,
+) -> Result<Event<'a>, Error> {
+    Ok(match id {
+        SectionId::Custom => {
+            let name = decoder.read_name()?;
+            let data = decoder.read_slice(decoder.remaining_slice().len())?;
+            Event::CustomSection(CustomSection::new(name, data))
+        }
+        SectionId::Type => Event::TypeSection(ModuleParser::parse_type_section(decoder)?),
+        SectionId::Import => Event::ImportSection(ModuleParser::parse_import_section(decoder)?),
+        SectionId::Function => {
+            Event::FunctionSection(ModuleParser::parse_function_section(decoder)?)
+        }
+        SectionId::Table => Event::TableSection(ModuleParser::parse_table_section(decoder)?),
+        SectionId::Memory => Event::MemorySection(ModuleParser::parse_memory_section(decoder)?),
+        SectionId::Global => Event::GlobalSection(ModuleParser::parse_global_section(decoder)?),
+        SectionId::Export => Event::ExportSection(ModuleParser::parse_export_section(decoder)?),
+        SectionId::Start => Event::StartSection(ModuleParser::parse_start_section(decoder)?),
+        SectionId::Element => Event::ElementSection(ModuleParser::parse_element_section(decoder)?),
+        SectionId::Data => Event::DataSection(ModuleParser::parse_data_section(decoder)?),
+        SectionId::DataCount => {
+            Event::DataCountSection(ModuleParser::parse_data_count_section(decoder)?)
+        }
+        SectionId::Tag => Event::TagSection(ModuleParser::parse_tag_section(decoder)?),
+        SectionId::Code => unreachable!("the code section is streamed body by body"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Event, StreamingParser};
+    use crate::error::{Error, Location};
+    use crate::limits::Limits;
+    use crate::module::Module;
+    use crate::section::SectionId;
+
+    fn stream(data: &[u8], chunk_size: usize) -> (usize, Vec<String>) {
+        let mut parser = StreamingParser::new();
+        let mut bodies = 0;
+        let mut exports = Vec::new();
+
+        let mut chunks = data.chunks(chunk_size);
+        loop {
+            match parser.next_event().unwrap() {
+                Event::NeedMoreData(hint) => {
+                    assert!(hint > 0);
+                    match chunks.next() {
+                        Some(chunk) => parser.push(chunk),
+                        None => parser.finish(),
+                    }
+                }
+                Event::FunctionBody(code) => {
+                    code.expr().unwrap();
+                    bodies += 1;
+                }
+                Event::ExportSection(section) => {
+                    exports.extend(section.0.iter().map(|export| export.name.to_string()));
+                }
+                Event::End => break,
+                _ => {}
+            }
+        }
+        assert_eq!(parser.offset(), data.len());
+        (bodies, exports)
+    }
+
+    #[test]
+    fn test_streaming_parse() {
+        let data = include_bytes!("../tests/pulldown-cmark.wasm");
+        let module = Module::from_bytes(data).parse().unwrap();
+        let exports = module
+            .export_section
+            .0
+            .iter()
+            .map(|export| export.name.to_string())
+            .collect::<Vec<_>>();
+
+        for chunk_size in [3, 4096, data.len()] {
+            let (bodies, streamed) = stream(data, chunk_size);
+            assert_eq!(bodies, module.code_section.0.len());
+            assert_eq!(streamed, exports);
+        }
+    }
+
+    #[test]
+    fn test_streaming_need_more_data() {
+        let data = wat::parse_str(r#"(module (func (export "f")))"#).unwrap();
+        let mut parser = StreamingParser::new();
+
+        parser.push(&data[..5]);
+        assert!(matches!(parser.next_event(), Ok(Event::NeedMoreData(3))));
+        parser.push(&data[5..12]);
+        assert!(matches!(
+            parser.next_event(),
+            Ok(Event::Header { version: 1 })
+        ));
+        let Ok(Event::NeedMoreData(hint)) = parser.next_event() else {
+            panic!("expect the type section to be incomplete");
+        };
+        assert_eq!(hint, 2);
+
+        parser.finish();
+        let err = parser.next_event().unwrap_err();
+        assert!(matches!(err.kind(), Error::UnexpectedEof(2)));
+        assert_eq!(err.offset(), Some(12));
+    }
+
+    #[test]
+    fn test_streaming_limits() {
+        let data = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (func (local i32 i32 i32)
+                    (block (loop (block (br 2))))
+                    (block))
+                (func)
+                (data (i32.const 0) "abcd"))
+            "#,
+        )
+        .unwrap();
+        let parse = |limits| {
+            let mut parser = StreamingParser::new().limits(limits);
+            parser.push(&data);
+            parser.finish();
+            loop {
+                if let Event::End = parser.next_event()? {
+                    return Ok(());
+                }
+            }
+        };
+        parse(Limits {
+            nesting_depth: 3,
+            ..Limits::default()
+        })
+        .unwrap();
+
+        let cases = [
+            (
+                Limits {
+                    module_size: 16,
+                    ..Limits::default()
+                },
+                "module size",
+            ),
+            (
+                Limits {
+                    sections: 2,
+                    ..Limits::default()
+                },
+                "section count",
+            ),
+            (
+                Limits {
+                    functions: 1,
+                    ..Limits::default()
+                },
+                "function count",
+            ),
+            (
+                Limits {
+                    locals: 2,
+                    ..Limits::default()
+                },
+                "locals count",
+            ),
+            (
+                Limits {
+                    nesting_depth: 2,
+                    ..Limits::default()
+                },
+                "nesting depth",
+            ),
+            (
+                Limits {
+                    segment_size: 3,
+                    ..Limits::default()
+                },
+                "data segment size",
+            ),
+        ];
+        for (limits, what) in cases {
+            let err: Error = parse(limits).unwrap_err();
+            assert!(
+                matches!(err.kind(), Error::LimitExceeded(name, _) if *name == what),
+                "{what}: {err}"
+            );
+        }
+
+        // A section larger than the module size fails before it is buffered.
+        let mut parser = StreamingParser::new().limits(Limits {
+            module_size: 1024,
+            ..Limits::default()
+        });
+        parser.push(b"\0asm\x01\0\0\0\x00\x80\x10");
+        assert!(matches!(parser.next_event(), Ok(Event::Header { .. })));
+        let err = parser.next_event().unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            Error::LimitExceeded("module size", 1024)
+        ));
+        assert_eq!(err.location(), Some(Location::Section(SectionId::Custom)));
+    }
+}
@@ -0,0 +1,107 @@
+//! The `target_features` custom section.
+//!
+//! https://github.com/WebAssembly/tool-conventions/blob/main/Linking.md#target-features-section
+
+use smol_str::SmolStr;
+
+use crate::decode::Decoder;
+use crate::encode::Encoder;
+use crate::error::Error;
+use crate::section::CustomSection;
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum FeaturePrefix {
+    /// `+`: the feature is used by the module.
+    Used = 0x2b,
+    /// `-`: the feature must not be used by any module it is linked with.
+    Disallowed = 0x2d,
+    /// `=`: the feature is required by every module it is linked with.
+    Required = 0x3d,
+}
+
+impl TryFrom<u8> for FeaturePrefix {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x2b => Ok(FeaturePrefix::Used),
+            0x2d => Ok(FeaturePrefix::Disallowed),
+            0x3d => Ok(FeaturePrefix::Required),
+            _ => Err(Error::InvalidType("FeaturePrefix", value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetFeature {
+    pub prefix: FeaturePrefix,
+    pub name: SmolStr,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TargetFeatures(pub Vec<TargetFeature>);
+
+impl TargetFeatures {
+    pub const SECTION_NAME: &'static str = "target_features";
+
+    pub fn parse(data: &[u8]) -> Result<TargetFeatures, Error> {
+        let mut decoder = Decoder::new(data);
+        let features = decoder.read_vec(|decoder| {
+            let prefix = FeaturePrefix::try_from(decoder.read_u8()?)?;
+            let name = decoder.read_str()?;
+            Ok(TargetFeature { prefix, name })
+        })?;
+
+        if !decoder.remaining_slice().is_empty() {
+            return Err(Error::InvalidCustomSection(
+                Self::SECTION_NAME,
+                "trailing bytes",
+            ));
+        }
+
+        Ok(TargetFeatures(features))
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut encoder = Encoder::new();
+        encoder.write_vec(&self.0, |encoder, feature| {
+            encoder.write_u8(feature.prefix as u8);
+            encoder.write_str(&feature.name);
+        });
+        encoder.finish()
+    }
+
+    pub fn to_custom_section(&self) -> CustomSection<'static> {
+        CustomSection::new(Self::SECTION_NAME, self.encode())
+    }
+
+    pub fn get(&self, name: &str) -> Option<FeaturePrefix> {
+        self.0
+            .iter()
+            .find(|feature| feature.name == name)
+            .map(|feature| feature.prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FeaturePrefix, TargetFeatures};
+
+    #[test]
+    fn test_target_features_round_trip() {
+        let data = [
+            0x03, // 3 features
+            b'+', 0x0b, b'b', b'u', b'l', b'k', b'-', b'm', b'e', b'm', b'o', b'r', b'y', //
+            b'-', 0x07, b'a', b't', b'o', b'm', b'i', b'c', b's', //
+            b'=', 0x04, b's', b'i', b'm', b'd',
+        ];
+        let features = TargetFeatures::parse(&data).unwrap();
+
+        assert_eq!(features.0.len(), 3);
+        assert_eq!(features.get("bulk-memory"), Some(FeaturePrefix::Used));
+        assert_eq!(features.get("atomics"), Some(FeaturePrefix::Disallowed));
+        assert_eq!(features.get("simd"), Some(FeaturePrefix::Required));
+        assert_eq!(features.get("gc"), None);
+        assert_eq!(features.encode(), data);
+    }
+}
@@ -4,6 +4,7 @@ use crate::instruction::ConstExpr;
 macro_rules! ty_enum {
     ($ty:ident { $($ele:tt = $val:tt,)* }) => {
         #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+        #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
         pub enum $ty {
             $($ele = $val,)*
         }
@@ -21,24 +22,62 @@ macro_rules! ty_enum {
     };
 }
 
-ty_enum!(RefType {
-    FuncRef = 0x70,
-    ExternRef = 0x6f,
-});
+pub(crate) use ty_enum;
 
-impl From<wasmparser::RefType> for RefType {
-    fn from(value: wasmparser::RefType) -> Self {
-        if value.is_func_ref() {
-            RefType::FuncRef
-        } else if value.is_extern_ref() {
-            RefType::ExternRef
-        } else {
-            panic!("wasm validation error")
+/// A reference type, `ref null? heaptype`.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RefType {
+    pub nullable: bool,
+    pub heap_type: HeapType,
+}
+
+impl RefType {
+    pub const FUNCREF: RefType = RefType::nullable(HeapType::Func);
+    pub const EXTERNREF: RefType = RefType::nullable(HeapType::Extern);
+    pub const ANYREF: RefType = RefType::nullable(HeapType::Any);
+    pub const EQREF: RefType = RefType::nullable(HeapType::Eq);
+    pub const I31REF: RefType = RefType::nullable(HeapType::I31);
+    pub const STRUCTREF: RefType = RefType::nullable(HeapType::Struct);
+    pub const ARRAYREF: RefType = RefType::nullable(HeapType::Array);
+    pub const EXNREF: RefType = RefType::nullable(HeapType::Exn);
+    pub const NULLREF: RefType = RefType::nullable(HeapType::None);
+    pub const NULLFUNCREF: RefType = RefType::nullable(HeapType::NoFunc);
+    pub const NULLEXTERNREF: RefType = RefType::nullable(HeapType::NoExtern);
+    pub const NULLEXNREF: RefType = RefType::nullable(HeapType::NoExn);
+
+    pub const fn new(nullable: bool, heap_type: HeapType) -> Self {
+        RefType {
+            nullable,
+            heap_type,
         }
     }
+
+    pub const fn nullable(heap_type: HeapType) -> Self {
+        RefType::new(true, heap_type)
+    }
+
+    pub const fn non_null(heap_type: HeapType) -> Self {
+        RefType::new(false, heap_type)
+    }
+}
+
+/// Decode the single byte abbreviations (`funcref`, `anyref`, ...),
+/// `ref null ht` (0x63) and `ref ht` (0x64) must be read by [Decoder::read_reftype].
+///
+/// [Decoder::read_reftype]: crate::decode::Decoder::read_reftype
+impl TryFrom<u8> for RefType {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        HeapType::from_abstract(value)
+            .map(RefType::nullable)
+            .ok_or(Error::InvalidType("RefType", value))
+    }
 }
 
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ValType {
     I32,
     I64,
@@ -58,88 +97,203 @@ impl TryFrom<u8> for ValType {
             0x7d => ValType::F32,
             0x7c => ValType::F64,
             0x7b => ValType::V128,
-            0x70 => ValType::Ref(RefType::FuncRef),
-            0x6f => ValType::Ref(RefType::ExternRef),
-            _ => return Err(Error::InvalidType("ValType", value)),
+            _ => ValType::Ref(
+                RefType::try_from(value).map_err(|_| Error::InvalidType("ValType", value))?,
+            ),
         })
     }
 }
 
-impl From<wasmparser::ValType> for ValType {
-    fn from(value: wasmparser::ValType) -> Self {
-        match value {
-            wasmparser::ValType::I32 => ValType::I32,
-            wasmparser::ValType::I64 => ValType::I64,
-            wasmparser::ValType::F32 => ValType::F32,
-            wasmparser::ValType::F64 => ValType::F64,
-            wasmparser::ValType::V128 => ValType::V128,
-            wasmparser::ValType::Ref(rt) => ValType::Ref(rt.into()),
-        }
-    }
-}
-
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ExternType {
     Func,
     Table,
     Mem,
     Global,
+    Tag,
 }
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ResultType(pub crate::SVec<ValType>);
 
+/// Bounds of a table or memory, `u64` to fit 64-bit memories.
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Limit {
-    pub min: u32,
-    pub max: Option<u32>,
+    pub min: u64,
+    pub max: Option<u64>,
+}
+
+// [memory64 proposal]: https://github.com/WebAssembly/memory64
+/// The type of addresses into a memory or table.
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IndexType {
+    #[default]
+    I32,
+    I64,
+}
+
+impl From<IndexType> for ValType {
+    fn from(ty: IndexType) -> Self {
+        match ty {
+            IndexType::I32 => ValType::I32,
+            IndexType::I64 => ValType::I64,
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TableType {
     pub element: RefType,
     pub limit: Limit,
+    pub index_type: IndexType,
+}
+
+/// A table defined by the module, `init` fills its elements.
+///
+/// Without `init`, the elements are null.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Table {
+    pub ty: TableType,
+    pub init: Option<ConstExpr>,
 }
 
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GlobalType {
     pub ty: ValType,
     pub mutable: bool,
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Global {
     pub ty: GlobalType,
     pub expr: ConstExpr,
 }
 
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
-pub struct MemoryType(pub Limit);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryType {
+    pub limit: Limit,
+    /// Shared between threads, see the [threads proposal](https://github.com/WebAssembly/threads).
+    pub shared: bool,
+    pub index_type: IndexType,
+}
+
+// [exception handling proposal]: https://github.com/WebAssembly/exception-handling
+ty_enum!(TagAttribute {
+    Exception = 0x00,
+});
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TagType {
+    pub attribute: TagAttribute,
+    /// Index of the function type describing the tag's parameters.
+    pub type_idx: u32,
+}
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FuncType {
     pub params: ResultType,
     pub results: ResultType,
 }
 
+// [gc proposal]: https://github.com/WebAssembly/gc
+/// A recursion group, introduced by `rec` (0x4e).
+/// All types inside the group may refer to each other.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecGroup(pub Vec<SubType>);
+
+/// A type definition with its declared supertypes.
+///
+/// A bare composite type in the binary (e.g. the MVP `0x60` func type)
+/// is a shorthand for a final sub type without supertypes.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubType {
+    pub is_final: bool,
+    pub supertypes: crate::SVec<u32>,
+    pub composite: CompositeType,
+}
+
+impl From<CompositeType> for SubType {
+    fn from(composite: CompositeType) -> Self {
+        SubType {
+            is_final: true,
+            supertypes: crate::SVec::new(),
+            composite,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CompositeType {
+    Func(FuncType),
+    Struct(StructType),
+    Array(ArrayType),
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructType {
+    pub fields: crate::SVec<FieldType>,
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArrayType(pub FieldType);
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldType {
+    pub ty: StorageType,
+    pub mutable: bool,
+}
+
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StorageType {
+    /// Packed 8-bit integer, only allowed as a field type.
+    I8,
+    /// Packed 16-bit integer, only allowed as a field type.
+    I16,
+    Val(ValType),
+}
+
+impl TryFrom<u8> for StorageType {
+    type Error = Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0x78 => StorageType::I8,
+            0x77 => StorageType::I16,
+            _ => StorageType::Val(
+                ValType::try_from(value).map_err(|_| Error::InvalidType("StorageType", value))?,
+            ),
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum BlockType {
     Empty,
     Type(ValType),
     FuncType(u32),
 }
 
-impl From<wasmparser::BlockType> for BlockType {
-    fn from(value: wasmparser::BlockType) -> Self {
-        match value {
-            wasmparser::BlockType::Empty => BlockType::Empty,
-            wasmparser::BlockType::Type(v) => BlockType::Type(v.into()),
-            wasmparser::BlockType::FuncType(idx) => BlockType::FuncType(idx),
-        }
-    }
-}
-
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum HeapType {
     Func,
     Extern,
@@ -179,27 +333,44 @@ pub enum HeapType {
     Concrete(u32),
 }
 
+impl HeapType {
+    /// Decode an abstract heap type from its single byte encoding,
+    /// which is also the encoding of the nullable reference type abbreviation.
+    pub fn from_abstract(byte: u8) -> Option<Self> {
+        // https://github.com/WebAssembly/gc/blob/main/proposals/gc/MVP.md#heap-types-2
+        Some(match byte {
+            0x74 => HeapType::NoExn,
+            0x73 => HeapType::NoFunc,
+            0x72 => HeapType::NoExtern,
+            0x71 => HeapType::None,
+            0x70 => HeapType::Func,
+            0x6f => HeapType::Extern,
+            0x6e => HeapType::Any,
+            0x6d => HeapType::Eq,
+            0x6c => HeapType::I31,
+            0x6b => HeapType::Struct,
+            0x6a => HeapType::Array,
+            0x69 => HeapType::Exn,
+            _ => return None,
+        })
+    }
+}
+
 impl TryFrom<i64> for HeapType {
     type Error = Error;
 
     fn try_from(value: i64) -> Result<Self, Self::Error> {
         if value >= 0 {
-            Ok(HeapType::Concrete(value as u32))
+            u32::try_from(value)
+                .map(HeapType::Concrete)
+                .map_err(|_| Error::Other("heap type index out of range"))
         } else {
-            // https://github.com/WebAssembly/gc/blob/main/proposals/gc/MVP.md#heap-types-2
-            match value as i8 {
-                -0x0d => Ok(HeapType::NoFunc),
-                -0x0e => Ok(HeapType::NoExtern),
-                -0x0f => Ok(HeapType::None),
-                -0x10 => Ok(HeapType::Func),
-                -0x11 => Ok(HeapType::Exn),
-                -0x12 => Ok(HeapType::Any),
-                -0x13 => Ok(HeapType::Eq),
-                -0x14 => Ok(HeapType::I31),
-                -0x15 => Ok(HeapType::Struct),
-                -0x16 => Ok(HeapType::Array),
-                op => Err(Error::InvalidType("HeapType", (op + 0x7f) as u8 + 0x01)),
+            // a negative s33 in one byte is the abstract heap type byte minus 0x80
+            let byte = (value & 0x7f) as u8;
+            if value < -0x40 {
+                return Err(Error::InvalidType("HeapType", byte));
             }
+            HeapType::from_abstract(byte).ok_or(Error::InvalidType("HeapType", byte))
         }
     }
 }
@@ -0,0 +1,2453 @@
+//! Validation of a parsed [Module] against the
+//! [spec](https://webassembly.github.io/spec/core/valid/index.html).
+//!
+//! Module level errors are returned as is, errors in a function body are located
+//! at the offending instruction, see [Error::offset] and [Error::location].
+
+use std::collections::{HashMap, HashSet};
+
+use crate::error::{Error, Location};
+use crate::instruction::{Catch, ConstExpr, Instruction, MemArg};
+use crate::module::Module;
+use crate::parser::imported_functions;
+use crate::section::{
+    Code, DataKind, ElementKind, ExportKind, ImportKind, TypeSection, TypeSectionTy,
+};
+use crate::types::{
+    ArrayType, BlockType, CompositeType, FieldType, FuncType, GlobalType, HeapType, IndexType,
+    Limit, MemoryType, RefType, ResultType, StorageType, StructType, SubType, TableType, ValType,
+};
+
+/// Maximum number of 64KiB pages of a memory.
+const MAX_PAGES: u64 = 1 << 16;
+
+/// Maximum number of 64KiB pages of a 64-bit memory.
+const MAX_PAGES_64: u64 = 1 << 48;
+
+/// Marks a type index relative to its own rec group in the canonical form of the group,
+/// type indices stay below it as the limits bound the module size.
+const REC_INDEX: u32 = 1 << 31;
+
+pub fn validate(module: &Module) -> Result<(), Error> {
+    validate_module(module, false)
+}
+
+/// [validate], and also reject imports repeating a module and field name.
+/// The spec allows them, but a linker resolving imports by name cannot tell them apart.
+pub fn validate_strict(module: &Module) -> Result<(), Error> {
+    validate_module(module, true)
+}
+
+fn validate_module(module: &Module, strict: bool) -> Result<(), Error> {
+    let mut ctx = Context::new(module);
+    ctx.canonicalize(&module.type_section)?;
+
+    for (idx, ty) in ctx.types.iter().enumerate() {
+        ctx.sub_type(idx as u32, ty)?;
+    }
+
+    let mut imports = HashSet::new();
+    for import in &module.import_section.0 {
+        if strict && !imports.insert((&import.module_name, &import.field_name)) {
+            return Err(Error::DuplicateImport(
+                import.module_name.to_string(),
+                import.field_name.to_string(),
+            ));
+        }
+        match &import.kind {
+            ImportKind::Func(ty) => {
+                ctx.func_type(*ty)?;
+            }
+            ImportKind::Table(ty) => ctx.table_type(ty)?,
+            ImportKind::Memory(ty) => ctx.memory_type(ty)?,
+            ImportKind::Global(ty) => ctx.val_type(ty.ty)?,
+            ImportKind::Tag(ty) => ctx.tag_type(ty.type_idx)?,
+        }
+    }
+    for ty in &module.func_section.0 {
+        ctx.func_type(*ty)?;
+    }
+    for (table, idx) in module.table_section.0.iter().zip(ctx.imported_tables..) {
+        ctx.table_type(&table.ty)?;
+        match &table.init {
+            // Only imported globals are defined before tables.
+            Some(init) => {
+                ctx.const_expr(init, ValType::Ref(table.ty.element), ctx.imported_globals)?
+            }
+            None if !table.ty.element.nullable => return Err(Error::MissingTableInit(idx)),
+            None => {}
+        }
+    }
+    for ty in &module.memory_section.0 {
+        ctx.memory_type(ty)?;
+    }
+    for ty in &module.tag_section.0 {
+        ctx.tag_type(ty.type_idx)?;
+    }
+    // Globals may only read imported or previously defined globals.
+    for (idx, global) in module.global_section.0.iter().enumerate() {
+        ctx.val_type(global.ty.ty)?;
+        ctx.const_expr(&global.expr, global.ty.ty, ctx.imported_globals + idx)?;
+    }
+
+    let mut exports = HashSet::new();
+    for export in &module.export_section.0 {
+        if !exports.insert(&export.name) {
+            return Err(Error::DuplicateExport(export.name.to_string()));
+        }
+        match export.kind {
+            ExportKind::Func(idx) => {
+                ctx.func(idx)?;
+            }
+            ExportKind::Table(idx) => {
+                ctx.table(idx)?;
+            }
+            ExportKind::Mem(idx) => {
+                ctx.memory(idx)?;
+            }
+            ExportKind::Global(idx) => {
+                ctx.global(idx)?;
+            }
+            ExportKind::Tag(idx) => {
+                ctx.tag(idx)?;
+            }
+        }
+    }
+
+    if let Some(start) = module.start_section.0 {
+        let ty = ctx.func(start)?;
+        if !ty.params.0.is_empty() || !ty.results.0.is_empty() {
+            return Err(Error::InvalidStartFunction(start));
+        }
+    }
+
+    for elem in &module.element_section.0 {
+        ctx.ref_type(elem.ty)?;
+        for expr in &elem.init {
+            ctx.const_expr(expr, ValType::Ref(elem.ty), ctx.globals.len())?;
+        }
+        if let ElementKind::Active { table, offset } = &elem.kind {
+            let table = ctx.table(table.unwrap_or(0))?;
+            if !ctx.ref_matches(elem.ty, table.element) {
+                return Err(Error::TypeMismatch(
+                    ValType::Ref(table.element),
+                    ValType::Ref(elem.ty),
+                ));
+            }
+            ctx.const_expr(offset, table.index_type.into(), ctx.globals.len())?;
+        }
+    }
+
+    for data in &module.data_section.0 {
+        if let DataKind::Active { memory, offset } = &data.kind {
+            let addr = ctx.memory(*memory)?;
+            ctx.const_expr(offset, addr, ctx.globals.len())?;
+        }
+    }
+    if let Some(count) = module.data_count_section.0 {
+        let data = module.data_section.0.len() as u32;
+        if count != data {
+            return Err(Error::DataCountMismatch(count, data));
+        }
+    }
+
+    let funcs = module.func_section.0.len() as u32;
+    let codes = module.code_section.0.len() as u32;
+    if funcs != codes {
+        return Err(Error::FunctionCodeMismatch(funcs, codes));
+    }
+    let imported = imported_functions(&module.import_section);
+    for (code, index) in module.code_section.0.iter().zip(imported..) {
+        ctx.code(code, index)?;
+    }
+
+    Ok(())
+}
+
+/// The index spaces of a module, see `C` in the spec.
+struct Context<'m> {
+    types: Vec<&'m SubType>,
+    /// The first type equivalent to each type, see [Context::canonicalize].
+    canonical: Vec<u32>,
+    /// Type index of every function, imported ones first.
+    funcs: Vec<u32>,
+    tables: Vec<TableType>,
+    imported_tables: u32,
+    memories: Vec<MemoryType>,
+    globals: Vec<GlobalType>,
+    imported_globals: usize,
+    /// Type index of every tag.
+    tags: Vec<u32>,
+    elems: Vec<RefType>,
+    data_count: Option<u32>,
+    /// Functions that `ref.func` may refer to in function bodies.
+    refs: HashSet<u32>,
+}
+
+impl<'m> Context<'m> {
+    fn new(module: &'m Module) -> Self {
+        let mut ctx = Context {
+            types: module.type_section.sub_types().collect(),
+            canonical: Vec::new(),
+            funcs: Vec::new(),
+            tables: Vec::new(),
+            imported_tables: 0,
+            memories: Vec::new(),
+            globals: Vec::new(),
+            imported_globals: 0,
+            tags: Vec::new(),
+            elems: module
+                .element_section
+                .0
+                .iter()
+                .map(|elem| elem.ty)
+                .collect(),
+            data_count: module.data_count_section.0,
+            refs: HashSet::new(),
+        };
+        for import in &module.import_section.0 {
+            match &import.kind {
+                ImportKind::Func(ty) => ctx.funcs.push(*ty),
+                ImportKind::Table(ty) => ctx.tables.push(*ty),
+                ImportKind::Memory(ty) => ctx.memories.push(*ty),
+                ImportKind::Global(ty) => ctx.globals.push(*ty),
+                ImportKind::Tag(ty) => ctx.tags.push(ty.type_idx),
+            }
+        }
+        ctx.imported_globals = ctx.globals.len();
+        ctx.funcs.extend(module.func_section.0.iter().copied());
+        ctx.imported_tables = ctx.tables.len() as u32;
+        ctx.tables
+            .extend(module.table_section.0.iter().map(|table| table.ty));
+        ctx.memories.extend(module.memory_section.0.iter().copied());
+        ctx.globals
+            .extend(module.global_section.0.iter().map(|global| global.ty));
+        ctx.tags
+            .extend(module.tag_section.0.iter().map(|ty| ty.type_idx));
+
+        let exprs = module
+            .global_section
+            .0
+            .iter()
+            .map(|global| &global.expr)
+            .chain(module.element_section.0.iter().flat_map(|elem| &elem.init));
+        for expr in exprs {
+            for instr in &expr.0 {
+                if let Instruction::RefFunc(idx) = instr {
+                    ctx.refs.insert(*idx);
+                }
+            }
+        }
+        for export in &module.export_section.0 {
+            if let ExportKind::Func(idx) = export.kind {
+                ctx.refs.insert(idx);
+            }
+        }
+        ctx
+    }
+
+    /// Types are equivalent when their rec groups are defined alike, with references inside
+    /// the group taken relative to it and references to earlier types by their canonical index.
+    fn canonicalize(&mut self, types: &TypeSection) -> Result<(), Error> {
+        let mut groups = HashMap::new();
+        let mut start = 0u32;
+        for group in &types.0 {
+            let group = match group {
+                TypeSectionTy::Sub(sub) => std::slice::from_ref(sub),
+                TypeSectionTy::Rec(group) => group.0.as_slice(),
+            };
+            let end = start + group.len() as u32;
+            let mut canonical_index = |idx: u32| match idx {
+                _ if idx < start => Ok(self.canonical[idx as usize]),
+                _ if idx < end => Ok(REC_INDEX | (idx - start)),
+                _ => Err(Error::UnknownIndex("type", idx)),
+            };
+            let key = group
+                .iter()
+                .map(|ty| map_sub_type(ty, &mut canonical_index))
+                .collect::<Result<Vec<_>, _>>()?;
+            let first = *groups.entry(key).or_insert(start);
+            self.canonical.extend(first..first + group.len() as u32);
+            start = end;
+        }
+        Ok(())
+    }
+
+    fn get_type(&self, idx: u32) -> Result<&'m SubType, Error> {
+        self.types
+            .get(idx as usize)
+            .copied()
+            .ok_or(Error::UnknownIndex("type", idx))
+    }
+
+    fn func_type(&self, idx: u32) -> Result<&'m FuncType, Error> {
+        match &self.get_type(idx)?.composite {
+            CompositeType::Func(ty) => Ok(ty),
+            _ => Err(Error::UnexpectedType("func", idx)),
+        }
+    }
+
+    fn struct_field(&self, idx: u32, field: u32) -> Result<FieldType, Error> {
+        match &self.get_type(idx)?.composite {
+            CompositeType::Struct(ty) => ty
+                .fields
+                .get(field as usize)
+                .copied()
+                .ok_or(Error::UnknownIndex("field", field)),
+            _ => Err(Error::UnexpectedType("struct", idx)),
+        }
+    }
+
+    fn array_field(&self, idx: u32) -> Result<FieldType, Error> {
+        match &self.get_type(idx)?.composite {
+            CompositeType::Array(ty) => Ok(ty.0),
+            _ => Err(Error::UnexpectedType("array", idx)),
+        }
+    }
+
+    fn func(&self, idx: u32) -> Result<&'m FuncType, Error> {
+        let ty = self
+            .funcs
+            .get(idx as usize)
+            .ok_or(Error::UnknownIndex("function", idx))?;
+        self.func_type(*ty)
+    }
+
+    fn table(&self, idx: u32) -> Result<TableType, Error> {
+        self.tables
+            .get(idx as usize)
+            .copied()
+            .ok_or(Error::UnknownIndex("table", idx))
+    }
+
+    /// Returns the address type of the memory.
+    fn memory(&self, idx: u32) -> Result<ValType, Error> {
+        self.memories
+            .get(idx as usize)
+            .map(|ty| ty.index_type.into())
+            .ok_or(Error::UnknownIndex("memory", idx))
+    }
+
+    fn global(&self, idx: u32) -> Result<GlobalType, Error> {
+        self.globals
+            .get(idx as usize)
+            .copied()
+            .ok_or(Error::UnknownIndex("global", idx))
+    }
+
+    fn tag(&self, idx: u32) -> Result<&'m FuncType, Error> {
+        let ty = self
+            .tags
+            .get(idx as usize)
+            .ok_or(Error::UnknownIndex("tag", idx))?;
+        self.func_type(*ty)
+    }
+
+    fn elem(&self, idx: u32) -> Result<RefType, Error> {
+        self.elems
+            .get(idx as usize)
+            .copied()
+            .ok_or(Error::UnknownIndex("elem segment", idx))
+    }
+
+    fn data(&self, idx: u32) -> Result<(), Error> {
+        let count = self.data_count.ok_or(Error::MissingDataCount)?;
+        if idx >= count {
+            return Err(Error::UnknownIndex("data segment", idx));
+        }
+        Ok(())
+    }
+
+    fn heap_type(&self, ty: HeapType) -> Result<(), Error> {
+        match ty {
+            HeapType::Concrete(idx) => self.get_type(idx).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
+    fn ref_type(&self, ty: RefType) -> Result<(), Error> {
+        self.heap_type(ty.heap_type)
+    }
+
+    fn val_type(&self, ty: ValType) -> Result<(), Error> {
+        match ty {
+            ValType::Ref(ty) => self.ref_type(ty),
+            _ => Ok(()),
+        }
+    }
+
+    fn storage_type(&self, ty: StorageType) -> Result<(), Error> {
+        match ty {
+            StorageType::Val(ty) => self.val_type(ty),
+            _ => Ok(()),
+        }
+    }
+
+    fn table_type(&self, ty: &TableType) -> Result<(), Error> {
+        self.ref_type(ty.element)?;
+        match ty.index_type {
+            IndexType::I32 => limit(ty.limit, u32::MAX as u64),
+            IndexType::I64 => limit(ty.limit, u64::MAX),
+        }
+    }
+
+    fn memory_type(&self, ty: &MemoryType) -> Result<(), Error> {
+        if ty.shared && ty.limit.max.is_none() {
+            return Err(Error::SharedMemoryWithoutMax);
+        }
+        match ty.index_type {
+            IndexType::I32 => limit(ty.limit, MAX_PAGES),
+            IndexType::I64 => limit(ty.limit, MAX_PAGES_64),
+        }
+    }
+
+    fn tag_type(&self, idx: u32) -> Result<(), Error> {
+        if !self.func_type(idx)?.results.0.is_empty() {
+            return Err(Error::UnexpectedType("tag", idx));
+        }
+        Ok(())
+    }
+
+    /// Supertypes must be defined before the type itself, must not be final,
+    /// and must be matched by its definition.
+    fn sub_type(&self, idx: u32, ty: &SubType) -> Result<(), Error> {
+        match &ty.composite {
+            CompositeType::Func(func) => {
+                for ty in func.params.0.iter().chain(&func.results.0) {
+                    self.val_type(*ty)?;
+                }
+            }
+            CompositeType::Struct(st) => {
+                for field in &st.fields {
+                    self.storage_type(field.ty)?;
+                }
+            }
+            CompositeType::Array(array) => self.storage_type(array.0.ty)?,
+        }
+        if ty.supertypes.len() > 1 {
+            return Err(Error::InvalidSubtype(idx, ty.supertypes[1]));
+        }
+        if let Some(&sup) = ty.supertypes.first() {
+            if sup >= idx {
+                return Err(Error::UnknownIndex("type", sup));
+            }
+            let sup_ty = self.get_type(sup)?;
+            if sup_ty.is_final || !self.composite_matches(&ty.composite, &sup_ty.composite) {
+                return Err(Error::InvalidSubtype(idx, sup));
+            }
+        }
+        Ok(())
+    }
+
+    fn composite_matches(&self, sub: &CompositeType, sup: &CompositeType) -> bool {
+        match (sub, sup) {
+            (CompositeType::Func(sub), CompositeType::Func(sup)) => {
+                sub.params.0.len() == sup.params.0.len()
+                    && sub.results.0.len() == sup.results.0.len()
+                    && sup
+                        .params
+                        .0
+                        .iter()
+                        .zip(&sub.params.0)
+                        .all(|(a, b)| self.matches(*a, *b))
+                    && sub
+                        .results
+                        .0
+                        .iter()
+                        .zip(&sup.results.0)
+                        .all(|(a, b)| self.matches(*a, *b))
+            }
+            (CompositeType::Struct(sub), CompositeType::Struct(sup)) => {
+                sub.fields.len() >= sup.fields.len()
+                    && sub
+                        .fields
+                        .iter()
+                        .zip(&sup.fields)
+                        .all(|(a, b)| self.field_matches(*a, *b))
+            }
+            (CompositeType::Array(sub), CompositeType::Array(sup)) => {
+                self.field_matches(sub.0, sup.0)
+            }
+            _ => false,
+        }
+    }
+
+    /// Mutable fields are invariant, immutable ones covariant.
+    fn field_matches(&self, sub: FieldType, sup: FieldType) -> bool {
+        sub.mutable == sup.mutable
+            && self.storage_matches(sub.ty, sup.ty)
+            && (!sub.mutable || self.storage_matches(sup.ty, sub.ty))
+    }
+
+    fn storage_matches(&self, sub: StorageType, sup: StorageType) -> bool {
+        match (sub, sup) {
+            (StorageType::Val(sub), StorageType::Val(sup)) => self.matches(sub, sup),
+            _ => sub == sup,
+        }
+    }
+
+    /// Whether `sub` is a subtype of `sup`.
+    fn matches(&self, sub: ValType, sup: ValType) -> bool {
+        match (sub, sup) {
+            (ValType::Ref(sub), ValType::Ref(sup)) => self.ref_matches(sub, sup),
+            _ => sub == sup,
+        }
+    }
+
+    fn ref_matches(&self, sub: RefType, sup: RefType) -> bool {
+        (!sub.nullable || sup.nullable) && self.heap_matches(sub.heap_type, sup.heap_type)
+    }
+
+    fn heap_matches(&self, sub: HeapType, sup: HeapType) -> bool {
+        if sub == sup {
+            return true;
+        }
+        match sub {
+            HeapType::Concrete(idx) => match sup {
+                HeapType::Concrete(sup) => self.declared_subtype(idx, sup),
+                _ => match self.types.get(idx as usize).map(|ty| &ty.composite) {
+                    Some(CompositeType::Func(_)) => sup == HeapType::Func,
+                    Some(CompositeType::Struct(_)) => {
+                        matches!(sup, HeapType::Struct | HeapType::Eq | HeapType::Any)
+                    }
+                    Some(CompositeType::Array(_)) => {
+                        matches!(sup, HeapType::Array | HeapType::Eq | HeapType::Any)
+                    }
+                    None => false,
+                },
+            },
+            // The bottom types match everything below their top type.
+            HeapType::None => self.heap_matches(sup, HeapType::Any),
+            HeapType::NoFunc => self.heap_matches(sup, HeapType::Func),
+            HeapType::NoExtern => sup == HeapType::Extern,
+            HeapType::NoExn => sup == HeapType::Exn,
+            HeapType::I31 | HeapType::Struct | HeapType::Array => {
+                matches!(sup, HeapType::Eq | HeapType::Any)
+            }
+            HeapType::Eq => sup == HeapType::Any,
+            HeapType::Func | HeapType::Extern | HeapType::Any | HeapType::Exn => false,
+        }
+    }
+
+    /// Follows the declared supertype chain of `sub`, which only goes to lower indices,
+    /// until it reaches a type equivalent to `sup`.
+    fn declared_subtype(&self, mut sub: u32, sup: u32) -> bool {
+        let Some(&sup) = self.canonical.get(sup as usize) else {
+            return false;
+        };
+        loop {
+            if self.canonical.get(sub as usize) == Some(&sup) {
+                return true;
+            }
+            match self
+                .types
+                .get(sub as usize)
+                .and_then(|ty| ty.supertypes.first())
+            {
+                Some(&next) if next < sub => sub = next,
+                _ => return false,
+            }
+        }
+    }
+
+    fn const_expr(&self, expr: &ConstExpr, ty: ValType, globals: usize) -> Result<(), Error> {
+        let results = [ty].into_iter().collect();
+        let mut validator = OperatorValidator::new(self, Vec::new(), 0, results, globals, true);
+        for instr in &expr.0 {
+            validator.instruction(instr)?;
+        }
+        validator.finish()
+    }
+
+    fn code(&self, code: &Code, index: u32) -> Result<(), Error> {
+        let location = Location::Function(index);
+        let ty = self.func(index)?;
+
+        // Consecutive locals of the same type are kept as one run, see [OperatorValidator::local].
+        let mut locals = Vec::with_capacity(ty.params.0.len() + code.locals.len());
+        let mut count = 0u32;
+        for ty in &ty.params.0 {
+            count += 1;
+            locals.push((count, *ty));
+        }
+        for local in &code.locals {
+            self.val_type(local.ty)
+                .map_err(|err| err.at(code.offset, location))?;
+            count = count
+                .checked_add(local.n)
+                .ok_or_else(|| Error::Other("too many locals").at(code.offset, location))?;
+            locals.push((count, local.ty));
+        }
+
+        let expr = code.expr_with_offsets()?;
+        let offsets = expr.offsets.unwrap_or_default();
+        let mut validator = OperatorValidator::new(
+            self,
+            locals,
+            ty.params.0.len() as u32,
+            ty.results.0.clone(),
+            self.globals.len(),
+            false,
+        );
+        for (instr, offset) in expr.instrs.iter().zip(offsets) {
+            validator
+                .instruction(instr)
+                .map_err(|err| err.at(offset, location))?;
+        }
+        validator
+            .finish()
+            .map_err(|err| err.at(code.offset + code.body.len(), location))
+    }
+}
+
+/// Rewrites the type indices of `ty` with `f`.
+fn map_sub_type(
+    ty: &SubType,
+    f: &mut impl FnMut(u32) -> Result<u32, Error>,
+) -> Result<SubType, Error> {
+    let mut val_type = |ty: ValType| -> Result<ValType, Error> {
+        Ok(match ty {
+            ValType::Ref(RefType {
+                nullable,
+                heap_type: HeapType::Concrete(idx),
+            }) => ValType::Ref(RefType::new(nullable, HeapType::Concrete(f(idx)?))),
+            ty => ty,
+        })
+    };
+    let mut field = |field: FieldType| -> Result<FieldType, Error> {
+        Ok(FieldType {
+            ty: match field.ty {
+                StorageType::Val(ty) => StorageType::Val(val_type(ty)?),
+                ty => ty,
+            },
+            ..field
+        })
+    };
+    let composite = match &ty.composite {
+        CompositeType::Func(func) => {
+            let mut result_type = |types: &ResultType| -> Result<ResultType, Error> {
+                Ok(ResultType(
+                    types
+                        .0
+                        .iter()
+                        .map(|ty| val_type(*ty))
+                        .collect::<Result<_, _>>()?,
+                ))
+            };
+            CompositeType::Func(FuncType {
+                params: result_type(&func.params)?,
+                results: result_type(&func.results)?,
+            })
+        }
+        CompositeType::Struct(st) => CompositeType::Struct(StructType {
+            fields: st
+                .fields
+                .iter()
+                .map(|ty| field(*ty))
+                .collect::<Result<_, _>>()?,
+        }),
+        CompositeType::Array(array) => CompositeType::Array(ArrayType(field(array.0)?)),
+    };
+    Ok(SubType {
+        is_final: ty.is_final,
+        supertypes: ty
+            .supertypes
+            .iter()
+            .map(|idx| f(*idx))
+            .collect::<Result<_, _>>()?,
+        composite,
+    })
+}
+
+fn limit(limit: Limit, max: u64) -> Result<(), Error> {
+    if limit.min > max {
+        return Err(Error::MemoryTooLarge(limit.min));
+    }
+    match limit.max {
+        Some(m) if m > max => Err(Error::MemoryTooLarge(m)),
+        Some(m) if m < limit.min => Err(Error::InvalidLimits(limit.min, m)),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FrameKind {
+    Block,
+    Loop,
+    If,
+    Else,
+    TryTable,
+    Try,
+    Catch,
+    CatchAll,
+}
+
+struct Frame {
+    kind: FrameKind,
+    params: crate::SVec<ValType>,
+    results: crate::SVec<ValType>,
+    /// Height of the operand stack when the block was entered.
+    height: usize,
+    /// Length of the init log when the block was entered.
+    inits: usize,
+    unreachable: bool,
+}
+
+impl Frame {
+    /// The values a branch to this block takes.
+    fn label_types(&self) -> &[ValType] {
+        match self.kind {
+            FrameKind::Loop => &self.params,
+            _ => &self.results,
+        }
+    }
+}
+
+/// Type checks a sequence of instructions with an operand and a control stack,
+/// following the algorithm in the appendix of the spec.
+struct OperatorValidator<'c, 'm> {
+    ctx: &'c Context<'m>,
+    /// Runs of locals as `(end, type)`, the parameters come first.
+    locals: Vec<(u32, ValType)>,
+    /// Index of the first declared local, parameters are always set.
+    first_local: u32,
+    /// Non-defaultable locals set so far, they may only be read once set.
+    inits: HashSet<u32>,
+    /// The locals of `inits` in the order they were set,
+    /// a block forgets those set inside it when it ends.
+    init_log: Vec<u32>,
+    results: crate::SVec<ValType>,
+    /// Number of globals visible to the expression.
+    globals: usize,
+    const_expr: bool,
+    /// `None` stands for an unknown type in unreachable code.
+    operands: Vec<Option<ValType>>,
+    frames: Vec<Frame>,
+}
+
+impl<'c, 'm> OperatorValidator<'c, 'm> {
+    fn new(
+        ctx: &'c Context<'m>,
+        locals: Vec<(u32, ValType)>,
+        first_local: u32,
+        results: crate::SVec<ValType>,
+        globals: usize,
+        const_expr: bool,
+    ) -> Self {
+        let frame = Frame {
+            kind: FrameKind::Block,
+            params: crate::SVec::new(),
+            results: results.clone(),
+            height: 0,
+            inits: 0,
+            unreachable: false,
+        };
+        OperatorValidator {
+            ctx,
+            locals,
+            first_local,
+            inits: HashSet::new(),
+            init_log: Vec::new(),
+            results,
+            globals,
+            const_expr,
+            operands: Vec::new(),
+            frames: vec![frame],
+        }
+    }
+
+    fn finish(&self) -> Result<(), Error> {
+        if !self.frames.is_empty() {
+            return Err(Error::InvalidControlFlow(
+                "expression is missing its final end",
+            ));
+        }
+        Ok(())
+    }
+
+    fn frame(&self) -> Result<&Frame, Error> {
+        self.frames
+            .last()
+            .ok_or(Error::InvalidControlFlow("instruction after the final end"))
+    }
+
+    fn push(&mut self, ty: ValType) {
+        self.operands.push(Some(ty));
+    }
+
+    fn pop(&mut self) -> Result<Option<ValType>, Error> {
+        let frame = self.frame()?;
+        if self.operands.len() == frame.height {
+            return match frame.unreachable {
+                true => Ok(None),
+                false => Err(Error::OperandStackUnderflow),
+            };
+        }
+        Ok(self.operands.pop().flatten())
+    }
+
+    fn pop_expect(&mut self, expected: ValType) -> Result<Option<ValType>, Error> {
+        let actual = self.pop()?;
+        match actual {
+            Some(actual) if !self.ctx.matches(actual, expected) => {
+                Err(Error::TypeMismatch(expected, actual))
+            }
+            _ => Ok(actual),
+        }
+    }
+
+    fn pop_ref(&mut self) -> Result<Option<RefType>, Error> {
+        match self.pop()? {
+            Some(ValType::Ref(ty)) => Ok(Some(ty)),
+            Some(ty) => Err(Error::ExpectedRef(ty)),
+            None => Ok(None),
+        }
+    }
+
+    /// Pops `types` in reverse order, returning what was actually on the stack.
+    fn pop_values(&mut self, types: &[ValType]) -> Result<crate::SVec<Option<ValType>>, Error> {
+        let mut values = crate::SVec::new();
+        for ty in types.iter().rev() {
+            values.push(self.pop_expect(*ty)?);
+        }
+        values.reverse();
+        Ok(values)
+    }
+
+    fn push_values(&mut self, types: &[ValType]) {
+        self.operands.extend(types.iter().copied().map(Some));
+    }
+
+    fn apply(&mut self, params: &[ValType], results: &[ValType]) -> Result<(), Error> {
+        self.pop_values(params)?;
+        self.push_values(results);
+        Ok(())
+    }
+
+    fn set_unreachable(&mut self) -> Result<(), Error> {
+        let frame = self
+            .frames
+            .last_mut()
+            .ok_or(Error::InvalidControlFlow("instruction after the final end"))?;
+        self.operands.truncate(frame.height);
+        frame.unreachable = true;
+        Ok(())
+    }
+
+    fn block_type(
+        &self,
+        ty: BlockType,
+    ) -> Result<(crate::SVec<ValType>, crate::SVec<ValType>), Error> {
+        Ok(match ty {
+            BlockType::Empty => (crate::SVec::new(), crate::SVec::new()),
+            BlockType::Type(ty) => {
+                self.ctx.val_type(ty)?;
+                (crate::SVec::new(), [ty].into_iter().collect())
+            }
+            BlockType::FuncType(idx) => {
+                let ty = self.ctx.func_type(idx)?;
+                (ty.params.0.clone(), ty.results.0.clone())
+            }
+        })
+    }
+
+    fn push_frame(
+        &mut self,
+        kind: FrameKind,
+        params: crate::SVec<ValType>,
+        results: crate::SVec<ValType>,
+    ) {
+        let height = self.operands.len();
+        self.operands.extend(params.iter().copied().map(Some));
+        self.frames.push(Frame {
+            kind,
+            params,
+            results,
+            height,
+            inits: self.init_log.len(),
+            unreachable: false,
+        });
+    }
+
+    fn enter_block(&mut self, kind: FrameKind, ty: BlockType) -> Result<(), Error> {
+        let (params, results) = self.block_type(ty)?;
+        self.pop_values(&params)?;
+        self.push_frame(kind, params, results);
+        Ok(())
+    }
+
+    fn pop_frame(&mut self) -> Result<Frame, Error> {
+        let frame = self.frame()?;
+        let (results, height) = (frame.results.clone(), frame.height);
+        self.pop_values(&results)?;
+        if self.operands.len() != height {
+            return Err(Error::ValuesRemaining(
+                (self.operands.len() - height) as u32,
+            ));
+        }
+        let frame = self.frames.pop().unwrap();
+        for idx in self.init_log.drain(frame.inits..) {
+            self.inits.remove(&idx);
+        }
+        Ok(frame)
+    }
+
+    fn expect_frame(&self, kinds: &[FrameKind], msg: &'static str) -> Result<(), Error> {
+        if !kinds.contains(&self.frame()?.kind) {
+            return Err(Error::InvalidControlFlow(msg));
+        }
+        Ok(())
+    }
+
+    fn label(&self, depth: u32) -> Result<&Frame, Error> {
+        let len = self.frames.len();
+        if depth as usize >= len {
+            return Err(Error::UnknownIndex("label", depth));
+        }
+        Ok(&self.frames[len - 1 - depth as usize])
+    }
+
+    fn label_types(&self, depth: u32) -> Result<crate::SVec<ValType>, Error> {
+        self.label(depth).map(|frame| frame.label_types().into())
+    }
+
+    /// Checks the values below the reference a `br_on_*` instruction leaves for its target.
+    fn branch_with_ref(&mut self, depth: u32, ty: Option<RefType>) -> Result<(), Error> {
+        let types = self.label_types(depth)?;
+        let Some((last, rest)) = types.split_last() else {
+            return Err(Error::ResultTypeMismatch);
+        };
+        let ValType::Ref(_) = last else {
+            return Err(Error::ExpectedRef(*last));
+        };
+        if let Some(ty) = ty {
+            if !self.ctx.matches(ValType::Ref(ty), *last) {
+                return Err(Error::TypeMismatch(*last, ValType::Ref(ty)));
+            }
+        }
+        let values = self.pop_values(rest)?;
+        self.operands.extend(values);
+        Ok(())
+    }
+
+    fn local(&self, idx: u32) -> Result<ValType, Error> {
+        let run = self.locals.partition_point(|(end, _)| *end <= idx);
+        self.locals
+            .get(run)
+            .map(|(_, ty)| *ty)
+            .ok_or(Error::UnknownIndex("local", idx))
+    }
+
+    fn local_get(&self, idx: u32) -> Result<ValType, Error> {
+        let ty = self.local(idx)?;
+        if idx >= self.first_local
+            && !Self::defaultable(StorageType::Val(ty))
+            && !self.inits.contains(&idx)
+        {
+            return Err(Error::UninitializedLocal(idx));
+        }
+        Ok(ty)
+    }
+
+    fn local_set(&mut self, idx: u32) -> Result<ValType, Error> {
+        let ty = self.local(idx)?;
+        if !Self::defaultable(StorageType::Val(ty)) && self.inits.insert(idx) {
+            self.init_log.push(idx);
+        }
+        Ok(ty)
+    }
+
+    /// Returns the address type of the memory `instr` accesses.
+    fn memarg(&self, instr: &Instruction) -> Result<ValType, Error> {
+        let (arg, natural) = memarg_align(instr);
+        let addr = self.ctx.memory(arg.memory)?;
+        if arg.align > natural {
+            return Err(Error::InvalidAlignment(arg.align, natural));
+        }
+        if addr == ValType::I32 && arg.offset > u32::MAX as u64 {
+            return Err(Error::OffsetTooLarge(arg.offset));
+        }
+        Ok(addr)
+    }
+
+    /// Pop `operands` and the address below them.
+    fn access(
+        &mut self,
+        addr: ValType,
+        operands: &[ValType],
+        results: &[ValType],
+    ) -> Result<(), Error> {
+        self.pop_values(operands)?;
+        self.pop_expect(addr)?;
+        self.push_values(results);
+        Ok(())
+    }
+
+    fn load(&mut self, instr: &Instruction, ty: ValType) -> Result<(), Error> {
+        let addr = self.memarg(instr)?;
+        self.access(addr, &[], &[ty])
+    }
+
+    fn store(&mut self, instr: &Instruction, ty: ValType) -> Result<(), Error> {
+        let addr = self.memarg(instr)?;
+        self.access(addr, &[ty], &[])
+    }
+
+    /// Atomic accesses must be naturally aligned.
+    fn atomic(
+        &mut self,
+        instr: &Instruction,
+        operands: &[ValType],
+        results: &[ValType],
+    ) -> Result<(), Error> {
+        let addr = self.memarg(instr)?;
+        let (arg, natural) = memarg_align(instr);
+        if arg.align != natural {
+            return Err(Error::InvalidAlignment(arg.align, natural));
+        }
+        self.access(addr, operands, results)
+    }
+
+    fn call(&mut self, ty: &FuncType) -> Result<(), Error> {
+        self.apply(&ty.params.0, &ty.results.0)
+    }
+
+    /// A tail call returns the callee results from the current function.
+    fn return_call(&mut self, ty: &FuncType) -> Result<(), Error> {
+        self.pop_values(&ty.params.0)?;
+        let results = &ty.results.0;
+        if results.len() != self.results.len()
+            || !results
+                .iter()
+                .zip(&self.results)
+                .all(|(a, b)| self.ctx.matches(*a, *b))
+        {
+            return Err(Error::ResultTypeMismatch);
+        }
+        self.set_unreachable()
+    }
+
+    fn unpacked(ty: StorageType) -> ValType {
+        match ty {
+            StorageType::I8 | StorageType::I16 => ValType::I32,
+            StorageType::Val(ty) => ty,
+        }
+    }
+
+    fn field_get(&mut self, field: FieldType, packed: bool) -> Result<ValType, Error> {
+        match (field.ty, packed) {
+            (StorageType::Val(_), true) => Err(Error::InvalidFieldAccess(
+                "signed or unsigned get of an unpacked field",
+            )),
+            (StorageType::I8 | StorageType::I16, false) => Err(Error::InvalidFieldAccess(
+                "packed field needs a signed or unsigned get",
+            )),
+            _ => Ok(Self::unpacked(field.ty)),
+        }
+    }
+
+    fn mutable(field: FieldType) -> Result<(), Error> {
+        if !field.mutable {
+            return Err(Error::InvalidFieldAccess("field is immutable"));
+        }
+        Ok(())
+    }
+
+    fn defaultable(ty: StorageType) -> bool {
+        !matches!(ty, StorageType::Val(ValType::Ref(ty)) if !ty.nullable)
+    }
+
+    fn numeric(field: FieldType) -> Result<(), Error> {
+        if let StorageType::Val(ValType::Ref(_)) = field.ty {
+            return Err(Error::InvalidFieldAccess(
+                "array initialized from data needs numeric elements",
+            ));
+        }
+        Ok(())
+    }
+
+    fn elem_into(&self, elem: u32, field: FieldType) -> Result<(), Error> {
+        let ty = ValType::Ref(self.ctx.elem(elem)?);
+        let expected = Self::unpacked(field.ty);
+        if !self.ctx.storage_matches(StorageType::Val(ty), field.ty) {
+            return Err(Error::TypeMismatch(expected, ty));
+        }
+        Ok(())
+    }
+
+    fn instruction(&mut self, instr: &Instruction) -> Result<(), Error> {
+        if self.const_expr && !instr.is_const() && *instr != Instruction::End {
+            return Err(Error::InvalidConstExprOpcode(instr.clone()));
+        }
+        let ctx = self.ctx;
+        match instr {
+            Instruction::Unreachable => self.set_unreachable()?,
+            Instruction::Nop => {}
+            Instruction::Block(ty) => self.enter_block(FrameKind::Block, *ty)?,
+            Instruction::Loop(ty) => self.enter_block(FrameKind::Loop, *ty)?,
+            Instruction::If(ty) => {
+                self.pop_expect(ValType::I32)?;
+                self.enter_block(FrameKind::If, *ty)?;
+            }
+            Instruction::Else => {
+                self.expect_frame(&[FrameKind::If], "else without a matching if")?;
+                let frame = self.pop_frame()?;
+                self.push_frame(FrameKind::Else, frame.params, frame.results);
+            }
+            Instruction::End => {
+                let mut frame = self.pop_frame()?;
+                // An `if` without `else` passes its parameters through.
+                if frame.kind == FrameKind::If {
+                    self.push_frame(FrameKind::Else, frame.params, frame.results);
+                    frame = self.pop_frame()?;
+                }
+                self.push_values(&frame.results);
+            }
+            Instruction::Br(depth) => {
+                let types = self.label_types(*depth)?;
+                self.pop_values(&types)?;
+                self.set_unreachable()?;
+            }
+            Instruction::BrIf(depth) => {
+                self.pop_expect(ValType::I32)?;
+                let types = self.label_types(*depth)?;
+                let values = self.pop_values(&types)?;
+                self.operands.extend(values);
+            }
+            Instruction::BrTable(table) => {
+                self.pop_expect(ValType::I32)?;
+                let arity = self.label_types(table.default)?.len() as u32;
+                for depth in &table.targets {
+                    let types = self.label_types(*depth)?;
+                    if types.len() as u32 != arity {
+                        return Err(Error::LabelArityMismatch(arity, types.len() as u32));
+                    }
+                    let values = self.pop_values(&types)?;
+                    self.operands.extend(values);
+                }
+                let types = self.label_types(table.default)?;
+                self.pop_values(&types)?;
+                self.set_unreachable()?;
+            }
+            Instruction::BrOnNull(depth) => {
+                let ty = self.pop_ref()?;
+                let types = self.label_types(*depth)?;
+                let values = self.pop_values(&types)?;
+                self.operands.extend(values);
+                self.operands
+                    .push(ty.map(|ty| ValType::Ref(RefType::non_null(ty.heap_type))));
+            }
+            Instruction::BrOnNonNull(depth) => {
+                let ty = self.pop_ref()?;
+                self.branch_with_ref(*depth, ty.map(|ty| RefType::non_null(ty.heap_type)))?;
+            }
+            Instruction::BrOnCast(depth, from, to) | Instruction::BrOnCastFail(depth, from, to) => {
+                ctx.ref_type(*from)?;
+                ctx.ref_type(*to)?;
+                if !ctx.ref_matches(*to, *from) {
+                    return Err(Error::TypeMismatch(ValType::Ref(*from), ValType::Ref(*to)));
+                }
+                self.pop_expect(ValType::Ref(*from))?;
+                let diff = RefType::new(from.nullable && !to.nullable, from.heap_type);
+                let (taken, rest) = match instr {
+                    Instruction::BrOnCast(..) => (*to, diff),
+                    _ => (diff, *to),
+                };
+                self.branch_with_ref(*depth, Some(taken))?;
+                self.push(ValType::Ref(rest));
+            }
+            Instruction::Return => {
+                let results = self.results.clone();
+                self.pop_values(&results)?;
+                self.set_unreachable()?;
+            }
+            Instruction::Call(idx) => self.call(ctx.func(*idx)?)?,
+            Instruction::CallIndirect(ty, table, _) => {
+                self.call_indirect(*table)?;
+                self.call(ctx.func_type(*ty)?)?;
+            }
+            Instruction::CallRef(ty) => {
+                self.pop_expect(ValType::Ref(RefType::nullable(HeapType::Concrete(*ty))))?;
+                self.call(ctx.func_type(*ty)?)?;
+            }
+            Instruction::ReturnCall(idx) => self.return_call(ctx.func(*idx)?)?,
+            Instruction::ReturnCallIndirect(ty, table) => {
+                self.call_indirect(*table)?;
+                self.return_call(ctx.func_type(*ty)?)?;
+            }
+            Instruction::ReturnCallRef(ty) => {
+                self.pop_expect(ValType::Ref(RefType::nullable(HeapType::Concrete(*ty))))?;
+                self.return_call(ctx.func_type(*ty)?)?;
+            }
+            Instruction::TryTable(table) => {
+                let (params, results) = self.block_type(table.ty)?;
+                for catch in &table.catches {
+                    let (types, label): (crate::SVec<ValType>, _) = match *catch {
+                        Catch::Catch { tag, label } => (ctx.tag(tag)?.params.0.clone(), label),
+                        Catch::CatchRef { tag, label } => {
+                            let mut types = ctx.tag(tag)?.params.0.clone();
+                            types.push(ValType::Ref(RefType::EXNREF));
+                            (types, label)
+                        }
+                        Catch::CatchAll { label } => (crate::SVec::new(), label),
+                        Catch::CatchAllRef { label } => {
+                            ([ValType::Ref(RefType::EXNREF)].into_iter().collect(), label)
+                        }
+                    };
+                    let expected = self.label_types(label)?;
+                    if types.len() != expected.len()
+                        || !types
+                            .iter()
+                            .zip(&expected)
+                            .all(|(a, b)| ctx.matches(*a, *b))
+                    {
+                        return Err(Error::ResultTypeMismatch);
+                    }
+                }
+                self.pop_values(&params)?;
+                self.push_frame(FrameKind::TryTable, params, results);
+            }
+            Instruction::Throw(tag) => {
+                self.pop_values(&ctx.tag(*tag)?.params.0)?;
+                self.set_unreachable()?;
+            }
+            Instruction::ThrowRef => {
+                self.pop_expect(ValType::Ref(RefType::EXNREF))?;
+                self.set_unreachable()?;
+            }
+            Instruction::Try(ty) => self.enter_block(FrameKind::Try, *ty)?,
+            Instruction::Catch(tag) => {
+                self.expect_frame(&[FrameKind::Try, FrameKind::Catch], "catch without a try")?;
+                let frame = self.pop_frame()?;
+                let params = ctx.tag(*tag)?.params.0.clone();
+                self.push_frame(FrameKind::Catch, params, frame.results);
+            }
+            Instruction::CatchAll => {
+                self.expect_frame(
+                    &[FrameKind::Try, FrameKind::Catch],
+                    "catch_all without a try",
+                )?;
+                let frame = self.pop_frame()?;
+                self.push_frame(FrameKind::CatchAll, crate::SVec::new(), frame.results);
+            }
+            Instruction::Delegate(depth) => {
+                self.expect_frame(&[FrameKind::Try], "delegate without a try")?;
+                let frame = self.pop_frame()?;
+                self.label(*depth)?;
+                self.push_values(&frame.results);
+            }
+            Instruction::Rethrow(depth) => {
+                if !matches!(
+                    self.label(*depth)?.kind,
+                    FrameKind::Catch | FrameKind::CatchAll
+                ) {
+                    return Err(Error::InvalidControlFlow("rethrow target is not a catch"));
+                }
+                self.set_unreachable()?;
+            }
+
+            Instruction::Drop => {
+                self.pop()?;
+            }
+            Instruction::Select => {
+                self.pop_expect(ValType::I32)?;
+                let a = self.pop()?;
+                let b = self.pop()?;
+                match (a, b) {
+                    (Some(ValType::Ref(_)), _) | (_, Some(ValType::Ref(_))) => {
+                        return Err(Error::Other(
+                            "type mismatch: select without a type only takes numeric operands",
+                        ))
+                    }
+                    (Some(a), Some(b)) if a != b => return Err(Error::TypeMismatch(a, b)),
+                    _ => self.operands.push(a.or(b)),
+                }
+            }
+            Instruction::TypedSelect(ty) => {
+                ctx.val_type(*ty)?;
+                self.apply(&[*ty, *ty, ValType::I32], &[*ty])?;
+            }
+
+            Instruction::LocalGet(idx) => {
+                let ty = self.local_get(*idx)?;
+                self.push(ty);
+            }
+            Instruction::LocalSet(idx) => {
+                let ty = self.local_set(*idx)?;
+                self.pop_expect(ty)?;
+            }
+            Instruction::LocalTee(idx) => {
+                let ty = self.local_set(*idx)?;
+                self.apply(&[ty], &[ty])?;
+            }
+            Instruction::GlobalGet(idx) => {
+                if *idx as usize >= self.globals {
+                    return Err(Error::UnknownIndex("global", *idx));
+                }
+                let global = ctx.global(*idx)?;
+                if self.const_expr && global.mutable {
+                    return Err(Error::InvalidConstExprOpcode(instr.clone()));
+                }
+                self.push(global.ty);
+            }
+            Instruction::GlobalSet(idx) => {
+                let global = ctx.global(*idx)?;
+                if !global.mutable {
+                    return Err(Error::ImmutableGlobal(*idx));
+                }
+                self.pop_expect(global.ty)?;
+            }
+
+            Instruction::MemorySize(mem) => {
+                let addr = ctx.memory(*mem)?;
+                self.push(addr);
+            }
+            Instruction::MemoryGrow(mem) => {
+                let addr = ctx.memory(*mem)?;
+                self.apply(&[addr], &[addr])?;
+            }
+            Instruction::MemoryInit(data, mem) => {
+                ctx.data(*data)?;
+                let addr = ctx.memory(*mem)?;
+                self.apply(&[addr, ValType::I32, ValType::I32], &[])?;
+            }
+            Instruction::DataDrop(data) => ctx.data(*data)?,
+            Instruction::MemoryCopy(dst, src) => {
+                let dst = ctx.memory(*dst)?;
+                let src = ctx.memory(*src)?;
+                // The length fits in both memories.
+                let len = if dst == ValType::I64 && src == ValType::I64 {
+                    ValType::I64
+                } else {
+                    ValType::I32
+                };
+                self.apply(&[dst, src, len], &[])?;
+            }
+            Instruction::MemoryFill(mem) => {
+                let addr = ctx.memory(*mem)?;
+                self.apply(&[addr, ValType::I32, addr], &[])?;
+            }
+            Instruction::MemoryDiscard(mem) => {
+                let addr = ctx.memory(*mem)?;
+                self.apply(&[addr; 2], &[])?;
+            }
+            Instruction::MemoryAtomicNotify(_) => {
+                self.atomic(instr, &[ValType::I32], &[ValType::I32])?
+            }
+            Instruction::MemoryAtomicWait32(_) => {
+                self.atomic(instr, &[ValType::I32, ValType::I64], &[ValType::I32])?
+            }
+            Instruction::MemoryAtomicWait64(_) => {
+                self.atomic(instr, &[ValType::I64, ValType::I64], &[ValType::I32])?
+            }
+            Instruction::AtomicFence => {}
+
+            Instruction::I32Const(_) => self.push(ValType::I32),
+            Instruction::I64Const(_) => self.push(ValType::I64),
+            Instruction::F32Const(_) => self.push(ValType::F32),
+            Instruction::F64Const(_) => self.push(ValType::F64),
+            Instruction::V128Const(_) => self.push(ValType::V128),
+            Instruction::I8x16Shuffle(_) => self.apply(&[ValType::V128; 2], &[ValType::V128])?,
+
+            Instruction::RefNull(ty) => {
+                ctx.heap_type(*ty)?;
+                self.push(ValType::Ref(RefType::nullable(*ty)));
+            }
+            Instruction::RefIsNull => {
+                self.pop_ref()?;
+                self.push(ValType::I32);
+            }
+            Instruction::RefFunc(idx) => {
+                ctx.func(*idx)?;
+                if !self.const_expr && !ctx.refs.contains(idx) {
+                    return Err(Error::UndeclaredFunctionReference(*idx));
+                }
+                let ty = HeapType::Concrete(ctx.funcs[*idx as usize]);
+                self.push(ValType::Ref(RefType::non_null(ty)));
+            }
+            Instruction::RefEq => {
+                let eq = ValType::Ref(RefType::EQREF);
+                self.apply(&[eq, eq], &[ValType::I32])?;
+            }
+            Instruction::RefAsNonNull => {
+                let ty = self.pop_ref()?;
+                self.operands
+                    .push(ty.map(|ty| ValType::Ref(RefType::non_null(ty.heap_type))));
+            }
+            Instruction::RefTestNonNull(ty) | Instruction::RefTestNullable(ty) => {
+                ctx.heap_type(*ty)?;
+                self.pop_ref()?;
+                self.push(ValType::I32);
+            }
+            Instruction::RefCastNonNull(ty) | Instruction::RefCastNullable(ty) => {
+                ctx.heap_type(*ty)?;
+                self.pop_ref()?;
+                let nullable = matches!(instr, Instruction::RefCastNullable(_));
+                self.push(ValType::Ref(RefType::new(nullable, *ty)));
+            }
+            Instruction::AnyConvertExtern => {
+                let ty = self.pop_expect(ValType::Ref(RefType::EXTERNREF))?;
+                self.operands.push(ty.map(|ty| match ty {
+                    ValType::Ref(ty) => ValType::Ref(RefType::new(ty.nullable, HeapType::Any)),
+                    ty => ty,
+                }));
+            }
+            Instruction::ExternConvertAny => {
+                let ty = self.pop_expect(ValType::Ref(RefType::ANYREF))?;
+                self.operands.push(ty.map(|ty| match ty {
+                    ValType::Ref(ty) => ValType::Ref(RefType::new(ty.nullable, HeapType::Extern)),
+                    ty => ty,
+                }));
+            }
+            Instruction::RefI31 => self.apply(
+                &[ValType::I32],
+                &[ValType::Ref(RefType::non_null(HeapType::I31))],
+            )?,
+            Instruction::I31GetS | Instruction::I31GetU => {
+                self.apply(&[ValType::Ref(RefType::I31REF)], &[ValType::I32])?
+            }
+
+            Instruction::StructNew(ty) => {
+                let CompositeType::Struct(st) = &ctx.get_type(*ty)?.composite else {
+                    return Err(Error::UnexpectedType("struct", *ty));
+                };
+                let fields = st
+                    .fields
+                    .iter()
+                    .map(|field| Self::unpacked(field.ty))
+                    .collect::<crate::SVec<_>>();
+                self.apply(&fields, &[concrete_ref(*ty, false)])?;
+            }
+            Instruction::StructNewDefault(ty) => {
+                let CompositeType::Struct(st) = &ctx.get_type(*ty)?.composite else {
+                    return Err(Error::UnexpectedType("struct", *ty));
+                };
+                if !st.fields.iter().all(|field| Self::defaultable(field.ty)) {
+                    return Err(Error::NotDefaultable(*ty));
+                }
+                self.push(concrete_ref(*ty, false));
+            }
+            Instruction::StructGet(ty, field)
+            | Instruction::StructGetS(ty, field)
+            | Instruction::StructGetU(ty, field) => {
+                let field = ctx.struct_field(*ty, *field)?;
+                let packed = !matches!(instr, Instruction::StructGet(..));
+                let result = self.field_get(field, packed)?;
+                self.apply(&[concrete_ref(*ty, true)], &[result])?;
+            }
+            Instruction::StructSet(ty, field) => {
+                let field = ctx.struct_field(*ty, *field)?;
+                Self::mutable(field)?;
+                self.apply(&[concrete_ref(*ty, true), Self::unpacked(field.ty)], &[])?;
+            }
+            Instruction::ArrayNew(ty) => {
+                let field = ctx.array_field(*ty)?;
+                self.apply(
+                    &[Self::unpacked(field.ty), ValType::I32],
+                    &[concrete_ref(*ty, false)],
+                )?;
+            }
+            Instruction::ArrayNewDefault(ty) => {
+                if !Self::defaultable(ctx.array_field(*ty)?.ty) {
+                    return Err(Error::NotDefaultable(*ty));
+                }
+                self.apply(&[ValType::I32], &[concrete_ref(*ty, false)])?;
+            }
+            Instruction::ArrayNewFixed(ty, n) => {
+                let elem = Self::unpacked(ctx.array_field(*ty)?.ty);
+                // In unreachable code the stack yields unknown values forever,
+                // one more pop than available is enough to tell.
+                let available = self.operands.len() - self.frame()?.height;
+                for _ in 0..(*n as usize).min(available + 1) {
+                    self.pop_expect(elem)?;
+                }
+                self.push(concrete_ref(*ty, false));
+            }
+            Instruction::ArrayNewData(ty, data) => {
+                Self::numeric(ctx.array_field(*ty)?)?;
+                ctx.data(*data)?;
+                self.apply(&[ValType::I32; 2], &[concrete_ref(*ty, false)])?;
+            }
+            Instruction::ArrayNewElem(ty, elem) => {
+                self.elem_into(*elem, ctx.array_field(*ty)?)?;
+                self.apply(&[ValType::I32; 2], &[concrete_ref(*ty, false)])?;
+            }
+            Instruction::ArrayGet(ty) | Instruction::ArrayGetS(ty) | Instruction::ArrayGetU(ty) => {
+                let field = ctx.array_field(*ty)?;
+                let packed = !matches!(instr, Instruction::ArrayGet(_));
+                let result = self.field_get(field, packed)?;
+                self.apply(&[concrete_ref(*ty, true), ValType::I32], &[result])?;
+            }
+            Instruction::ArraySet(ty) => {
+                let field = ctx.array_field(*ty)?;
+                Self::mutable(field)?;
+                self.apply(
+                    &[
+                        concrete_ref(*ty, true),
+                        ValType::I32,
+                        Self::unpacked(field.ty),
+                    ],
+                    &[],
+                )?;
+            }
+            Instruction::ArrayLen => {
+                self.apply(&[ValType::Ref(RefType::ARRAYREF)], &[ValType::I32])?
+            }
+            Instruction::ArrayFill(ty) => {
+                let field = ctx.array_field(*ty)?;
+                Self::mutable(field)?;
+                self.apply(
+                    &[
+                        concrete_ref(*ty, true),
+                        ValType::I32,
+                        Self::unpacked(field.ty),
+                        ValType::I32,
+                    ],
+                    &[],
+                )?;
+            }
+            Instruction::ArrayCopy(dst, src) => {
+                let dst_field = ctx.array_field(*dst)?;
+                let src_field = ctx.array_field(*src)?;
+                Self::mutable(dst_field)?;
+                if !ctx.storage_matches(src_field.ty, dst_field.ty) {
+                    return Err(Error::TypeMismatch(
+                        Self::unpacked(dst_field.ty),
+                        Self::unpacked(src_field.ty),
+                    ));
+                }
+                self.apply(
+                    &[
+                        concrete_ref(*dst, true),
+                        ValType::I32,
+                        concrete_ref(*src, true),
+                        ValType::I32,
+                        ValType::I32,
+                    ],
+                    &[],
+                )?;
+            }
+            Instruction::ArrayInitData(ty, data) => {
+                let field = ctx.array_field(*ty)?;
+                Self::mutable(field)?;
+                Self::numeric(field)?;
+                ctx.data(*data)?;
+                self.apply(
+                    &[
+                        concrete_ref(*ty, true),
+                        ValType::I32,
+                        ValType::I32,
+                        ValType::I32,
+                    ],
+                    &[],
+                )?;
+            }
+            Instruction::ArrayInitElem(ty, elem) => {
+                let field = ctx.array_field(*ty)?;
+                Self::mutable(field)?;
+                self.elem_into(*elem, field)?;
+                self.apply(
+                    &[
+                        concrete_ref(*ty, true),
+                        ValType::I32,
+                        ValType::I32,
+                        ValType::I32,
+                    ],
+                    &[],
+                )?;
+            }
+
+            Instruction::TableGet(table) => {
+                let table = ctx.table(*table)?;
+                let ty = ValType::Ref(table.element);
+                self.apply(&[table.index_type.into()], &[ty])?;
+            }
+            Instruction::TableSet(table) => {
+                let table = ctx.table(*table)?;
+                let ty = ValType::Ref(table.element);
+                self.apply(&[table.index_type.into(), ty], &[])?;
+            }
+            Instruction::TableSize(table) => {
+                let table = ctx.table(*table)?;
+                self.push(table.index_type.into());
+            }
+            Instruction::TableGrow(table) => {
+                let table = ctx.table(*table)?;
+                let (ty, addr) = (ValType::Ref(table.element), table.index_type.into());
+                self.apply(&[ty, addr], &[addr])?;
+            }
+            Instruction::TableFill(table) => {
+                let table = ctx.table(*table)?;
+                let (ty, addr) = (ValType::Ref(table.element), table.index_type.into());
+                self.apply(&[addr, ty, addr], &[])?;
+            }
+            Instruction::TableCopy(dst, src) => {
+                let dst = ctx.table(*dst)?;
+                let src = ctx.table(*src)?;
+                if !ctx.ref_matches(src.element, dst.element) {
+                    return Err(Error::TypeMismatch(
+                        ValType::Ref(dst.element),
+                        ValType::Ref(src.element),
+                    ));
+                }
+                // The length fits in both tables.
+                let len = if dst.index_type == IndexType::I64 && src.index_type == IndexType::I64 {
+                    ValType::I64
+                } else {
+                    ValType::I32
+                };
+                self.apply(&[dst.index_type.into(), src.index_type.into(), len], &[])?;
+            }
+            Instruction::TableInit(elem, table) => {
+                let src = ctx.elem(*elem)?;
+                let dst = ctx.table(*table)?;
+                if !ctx.ref_matches(src, dst.element) {
+                    return Err(Error::TypeMismatch(
+                        ValType::Ref(dst.element),
+                        ValType::Ref(src),
+                    ));
+                }
+                self.apply(&[dst.index_type.into(), ValType::I32, ValType::I32], &[])?;
+            }
+            Instruction::ElemDrop(elem) => {
+                ctx.elem(*elem)?;
+            }
+
+            Instruction::I32Load(_) => self.load(instr, ValType::I32)?,
+            Instruction::I64Load(_) => self.load(instr, ValType::I64)?,
+            Instruction::F32Load(_) => self.load(instr, ValType::F32)?,
+            Instruction::F64Load(_) => self.load(instr, ValType::F64)?,
+            Instruction::I32Load8S(_) | Instruction::I32Load8U(_) => {
+                self.load(instr, ValType::I32)?
+            }
+            Instruction::I32Load16S(_) | Instruction::I32Load16U(_) => {
+                self.load(instr, ValType::I32)?
+            }
+            Instruction::I64Load8S(_) | Instruction::I64Load8U(_) => {
+                self.load(instr, ValType::I64)?
+            }
+            Instruction::I64Load16S(_) | Instruction::I64Load16U(_) => {
+                self.load(instr, ValType::I64)?
+            }
+            Instruction::I64Load32S(_) | Instruction::I64Load32U(_) => {
+                self.load(instr, ValType::I64)?
+            }
+            Instruction::I32Store(_) => self.store(instr, ValType::I32)?,
+            Instruction::I64Store(_) => self.store(instr, ValType::I64)?,
+            Instruction::F32Store(_) => self.store(instr, ValType::F32)?,
+            Instruction::F64Store(_) => self.store(instr, ValType::F64)?,
+            Instruction::I32Store8(_) => self.store(instr, ValType::I32)?,
+            Instruction::I32Store16(_) => self.store(instr, ValType::I32)?,
+            Instruction::I64Store8(_) => self.store(instr, ValType::I64)?,
+            Instruction::I64Store16(_) => self.store(instr, ValType::I64)?,
+            Instruction::I64Store32(_) => self.store(instr, ValType::I64)?,
+            Instruction::I32Eqz => self.apply(&[ValType::I32], &[ValType::I32])?,
+            Instruction::I32Eq
+            | Instruction::I32Ne
+            | Instruction::I32LtS
+            | Instruction::I32LtU
+            | Instruction::I32GtS
+            | Instruction::I32GtU
+            | Instruction::I32LeS
+            | Instruction::I32LeU
+            | Instruction::I32GeS
+            | Instruction::I32GeU => self.apply(&[ValType::I32; 2], &[ValType::I32])?,
+            Instruction::I64Eqz => self.apply(&[ValType::I64], &[ValType::I32])?,
+            Instruction::I64Eq
+            | Instruction::I64Ne
+            | Instruction::I64LtS
+            | Instruction::I64LtU
+            | Instruction::I64GtS
+            | Instruction::I64GtU
+            | Instruction::I64LeS
+            | Instruction::I64LeU
+            | Instruction::I64GeS
+            | Instruction::I64GeU => self.apply(&[ValType::I64; 2], &[ValType::I32])?,
+            Instruction::F32Eq
+            | Instruction::F32Ne
+            | Instruction::F32Lt
+            | Instruction::F32Gt
+            | Instruction::F32Le
+            | Instruction::F32Ge => self.apply(&[ValType::F32; 2], &[ValType::I32])?,
+            Instruction::F64Eq
+            | Instruction::F64Ne
+            | Instruction::F64Lt
+            | Instruction::F64Gt
+            | Instruction::F64Le
+            | Instruction::F64Ge => self.apply(&[ValType::F64; 2], &[ValType::I32])?,
+            Instruction::I32Clz
+            | Instruction::I32Ctz
+            | Instruction::I32Popcnt
+            | Instruction::I32Extend8S
+            | Instruction::I32Extend16S => self.apply(&[ValType::I32], &[ValType::I32])?,
+            Instruction::I32Add
+            | Instruction::I32Sub
+            | Instruction::I32Mul
+            | Instruction::I32DivS
+            | Instruction::I32DivU
+            | Instruction::I32RemS
+            | Instruction::I32RemU
+            | Instruction::I32And
+            | Instruction::I32Or
+            | Instruction::I32Xor
+            | Instruction::I32Shl
+            | Instruction::I32ShrS
+            | Instruction::I32ShrU
+            | Instruction::I32Rotl
+            | Instruction::I32Rotr => self.apply(&[ValType::I32; 2], &[ValType::I32])?,
+            Instruction::I64Clz
+            | Instruction::I64Ctz
+            | Instruction::I64Popcnt
+            | Instruction::I64Extend8S
+            | Instruction::I64Extend16S
+            | Instruction::I64Extend32S => self.apply(&[ValType::I64], &[ValType::I64])?,
+            Instruction::I64Add
+            | Instruction::I64Sub
+            | Instruction::I64Mul
+            | Instruction::I64DivS
+            | Instruction::I64DivU
+            | Instruction::I64RemS
+            | Instruction::I64RemU
+            | Instruction::I64And
+            | Instruction::I64Or
+            | Instruction::I64Xor
+            | Instruction::I64Shl
+            | Instruction::I64ShrS
+            | Instruction::I64ShrU
+            | Instruction::I64Rotl
+            | Instruction::I64Rotr => self.apply(&[ValType::I64; 2], &[ValType::I64])?,
+            Instruction::F32Abs
+            | Instruction::F32Neg
+            | Instruction::F32Ceil
+            | Instruction::F32Floor
+            | Instruction::F32Trunc
+            | Instruction::F32Nearest
+            | Instruction::F32Sqrt => self.apply(&[ValType::F32], &[ValType::F32])?,
+            Instruction::F32Add
+            | Instruction::F32Sub
+            | Instruction::F32Mul
+            | Instruction::F32Div
+            | Instruction::F32Min
+            | Instruction::F32Max
+            | Instruction::F32Copysign => self.apply(&[ValType::F32; 2], &[ValType::F32])?,
+            Instruction::F64Abs
+            | Instruction::F64Neg
+            | Instruction::F64Ceil
+            | Instruction::F64Floor
+            | Instruction::F64Trunc
+            | Instruction::F64Nearest
+            | Instruction::F64Sqrt => self.apply(&[ValType::F64], &[ValType::F64])?,
+            Instruction::F64Add
+            | Instruction::F64Sub
+            | Instruction::F64Mul
+            | Instruction::F64Div
+            | Instruction::F64Min
+            | Instruction::F64Max
+            | Instruction::F64Copysign => self.apply(&[ValType::F64; 2], &[ValType::F64])?,
+            Instruction::I32WrapI64 => self.apply(&[ValType::I64], &[ValType::I32])?,
+            Instruction::I32TruncF32S
+            | Instruction::I32TruncF32U
+            | Instruction::I32ReinterpretF32
+            | Instruction::I32TruncSatF32S
+            | Instruction::I32TruncSatF32U => self.apply(&[ValType::F32], &[ValType::I32])?,
+            Instruction::I32TruncF64S
+            | Instruction::I32TruncF64U
+            | Instruction::I32TruncSatF64S
+            | Instruction::I32TruncSatF64U => self.apply(&[ValType::F64], &[ValType::I32])?,
+            Instruction::I64ExtendI32S | Instruction::I64ExtendI32U => {
+                self.apply(&[ValType::I32], &[ValType::I64])?
+            }
+            Instruction::I64TruncF32S
+            | Instruction::I64TruncF32U
+            | Instruction::I64TruncSatF32S
+            | Instruction::I64TruncSatF32U => self.apply(&[ValType::F32], &[ValType::I64])?,
+            Instruction::I64TruncF64S
+            | Instruction::I64TruncF64U
+            | Instruction::I64ReinterpretF64
+            | Instruction::I64TruncSatF64S
+            | Instruction::I64TruncSatF64U => self.apply(&[ValType::F64], &[ValType::I64])?,
+            Instruction::F32ConvertI32S
+            | Instruction::F32ConvertI32U
+            | Instruction::F32ReinterpretI32 => self.apply(&[ValType::I32], &[ValType::F32])?,
+            Instruction::F32ConvertI64S | Instruction::F32ConvertI64U => {
+                self.apply(&[ValType::I64], &[ValType::F32])?
+            }
+            Instruction::F32DemoteF64 => self.apply(&[ValType::F64], &[ValType::F32])?,
+            Instruction::F64ConvertI32S | Instruction::F64ConvertI32U => {
+                self.apply(&[ValType::I32], &[ValType::F64])?
+            }
+            Instruction::F64ConvertI64S
+            | Instruction::F64ConvertI64U
+            | Instruction::F64ReinterpretI64 => self.apply(&[ValType::I64], &[ValType::F64])?,
+            Instruction::F64PromoteF32 => self.apply(&[ValType::F32], &[ValType::F64])?,
+            Instruction::V128Load(_) => self.load(instr, ValType::V128)?,
+            Instruction::V128Load8x8S(_)
+            | Instruction::V128Load8x8U(_)
+            | Instruction::V128Load16x4S(_)
+            | Instruction::V128Load16x4U(_)
+            | Instruction::V128Load32x2S(_)
+            | Instruction::V128Load32x2U(_)
+            | Instruction::V128Load64Splat(_)
+            | Instruction::V128Load64Zero(_) => self.load(instr, ValType::V128)?,
+            Instruction::V128Load8Splat(_) => self.load(instr, ValType::V128)?,
+            Instruction::V128Load16Splat(_) => self.load(instr, ValType::V128)?,
+            Instruction::V128Load32Splat(_) | Instruction::V128Load32Zero(_) => {
+                self.load(instr, ValType::V128)?
+            }
+            Instruction::V128Store(_) => self.store(instr, ValType::V128)?,
+            Instruction::V128Load8Lane(_, _) => {
+                let addr = self.memarg(instr)?;
+                self.access(addr, &[ValType::V128], &[ValType::V128])?
+            }
+            Instruction::V128Load16Lane(_, _) => {
+                let addr = self.memarg(instr)?;
+                self.access(addr, &[ValType::V128], &[ValType::V128])?
+            }
+            Instruction::V128Load32Lane(_, _) => {
+                let addr = self.memarg(instr)?;
+                self.access(addr, &[ValType::V128], &[ValType::V128])?
+            }
+            Instruction::V128Load64Lane(_, _) => {
+                let addr = self.memarg(instr)?;
+                self.access(addr, &[ValType::V128], &[ValType::V128])?
+            }
+            Instruction::V128Store8Lane(_, _) => {
+                let addr = self.memarg(instr)?;
+                self.access(addr, &[ValType::V128], &[])?
+            }
+            Instruction::V128Store16Lane(_, _) => {
+                let addr = self.memarg(instr)?;
+                self.access(addr, &[ValType::V128], &[])?
+            }
+            Instruction::V128Store32Lane(_, _) => {
+                let addr = self.memarg(instr)?;
+                self.access(addr, &[ValType::V128], &[])?
+            }
+            Instruction::V128Store64Lane(_, _) => {
+                let addr = self.memarg(instr)?;
+                self.access(addr, &[ValType::V128], &[])?
+            }
+            Instruction::I8x16ExtractLaneS(_)
+            | Instruction::I8x16ExtractLaneU(_)
+            | Instruction::I16x8ExtractLaneS(_)
+            | Instruction::I16x8ExtractLaneU(_)
+            | Instruction::I32x4ExtractLane(_) => self.apply(&[ValType::V128], &[ValType::I32])?,
+            Instruction::I8x16ReplaceLane(_)
+            | Instruction::I16x8ReplaceLane(_)
+            | Instruction::I32x4ReplaceLane(_) => {
+                self.apply(&[ValType::V128, ValType::I32], &[ValType::V128])?
+            }
+            Instruction::I64x2ExtractLane(_) => self.apply(&[ValType::V128], &[ValType::I64])?,
+            Instruction::I64x2ReplaceLane(_) => {
+                self.apply(&[ValType::V128, ValType::I64], &[ValType::V128])?
+            }
+            Instruction::F32x4ExtractLane(_) => self.apply(&[ValType::V128], &[ValType::F32])?,
+            Instruction::F32x4ReplaceLane(_) => {
+                self.apply(&[ValType::V128, ValType::F32], &[ValType::V128])?
+            }
+            Instruction::F64x2ExtractLane(_) => self.apply(&[ValType::V128], &[ValType::F64])?,
+            Instruction::F64x2ReplaceLane(_) => {
+                self.apply(&[ValType::V128, ValType::F64], &[ValType::V128])?
+            }
+            Instruction::I8x16Swizzle
+            | Instruction::I8x16Eq
+            | Instruction::I8x16Ne
+            | Instruction::I8x16LtS
+            | Instruction::I8x16LtU
+            | Instruction::I8x16GtS
+            | Instruction::I8x16GtU
+            | Instruction::I8x16LeS
+            | Instruction::I8x16LeU
+            | Instruction::I8x16GeS
+            | Instruction::I8x16GeU
+            | Instruction::I16x8Eq
+            | Instruction::I16x8Ne
+            | Instruction::I16x8LtS
+            | Instruction::I16x8LtU
+            | Instruction::I16x8GtS
+            | Instruction::I16x8GtU
+            | Instruction::I16x8LeS
+            | Instruction::I16x8LeU
+            | Instruction::I16x8GeS
+            | Instruction::I16x8GeU
+            | Instruction::I32x4Eq
+            | Instruction::I32x4Ne
+            | Instruction::I32x4LtS
+            | Instruction::I32x4LtU
+            | Instruction::I32x4GtS
+            | Instruction::I32x4GtU
+            | Instruction::I32x4LeS
+            | Instruction::I32x4LeU
+            | Instruction::I32x4GeS
+            | Instruction::I32x4GeU
+            | Instruction::I64x2Eq
+            | Instruction::I64x2Ne
+            | Instruction::I64x2LtS
+            | Instruction::I64x2GtS
+            | Instruction::I64x2LeS
+            | Instruction::I64x2GeS
+            | Instruction::F32x4Eq
+            | Instruction::F32x4Ne
+            | Instruction::F32x4Lt
+            | Instruction::F32x4Gt
+            | Instruction::F32x4Le
+            | Instruction::F32x4Ge
+            | Instruction::F64x2Eq
+            | Instruction::F64x2Ne
+            | Instruction::F64x2Lt
+            | Instruction::F64x2Gt
+            | Instruction::F64x2Le
+            | Instruction::F64x2Ge
+            | Instruction::V128And
+            | Instruction::V128AndNot
+            | Instruction::V128Or
+            | Instruction::V128Xor
+            | Instruction::I8x16NarrowI16x8S
+            | Instruction::I8x16NarrowI16x8U
+            | Instruction::I8x16Add
+            | Instruction::I8x16AddSatS
+            | Instruction::I8x16AddSatU
+            | Instruction::I8x16Sub
+            | Instruction::I8x16SubSatS
+            | Instruction::I8x16SubSatU
+            | Instruction::I8x16MinS
+            | Instruction::I8x16MinU
+            | Instruction::I8x16MaxS
+            | Instruction::I8x16MaxU
+            | Instruction::I8x16AvgrU
+            | Instruction::I16x8Q15MulrSatS
+            | Instruction::I16x8NarrowI32x4S
+            | Instruction::I16x8NarrowI32x4U
+            | Instruction::I16x8Add
+            | Instruction::I16x8AddSatS
+            | Instruction::I16x8AddSatU
+            | Instruction::I16x8Sub
+            | Instruction::I16x8SubSatS
+            | Instruction::I16x8SubSatU
+            | Instruction::I16x8Mul
+            | Instruction::I16x8MinS
+            | Instruction::I16x8MinU
+            | Instruction::I16x8MaxS
+            | Instruction::I16x8MaxU
+            | Instruction::I16x8AvgrU
+            | Instruction::I16x8ExtMulLowI8x16S
+            | Instruction::I16x8ExtMulHighI8x16S
+            | Instruction::I16x8ExtMulLowI8x16U
+            | Instruction::I16x8ExtMulHighI8x16U
+            | Instruction::I32x4Add
+            | Instruction::I32x4Sub
+            | Instruction::I32x4Mul
+            | Instruction::I32x4MinS
+            | Instruction::I32x4MinU
+            | Instruction::I32x4MaxS
+            | Instruction::I32x4MaxU
+            | Instruction::I32x4DotI16x8S
+            | Instruction::I32x4ExtMulLowI16x8S
+            | Instruction::I32x4ExtMulHighI16x8S
+            | Instruction::I32x4ExtMulLowI16x8U
+            | Instruction::I32x4ExtMulHighI16x8U
+            | Instruction::I64x2Add
+            | Instruction::I64x2Sub
+            | Instruction::I64x2Mul
+            | Instruction::I64x2ExtMulLowI32x4S
+            | Instruction::I64x2ExtMulHighI32x4S
+            | Instruction::I64x2ExtMulLowI32x4U
+            | Instruction::I64x2ExtMulHighI32x4U
+            | Instruction::F32x4Add
+            | Instruction::F32x4Sub
+            | Instruction::F32x4Mul
+            | Instruction::F32x4Div
+            | Instruction::F32x4Min
+            | Instruction::F32x4Max
+            | Instruction::F32x4PMin
+            | Instruction::F32x4PMax
+            | Instruction::F64x2Add
+            | Instruction::F64x2Sub
+            | Instruction::F64x2Mul
+            | Instruction::F64x2Div
+            | Instruction::F64x2Min
+            | Instruction::F64x2Max
+            | Instruction::F64x2PMin
+            | Instruction::F64x2PMax
+            | Instruction::I8x16RelaxedSwizzle
+            | Instruction::F32x4RelaxedMin
+            | Instruction::F32x4RelaxedMax
+            | Instruction::F64x2RelaxedMin
+            | Instruction::F64x2RelaxedMax
+            | Instruction::I16x8RelaxedQ15mulrS
+            | Instruction::I16x8RelaxedDotI8x16I7x16S => {
+                self.apply(&[ValType::V128; 2], &[ValType::V128])?
+            }
+            Instruction::I8x16Splat | Instruction::I16x8Splat | Instruction::I32x4Splat => {
+                self.apply(&[ValType::I32], &[ValType::V128])?
+            }
+            Instruction::I64x2Splat => self.apply(&[ValType::I64], &[ValType::V128])?,
+            Instruction::F32x4Splat => self.apply(&[ValType::F32], &[ValType::V128])?,
+            Instruction::F64x2Splat => self.apply(&[ValType::F64], &[ValType::V128])?,
+            Instruction::V128Not
+            | Instruction::I8x16Abs
+            | Instruction::I8x16Neg
+            | Instruction::I8x16Popcnt
+            | Instruction::I16x8ExtAddPairwiseI8x16S
+            | Instruction::I16x8ExtAddPairwiseI8x16U
+            | Instruction::I16x8Abs
+            | Instruction::I16x8Neg
+            | Instruction::I16x8ExtendLowI8x16S
+            | Instruction::I16x8ExtendHighI8x16S
+            | Instruction::I16x8ExtendLowI8x16U
+            | Instruction::I16x8ExtendHighI8x16U
+            | Instruction::I32x4ExtAddPairwiseI16x8S
+            | Instruction::I32x4ExtAddPairwiseI16x8U
+            | Instruction::I32x4Abs
+            | Instruction::I32x4Neg
+            | Instruction::I32x4ExtendLowI16x8S
+            | Instruction::I32x4ExtendHighI16x8S
+            | Instruction::I32x4ExtendLowI16x8U
+            | Instruction::I32x4ExtendHighI16x8U
+            | Instruction::I64x2Abs
+            | Instruction::I64x2Neg
+            | Instruction::I64x2ExtendLowI32x4S
+            | Instruction::I64x2ExtendHighI32x4S
+            | Instruction::I64x2ExtendLowI32x4U
+            | Instruction::I64x2ExtendHighI32x4U
+            | Instruction::F32x4Ceil
+            | Instruction::F32x4Floor
+            | Instruction::F32x4Trunc
+            | Instruction::F32x4Nearest
+            | Instruction::F32x4Abs
+            | Instruction::F32x4Neg
+            | Instruction::F32x4Sqrt
+            | Instruction::F64x2Ceil
+            | Instruction::F64x2Floor
+            | Instruction::F64x2Trunc
+            | Instruction::F64x2Nearest
+            | Instruction::F64x2Abs
+            | Instruction::F64x2Neg
+            | Instruction::F64x2Sqrt
+            | Instruction::I32x4TruncSatF32x4S
+            | Instruction::I32x4TruncSatF32x4U
+            | Instruction::F32x4ConvertI32x4S
+            | Instruction::F32x4ConvertI32x4U
+            | Instruction::I32x4TruncSatF64x2SZero
+            | Instruction::I32x4TruncSatF64x2UZero
+            | Instruction::F64x2ConvertLowI32x4S
+            | Instruction::F64x2ConvertLowI32x4U
+            | Instruction::F32x4DemoteF64x2Zero
+            | Instruction::F64x2PromoteLowF32x4
+            | Instruction::I32x4RelaxedTruncF32x4S
+            | Instruction::I32x4RelaxedTruncF32x4U
+            | Instruction::I32x4RelaxedTruncF64x2SZero
+            | Instruction::I32x4RelaxedTruncF64x2UZero => {
+                self.apply(&[ValType::V128], &[ValType::V128])?
+            }
+            Instruction::V128Bitselect
+            | Instruction::F32x4RelaxedMadd
+            | Instruction::F32x4RelaxedNmadd
+            | Instruction::F64x2RelaxedMadd
+            | Instruction::F64x2RelaxedNmadd
+            | Instruction::I8x16RelaxedLaneselect
+            | Instruction::I16x8RelaxedLaneselect
+            | Instruction::I32x4RelaxedLaneselect
+            | Instruction::I64x2RelaxedLaneselect
+            | Instruction::I32x4RelaxedDotI8x16I7x16AddS => {
+                self.apply(&[ValType::V128; 3], &[ValType::V128])?
+            }
+            Instruction::V128AnyTrue
+            | Instruction::I8x16AllTrue
+            | Instruction::I8x16Bitmask
+            | Instruction::I16x8AllTrue
+            | Instruction::I16x8Bitmask
+            | Instruction::I32x4AllTrue
+            | Instruction::I32x4Bitmask
+            | Instruction::I64x2AllTrue
+            | Instruction::I64x2Bitmask => self.apply(&[ValType::V128], &[ValType::I32])?,
+            Instruction::I8x16Shl
+            | Instruction::I8x16ShrS
+            | Instruction::I8x16ShrU
+            | Instruction::I16x8Shl
+            | Instruction::I16x8ShrS
+            | Instruction::I16x8ShrU
+            | Instruction::I32x4Shl
+            | Instruction::I32x4ShrS
+            | Instruction::I32x4ShrU
+            | Instruction::I64x2Shl
+            | Instruction::I64x2ShrS
+            | Instruction::I64x2ShrU => {
+                self.apply(&[ValType::V128, ValType::I32], &[ValType::V128])?
+            }
+            Instruction::I32AtomicLoad(_) => self.atomic(instr, &[], &[ValType::I32])?,
+            Instruction::I64AtomicLoad(_) => self.atomic(instr, &[], &[ValType::I64])?,
+            Instruction::I32AtomicLoad8U(_) => self.atomic(instr, &[], &[ValType::I32])?,
+            Instruction::I32AtomicLoad16U(_) => self.atomic(instr, &[], &[ValType::I32])?,
+            Instruction::I64AtomicLoad8U(_) => self.atomic(instr, &[], &[ValType::I64])?,
+            Instruction::I64AtomicLoad16U(_) => self.atomic(instr, &[], &[ValType::I64])?,
+            Instruction::I64AtomicLoad32U(_) => self.atomic(instr, &[], &[ValType::I64])?,
+            Instruction::I32AtomicStore(_) => self.atomic(instr, &[ValType::I32], &[])?,
+            Instruction::I64AtomicStore(_) => self.atomic(instr, &[ValType::I64], &[])?,
+            Instruction::I32AtomicStore8(_) => self.atomic(instr, &[ValType::I32], &[])?,
+            Instruction::I32AtomicStore16(_) => self.atomic(instr, &[ValType::I32], &[])?,
+            Instruction::I64AtomicStore8(_) => self.atomic(instr, &[ValType::I64], &[])?,
+            Instruction::I64AtomicStore16(_) => self.atomic(instr, &[ValType::I64], &[])?,
+            Instruction::I64AtomicStore32(_) => self.atomic(instr, &[ValType::I64], &[])?,
+            Instruction::I32AtomicRmwAdd(_)
+            | Instruction::I32AtomicRmwSub(_)
+            | Instruction::I32AtomicRmwAnd(_)
+            | Instruction::I32AtomicRmwOr(_)
+            | Instruction::I32AtomicRmwXor(_)
+            | Instruction::I32AtomicRmwXchg(_) => {
+                self.atomic(instr, &[ValType::I32], &[ValType::I32])?
+            }
+            Instruction::I64AtomicRmwAdd(_)
+            | Instruction::I64AtomicRmwSub(_)
+            | Instruction::I64AtomicRmwAnd(_)
+            | Instruction::I64AtomicRmwOr(_)
+            | Instruction::I64AtomicRmwXor(_)
+            | Instruction::I64AtomicRmwXchg(_) => {
+                self.atomic(instr, &[ValType::I64], &[ValType::I64])?
+            }
+            Instruction::I32AtomicRmw8AddU(_)
+            | Instruction::I32AtomicRmw8SubU(_)
+            | Instruction::I32AtomicRmw8AndU(_)
+            | Instruction::I32AtomicRmw8OrU(_)
+            | Instruction::I32AtomicRmw8XorU(_)
+            | Instruction::I32AtomicRmw8XchgU(_) => {
+                self.atomic(instr, &[ValType::I32], &[ValType::I32])?
+            }
+            Instruction::I32AtomicRmw16AddU(_)
+            | Instruction::I32AtomicRmw16SubU(_)
+            | Instruction::I32AtomicRmw16AndU(_)
+            | Instruction::I32AtomicRmw16OrU(_)
+            | Instruction::I32AtomicRmw16XorU(_)
+            | Instruction::I32AtomicRmw16XchgU(_) => {
+                self.atomic(instr, &[ValType::I32], &[ValType::I32])?
+            }
+            Instruction::I64AtomicRmw8AddU(_)
+            | Instruction::I64AtomicRmw8SubU(_)
+            | Instruction::I64AtomicRmw8AndU(_)
+            | Instruction::I64AtomicRmw8OrU(_)
+            | Instruction::I64AtomicRmw8XorU(_)
+            | Instruction::I64AtomicRmw8XchgU(_) => {
+                self.atomic(instr, &[ValType::I64], &[ValType::I64])?
+            }
+            Instruction::I64AtomicRmw16AddU(_)
+            | Instruction::I64AtomicRmw16SubU(_)
+            | Instruction::I64AtomicRmw16AndU(_)
+            | Instruction::I64AtomicRmw16OrU(_)
+            | Instruction::I64AtomicRmw16XorU(_)
+            | Instruction::I64AtomicRmw16XchgU(_) => {
+                self.atomic(instr, &[ValType::I64], &[ValType::I64])?
+            }
+            Instruction::I64AtomicRmw32AddU(_)
+            | Instruction::I64AtomicRmw32SubU(_)
+            | Instruction::I64AtomicRmw32AndU(_)
+            | Instruction::I64AtomicRmw32OrU(_)
+            | Instruction::I64AtomicRmw32XorU(_)
+            | Instruction::I64AtomicRmw32XchgU(_) => {
+                self.atomic(instr, &[ValType::I64], &[ValType::I64])?
+            }
+            Instruction::I32AtomicRmwCmpxchg(_) => {
+                self.atomic(instr, &[ValType::I32, ValType::I32], &[ValType::I32])?
+            }
+            Instruction::I64AtomicRmwCmpxchg(_) => {
+                self.atomic(instr, &[ValType::I64, ValType::I64], &[ValType::I64])?
+            }
+            Instruction::I32AtomicRmw8CmpxchgU(_) => {
+                self.atomic(instr, &[ValType::I32, ValType::I32], &[ValType::I32])?
+            }
+            Instruction::I32AtomicRmw16CmpxchgU(_) => {
+                self.atomic(instr, &[ValType::I32, ValType::I32], &[ValType::I32])?
+            }
+            Instruction::I64AtomicRmw8CmpxchgU(_) => {
+                self.atomic(instr, &[ValType::I64, ValType::I64], &[ValType::I64])?
+            }
+            Instruction::I64AtomicRmw16CmpxchgU(_) => {
+                self.atomic(instr, &[ValType::I64, ValType::I64], &[ValType::I64])?
+            }
+            Instruction::I64AtomicRmw32CmpxchgU(_) => {
+                self.atomic(instr, &[ValType::I64, ValType::I64], &[ValType::I64])?
+            }
+        }
+        Ok(())
+    }
+
+    fn call_indirect(&mut self, table: u32) -> Result<(), Error> {
+        let table = self.ctx.table(table)?;
+        if !self.ctx.ref_matches(table.element, RefType::FUNCREF) {
+            return Err(Error::TypeMismatch(
+                ValType::Ref(RefType::FUNCREF),
+                ValType::Ref(table.element),
+            ));
+        }
+        self.pop_expect(table.index_type.into())?;
+        Ok(())
+    }
+}
+
+/// The memarg of a memory instruction and its natural alignment.
+fn memarg_align(instr: &Instruction) -> (&MemArg, u8) {
+    let (Some(arg), Some(natural)) = (instr.memarg(), instr.natural_align()) else {
+        unreachable!("{instr:?} does not access memory")
+    };
+    (arg, natural)
+}
+
+/// A reference to the defined type `idx`.
+fn concrete_ref(idx: u32, nullable: bool) -> ValType {
+    ValType::Ref(RefType::new(nullable, HeapType::Concrete(idx)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::{Error, Location};
+    use crate::instruction::Instruction;
+    use crate::module::Module;
+    use crate::types::{CompositeType, ValType};
+
+    fn validate(wat: &str) -> Result<(), Error> {
+        let data = wat::parse_str(wat).unwrap();
+        let module = Module::from_bytes(&data).parse()?;
+        module.validate()
+    }
+
+    #[test]
+    fn test_validate_binaries() {
+        for data in [
+            include_bytes!("../tests/pulldown-cmark.wasm").as_slice(),
+            include_bytes!("../tests/bz2.wasm"),
+            include_bytes!("../tests/lots-of-types.wasm"),
+        ] {
+            let module = Module::from_bytes(data).parse().unwrap();
+            if let Err(err) = module.validate() {
+                panic!("{err}");
+            }
+        }
+    }
+
+    /// wasm-smith lets a subtype change the mutability of a field, which the spec forbids.
+    fn changes_field_mutability(module: &Module) -> bool {
+        let types = module.type_section.sub_types().collect::<Vec<_>>();
+        let fields = |ty: &CompositeType| match ty {
+            CompositeType::Struct(st) => st.fields.iter().map(|field| field.mutable).collect(),
+            CompositeType::Array(array) => vec![array.0.mutable],
+            CompositeType::Func(_) => Vec::new(),
+        };
+        types.iter().any(|ty| {
+            let sup = ty
+                .supertypes
+                .first()
+                .and_then(|sup| types.get(*sup as usize));
+            sup.is_some_and(|sup| {
+                let sub_fields = fields(&ty.composite);
+                let sup_fields = fields(&sup.composite);
+                sub_fields
+                    .iter()
+                    .zip(&sup_fields)
+                    .any(|(sub, sup)| sub != sup)
+            })
+        })
+    }
+
+    #[test]
+    fn test_validate_smith() {
+        let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+        for i in 0..200 {
+            let bytes = (0..4096)
+                .map(|_| {
+                    seed ^= seed << 13;
+                    seed ^= seed >> 7;
+                    seed ^= seed << 17;
+                    seed as u8
+                })
+                .collect::<Vec<_>>();
+            let config = wasm_smith::Config {
+                simd_enabled: i % 2 == 0,
+                relaxed_simd_enabled: i % 4 == 0,
+                reference_types_enabled: true,
+                gc_enabled: true,
+                bulk_memory_enabled: true,
+                exceptions_enabled: i % 3 == 0,
+                tail_call_enabled: true,
+                memory64_enabled: i % 5 == 0,
+                threads_enabled: i % 2 == 1,
+                max_memories: 1 + i % 3,
+                ..Default::default()
+            };
+            let mut u = arbitrary::Unstructured::new(&bytes);
+            let Ok(module) = wasm_smith::Module::new(config, &mut u) else {
+                continue;
+            };
+            let data = module.to_bytes();
+            let module = Module::from_bytes(&data).parse().unwrap();
+            if changes_field_mutability(&module) {
+                continue;
+            }
+            if let Err(err) = module.validate() {
+                panic!("module {i}: {err}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_type_equivalence() {
+        validate(
+            r#"
+            (module
+                (type $a (struct (field i32)))
+                (type $b (struct (field i32)))
+                (type $s (sub (struct (field (ref $a)))))
+                (type $t (sub $s (struct (field (ref $b))))))
+            "#,
+        )
+        .unwrap();
+        validate(
+            r#"
+            (module
+                (type $a (func))
+                (type $b (func))
+                (func $f (type $a))
+                (func $g (param (ref $b)))
+                (elem declare func $f)
+                (func (call $g (ref.func $f))))
+            "#,
+        )
+        .unwrap();
+
+        // Equivalent rec groups must be defined alike, not only have equal members.
+        let err = validate(
+            r#"
+            (module
+                (rec (type $a (struct (field (ref null $a)))) (type (struct)))
+                (rec (type $b (struct (field (ref null $b)))))
+                (func (param (ref $a)) (result (ref $b)) (local.get 0)))
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(err.kind(), Error::TypeMismatch(..)));
+    }
+
+    #[test]
+    fn test_validate_errors() {
+        let err = validate("(module (func (result i32) (i64.const 0)))").unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            Error::TypeMismatch(ValType::I32, ValType::I64)
+        ));
+        assert_eq!(err.location(), Some(Location::Function(0)));
+
+        let err = validate(
+            r#"
+            (module
+                (import "env" "f" (func))
+                (func (param i32) (local i64)
+                    (nop)
+                    (local.set 2 (i32.const 0))))
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(err.kind(), Error::UnknownIndex("local", 2)));
+        assert_eq!(err.location(), Some(Location::Function(1)));
+
+        let err =
+            validate("(module (type $s (struct)) (func (local (ref $s)) (drop (local.get 0))))")
+                .unwrap_err();
+        assert!(matches!(err.kind(), Error::UninitializedLocal(0)));
+        validate(
+            r#"
+            (module
+                (type $s (struct))
+                (func (param (ref $s)) (local (ref $s))
+                    (local.set 1 (local.get 0))
+                    (drop (local.get 1))))
+            "#,
+        )
+        .unwrap();
+        // A local set inside a block is unset again after it.
+        let err = validate(
+            r#"
+            (module
+                (type $s (struct))
+                (func (param (ref $s)) (local (ref $s))
+                    (block (local.set 1 (local.get 0)) (drop (local.get 1)))
+                    (drop (local.get 1))))
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(err.kind(), Error::UninitializedLocal(1)));
+
+        let err = validate("(module (func (br 1)))").unwrap_err();
+        assert!(matches!(err.kind(), Error::UnknownIndex("label", 1)));
+        let err = validate("(module (func (br 0xffffffff)))").unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            Error::UnknownIndex("label", 0xffff_ffff)
+        ));
+
+        let err = validate("(module (func (block (result i32) (br 0))))").unwrap_err();
+        assert!(matches!(err.kind(), Error::OperandStackUnderflow));
+
+        let err = validate(
+            "(module (func (block (result i32) (block (br_table 0 1 (i32.const 0)))) drop))",
+        )
+        .unwrap_err();
+        assert!(matches!(err.kind(), Error::LabelArityMismatch(1, 0)));
+
+        let err = validate("(module (func (drop (ref.func 0))))").unwrap_err();
+        assert!(matches!(err.kind(), Error::UndeclaredFunctionReference(0)));
+        validate("(module (elem declare func 0) (func (drop (ref.func 0))))").unwrap();
+
+        let err = validate(
+            r#"
+            (module
+                (import "env" "g" (global (mut i32)))
+                (global i32 (global.get 0)))
+            "#,
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::InvalidConstExprOpcode(Instruction::GlobalGet(0))
+        ));
+
+        let data = wat::parse_str(
+            r#"(module (import "env" "f" (func)) (import "env" "f" (func (param i32))))"#,
+        )
+        .unwrap();
+        let module = Module::from_bytes(&data).parse().unwrap();
+        module.validate().unwrap();
+        assert!(matches!(
+            module.validate_strict(),
+            Err(Error::DuplicateImport(module, field)) if module == "env" && field == "f"
+        ));
+
+        let err = validate(r#"(module (func (export "a")) (func (export "a")))"#).unwrap_err();
+        assert!(matches!(err, Error::DuplicateExport(name) if name == "a"));
+
+        let err = validate("(module (global i32 (ref.is_null (ref.null func))))").unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            Error::InvalidConstExprOpcode(Instruction::RefIsNull)
+        ));
+
+        // A shared memory without a maximum.
+        let data = b"\0asm\x01\0\0\0\x05\x03\x01\x02\x01";
+        let err = Module::from_bytes(data).parse().unwrap().validate();
+        assert!(matches!(err, Err(Error::SharedMemoryWithoutMax)));
+
+        let err = validate(
+            "(module (memory 1) (func (drop (i32.load offset=0x1_0000_0000 (i32.const 0)))))",
+        )
+        .unwrap_err();
+        assert!(matches!(err.kind(), Error::OffsetTooLarge(0x1_0000_0000)));
+        validate(
+            "(module (memory i64 1) (func (drop (i32.load offset=0x1_0000_0000 (i64.const 0)))))",
+        )
+        .unwrap();
+
+        // Anything goes after an unconditional branch.
+        validate("(module (func (result i32) unreachable i32.add))").unwrap();
+        validate(
+            "(module (func (param i32) (result i32) (if (result i32) (local.get 0) (then (i32.const 1)) (else (i32.const 2)))))",
+        )
+        .unwrap();
+    }
+}